@@ -0,0 +1,2 @@
+-- Link of IOTA Streams message a reading was received with
+ALTER TABLE sensor_data ADD COLUMN msg_link TEXT;
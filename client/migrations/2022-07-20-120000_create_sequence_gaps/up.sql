@@ -0,0 +1,12 @@
+-- Sequence gaps of IOTA Streams publishers, readings of missed messages are missing
+CREATE TABLE IF NOT EXISTS sequence_gaps (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    channel_id INTEGER NOT NULL,
+    publisher TEXT NOT NULL,
+    from_seq BIGINT NOT NULL,
+    to_seq BIGINT NOT NULL,
+    recovered BIGINT NOT NULL,
+    detected_at BIGINT NOT NULL,
+    FOREIGN KEY (channel_id)
+        REFERENCES channels (id)
+);
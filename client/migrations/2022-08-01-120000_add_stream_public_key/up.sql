@@ -0,0 +1,2 @@
+-- Public key of own subscriber, sent with subscription link for branch access
+ALTER TABLE streams ADD COLUMN public_key TEXT;
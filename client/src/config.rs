@@ -1,70 +1,151 @@
-use confy;
-use serde_derive::{Deserialize, Serialize};
-use std::env;
-// MQTT Topics
-pub const TOPIC_DID: &str = "did";
-pub const TOPIC_SENSOR_VALUE: &str = "sensors";
-pub const TOPIC_SETTING: &str = "settings";
-pub const TOPIC_IDENTITY: &str = "identity";
-pub const TOPIC_STREAM: &str = "stream";
-pub const TOPIC_COMMAND: &str = "command";
-pub const PUBLIC_CHANNEL_ID: &str = "public_stream";
-// Gateway
-// Number of Subscribers Expected
-pub const MQTT_SOCKET: &str = "0.0.0.0:50054";
-/// Default Streams Service GRPC Socket
-pub const STREAMS_SOCKET: &str = "0.0.0.0:50052";
-/// Default Identity Service GRPC Socket
-pub const IDENTITY_SOCKET: &str = "0.0.0.0:50053";
-pub const ENV_IS_FACTORY: &str = "IS_FACTORY"; // bool "true", "false"
-pub const ENV_ANNLINK_PUBLIC: &str = "PUBLIC_ANN_LINK";
-pub const ENV_DEVICE_NAME: &str = "DEVICE_NAME";
-pub const ENV_DEVICE_TYPE: &str = "DEVICE_TYPE";
-pub const ENV_DEVICE_ID: &str = "DEVICE_ID";
-pub const ENV_CHANNELS_KEY: &str = "CHANNEL_IDS"; // Seperated with ';'
-pub const ENV_THING_KEY: &str = "THING_NAME";
-pub const ENV_THING_PWD: &str = "THING_PWD";
-#[allow(dead_code)]
-pub const ENV_LATEST_TIMESTAMP: &str = "LATEST_TIMESTAMP";
-pub const ENV_SENSOR_KEYS: &str = "SENSOR_IDS"; // Seperated with ';'
-/// ENV for GRPC Socket
-const ENV_GRPC_SOCKET: &str = "GATEWAY_GRPC_SOCKET";
-/// Default GRPC Socket
-const DEFAULT_GRPC_SOCKET: &str = "[::0]:50051";
-/// Structure used to parse configuration file
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SensorConfig {
-    pub grpc: Grpc,
-}
-/// Socket needed for GRPC server, for example 0.0.0.0:50051
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Grpc {
-    pub socket: String,
-}
-/// Default implementation uses socket at 0.0.0.0:50051, default can be set via ENVs
-/// and Sensor information
-impl Default for SensorConfig {
-    fn default() -> Self {
-        SensorConfig {
-            grpc: Grpc {
-                socket: env::var(ENV_GRPC_SOCKET)
-                    .unwrap_or_else(|_| DEFAULT_GRPC_SOCKET.to_string()),
-            },
-        }
-    }
-}
-/// Configuration file "streams-grpc.toml" is located at ./config/
-/// Function tries to load configuration or creates default
-#[allow(dead_code)]
-pub fn load_config_file() -> SensorConfig {
-    let config_dir = env::current_dir()
-        .unwrap()
-        .join("config")
-        .join("client-grpc.toml");
-    let res = confy::load_path(config_dir);
-    let cfg: SensorConfig = match res {
-        Ok(r) => r,
-        Err(_e) => SensorConfig::default(),
-    };
-    cfg
-}
+use confy;
+use serde_derive::{Deserialize, Serialize};
+use std::env;
+// MQTT Topics
+pub const TOPIC_DID: &str = "did";
+pub const TOPIC_SENSOR_VALUE: &str = "sensors";
+pub const TOPIC_SETTING: &str = "settings";
+pub const TOPIC_IDENTITY: &str = "identity";
+pub const TOPIC_STREAM: &str = "stream";
+pub const TOPIC_COMMAND: &str = "command";
+pub const TOPIC_COMMAND_RESULT: &str = "command_result";
+pub const PUBLIC_CHANNEL_ID: &str = "public_stream";
+// Gateway
+// Number of Subscribers Expected
+pub const MQTT_SOCKET: &str = "0.0.0.0:50054";
+/// Default Streams Service GRPC Socket
+pub const STREAMS_SOCKET: &str = "0.0.0.0:50052";
+/// Default Identity Service GRPC Socket
+pub const IDENTITY_SOCKET: &str = "0.0.0.0:50053";
+/// Code of Identity Service Verification if Credential is Revoked
+pub const CODE_CREDENTIAL_REVOKED: i32 = -3;
+pub const ENV_IS_FACTORY: &str = "IS_FACTORY"; // bool "true", "false"
+pub const ENV_ANNLINK_PUBLIC: &str = "PUBLIC_ANN_LINK";
+pub const ENV_DEVICE_NAME: &str = "DEVICE_NAME";
+pub const ENV_DEVICE_TYPE: &str = "DEVICE_TYPE";
+pub const ENV_DEVICE_ID: &str = "DEVICE_ID";
+pub const ENV_CHANNELS_KEY: &str = "CHANNEL_IDS"; // Seperated with ';'
+pub const ENV_THING_KEY: &str = "THING_NAME";
+pub const ENV_THING_PWD: &str = "THING_PWD";
+#[allow(dead_code)]
+pub const ENV_LATEST_TIMESTAMP: &str = "LATEST_TIMESTAMP";
+pub const ENV_SENSOR_KEYS: &str = "SENSOR_IDS"; // Seperated with ';'
+pub const ENV_BRANCHES: &str = "BRANCHES"; // Seperated with ';', not set for all branches
+/// ENV for GRPC Socket
+const ENV_GRPC_SOCKET: &str = "GATEWAY_GRPC_SOCKET";
+/// Default GRPC Socket
+const DEFAULT_GRPC_SOCKET: &str = "[::0]:50051";
+/// ENV for Retention Check Interval, 0 disables pruning
+const ENV_RETENTION_INTERVAL: &str = "CLIENT_RETENTION_INTERVAL_MS";
+/// Default Retention Check Interval in ms
+const DEFAULT_RETENTION_INTERVAL: u64 = 3600000;
+/// ENV for Maximum Age of Sensor Entries, 0 disables age limit
+const ENV_RETENTION_MAX_AGE: &str = "CLIENT_RETENTION_MAX_AGE_S";
+/// Default Maximum Age of Sensor Entries in s (7 days)
+const DEFAULT_RETENTION_MAX_AGE: i64 = 604800;
+/// ENV for Maximum Number of Sensor Entries per Sensor, 0 disables row limit
+const ENV_RETENTION_MAX_ROWS: &str = "CLIENT_RETENTION_MAX_ROWS_PER_SENSOR";
+/// Default Maximum Number of Sensor Entries per Sensor
+const DEFAULT_RETENTION_MAX_ROWS: i64 = 0;
+/// ENV for Archiving Pruned Sensor Entries ("true", "false")
+const ENV_RETENTION_ARCHIVE: &str = "CLIENT_RETENTION_ARCHIVE";
+/// Default Directory for Archived Sensor Entries
+const DEFAULT_ARCHIVE_DIR: &str = "archive";
+/// ENV for Messages Fetched Backwards per Publisher with Sequence Gap, 0 disables walking back
+const ENV_SYNC_WALK_BACK: &str = "CLIENT_SYNC_WALK_BACK";
+/// Structure used to parse configuration file
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SensorConfig {
+    pub grpc: Grpc,
+    #[serde(default)]
+    pub retention: Retention,
+    #[serde(default)]
+    pub sync: SubscriberSync,
+}
+/// Socket needed for GRPC server, for example 0.0.0.0:50051
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Grpc {
+    pub socket: String,
+}
+/// Retention rules for sensor entries, pruned entries can be archived
+/// as gzip compressed JSON lines to archive_dir
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Retention {
+    pub interval_ms: u64,
+    pub max_age_s: i64,
+    pub max_rows_per_sensor: i64,
+    pub only_delivered: bool,
+    pub archive: bool,
+    pub archive_dir: String,
+}
+/// Default retention rules, default can be set via ENVs
+impl Default for Retention {
+    fn default() -> Self {
+        Retention {
+            interval_ms: env::var(ENV_RETENTION_INTERVAL)
+                .ok()
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(DEFAULT_RETENTION_INTERVAL),
+            max_age_s: env::var(ENV_RETENTION_MAX_AGE)
+                .ok()
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(DEFAULT_RETENTION_MAX_AGE),
+            max_rows_per_sensor: env::var(ENV_RETENTION_MAX_ROWS)
+                .ok()
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(DEFAULT_RETENTION_MAX_ROWS),
+            only_delivered: true,
+            archive: env::var(ENV_RETENTION_ARCHIVE)
+                .ok()
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(false),
+            archive_dir: DEFAULT_ARCHIVE_DIR.to_string(),
+        }
+    }
+}
+/// Subscriber synchronisation, walk_back: Messages fetched backwards
+/// per publisher with sequence gap
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubscriberSync {
+    pub walk_back: u32,
+}
+/// Default synchronisation without walking back, default can be set via ENV
+impl Default for SubscriberSync {
+    fn default() -> Self {
+        SubscriberSync {
+            walk_back: env::var(ENV_SYNC_WALK_BACK)
+                .ok()
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(0),
+        }
+    }
+}
+/// Default implementation uses socket at 0.0.0.0:50051, default can be set via ENVs
+/// and Sensor information
+impl Default for SensorConfig {
+    fn default() -> Self {
+        SensorConfig {
+            grpc: Grpc {
+                socket: env::var(ENV_GRPC_SOCKET)
+                    .unwrap_or_else(|_| DEFAULT_GRPC_SOCKET.to_string()),
+            },
+            retention: Retention::default(),
+            sync: SubscriberSync::default(),
+        }
+    }
+}
+/// Configuration file "streams-grpc.toml" is located at ./config/
+/// Function tries to load configuration or creates default
+#[allow(dead_code)]
+pub fn load_config_file() -> SensorConfig {
+    let config_dir = env::current_dir()
+        .unwrap()
+        .join("config")
+        .join("client-grpc.toml");
+    let res = confy::load_path(config_dir);
+    let cfg: SensorConfig = match res {
+        Ok(r) => r,
+        Err(_e) => SensorConfig::default(),
+    };
+    cfg
+}
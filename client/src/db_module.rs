@@ -7,8 +7,8 @@ use dotenv::dotenv;
 use std::env;
 
 use schema::{
-    channels, config, identification, identities, sensor_data, sensor_types, sensors, streams,
-    things,
+    channels, config, identification, identities, sensor_data, sensor_types, sensors,
+    sequence_gaps, streams, things,
 };
 
 /// Connect to Database
@@ -389,6 +389,7 @@ pub struct StreamsEntry {
     pub sub_link: String,
     pub key_link: String,
     pub msg_link: String,
+    pub seed_nonce: String,
 }
 /// Create Stream
 pub fn create_stream<'a>(conn: &SqliteConnection, entry: StreamsEntry) -> Result<usize, i32> {
@@ -399,6 +400,7 @@ pub fn create_stream<'a>(conn: &SqliteConnection, entry: StreamsEntry) -> Result
         key_link: &entry.key_link,
         msg_link: &entry.msg_link,
         num_subs: 0,
+        seed_nonce: &entry.seed_nonce,
     };
 
     let entry = match diesel::insert_into(streams::table)
@@ -467,6 +469,38 @@ pub fn create_identity<'a>(
     };
     Ok(entry)
 }
+/// Table Sequence Gaps CRUD
+/// Create Sequence Gap
+pub fn create_sequence_gap(
+    conn: &SqliteConnection,
+    channel_id: i32,
+    publisher: &str,
+    from_seq: i64,
+    to_seq: i64,
+    recovered: i64,
+    detected_at: i64,
+) -> Result<usize, i32> {
+    let new_entry = models::NewSequenceGap {
+        channel_id: channel_id,
+        publisher: publisher,
+        from_seq: from_seq,
+        to_seq: to_seq,
+        recovered: recovered,
+        detected_at: detected_at,
+    };
+
+    let entry = match diesel::insert_into(sequence_gaps::table)
+        .values(&new_entry)
+        .execute(conn)
+    {
+        Ok(r) => r,
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+    Ok(entry)
+}
 /// Table Config CRUD
 /// Create Config
 pub fn create_configuration(
@@ -516,6 +550,9 @@ pub fn update_stream(
         "num_subs" => diesel::update(streams.filter(channel_id.eq(channel_identifier)))
             .set(num_subs.eq(num_subscribers))
             .execute(conn),
+        "public_key" => diesel::update(streams.filter(channel_id.eq(channel_identifier)))
+            .set(public_key.eq(link))
+            .execute(conn),
         _ => {
             error!("{}", query);
             return Err(-1);
@@ -566,6 +603,118 @@ pub fn update_sensor_entry(
         }
     };
 }
+/// Update Message Link of Sensor Entry
+pub fn update_sensor_msg_link(
+    conn: &SqliteConnection,
+    identifier: i32,
+    link: &str,
+) -> Result<i32, i32> {
+    use self::sensor_data::dsl::*;
+    match diesel::update(sensor_data.filter(id.eq(identifier)))
+        .set(msg_link.eq(link))
+        .execute(conn)
+    {
+        Ok(r) => {
+            info!("Affected Rows: {}", r);
+            return Ok(r as i32);
+        }
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+}
+/// Select Sensor Entries older than timestamp, only delivered (verified, published if require_published) if only_delivered
+pub fn select_expired_sensor_entries(
+    conn: &SqliteConnection,
+    timestamp: i64,
+    only_delivered: bool,
+    require_published: bool,
+) -> Result<Vec<models::SensorData>, i32> {
+    use self::sensor_data::dsl::*;
+    let mut query = sensor_data.filter(sensor_time.lt(timestamp)).into_boxed();
+    if only_delivered {
+        query = query.filter(verified.eq(true));
+        if require_published {
+            query = query.filter(iota.eq(true));
+        }
+    }
+    let result = match query.order(id.asc()).load::<models::SensorData>(conn) {
+        Ok(r) => r,
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+    Ok(result)
+}
+/// Select Sensor Entries of a Sensor exceeding the newest max_rows entries,
+/// only delivered (verified, published if require_published) if only_delivered
+pub fn select_excess_sensor_entries(
+    conn: &SqliteConnection,
+    sensor_identifier: i32,
+    max_rows: i64,
+    only_delivered: bool,
+    require_published: bool,
+) -> Result<Vec<models::SensorData>, i32> {
+    use self::sensor_data::dsl::*;
+    let mut query = sensor_data
+        .filter(sensor_id.eq(sensor_identifier))
+        .into_boxed();
+    if only_delivered {
+        query = query.filter(verified.eq(true));
+        if require_published {
+            query = query.filter(iota.eq(true));
+        }
+    }
+    let result = match query
+        .order((sensor_time.desc(), id.desc()))
+        // SQLite needs a limit for offset, -1 means no limit
+        .limit(-1)
+        .offset(max_rows)
+        .load::<models::SensorData>(conn)
+    {
+        Ok(r) => r,
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+    Ok(result)
+}
+/// Select IDs of Sensors with Sensor Entries
+pub fn select_sensor_ids_with_entries(conn: &SqliteConnection) -> Result<Vec<i32>, i32> {
+    use self::sensor_data::dsl::*;
+    let result = match sensor_data.select(sensor_id).distinct().load::<i32>(conn) {
+        Ok(r) => r,
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+    Ok(result)
+}
+/// Delete Sensor Entries, all or none are deleted
+pub fn delete_sensor_entries(conn: &SqliteConnection, identifiers: &Vec<i32>) -> Result<i32, i32> {
+    use self::sensor_data::dsl::*;
+    let result = conn.transaction::<usize, diesel::result::Error, _>(|| {
+        let rows = diesel::delete(sensor_data.filter(id.eq_any(identifiers))).execute(conn)?;
+        if rows != identifiers.len() {
+            return Err(diesel::result::Error::RollbackTransaction);
+        }
+        Ok(rows)
+    });
+    match result {
+        Ok(r) => {
+            info!("Deleted Rows: {}", r);
+            return Ok(r as i32);
+        }
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+}
 /// Select Sensor Type
 pub fn select_sensor_type_by_desc(
     conn: &SqliteConnection,
@@ -669,6 +818,7 @@ pub struct SensorDataEntry {
     pub mqtt: bool,
     pub iota: bool,
     pub verified: bool,
+    pub msg_link: Option<String>,
 }
 /// Create Sensor Data
 pub fn create_sensor_data<'a>(
@@ -682,6 +832,7 @@ pub fn create_sensor_data<'a>(
         mqtt: entry.mqtt,
         iota: entry.iota,
         verified: entry.verified,
+        msg_link: entry.msg_link.as_deref(),
     };
 
     let entry = match diesel::insert_into(sensor_data::table)
@@ -13,12 +13,14 @@ mod models;
 mod prolog;
 mod recv_mqtt;
 mod req_verification;
+mod retention;
 mod schema;
 mod send_mqtt;
 mod state_machine;
 mod util;
 
 use db_module as db;
+use tokio::join;
 use tokio::time::{sleep, Duration};
 
 pub mod grpc_streams {
@@ -35,6 +37,7 @@ pub mod grpc_mqtt {
 }
 
 use prolog::init;
+use retention::retention_worker;
 use state_machine::state_machine;
 // Embed SQL in Binary
 embed_migrations!();
@@ -54,7 +57,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }) {}
     info!("----------------------------- Start Main Program -----------------------------");
-    let _ = state_machine().await;
+    let _ = join!(state_machine(), retention_worker());
 
     Ok(())
 }
@@ -1,6 +1,6 @@
 use crate::schema::{
-    channels, config, identification, identities, sensor_data, sensor_types, sensors, streams,
-    things,
+    channels, config, identification, identities, sensor_data, sensor_types, sensors,
+    sequence_gaps, streams, things,
 };
 use diesel::{Insertable, Queryable};
 // Database Models
@@ -55,6 +55,8 @@ pub struct Stream {
     pub key_link: Option<String>,
     pub msg_link: Option<String>,
     pub num_subs: Option<i32>,
+    pub public_key: Option<String>,
+    pub seed_nonce: String,
 }
 
 #[derive(Insertable)]
@@ -66,6 +68,7 @@ pub struct NewStream<'a> {
     pub key_link: &'a str,
     pub msg_link: &'a str,
     pub num_subs: i32,
+    pub seed_nonce: &'a str,
 }
 
 #[derive(Queryable, Debug)]
@@ -139,6 +142,7 @@ pub struct SensorData {
     pub mqtt: Option<bool>,
     pub iota: Option<bool>,
     pub verified: Option<bool>,
+    pub msg_link: Option<String>,
 }
 
 #[derive(Insertable)]
@@ -150,4 +154,27 @@ pub struct NewSensorData<'a> {
     pub mqtt: bool,
     pub iota: bool,
     pub verified: bool,
+    pub msg_link: Option<&'a str>,
+}
+
+#[derive(Queryable, Debug)]
+pub struct SequenceGap {
+    pub id: i32,
+    pub channel_id: i32,
+    pub publisher: String,
+    pub from_seq: i64,
+    pub to_seq: i64,
+    pub recovered: i64,
+    pub detected_at: i64,
+}
+
+#[derive(Insertable)]
+#[table_name = "sequence_gaps"]
+pub struct NewSequenceGap<'a> {
+    pub channel_id: i32,
+    pub publisher: &'a str,
+    pub from_seq: i64,
+    pub to_seq: i64,
+    pub recovered: i64,
+    pub detected_at: i64,
 }
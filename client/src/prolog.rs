@@ -333,6 +333,9 @@ pub async fn init_streams(
                     id: author_id.to_string(),
                     msg_type: 1, // CreateNewAuthor
                     link: "".to_string(),
+                    branch: "".to_string(),
+                    multi_branch: false,
+                    seed_nonce: "".to_string(),
                 })
                 .await
             {
@@ -354,6 +357,7 @@ pub async fn init_streams(
                     sub_link: "".to_string(),
                     key_link: "".to_string(),
                     msg_link: author.link.clone(),
+                    seed_nonce: author.seed_nonce.clone(),
                 },
             ) {
                 Ok(_) => info!("Streams Entry Created for Channel ID: {}", channel_id),
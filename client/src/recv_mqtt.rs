@@ -1,24 +1,27 @@
 #![allow(dead_code)]
+use chrono::Utc;
 use prost::Message;
 
 use std::env;
 use std::io::Cursor;
 
 use crate::config::{
-    ENV_THING_KEY, ENV_THING_PWD, TOPIC_COMMAND, TOPIC_DID, TOPIC_IDENTITY, TOPIC_SENSOR_VALUE,
-    TOPIC_SETTING, TOPIC_STREAM,
+    load_config_file, CODE_CREDENTIAL_REVOKED, ENV_THING_KEY, ENV_THING_PWD, TOPIC_COMMAND,
+    TOPIC_COMMAND_RESULT, TOPIC_DID, TOPIC_IDENTITY, TOPIC_SENSOR_VALUE, TOPIC_SETTING,
+    TOPIC_STREAM,
 };
 use crate::db_module as db;
 use crate::grpc_identity::iota_identifier_client::IotaIdentifierClient;
 use crate::grpc_identity::IotaIdentityRequest;
 use crate::grpc_mqtt::mqtt_operator_client::MqttOperatorClient;
 use crate::grpc_mqtt::{MqttMsgsReply, MqttRequest};
-use crate::grpc_streams::IotaStreamsRequest;
+use crate::grpc_streams::{IotaStreamsRequest, IotaStreamsSyncRequest};
 use crate::models::{Identity, Sensor, SensorData, SensorType, Stream};
 use crate::mqtt_encoder as enc;
 use crate::util::{
     connect_identity, connect_mqtt, connect_streams, get_channel, get_identification, get_thing,
-    helper_send_mqtt, send_sublink, serialize_msg, update_streams_entry,
+    helper_send_mqtt, is_subscribed_branch, make_command_challenge, send_sublink, serialize_msg,
+    update_streams_entry,
 };
 use serde_derive::{Deserialize, Serialize};
 use std::fs;
@@ -35,6 +38,30 @@ struct MessageFromJson {
     unit: String,
     timestamp: i64,
 }
+/// Single sensor reading of a batch message
+#[derive(Serialize, Deserialize, Clone)]
+struct ReadingFromJson {
+    sensor_id: String,
+    sensor_name: String,
+    sensor_type: String,
+    value: String,
+    unit: String,
+    timestamp: i64,
+}
+/// Batch of sensor readings sent with one IOTA Streams message
+#[derive(Serialize, Deserialize, Clone)]
+struct BatchFromJson {
+    did: String,
+    verifiable_credential: String,
+    readings: Vec<ReadingFromJson>,
+}
+/// IOTA Streams message holds either a batch or a single sensor reading
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+enum IotaMessageFromJson {
+    Batch(BatchFromJson),
+    Single(MessageFromJson),
+}
 
 pub async fn receive_mqtt_messages(channel_key: &str, postfix: i32) -> Result<String, String> {
     info!("--- receive_mqtt_messages() ---");
@@ -46,6 +73,7 @@ pub async fn receive_mqtt_messages(channel_key: &str, postfix: i32) -> Result<St
             TOPIC_STREAM => mqtt_streams(payload.to_vec(), channel_key).await,
             TOPIC_SETTING => mqtt_settings(payload.to_vec()).await,
             TOPIC_COMMAND => mqtt_command(payload.to_vec()).await,
+            TOPIC_COMMAND_RESULT => mqtt_command_result(payload.to_vec()).await,
             TOPIC_IDENTITY => mqtt_first_verification(payload.to_vec()).await,
             TOPIC_SENSOR_VALUE => mqtt_save_sensor_data(payload.to_vec(), channel_key).await,
             e => Err(format!("Topic {} not Found", e)),
@@ -69,24 +97,43 @@ pub async fn iota_sensor_data(channel_key: &str) -> Result<u32, String> {
         Some(r) => r,
         None => "".to_string(),
     };
+    let public_key = match stream_entry.public_key {
+        Some(r) => r,
+        None => "".to_string(),
+    };
     // Check if Keyloads are sent,
     match stream_entry.key_link {
         Some(r) => {
             if r.is_empty() {
-                send_sublink(&mut mqtt_client, &db_client, &sub_link, channel_key).await?;
+                send_sublink(
+                    &mut mqtt_client,
+                    &db_client,
+                    &sub_link,
+                    &public_key,
+                    channel_key,
+                )
+                .await?;
                 return Err("No IOTA Streams Connection Established (Keyload Missing)".to_string());
             }
         }
         None => {
-            send_sublink(&mut mqtt_client, &db_client, &sub_link, channel_key).await?;
+            send_sublink(
+                &mut mqtt_client,
+                &db_client,
+                &sub_link,
+                &public_key,
+                channel_key,
+            )
+            .await?;
             return Err("No IOTA Streams Connection Established (Keyload Missing)".to_string());
         }
     };
+    // Fetch next messages and report messages missed by the subscriber
     let msgs = match streams_client
-        .receive_messages(IotaStreamsRequest {
+        .sync_subscriber(IotaStreamsSyncRequest {
             id: channel_key.to_string(),
-            link: "".to_string(),
-            msg_type: 6, // ReceiveMessages
+            msg_type: 12, // SyncSubscriber
+            walk_back: load_config_file().sync.walk_back,
         })
         .await
     {
@@ -94,7 +141,38 @@ pub async fn iota_sensor_data(channel_key: &str) -> Result<u32, String> {
         Err(e) => return Err(format!("Unable to Receive Messages: {}", e)),
     };
     for msg in msgs.messages {
-        save_iota_sensor_data(&db_client, &msg, channel.id).await?;
+        info!(
+            "Message: {}, Publisher: {}, Seq: {}, Public Header: {}",
+            msg.link,
+            msg.publisher,
+            msg.seq_num,
+            String::from_utf8_lossy(&msg.public_payload)
+        );
+        let payload = match String::from_utf8(msg.masked_payload) {
+            Ok(r) => r,
+            Err(e) => {
+                error!("Unable to Decode Message {}: {}", msg.link, e);
+                continue;
+            }
+        };
+        save_iota_sensor_data(&db_client, &payload, channel.id, &msg.link).await?;
+    }
+    for gap in msgs.gaps {
+        error!(
+            "Missing Readings: Publisher {} Sequence {} to {}, Recovered: {}",
+            gap.publisher, gap.from_seq, gap.to_seq, gap.recovered
+        );
+        if let Err(e) = db::create_sequence_gap(
+            &db_client,
+            channel.id,
+            &gap.publisher,
+            gap.from_seq as i64,
+            gap.to_seq as i64,
+            gap.recovered as i64,
+            Utc::now().timestamp(),
+        ) {
+            return Err(format!("Unable to Save Sequence Gap: {}", e));
+        }
     }
 
     Ok(0)
@@ -138,6 +216,11 @@ pub async fn mqtt_first_verification(payload: Vec<u8>) -> Result<u32, String> {
     let is_verified = if response.code == 0 { true } else { false };
     let _ = get_identity(&db_client, &msg.did)?;
     update_identity(&db_client, &msg.did, is_verified)?;
+    // Revoked credentials can not be verified again
+    if response.code == CODE_CREDENTIAL_REVOKED {
+        error!("Credential of DID {} Revoked", &msg.did);
+        update_identity_unverifiable(&db_client, &msg.did)?;
+    }
     Ok(0)
 }
 
@@ -179,6 +262,10 @@ pub async fn mqtt_streams(payload: Vec<u8>, channel_key: &str) -> Result<u32, St
         )
         .await?;
     } else if !msg.keyload_link.is_empty() && is_verified {
+        if !is_subscribed_branch(&msg.branches) {
+            info!("Keyload of Branch not Subscribed: {:?}", &msg.branches);
+            return Ok(0);
+        }
         add_keyload(&db_client, &sub_id, &msg.keyload_link, channel_key).await?;
     }
     info!("Streams Message Processed");
@@ -280,6 +367,7 @@ pub fn save_mqtt_sensor_data(
                         msg.timestamp,
                         true,
                         false,
+                        None,
                     )?;
                     return Ok(0);
                 }
@@ -309,13 +397,46 @@ async fn save_iota_sensor_data(
     db_client: &diesel::SqliteConnection,
     message: &str,
     channel_id: i32,
+    msg_link: &str,
 ) -> Result<u32, String> {
-    let msg: MessageFromJson = match serde_json::from_str(message) {
+    let msg: IotaMessageFromJson = match serde_json::from_str(message) {
         Ok(r) => r,
         Err(e) => {
             return Err(format!("Unable to Parse JSON to Message: {}", e));
         }
     };
+    match msg {
+        IotaMessageFromJson::Single(msg) => {
+            save_sensor_reading(db_client, msg, channel_id, msg_link)
+        }
+        IotaMessageFromJson::Batch(batch) => {
+            info!("Received Batch of {} Readings", batch.readings.len());
+            for reading in batch.readings {
+                let msg = MessageFromJson {
+                    did: batch.did.clone(),
+                    verifiable_credential: batch.verifiable_credential.clone(),
+                    sensor_id: reading.sensor_id,
+                    sensor_name: reading.sensor_name,
+                    sensor_type: reading.sensor_type,
+                    value: reading.value,
+                    unit: reading.unit,
+                    timestamp: reading.timestamp,
+                };
+                save_sensor_reading(db_client, msg, channel_id, msg_link)?;
+            }
+            Ok(0)
+        }
+    }
+}
+
+/// Save or verify a single sensor reading received over IOTA Streams,
+/// msg_link: Link of message reading was received with
+fn save_sensor_reading(
+    db_client: &diesel::SqliteConnection,
+    msg: MessageFromJson,
+    channel_id: i32,
+    msg_link: &str,
+) -> Result<u32, String> {
     match db::create_sensor_type(&db_client, &msg.sensor_type, &msg.unit) {
         Ok(_) => info!("Sensor Type Entry Created for Sensor: {}", &msg.sensor_type),
         Err(e) => {
@@ -371,6 +492,7 @@ async fn save_iota_sensor_data(
                     msg.timestamp,
                     false,
                     true,
+                    Some(msg_link),
                 )?;
                 return Ok(0);
             }
@@ -378,6 +500,9 @@ async fn save_iota_sensor_data(
     for data in data_lst {
         let id = data.id;
         if compare_iota_to_db(data, msg.clone(), sensor_type.clone(), sensor.clone()) {
+            if let Err(e) = db::update_sensor_msg_link(&db_client, id, msg_link) {
+                error!("Unable to Save Message Link of Data Entry: {}", e);
+            }
             match db::update_sensor_entry(&db_client, id, "verified", true) {
                 Ok(_) => {
                     info!("Data Entry Verified");
@@ -453,6 +578,7 @@ fn make_sensor_data_entry(
     time: i64,
     is_mqtt: bool,
     is_iota: bool,
+    msg_link: Option<&str>,
 ) -> Result<u32, String> {
     match db::create_sensor_data(
         db_client,
@@ -463,6 +589,7 @@ fn make_sensor_data_entry(
             mqtt: is_mqtt,
             iota: is_iota,
             verified: false,
+            msg_link: msg_link.map(|r| r.to_string()),
         },
     ) {
         Ok(_) => {
@@ -504,6 +631,11 @@ async fn verify_identity(
                 update_identity(&db_client, &response.did, true)?;
             }
         };
+    } else if response.code == CODE_CREDENTIAL_REVOKED {
+        error!("Credential of DID {} Revoked", &response.did);
+        let _ = get_identity(&db_client, &response.did)?;
+        update_identity(&db_client, &response.did, false)?;
+        update_identity_unverifiable(&db_client, &response.did)?;
     }
     return Ok(0);
 }
@@ -537,9 +669,56 @@ pub async fn mqtt_settings(payload: Vec<u8>) -> Result<u32, String> {
 }
 
 pub async fn mqtt_command(payload: Vec<u8>) -> Result<u32, String> {
-    // Not Implemented
-    info!("--- mqtt_command() --- not Implemented");
-    let _msg = enc::Command::decode(&mut Cursor::new(payload));
+    info!("--- mqtt_command() ---");
+    let msg = match enc::Command::decode(&mut Cursor::new(payload)) {
+        Ok(res) => res,
+        Err(e) => return Err(format!("Unable to Decode Payload: {}", e)),
+    };
+    // Commands are executed by gateways
+    info!(
+        "Command {:?} with ID {} Addressed to Gateway",
+        enc::CommandType::from_i32(msg.cmd_type).unwrap_or(enc::CommandType::Unknown),
+        &msg.command_id
+    );
+    Ok(0)
+}
+/// Verify signature of command result sent by gateway
+pub async fn mqtt_command_result(payload: Vec<u8>) -> Result<u32, String> {
+    info!("--- mqtt_command_result() ---");
+    let msg = match enc::CommandResult::decode(&mut Cursor::new(payload)) {
+        Ok(res) => res,
+        Err(e) => return Err(format!("Unable to Decode Payload: {}", e)),
+    };
+    // Challenge has to match result, otherwise result was altered
+    let challenge = make_command_challenge(&msg.command_id, msg.cmd_type, msg.code, &msg.status);
+    if !challenge.eq(&msg.challenge) {
+        return Err(format!(
+            "Command Result with ID {} Doesn't Match Challenge",
+            &msg.command_id
+        ));
+    }
+    let mut identity_client = connect_identity().await?;
+    let response = match identity_client
+        .verify_identity(tonic::Request::new(IotaIdentityRequest {
+            did: msg.did.clone(),
+            challenge: challenge,
+            verifiable_credential: msg.vc,
+        }))
+        .await
+    {
+        Ok(res) => res.into_inner(),
+        Err(e) => return Err(format!("Unable to Verify Command Result: {}", e)),
+    };
+    if response.code != 0 {
+        return Err(format!(
+            "Unable to Verify Command Result with ID {} from DID: {}",
+            &msg.command_id, &msg.did
+        ));
+    }
+    info!(
+        "Verified Command Result, ID: {}, DID: {}, Code: {}, Status: {}",
+        &msg.command_id, &msg.did, msg.code, &msg.status
+    );
     Ok(0)
 }
 
@@ -552,8 +731,9 @@ pub async fn add_keyload(
     let channel = get_channel(&db_client, &channel_key)?;
     let stream_entry = get_streams(&db_client, channel.id)?;
     match stream_entry.key_link {
+        // Keyload already known, rotated keyloads are added
         Some(r) => {
-            if !r.is_empty() {
+            if r.eq(key_link) {
                 return Ok(0);
             }
         }
@@ -566,6 +746,9 @@ pub async fn add_keyload(
         id: device_id.to_string(),
         msg_type: 4,
         link: key_link.to_string(),
+        branch: "".to_string(),
+        multi_branch: false,
+        seed_nonce: "".to_string(),
     };
     match stream_client
         .receive_keyload(tonic::Request::new(msg))
@@ -608,6 +791,30 @@ pub async fn make_subscriber(
     match get_streams(&db_client, channel.id) {
         Ok(r) => {
             info!("Stream Entry ID: {}", r.id);
+            // Announcement sent again, send subscription link if keyload is missing
+            let key_link = match r.key_link {
+                Some(r) => r,
+                None => "".to_string(),
+            };
+            if key_link.is_empty() {
+                let sub_link = match r.sub_link {
+                    Some(r) => r,
+                    None => "".to_string(),
+                };
+                let public_key = match r.public_key {
+                    Some(r) => r,
+                    None => "".to_string(),
+                };
+                let status = send_sublink(
+                    &mut mqtt_client,
+                    db_client,
+                    &sub_link,
+                    &public_key,
+                    channel_key,
+                )
+                .await?;
+                info!("{}", status);
+            }
             return Ok(0);
         }
         Err(_) => {
@@ -619,6 +826,7 @@ pub async fn make_subscriber(
                     sub_link: "".to_string(),
                     key_link: "".to_string(),
                     msg_link: "".to_string(),
+                    seed_nonce: "".to_string(),
                 },
             ) {
                 Ok(_) => info!("Streams Entry Created for Channel ID: {}", channel.id),
@@ -635,40 +843,37 @@ pub async fn make_subscriber(
         id: device_id.to_string(),
         msg_type: 2,
         link: ann_link.to_string(),
+        branch: "".to_string(),
+        multi_branch: false,
+        seed_nonce: "".to_string(),
     };
-    let sublink = match stream_client
+    let (sublink, public_key) = match stream_client
         .create_new_subscriber(tonic::Request::new(msg))
         .await
     {
         Ok(res) => {
             let response = res.into_inner();
             if response.code == 0 {
-                response.link
+                (response.link, response.public_key)
             } else {
                 return Err("Unable to Create Subscriber".to_string());
             }
         }
         Err(e) => return Err(format!("Unable to Create Subscriber: {}", e)),
     };
-    // Get Thing ID
-    let thing = get_thing(&db_client, &thing_key)?;
-    // Get own DID
-    let identity = get_identification(&db_client, thing.id)?;
-    // Send Sub Link over MQTT
-    let payload = serialize_msg(&enc::Streams {
-        announcement_link: "".to_string(),
-        subscription_link: sublink.clone(),
-        keyload_link: "".to_string(),
-        did: identity.did,
-        vc: match identity.vc {
-            Some(r) => r,
-            None => "".to_string(),
-        },
-    });
-    info!("Send Subscription Link over MQTT");
-    helper_send_mqtt(&mut mqtt_client, payload, TOPIC_STREAM, channel_key).await?;
-    // Save Sub Link
+    // Save Sub Link and Public Key, needed to send subscription again
     update_streams_entry(db_client, &sublink, 0, "subscription", channel.id)?;
+    update_streams_entry(db_client, &public_key, 0, "public_key", channel.id)?;
+    // Send signed Sub Link over MQTT
+    info!("Send Subscription Link over MQTT");
+    send_sublink(
+        &mut mqtt_client,
+        db_client,
+        &sublink,
+        &public_key,
+        channel_key,
+    )
+    .await?;
     Ok(0)
 }
 
@@ -0,0 +1,138 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_json::json;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::time::{interval, Duration};
+
+use crate::config::{load_config_file, Retention};
+use crate::db_module as db;
+use crate::models::SensorData;
+use crate::util::is_factory;
+
+/// Maximum number of sensor entries deleted per transaction
+const DELETE_CHUNK_SIZE: usize = 500;
+
+/// Periodically prune sensor entries according to retention rules
+pub async fn retention_worker() {
+    info!("--- retention_worker() ---");
+    let cfg = load_config_file().retention;
+    if cfg.interval_ms == 0 {
+        info!("Retention Disabled");
+        return;
+    }
+    let mut timer = interval(Duration::from_millis(cfg.interval_ms));
+    loop {
+        timer.tick().await;
+        match prune_sensor_data(&cfg) {
+            Ok(r) => info!("{}", r),
+            Err(e) => error!("{}", e),
+        }
+    }
+}
+
+/// Select sensor entries exceeding max age or max rows per sensor,
+/// archive them if enabled and delete them.
+/// Factory clients also wait for verified entries to be published
+pub fn prune_sensor_data(cfg: &Retention) -> Result<String, String> {
+    info!("--- prune_sensor_data() ---");
+    let db_client = db::establish_connection();
+    let require_published = is_factory();
+    let mut entries: Vec<SensorData> = Vec::new();
+    if cfg.max_age_s > 0 {
+        let timestamp = get_timestamp()? - cfg.max_age_s;
+        match db::select_expired_sensor_entries(
+            &db_client,
+            timestamp,
+            cfg.only_delivered,
+            require_published,
+        ) {
+            Ok(r) => entries.extend(r),
+            Err(_) => return Err("Unable to Select Expired Sensor Entries".to_string()),
+        };
+    }
+    if cfg.max_rows_per_sensor > 0 {
+        let sensor_ids = match db::select_sensor_ids_with_entries(&db_client) {
+            Ok(r) => r,
+            Err(_) => return Err("Unable to Select Sensors with Sensor Entries".to_string()),
+        };
+        for sensor_id in sensor_ids {
+            match db::select_excess_sensor_entries(
+                &db_client,
+                sensor_id,
+                cfg.max_rows_per_sensor,
+                cfg.only_delivered,
+                require_published,
+            ) {
+                Ok(r) => entries.extend(r),
+                Err(_) => {
+                    return Err(format!(
+                        "Unable to Select Excess Sensor Entries for Sensor: {}",
+                        sensor_id
+                    ))
+                }
+            };
+        }
+    }
+    // Entries can exceed both rules
+    entries.sort_by_key(|r| r.id);
+    entries.dedup_by_key(|r| r.id);
+    if entries.is_empty() {
+        return Ok("No Sensor Entries to Prune".to_string());
+    }
+    if cfg.archive {
+        archive_sensor_data(&cfg.archive_dir, &entries)?;
+    }
+    let entry_ids: Vec<i32> = entries.iter().map(|r| r.id).collect();
+    let mut pruned = 0;
+    for chunk in entry_ids.chunks(DELETE_CHUNK_SIZE) {
+        match db::delete_sensor_entries(&db_client, &chunk.to_vec()) {
+            Ok(r) => pruned += r,
+            Err(_) => return Err(format!("Unable to Delete Sensor Entries: {:?}", chunk)),
+        };
+    }
+    Ok(format!("Pruned {} Sensor Entries", pruned))
+}
+
+/// Write sensor entries as gzip compressed JSON lines to archive directory
+fn archive_sensor_data(archive_dir: &str, entries: &Vec<SensorData>) -> Result<(), String> {
+    let dir = Path::new(archive_dir);
+    if let Err(e) = fs::create_dir_all(dir) {
+        return Err(format!("Unable to Create Archive Directory: {}", e));
+    }
+    let path = dir.join(format!("sensor_data_{}.jsonl.gz", get_timestamp()?));
+    let file = match File::create(&path) {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to Create Archive File: {}", e)),
+    };
+    let mut encoder = GzEncoder::new(file, Compression::default());
+    for entry in entries {
+        let line = json!({
+            "id": entry.id,
+            "sensor_id": entry.sensor_id,
+            "sensor_value": entry.sensor_value,
+            "sensor_time": entry.sensor_time,
+            "mqtt": entry.mqtt,
+            "iota": entry.iota,
+            "verified": entry.verified,
+            "msg_link": entry.msg_link,
+        });
+        if let Err(e) = writeln!(encoder, "{}", line) {
+            return Err(format!("Unable to Write Archive File: {}", e));
+        }
+    }
+    match encoder.finish() {
+        Ok(_) => info!("Archived {} Sensor Entries to {:?}", entries.len(), path),
+        Err(e) => return Err(format!("Unable to Write Archive File: {}", e)),
+    };
+    Ok(())
+}
+
+fn get_timestamp() -> Result<i64, String> {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(r) => Ok(r.as_secs() as i64),
+        Err(e) => Err(format!("Unable to Get Timestamp: {}", e)),
+    }
+}
@@ -41,6 +41,7 @@ table! {
         mqtt -> Nullable<Bool>,
         iota -> Nullable<Bool>,
         verified -> Nullable<Bool>,
+        msg_link -> Nullable<Text>,
     }
 }
 
@@ -62,6 +63,18 @@ table! {
     }
 }
 
+table! {
+    sequence_gaps (id) {
+        id -> Integer,
+        channel_id -> Integer,
+        publisher -> Text,
+        from_seq -> BigInt,
+        to_seq -> BigInt,
+        recovered -> BigInt,
+        detected_at -> BigInt,
+    }
+}
+
 table! {
     streams (id) {
         id -> Integer,
@@ -71,6 +84,8 @@ table! {
         key_link -> Nullable<Text>,
         msg_link -> Nullable<Text>,
         num_subs -> Nullable<Integer>,
+        public_key -> Nullable<Text>,
+        seed_nonce -> Text,
     }
 }
 
@@ -86,6 +101,7 @@ joinable!(config -> things (thing_id));
 joinable!(identification -> things (thing_id));
 joinable!(sensor_data -> sensors (sensor_id));
 joinable!(sensors -> channels (channel_id));
+joinable!(sequence_gaps -> channels (channel_id));
 joinable!(sensors -> sensor_types (sensor_types_id));
 joinable!(streams -> channels (channel_id));
 
@@ -97,6 +113,7 @@ allow_tables_to_appear_in_same_query!(
     sensor_data,
     sensor_types,
     sensors,
+    sequence_gaps,
     streams,
     things,
 );
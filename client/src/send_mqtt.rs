@@ -1,4 +1,3 @@
-use chrono::{DateTime, NaiveDateTime, Utc};
 use serde_json::json;
 use std::env;
 
@@ -11,6 +10,10 @@ use crate::util::{
     connect_streams, get_channel, get_identification, get_thing, update_streams_entry,
 };
 
+/// Version of public header and payload format of tangle messages, same as gateway:
+/// timestamps are Unix timestamps in s
+const PAYLOAD_SCHEMA_VERSION: u32 = 1;
+
 pub async fn send_sensor_data(channel_key: &str, sensor_id: &str) -> Result<String, String> {
     info!("--- send_sensor_data() ---");
     let author_id = env::var(ENV_DEVICE_ID).expect("ENV for Author ID not Found");
@@ -70,13 +73,27 @@ pub async fn send_sensor_data(channel_key: &str, sensor_id: &str) -> Result<Stri
             "sensor_type": sensor_type.description,
             "value": val.sensor_value,
             "unit": sensor_type.unit,
-            "timestamp": unix_to_utc(val.sensor_time).to_string(),
+            "timestamp": val.sensor_time,
+        })
+        .to_string();
+        // Make Public Header, readable without Keyload
+        let public_payload = json!({
+            "schema_version": PAYLOAD_SCHEMA_VERSION,
+            "sensor_types": [sensor_type.description],
+            "timestamp": val.sensor_time,
+            "readings": 1,
         })
         .to_string();
         info!("Send Message to Tangle");
         // Send IOTA Streams Message
-        let response =
-            send_message_to_tangle(&mut stream_client, &msg_link, &payload, &author_id).await?;
+        let response = send_message_to_tangle(
+            &mut stream_client,
+            &msg_link,
+            &public_payload,
+            &payload,
+            &author_id,
+        )
+        .await?;
         if response.link.is_empty() {
             error!("Error: Received Message Link Empty");
         } else {
@@ -93,14 +110,10 @@ pub async fn send_sensor_data(channel_key: &str, sensor_id: &str) -> Result<Stri
     Ok("Exit with Success: send_sensor_data()".to_string())
 }
 
-fn unix_to_utc(timestamp: i64) -> DateTime<Utc> {
-    let naive = NaiveDateTime::from_timestamp_opt(timestamp, 0).unwrap();
-    DateTime::<Utc>::from_utc(naive, Utc)
-}
-
 async fn send_message_to_tangle(
     stream_client: &mut IotaStreamerClient<tonic::transport::Channel>,
     msg_link: &str,
+    public_payload: &str,
     payload: &str,
     author_id: &str,
 ) -> Result<IotaStreamsReply, String> {
@@ -111,6 +124,8 @@ async fn send_message_to_tangle(
             message_link: msg_link.to_string(),
             message_length: payload.len() as u32,
             message: payload.to_string(),
+            branch: "".to_string(),
+            public_message: public_payload.to_string(),
         })
         .await
     {
@@ -5,11 +5,12 @@ use std::fmt::Debug;
 use std::str::FromStr;
 
 use crate::config::{
-    ENV_CHANNELS_KEY, ENV_IS_FACTORY, ENV_SENSOR_KEYS, ENV_THING_KEY, ENV_THING_PWD,
+    ENV_BRANCHES, ENV_CHANNELS_KEY, ENV_IS_FACTORY, ENV_SENSOR_KEYS, ENV_THING_KEY, ENV_THING_PWD,
     IDENTITY_SOCKET, MQTT_SOCKET, STREAMS_SOCKET, TOPIC_STREAM,
 };
 use crate::db_module as db;
 use crate::grpc_identity::iota_identifier_client::IotaIdentifierClient;
+use crate::grpc_identity::IotaIdentityRequest;
 use crate::grpc_mqtt::mqtt_operator_client::MqttOperatorClient;
 use crate::grpc_mqtt::MqttRequest;
 use crate::grpc_streams::iota_streamer_client::IotaStreamerClient;
@@ -52,6 +53,24 @@ pub fn get_sensor_ids() -> Vec<String> {
     keys_string
 }
 
+/// Get Branches of multi branch channels to subscribe to, split string over seperator ';'
+/// Empty for all branches
+pub fn get_branches() -> Vec<String> {
+    let branches = env::var(ENV_BRANCHES).unwrap_or_else(|_| "".to_string());
+    branches
+        .split(';')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect()
+}
+/// Keyload belongs to subscribed branch, keyloads without branch are always accepted
+pub fn is_subscribed_branch(keyload_branches: &Vec<String>) -> bool {
+    let branches = get_branches();
+    branches.is_empty()
+        || keyload_branches.is_empty()
+        || keyload_branches.iter().any(|r| branches.contains(r))
+}
+
 pub fn update_streams_entry(
     db_client: &diesel::SqliteConnection,
     link: &str,
@@ -92,6 +111,7 @@ pub async fn send_sublink(
     mqtt_client: &mut MqttOperatorClient<tonic::transport::Channel>,
     db_client: &diesel::SqliteConnection,
     sub_link: &str,
+    public_key: &str,
     channel_id: &str,
 ) -> Result<String, String> {
     let sub_link = sub_link.to_string();
@@ -101,20 +121,47 @@ pub async fn send_sublink(
     let thing_key = env::var(ENV_THING_KEY).expect("ENV for Thing Key not Found");
     let thing = get_thing(db_client, &thing_key)?;
     let identity = get_identification(&db_client, thing.id)?;
+    // Gateway needs public key to give access to branches
+    let public_key = public_key.to_string();
+    // Sign subscription, gateway only adds subscribers with matching DID
+    let mut identity_client = connect_identity().await?;
+    let response = match identity_client
+        .proof_identity(tonic::Request::new(IotaIdentityRequest {
+            did: identity.did.clone(),
+            challenge: make_subscription_challenge(&identity.did, &sub_link, &public_key),
+            verifiable_credential: match identity.vc {
+                Some(r) => r,
+                None => "".to_string(),
+            },
+        }))
+        .await
+    {
+        Ok(res) => res.into_inner(),
+        Err(e) => return Err(format!("Unable to Sign Subscription: {}", e)),
+    };
     let payload = serialize_msg(&enc::Streams {
         did: identity.did,
         announcement_link: "".to_string(),
         subscription_link: sub_link,
         keyload_link: "".to_string(),
-        vc: match identity.vc {
-            Some(r) => r,
-            None => "".to_string(),
-        },
+        vc: response.verifiable_credential,
+        public_key: public_key,
+        branches: get_branches(),
     });
     helper_send_mqtt(mqtt_client, payload, TOPIC_STREAM, channel_id).await?;
     Ok("Send Subscription Link".to_string())
 }
 
+/// Challenge used to sign subscriptions, binds public key and link to DID
+pub fn make_subscription_challenge(did: &str, sub_link: &str, public_key: &str) -> String {
+    format!("{}:{}:{}", did, sub_link, public_key)
+}
+
+/// Challenge used to sign command results, binds signature to result
+pub fn make_command_challenge(command_id: &str, cmd_type: i32, code: i32, status: &str) -> String {
+    format!("{}:{}:{}:{}", command_id, cmd_type, code, status)
+}
+
 pub fn generate_random_sequence() -> String {
     let rand_string: String = thread_rng()
         .sample_iter(&Alphanumeric)
@@ -0,0 +1,14 @@
+-- Subscriptions per channel, a participant can subscribe to several channels
+CREATE TABLE IF NOT EXISTS subscribers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    channel_id INTEGER NOT NULL,
+    did TEXT NOT NULL,
+    UNIQUE (channel_id, did),
+    FOREIGN KEY (channel_id)
+        REFERENCES channels (id)
+);
+
+-- Existing subscriptions belong to the single channel used so far
+INSERT INTO subscribers (channel_id, did)
+    SELECT (SELECT MIN(id) FROM channels), did FROM identities
+    WHERE subscribed = TRUE AND EXISTS (SELECT 1 FROM channels);
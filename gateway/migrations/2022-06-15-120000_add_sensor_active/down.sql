@@ -0,0 +1,15 @@
+-- This file should undo anything in `up.sql`
+CREATE TABLE sensors_backup (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    channel_id INTEGER NOT NULL,
+    sensor_types_id INTEGER NOT NULL,
+    sensor_id TEXT NOT NULL UNIQUE,
+    sensor_name TEXT,
+    FOREIGN KEY (channel_id)
+        REFERENCES channels (id),
+    FOREIGN KEY (sensor_types_id)
+        REFERENCES sensor_types (id)
+);
+INSERT INTO sensors_backup SELECT id, channel_id, sensor_types_id, sensor_id, sensor_name FROM sensors;
+DROP TABLE sensors;
+ALTER TABLE sensors_backup RENAME TO sensors;
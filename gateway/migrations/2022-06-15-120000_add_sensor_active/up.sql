@@ -0,0 +1,2 @@
+-- Sensors removed from configuration are kept as inactive
+ALTER TABLE sensors ADD COLUMN active BOOLEAN NOT NULL DEFAULT TRUE;
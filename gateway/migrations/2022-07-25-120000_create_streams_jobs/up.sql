@@ -0,0 +1,15 @@
+-- Jobs submitted to the Streams service, finished by the job worker
+-- state: queued, running, done or failed, did: Subscriber of AddSubscriber jobs
+CREATE TABLE IF NOT EXISTS streams_jobs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    job_id TEXT NOT NULL UNIQUE,
+    channel_id INTEGER NOT NULL,
+    msg_type INTEGER NOT NULL,
+    did TEXT NOT NULL DEFAULT '',
+    state TEXT NOT NULL,
+    link TEXT,
+    status TEXT,
+    created BIGINT NOT NULL,
+    FOREIGN KEY (channel_id)
+        REFERENCES channels (id)
+);
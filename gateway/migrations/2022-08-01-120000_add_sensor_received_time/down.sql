@@ -0,0 +1,15 @@
+-- This file should undo anything in `up.sql`
+CREATE TABLE sensor_data_backup (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    sensor_id INTEGER NOT NULL,
+    sensor_value TEXT NOT NULL,
+    sensor_time BIGINT NOT NULL,
+    mqtt BOOLEAN DEFAULT FALSE,
+    iota BOOLEAN DEFAULT FALSE,
+    verified BOOLEAN DEFAULT FALSE,
+    FOREIGN KEY (sensor_id)
+        REFERENCES sensors (id)
+);
+INSERT INTO sensor_data_backup SELECT id, sensor_id, sensor_value, sensor_time, mqtt, iota, verified FROM sensor_data;
+DROP TABLE sensor_data;
+ALTER TABLE sensor_data_backup RENAME TO sensor_data;
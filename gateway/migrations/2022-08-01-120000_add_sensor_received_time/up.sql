@@ -0,0 +1,2 @@
+-- Receive time of gateway, batch window does not depend on sensor clocks
+ALTER TABLE sensor_data ADD COLUMN received_time BIGINT NOT NULL DEFAULT 0;
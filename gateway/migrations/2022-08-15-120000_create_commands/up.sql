@@ -0,0 +1,8 @@
+-- Commands already executed, replayed commands are dropped
+-- issued_at: Unix timestamp in s, entries older than commands max_age_s are deleted
+CREATE TABLE IF NOT EXISTS commands (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    command_id TEXT NOT NULL UNIQUE,
+    did TEXT NOT NULL,
+    issued_at BIGINT NOT NULL
+);
@@ -0,0 +1,17 @@
+-- This file should undo anything in `up.sql`
+CREATE TABLE streams_backup (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    channel_id INTEGER NOT NULL,
+    ann_link TEXT NOT NULL,
+    sub_link TEXT,
+    key_link TEXT,
+    msg_link TEXT,
+    num_subs INTEGER,
+    branch TEXT NOT NULL DEFAULT '',
+    FOREIGN KEY (channel_id)
+        REFERENCES channels (id),
+    UNIQUE (channel_id, branch)
+);
+INSERT INTO streams_backup SELECT id, channel_id, ann_link, sub_link, key_link, msg_link, num_subs, branch FROM streams;
+DROP TABLE streams;
+ALTER TABLE streams_backup RENAME TO streams;
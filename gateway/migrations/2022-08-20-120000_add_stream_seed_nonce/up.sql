@@ -0,0 +1,2 @@
+-- Seed nonce of channel author, needed with announcement link to recover the author
+ALTER TABLE streams ADD COLUMN seed_nonce TEXT NOT NULL DEFAULT '';
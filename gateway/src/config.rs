@@ -1,96 +1,400 @@
-use confy;
-use serde_derive::{Deserialize, Serialize};
-use std::env;
-// MQTT Topics
-pub const TOPIC_DID: &str = "did";
-pub const TOPIC_SENSOR_VALUE: &str = "sensors";
-pub const TOPIC_SETTING: &str = "settings";
-pub const TOPIC_IDENTITY: &str = "identity";
-pub const TOPIC_STREAM: &str = "stream";
-pub const TOPIC_COMMAND: &str = "command";
-// Gateway
-// Number of Subscribers Expected
-pub const MQTT_SOCKET: &str = "0.0.0.0:50054";
-/// Default Streams Service GRPC Socket
-pub const STREAMS_SOCKET: &str = "0.0.0.0:50052";
-/// Default Identity Service GRPC Socket
-pub const IDENTITY_SOCKET: &str = "0.0.0.0:50053";
-pub const ENV_TOTAL_NUM_SUBSCRIBER: &str = "NUM_SUBSCRIBER";
-pub const ENV_DEVICE_NAME: &str = "DEVICE_NAME";
-pub const ENV_DEVICE_TYPE: &str = "DEVICE_TYPE";
-pub const ENV_DEVICE_ID: &str = "DEVICE_ID";
-pub const ENV_CHANNEL_KEY: &str = "CHANNEL_ID";
-pub const ENV_THING_KEY: &str = "THING_NAME";
-pub const ENV_THING_PWD: &str = "THING_PWD";
-/// ENV for GRPC Socket
-const ENV_GRPC_SOCKET: &str = "GATEWAY_GRPC_SOCKET";
-/// Default GRPC Socket
-const DEFAULT_GRPC_SOCKET: &str = "0.0.0.0:50051";
-/// Default for Channel Buffer Size
-pub const DEFAULT_BUFFER_SIZE: usize = 32;
-/// Structure used to parse configuration file
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SensorConfig {
-    pub grpc: Grpc,
-    pub sensors: Sensors,
-}
-/// Socket needed for GRPC server, for example \[::1]:50051
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Grpc {
-    pub socket: String,
-}
-/// Used Sensors
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Sensors {
-    pub list: Vec<Sensor>,
-}
-/// Sensor Information
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Sensor {
-    pub sensor_id: String,
-    pub sensor_name: String,
-    pub type_descr: String,
-    pub unit: String,
-}
-/// Default implementation uses socket at \[::1]:50051, default can be set via ENVs
-/// and Sensor information
-impl Default for SensorConfig {
-    fn default() -> Self {
-        SensorConfig {
-            grpc: Grpc {
-                socket: env::var(ENV_GRPC_SOCKET)
-                    .unwrap_or_else(|_| DEFAULT_GRPC_SOCKET.to_string()),
-            },
-            sensors: Sensors {
-                list: vec![
-                    Sensor {
-                        sensor_id: "12".to_string(),
-                        sensor_name: "MPC 7812".to_string(),
-                        type_descr: "Temperature".to_string(),
-                        unit: "C".to_string(),
-                    },
-                    Sensor {
-                        sensor_id: "5".to_string(),
-                        sensor_name: "DHT 15".to_string(),
-                        type_descr: "Humidity".to_string(),
-                        unit: "%".to_string(),
-                    },
-                ],
-            },
-        }
-    }
-}
-/// Configuration file "streams-grpc.toml" is located at ./config/
-/// Function tries to load configuration or creates default
-pub fn load_config_file() -> SensorConfig {
-    let config_dir = env::current_dir()
-        .unwrap()
-        .join("config")
-        .join("gateway-grpc.toml");
-    let res = confy::load_path(config_dir);
-    let cfg: SensorConfig = match res {
-        Ok(r) => r,
-        Err(_e) => SensorConfig::default(),
-    };
-    cfg
-}
+use confy;
+use serde_derive::{Deserialize, Serialize};
+use std::env;
+use std::path::PathBuf;
+// MQTT Topics
+pub const TOPIC_DID: &str = "did";
+pub const TOPIC_SENSOR_VALUE: &str = "sensors";
+pub const TOPIC_SETTING: &str = "settings";
+pub const TOPIC_IDENTITY: &str = "identity";
+pub const TOPIC_STREAM: &str = "stream";
+pub const TOPIC_COMMAND: &str = "command";
+pub const TOPIC_COMMAND_RESULT: &str = "command_result";
+// Gateway
+// Number of Subscribers Expected
+pub const MQTT_SOCKET: &str = "0.0.0.0:50054";
+/// Default Streams Service GRPC Socket
+pub const STREAMS_SOCKET: &str = "0.0.0.0:50052";
+/// Default Identity Service GRPC Socket
+pub const IDENTITY_SOCKET: &str = "0.0.0.0:50053";
+/// Code of Identity Service Verification if Credential Signature is Invalid
+pub const CODE_NOT_VERIFIED: i32 = -1;
+/// Code of Identity Service Verification if Credential is Revoked
+pub const CODE_CREDENTIAL_REVOKED: i32 = -3;
+pub const ENV_TOTAL_NUM_SUBSCRIBER: &str = "NUM_SUBSCRIBER";
+pub const ENV_DEVICE_NAME: &str = "DEVICE_NAME";
+pub const ENV_DEVICE_TYPE: &str = "DEVICE_TYPE";
+pub const ENV_DEVICE_ID: &str = "DEVICE_ID";
+pub const ENV_CHANNEL_KEY: &str = "CHANNEL_ID";
+pub const ENV_THING_KEY: &str = "THING_NAME";
+pub const ENV_THING_PWD: &str = "THING_PWD";
+/// ENV for GRPC Socket
+const ENV_GRPC_SOCKET: &str = "GATEWAY_GRPC_SOCKET";
+/// Default GRPC Socket
+const DEFAULT_GRPC_SOCKET: &str = "0.0.0.0:50051";
+/// ENV for Query GRPC Socket
+const ENV_QUERY_SOCKET: &str = "GATEWAY_QUERY_SOCKET";
+/// Default Query GRPC Socket
+const DEFAULT_QUERY_SOCKET: &str = "127.0.0.1:50055";
+/// Default for Channel Buffer Size
+pub const DEFAULT_BUFFER_SIZE: usize = 32;
+/// ENV for Fallback Polling Interval of Gateway Workers
+const ENV_POLL_INTERVAL: &str = "GATEWAY_POLL_INTERVAL_MS";
+/// Default Fallback Polling Interval in ms
+const DEFAULT_POLL_INTERVAL: u64 = 10000;
+/// ENV for Identity Verification Interval
+const ENV_IDENTITY_INTERVAL: &str = "GATEWAY_IDENTITY_INTERVAL_MS";
+/// Default Identity Verification Interval in ms
+const DEFAULT_IDENTITY_INTERVAL: u64 = 30000;
+/// ENV for Configuration File Check Interval
+const ENV_CONFIG_INTERVAL: &str = "GATEWAY_CONFIG_INTERVAL_MS";
+/// Default Configuration File Check Interval in ms
+const DEFAULT_CONFIG_INTERVAL: u64 = 5000;
+/// ENV for Check Interval of Jobs Submitted to Streams Service
+const ENV_JOB_INTERVAL: &str = "GATEWAY_JOB_INTERVAL_MS";
+/// Default Check Interval of Streams Jobs in ms
+const DEFAULT_JOB_INTERVAL: u64 = 2000;
+/// ENV for Maximum Number of Sensor Entries per Tangle Message
+const ENV_BATCH_MAX_ROWS: &str = "GATEWAY_BATCH_MAX_ROWS";
+/// Default Maximum Number of Sensor Entries per Tangle Message
+const DEFAULT_BATCH_MAX_ROWS: i64 = 10;
+/// ENV for Time Window to Collect Sensor Entries for Tangle Message
+const ENV_BATCH_WINDOW: &str = "GATEWAY_BATCH_WINDOW_MS";
+/// Default Time Window in ms
+const DEFAULT_BATCH_WINDOW: i64 = 5000;
+/// ENV for Retention Check Interval, 0 disables pruning
+const ENV_RETENTION_INTERVAL: &str = "GATEWAY_RETENTION_INTERVAL_MS";
+/// Default Retention Check Interval in ms, pruning is opt-in
+const DEFAULT_RETENTION_INTERVAL: u64 = 0;
+/// ENV for Maximum Age of Sensor Entries, 0 disables age limit
+const ENV_RETENTION_MAX_AGE: &str = "GATEWAY_RETENTION_MAX_AGE_S";
+/// Default Maximum Age of Sensor Entries in s (7 days)
+const DEFAULT_RETENTION_MAX_AGE: i64 = 604800;
+/// ENV for Maximum Number of Sensor Entries per Sensor, 0 disables row limit
+const ENV_RETENTION_MAX_ROWS: &str = "GATEWAY_RETENTION_MAX_ROWS_PER_SENSOR";
+/// Default Maximum Number of Sensor Entries per Sensor
+const DEFAULT_RETENTION_MAX_ROWS: i64 = 0;
+/// ENV for Archiving Pruned Sensor Entries ("true", "false")
+const ENV_RETENTION_ARCHIVE: &str = "GATEWAY_RETENTION_ARCHIVE";
+/// Default Directory for Archived Sensor Entries
+const DEFAULT_ARCHIVE_DIR: &str = "archive";
+/// ENV for Maximum Age of Commands, older or replayed commands are dropped
+const ENV_COMMAND_MAX_AGE: &str = "GATEWAY_COMMAND_MAX_AGE_S";
+/// Default Maximum Age of Commands in s
+const DEFAULT_COMMAND_MAX_AGE: i64 = 300;
+/// Structure used to parse configuration file
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SensorConfig {
+    pub grpc: Grpc,
+    #[serde(default)]
+    pub query: Query,
+    pub sensors: Sensors,
+    #[serde(default)]
+    pub channels: Channels,
+    #[serde(default)]
+    pub intervals: Intervals,
+    #[serde(default)]
+    pub batch: Batch,
+    #[serde(default)]
+    pub retention: Retention,
+    #[serde(default)]
+    pub commands: Commands,
+}
+/// Socket needed for GRPC server, for example \[::1]:50051
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Grpc {
+    pub socket: String,
+}
+/// Socket for local query GRPC server, for example 0.0.0.0:50055
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Query {
+    pub socket: String,
+}
+/// Default query socket, default can be set via ENV
+impl Default for Query {
+    fn default() -> Self {
+        Query {
+            socket: env::var(ENV_QUERY_SOCKET).unwrap_or_else(|_| DEFAULT_QUERY_SOCKET.to_string()),
+        }
+    }
+}
+/// Used Sensors
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Sensors {
+    pub list: Vec<Sensor>,
+}
+/// Sensor Information
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Sensor {
+    pub sensor_id: String,
+    pub sensor_name: String,
+    pub type_descr: String,
+    pub unit: String,
+}
+/// Channels fed by gateway, if empty a single channel is configured via ENVs
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct Channels {
+    pub list: Vec<ChannelConfig>,
+}
+/// Channel Information: Each channel has its own IOTA Streams author and keyload,
+/// sensors lists the IDs of the sensors publishing to this channel.
+/// Multi branch channels have one branch and keyload per group or per sensor not in a group
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChannelConfig {
+    pub channel_key: String,
+    pub author_id: String,
+    pub num_subscriber: i32,
+    pub sensors: Vec<String>,
+    #[serde(default)]
+    pub multi_branch: bool,
+    #[serde(default)]
+    pub groups: Vec<BranchGroup>,
+}
+/// Group of sensors sharing a branch, name is used as branch identity
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BranchGroup {
+    pub name: String,
+    pub sensors: Vec<String>,
+}
+
+impl ChannelConfig {
+    /// Branch of sensor, empty for single branch channels
+    pub fn get_branch(&self, sensor_id: &str) -> String {
+        if !self.multi_branch {
+            return "".to_string();
+        }
+        match self
+            .groups
+            .iter()
+            .find(|r| r.sensors.iter().any(|s| s.eq(sensor_id)))
+        {
+            Some(r) => r.name.clone(),
+            None => sensor_id.to_string(),
+        }
+    }
+    /// Branches of channel, single branch channels have branch ""
+    pub fn get_branches(&self) -> Vec<String> {
+        let mut branches: Vec<String> = Vec::new();
+        for sensor_id in &self.sensors {
+            let branch = self.get_branch(sensor_id);
+            if !branches.contains(&branch) {
+                branches.push(branch);
+            }
+        }
+        if branches.is_empty() {
+            branches.push("".to_string());
+        }
+        branches
+    }
+    /// Sensors of branch, empty for all sensors of channel
+    pub fn get_branch_sensors(&self, branch: &str) -> Vec<String> {
+        if branch.is_empty() {
+            return Vec::new();
+        }
+        self.sensors
+            .iter()
+            .filter(|r| self.get_branch(r).eq(branch))
+            .cloned()
+            .collect()
+    }
+}
+/// Worker intervals in ms. Workers are woken through channels,
+/// polling is only used as fallback
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Intervals {
+    pub poll_ms: u64,
+    pub identity_ms: u64,
+    #[serde(default = "default_config_interval")]
+    pub config_ms: u64,
+    #[serde(default = "default_job_interval")]
+    pub job_ms: u64,
+}
+/// Default intervals, default can be set via ENVs
+impl Default for Intervals {
+    fn default() -> Self {
+        Intervals {
+            poll_ms: env::var(ENV_POLL_INTERVAL)
+                .ok()
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(DEFAULT_POLL_INTERVAL),
+            identity_ms: env::var(ENV_IDENTITY_INTERVAL)
+                .ok()
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(DEFAULT_IDENTITY_INTERVAL),
+            config_ms: default_config_interval(),
+            job_ms: default_job_interval(),
+        }
+    }
+}
+/// Default configuration file check interval, default can be set via ENV
+fn default_config_interval() -> u64 {
+    env::var(ENV_CONFIG_INTERVAL)
+        .ok()
+        .and_then(|r| r.parse().ok())
+        .unwrap_or(DEFAULT_CONFIG_INTERVAL)
+}
+/// Default check interval of jobs submitted to Streams service, default can be set via ENV
+fn default_job_interval() -> u64 {
+    env::var(ENV_JOB_INTERVAL)
+        .ok()
+        .and_then(|r| r.parse().ok())
+        .unwrap_or(DEFAULT_JOB_INTERVAL)
+}
+/// Batching of sensor entries sent to the tangle. A batch is sent if it holds max_rows
+/// entries or if its oldest entry is older than window_ms
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Batch {
+    pub max_rows: i64,
+    pub window_ms: i64,
+}
+/// Default batch settings, default can be set via ENVs
+impl Default for Batch {
+    fn default() -> Self {
+        Batch {
+            max_rows: env::var(ENV_BATCH_MAX_ROWS)
+                .ok()
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(DEFAULT_BATCH_MAX_ROWS),
+            window_ms: env::var(ENV_BATCH_WINDOW)
+                .ok()
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(DEFAULT_BATCH_WINDOW),
+        }
+    }
+}
+/// Retention rules for sensor entries, pruned entries can be archived
+/// as gzip compressed JSON lines to archive_dir
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Retention {
+    pub interval_ms: u64,
+    pub max_age_s: i64,
+    pub max_rows_per_sensor: i64,
+    pub only_delivered: bool,
+    pub archive: bool,
+    pub archive_dir: String,
+}
+/// Default retention rules, default can be set via ENVs
+impl Default for Retention {
+    fn default() -> Self {
+        Retention {
+            interval_ms: env::var(ENV_RETENTION_INTERVAL)
+                .ok()
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(DEFAULT_RETENTION_INTERVAL),
+            max_age_s: env::var(ENV_RETENTION_MAX_AGE)
+                .ok()
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(DEFAULT_RETENTION_MAX_AGE),
+            max_rows_per_sensor: env::var(ENV_RETENTION_MAX_ROWS)
+                .ok()
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(DEFAULT_RETENTION_MAX_ROWS),
+            only_delivered: true,
+            archive: env::var(ENV_RETENTION_ARCHIVE)
+                .ok()
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(false),
+            archive_dir: DEFAULT_ARCHIVE_DIR.to_string(),
+        }
+    }
+}
+/// Commands issued more than max_age_s ago (or ahead of gateway clock) are dropped,
+/// IDs of executed commands are remembered for max_age_s
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Commands {
+    pub max_age_s: i64,
+}
+/// Default maximum age of commands, default can be set via ENV
+impl Default for Commands {
+    fn default() -> Self {
+        Commands {
+            max_age_s: env::var(ENV_COMMAND_MAX_AGE)
+                .ok()
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(DEFAULT_COMMAND_MAX_AGE),
+        }
+    }
+}
+/// Default implementation uses socket at \[::1]:50051, default can be set via ENVs
+/// and Sensor information
+impl Default for SensorConfig {
+    fn default() -> Self {
+        SensorConfig {
+            grpc: Grpc {
+                socket: env::var(ENV_GRPC_SOCKET)
+                    .unwrap_or_else(|_| DEFAULT_GRPC_SOCKET.to_string()),
+            },
+            query: Query::default(),
+            sensors: Sensors {
+                list: vec![
+                    Sensor {
+                        sensor_id: "12".to_string(),
+                        sensor_name: "MPC 7812".to_string(),
+                        type_descr: "Temperature".to_string(),
+                        unit: "C".to_string(),
+                    },
+                    Sensor {
+                        sensor_id: "5".to_string(),
+                        sensor_name: "DHT 15".to_string(),
+                        type_descr: "Humidity".to_string(),
+                        unit: "%".to_string(),
+                    },
+                ],
+            },
+            channels: Channels::default(),
+            intervals: Intervals::default(),
+            batch: Batch::default(),
+            retention: Retention::default(),
+            commands: Commands::default(),
+        }
+    }
+}
+/// Configuration file "gateway-grpc.toml" is located at ./config/
+pub fn get_config_path() -> PathBuf {
+    env::current_dir()
+        .unwrap()
+        .join("config")
+        .join("gateway-grpc.toml")
+}
+/// Function tries to load configuration or creates default
+pub fn load_config_file() -> SensorConfig {
+    let cfg: SensorConfig = match try_load_config_file() {
+        Ok(r) => r,
+        Err(_e) => SensorConfig::default(),
+    };
+    cfg
+}
+/// Load configuration, returns error instead of default if file is invalid
+pub fn try_load_config_file() -> Result<SensorConfig, String> {
+    match confy::load_path(get_config_path()) {
+        Ok(r) => Ok(r),
+        Err(e) => Err(format!("Unable to Load Configuration File: {}", e)),
+    }
+}
+/// Channels from configuration file, falls back to single channel
+/// configured via ENVs with all sensors
+pub fn get_channels(cfg: &SensorConfig) -> Vec<ChannelConfig> {
+    if !cfg.channels.list.is_empty() {
+        return cfg.channels.list.clone();
+    }
+    vec![ChannelConfig {
+        channel_key: env::var(ENV_CHANNEL_KEY).expect("ENV for Channel Key not Found"),
+        author_id: env::var(ENV_DEVICE_ID).expect("ENV for Author ID not Found"),
+        num_subscriber: env::var(ENV_TOTAL_NUM_SUBSCRIBER)
+            .expect("ENV for Number of Subscribers not Found")
+            .parse()
+            .expect("ENV for Number of Subscribers not a Number"),
+        sensors: cfg
+            .sensors
+            .list
+            .iter()
+            .map(|r| r.sensor_id.clone())
+            .collect(),
+        multi_branch: false,
+        groups: Vec::new(),
+    }]
+}
+/// Load channels from configuration file
+pub fn load_channels() -> Vec<ChannelConfig> {
+    get_channels(&load_config_file())
+}
@@ -0,0 +1,49 @@
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+use tokio::time::{interval, Duration};
+
+use crate::config::{get_config_path, try_load_config_file};
+use crate::db_module as db;
+use crate::prolog::sync_sensor_entries;
+
+/// Watch configuration file and apply added, changed or removed sensors at runtime.
+/// Changes of the channel list need a restart
+pub async fn config_watcher(period: Duration) {
+    info!("--- config_watcher() ---");
+    let path = get_config_path();
+    let mut last_modified = get_modified(&path);
+    let mut timer = interval(period);
+    loop {
+        timer.tick().await;
+        let modified = get_modified(&path);
+        if modified == last_modified {
+            continue;
+        }
+        info!("Configuration File Changed: {:?}", &path);
+        // Invalid files are skipped, file is checked again after next change
+        let cfg = match try_load_config_file() {
+            Ok(r) => r,
+            Err(e) => {
+                error!("{}", e);
+                last_modified = modified;
+                continue;
+            }
+        };
+        let db_client = db::establish_connection();
+        match sync_sensor_entries(&db_client, &cfg) {
+            Ok(_) => {
+                info!("Sensor Entries Reloaded");
+                last_modified = modified;
+            }
+            Err(_) => error!("Unable to Reload Sensor Entries, Retry with Next Check"),
+        };
+    }
+}
+
+fn get_modified(path: &Path) -> Option<SystemTime> {
+    match fs::metadata(path) {
+        Ok(r) => r.modified().ok(),
+        Err(_) => None,
+    }
+}
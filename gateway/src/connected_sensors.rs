@@ -1,22 +1,52 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::mpsc;
 
 use crate::db_module as db;
+use crate::state_machine::{wake, Event};
 use sensor_grpc_adapter as adapter;
 
+/// Command for a sensor, delivered with the reply to the next sensor data
+#[derive(Debug)]
+pub struct SensorCommand {
+    pub sensor_id: String,
+    pub command: String,
+    pub payload: String,
+}
+
 pub async fn receive_sensor_data(
     mut rx: mpsc::Receiver<sensor_grpc_adapter::ServerSensorChannel>,
+    data_tx: mpsc::Sender<Event>,
+    mut cmd_rx: mpsc::Receiver<SensorCommand>,
 ) -> Result<(), String> {
     info!("--- receive_sensor_data() ---");
+    // Commands waiting for delivery, Key: Sensor ID
+    let mut pending: HashMap<String, VecDeque<SensorCommand>> = HashMap::new();
     loop {
         // Wait for Sensor Data from Sensor GRPC Service
         match rx.recv().await {
             Some(msg) => {
+                while let Ok(cmd) = cmd_rx.try_recv() {
+                    pending
+                        .entry(cmd.sensor_id.clone())
+                        .or_insert_with(VecDeque::new)
+                        .push_back(cmd);
+                }
                 // Connect to Database
                 let db_client = db::establish_connection();
                 info!("Sensor Data: {:?}", msg.data);
                 // println!("{}", "-".repeat(20));
                 // Retrieve Sensor ID (Sensor Table ID not Unique Sensor Identificator) from DB
                 let _ = match db::select_sensor_by_name(&db_client, &msg.data.sensor_id) {
+                    // Sensors removed from configuration are rejected
+                    Ok(sensor) if !sensor.active => {
+                        error!("Sensor Inactive with ID: {}", &msg.data.sensor_id);
+                        msg.tx.send(adapter::SensorReply {
+                            status: "Sensor Inactive".to_string(),
+                            command: "".to_string(),
+                            payload: "".to_string(),
+                        })
+                    }
                     Ok(sensor) => {
                         info!("Sensor Selected with ID: {}", &msg.data.sensor_id);
                         // If Successful Parse Sensor Data and ...
@@ -27,16 +57,31 @@ pub async fn receive_sensor_data(
                             mqtt: false,
                             iota: false,
                             verified: false,
+                            received_time: get_timestamp(),
                         };
                         // Try making new DB Entry ...
                         match db::create_sensor_data(&db_client, entry) {
                             // On Success Positive GRPC Response
                             Ok(_) => {
                                 info!("Sensor Data Saved to DB");
+                                // Wake Sensor Data Worker
+                                wake(&data_tx, Event::SensorData);
+                                // Deliver Pending Command for Sensor
+                                let (command, payload) =
+                                    match next_command(&mut pending, &msg.data.sensor_id) {
+                                        Some(cmd) => {
+                                            info!(
+                                                "Send Command {} to Sensor: {}",
+                                                &cmd.command, &msg.data.sensor_id
+                                            );
+                                            (cmd.command, cmd.payload)
+                                        }
+                                        None => ("".to_string(), "".to_string()),
+                                    };
                                 msg.tx.send(adapter::SensorReply {
                                     status: "Ok".to_string(),
-                                    command: "".to_string(),
-                                    payload: "".to_string(),
+                                    command: command,
+                                    payload: payload,
                                 })
                             }
                             // On Failure Respond with Error
@@ -64,3 +109,22 @@ pub async fn receive_sensor_data(
         };
     }
 }
+/// Take oldest pending command for sensor
+fn next_command(
+    pending: &mut HashMap<String, VecDeque<SensorCommand>>,
+    sensor_id: &str,
+) -> Option<SensorCommand> {
+    let queue = pending.get_mut(sensor_id)?;
+    let cmd = queue.pop_front();
+    if queue.is_empty() {
+        pending.remove(sensor_id);
+    }
+    cmd
+}
+/// Receive time of sensor data, independent of sensor clock
+fn get_timestamp() -> i64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(r) => r.as_secs() as i64,
+        Err(_) => 0,
+    }
+}
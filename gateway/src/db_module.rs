@@ -7,8 +7,8 @@ use dotenv::dotenv;
 use std::env;
 
 use schema::{
-    channels, config, identification, identities, sensor_data, sensor_types, sensors, streams,
-    things,
+    channels, commands, config, identification, identities, sensor_data, sensor_types, sensors,
+    streams, streams_jobs, subscribers, things,
 };
 
 /// Connect to Database
@@ -58,6 +58,65 @@ pub fn select_sensor_by_name(
     };
     Ok(entry)
 }
+/// Select all Sensors
+pub fn select_sensors(conn: &SqliteConnection) -> Result<Vec<models::Sensor>, i32> {
+    use self::sensors::dsl::*;
+    let entries = match sensors.order(id.asc()).load::<models::Sensor>(conn) {
+        Ok(r) => r,
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+    Ok(entries)
+}
+/// Select all Sensor Types
+pub fn select_sensor_types(conn: &SqliteConnection) -> Result<Vec<models::SensorType>, i32> {
+    use self::sensor_types::dsl::*;
+    let entries = match sensor_types
+        .order(id.asc())
+        .load::<models::SensorType>(conn)
+    {
+        Ok(r) => r,
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+    Ok(entries)
+}
+/// Select Page of Sensor Entries of a Sensor in time range, ordered by ID.
+/// to_time = 0 disables upper time limit
+pub fn select_sensor_entries_page(
+    conn: &SqliteConnection,
+    sensor_identifier: i32,
+    from_time: i64,
+    to_time: i64,
+    after_id: i32,
+    page_size: i64,
+) -> Result<Vec<models::SensorData>, i32> {
+    use self::sensor_data::dsl::*;
+    let mut query = sensor_data
+        .filter(sensor_id.eq(sensor_identifier))
+        .filter(sensor_time.ge(from_time))
+        .filter(id.gt(after_id))
+        .into_boxed();
+    if to_time > 0 {
+        query = query.filter(sensor_time.le(to_time));
+    }
+    let entries = match query
+        .order(id.asc())
+        .limit(page_size)
+        .load::<models::SensorData>(conn)
+    {
+        Ok(r) => r,
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+    Ok(entries)
+}
 /// Select Sensor Entry
 pub fn select_sensor_entry(
     conn: &SqliteConnection,
@@ -69,18 +128,22 @@ pub fn select_sensor_entry(
     let query = match query {
         "mqtt" => sensor_data
             .filter(mqtt.eq(is_true))
+            .order(id.asc())
             .limit(20)
             .get_results::<models::SensorData>(conn),
         "iota" => sensor_data
             .filter(iota.eq(is_true))
+            .order(id.asc())
             .limit(20)
             .get_results::<models::SensorData>(conn),
         "verified" => sensor_data
             .filter(verified.eq(is_true))
+            .order(id.asc())
             .limit(20)
             .get_results::<models::SensorData>(conn),
         "timestamp" => sensor_data
             .filter(sensor_time.gt(timestamp)) // greater then
+            .order(id.asc())
             .limit(20)
             .get_results::<models::SensorData>(conn),
         e => {
@@ -97,6 +160,30 @@ pub fn select_sensor_entry(
     };
     Ok(results)
 }
+/// Select Sensor Entries not Sent via MQTT for Sensors of Channel
+pub fn select_unsent_mqtt_entries(
+    conn: &SqliteConnection,
+    channel_identifier: i32,
+) -> Result<Vec<models::SensorData>, i32> {
+    use self::sensor_data::dsl::*;
+    let channel_sensors = sensors::table
+        .filter(sensors::channel_id.eq(channel_identifier))
+        .select(sensors::id);
+    let results = match sensor_data
+        .filter(mqtt.eq(false))
+        .filter(sensor_id.eq_any(channel_sensors))
+        .order(id.asc())
+        .limit(20)
+        .get_results::<models::SensorData>(conn)
+    {
+        Ok(r) => r,
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+    Ok(results)
+}
 /// Select Thing
 pub fn select_thing(conn: &SqliteConnection, th_key: &str) -> Result<models::Thing, i32> {
     use self::things::dsl::*;
@@ -240,6 +327,209 @@ pub fn update_identity_to_subscribed(
         }
     };
 }
+/// Table Subscribers CRUD
+/// Select Subscription of Identity to Channel
+pub fn select_subscriber(
+    conn: &SqliteConnection,
+    channel_identifier: i32,
+    digital_id: &str,
+) -> Result<models::Subscriber, i32> {
+    use self::subscribers::dsl::*;
+    let entry = match subscribers
+        .filter(channel_id.eq(channel_identifier))
+        .filter(did.eq(digital_id))
+        .limit(1)
+        .get_result::<models::Subscriber>(conn)
+    {
+        Ok(r) => r,
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+    Ok(entry)
+}
+/// Create Subscription of Identity to Channel
+pub fn create_subscriber<'a>(
+    conn: &SqliteConnection,
+    channel_id: i32,
+    did: &'a str,
+) -> Result<usize, i32> {
+    let new_entry = models::NewSubscriber {
+        channel_id: channel_id,
+        did: did,
+    };
+
+    let entry = match diesel::insert_into(subscribers::table)
+        .values(&new_entry)
+        .execute(conn)
+    {
+        Ok(r) => r,
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+    Ok(entry)
+}
+/// Delete Subscription of Identity to Channel
+pub fn delete_subscriber(
+    conn: &SqliteConnection,
+    channel_identifier: i32,
+    digital_id: &str,
+) -> Result<i32, i32> {
+    use self::subscribers::dsl::*;
+    match diesel::delete(
+        subscribers
+            .filter(channel_id.eq(channel_identifier))
+            .filter(did.eq(digital_id)),
+    )
+    .execute(conn)
+    {
+        Ok(r) => {
+            info!("Affected Rows: {}", r);
+            return Ok(r as i32);
+        }
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+}
+/// Table Streams Jobs CRUD
+/// Select Unfinished (queued, running) Jobs of Channel
+pub fn select_pending_streams_jobs(
+    conn: &SqliteConnection,
+    channel_identifier: i32,
+) -> Result<Vec<models::StreamsJob>, i32> {
+    use self::streams_jobs::dsl::*;
+    match streams_jobs
+        .filter(channel_id.eq(channel_identifier))
+        .filter(state.eq_any(vec!["queued", "running"]))
+        .order(id.asc())
+        .load::<models::StreamsJob>(conn)
+    {
+        Ok(r) => return Ok(r),
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+}
+/// Select Unfinished Job of Channel by Message Type and DID
+pub fn select_pending_streams_job(
+    conn: &SqliteConnection,
+    channel_identifier: i32,
+    job_msg_type: i32,
+    digital_id: &str,
+) -> Result<models::StreamsJob, i32> {
+    use self::streams_jobs::dsl::*;
+    match streams_jobs
+        .filter(channel_id.eq(channel_identifier))
+        .filter(msg_type.eq(job_msg_type))
+        .filter(did.eq(digital_id))
+        .filter(state.eq_any(vec!["queued", "running"]))
+        .limit(1)
+        .get_result::<models::StreamsJob>(conn)
+    {
+        Ok(r) => return Ok(r),
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+}
+/// Create Job Submitted to Streams Service
+pub fn create_streams_job<'a>(
+    conn: &SqliteConnection,
+    job_id: &'a str,
+    channel_id: i32,
+    msg_type: i32,
+    did: &'a str,
+    created: i64,
+) -> Result<usize, i32> {
+    let new_entry = models::NewStreamsJob {
+        job_id: job_id,
+        channel_id: channel_id,
+        msg_type: msg_type,
+        did: did,
+        state: "queued",
+        created: created,
+    };
+    match diesel::insert_into(streams_jobs::table)
+        .values(&new_entry)
+        .execute(conn)
+    {
+        Ok(r) => return Ok(r),
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+}
+/// Update State, Link and Status of Job
+pub fn update_streams_job(
+    conn: &SqliteConnection,
+    identifier: &str,
+    job_state: &str,
+    job_link: &str,
+    job_status: &str,
+) -> Result<i32, i32> {
+    use self::streams_jobs::dsl::*;
+    match diesel::update(streams_jobs)
+        .filter(job_id.eq(identifier))
+        .set((
+            state.eq(job_state),
+            link.eq(job_link),
+            status.eq(job_status),
+        ))
+        .execute(conn)
+    {
+        Ok(r) => {
+            info!("Affected Rows: {}", r);
+            return Ok(r as i32);
+        }
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+}
+/// Table Commands CRUD
+/// Create Executed Command, fails if command ID was already executed
+pub fn create_command<'a>(
+    conn: &SqliteConnection,
+    command_id: &'a str,
+    did: &'a str,
+    issued_at: i64,
+) -> Result<usize, i32> {
+    let new_entry = models::NewCommand {
+        command_id: command_id,
+        did: did,
+        issued_at: issued_at,
+    };
+    match diesel::insert_into(commands::table)
+        .values(&new_entry)
+        .execute(conn)
+    {
+        Ok(r) => return Ok(r),
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+}
+/// Delete Commands Issued before Timestamp
+pub fn delete_expired_commands(conn: &SqliteConnection, timestamp: i64) -> Result<i32, i32> {
+    use self::commands::dsl::*;
+    match diesel::delete(commands.filter(issued_at.lt(timestamp))).execute(conn) {
+        Ok(r) => return Ok(r as i32),
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+}
 /// Select Identity
 pub fn select_identity(conn: &SqliteConnection, digital_id: &str) -> Result<models::Identity, i32> {
     use self::identities::dsl::*;
@@ -275,6 +565,28 @@ pub fn select_identities(
     };
     Ok(results)
 }
+/// Select Identities Subscribed to Channel and Marked as Unverifiable
+pub fn select_unverifiable_subscribers(
+    conn: &SqliteConnection,
+    channel_identifier: i32,
+) -> Result<Vec<models::Identity>, i32> {
+    use self::identities::dsl::*;
+    let subscribed_dids = subscribers::table
+        .filter(subscribers::channel_id.eq(channel_identifier))
+        .select(subscribers::did);
+    let results = match identities
+        .filter(unverifiable.eq(true))
+        .filter(did.eq_any(subscribed_dids))
+        .get_results::<models::Identity>(conn)
+    {
+        Ok(r) => r,
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+    Ok(results)
+}
 /// Select Identification
 pub fn select_identification(
     conn: &SqliteConnection,
@@ -321,14 +633,23 @@ pub fn create_identification<'a>(
     };
     Ok(entry)
 }
-/// Select Stream
+/// Select Stream, Entry of Channel without Branch
 pub fn select_stream(
     conn: &SqliteConnection,
     channel_identifier: i32,
+) -> Result<models::Stream, i32> {
+    select_branch_stream(conn, channel_identifier, "")
+}
+/// Select Stream of Branch
+pub fn select_branch_stream(
+    conn: &SqliteConnection,
+    channel_identifier: i32,
+    branch_name: &str,
 ) -> Result<models::Stream, i32> {
     use self::streams::dsl::*;
     let entry = match streams
         .filter(channel_id.eq(channel_identifier))
+        .filter(branch.eq(branch_name))
         .limit(1)
         .get_result::<models::Stream>(conn)
     {
@@ -348,6 +669,8 @@ pub struct StreamsEntry {
     pub sub_link: String,
     pub key_link: String,
     pub msg_link: String,
+    pub branch: String,
+    pub seed_nonce: String,
 }
 /// Create Stream
 pub fn create_stream<'a>(conn: &SqliteConnection, entry: StreamsEntry) -> Result<usize, i32> {
@@ -358,6 +681,8 @@ pub fn create_stream<'a>(conn: &SqliteConnection, entry: StreamsEntry) -> Result
         key_link: &entry.key_link,
         msg_link: &entry.msg_link,
         num_subs: 0,
+        branch: &entry.branch,
+        seed_nonce: &entry.seed_nonce,
     };
 
     let entry = match diesel::insert_into(streams::table)
@@ -451,29 +776,35 @@ pub fn create_configuration(
     };
     Ok(entry)
 }
-/// Update Stream
+/// Update Stream, Entry of Channel without Branch
 pub fn update_stream(
     conn: &SqliteConnection,
     channel_identifier: i32,
     query: &str,
     link: &str,
     num_subscribers: i32,
+) -> Result<i32, i32> {
+    update_branch_stream(conn, channel_identifier, "", query, link, num_subscribers)
+}
+/// Update Stream of Branch
+pub fn update_branch_stream(
+    conn: &SqliteConnection,
+    channel_identifier: i32,
+    branch_name: &str,
+    query: &str,
+    link: &str,
+    num_subscribers: i32,
 ) -> Result<i32, i32> {
     use self::streams::dsl::*;
+    let target = streams
+        .filter(channel_id.eq(channel_identifier))
+        .filter(branch.eq(branch_name));
     let query = match query {
-        "announcement" => diesel::update(streams.filter(channel_id.eq(channel_identifier)))
-            .set(ann_link.eq(link))
-            .execute(conn),
-        "subscription" => diesel::update(streams.filter(channel_id.eq(channel_identifier)))
-            .set(sub_link.eq(link))
-            .execute(conn),
-        "keyload" => diesel::update(streams.filter(channel_id.eq(channel_identifier)))
-            .set(key_link.eq(link))
-            .execute(conn),
-        "msg_link" => diesel::update(streams.filter(channel_id.eq(channel_identifier)))
-            .set(msg_link.eq(link))
-            .execute(conn),
-        "num_subs" => diesel::update(streams.filter(channel_id.eq(channel_identifier)))
+        "announcement" => diesel::update(target).set(ann_link.eq(link)).execute(conn),
+        "subscription" => diesel::update(target).set(sub_link.eq(link)).execute(conn),
+        "keyload" => diesel::update(target).set(key_link.eq(link)).execute(conn),
+        "msg_link" => diesel::update(target).set(msg_link.eq(link)).execute(conn),
+        "num_subs" => diesel::update(target)
             .set(num_subs.eq(num_subscribers))
             .execute(conn),
         _ => {
@@ -526,6 +857,154 @@ pub fn update_sensor_entry(
         }
     };
 }
+/// Select Sensor Entries not Sent to Tangle, Oldest First
+/// Sensor Identifiers restrict Entries to Sensors of a Branch, empty for all Sensors of Channel
+pub fn select_unsent_iota_entries(
+    conn: &SqliteConnection,
+    channel_identifier: i32,
+    sensor_identifiers: &Vec<String>,
+    max_entries: i64,
+) -> Result<Vec<models::SensorData>, i32> {
+    use self::sensor_data::dsl::*;
+    let mut channel_sensors = sensors::table
+        .filter(sensors::channel_id.eq(channel_identifier))
+        .select(sensors::id)
+        .into_boxed();
+    if !sensor_identifiers.is_empty() {
+        channel_sensors = channel_sensors.filter(sensors::sensor_id.eq_any(sensor_identifiers));
+    }
+    let results = match sensor_data
+        .filter(iota.eq(false))
+        .filter(sensor_id.eq_any(channel_sensors))
+        .order(id.asc())
+        .limit(max_entries)
+        .get_results::<models::SensorData>(conn)
+    {
+        Ok(r) => r,
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+    Ok(results)
+}
+/// Mark Sensor Entries as Sent to Tangle, all or none are updated
+pub fn update_sensor_entries_to_iota(
+    conn: &SqliteConnection,
+    identifiers: &Vec<i32>,
+) -> Result<i32, i32> {
+    use self::sensor_data::dsl::*;
+    let result = conn.transaction::<usize, diesel::result::Error, _>(|| {
+        let rows = diesel::update(sensor_data.filter(id.eq_any(identifiers)))
+            .set(iota.eq(true))
+            .execute(conn)?;
+        if rows != identifiers.len() {
+            return Err(diesel::result::Error::RollbackTransaction);
+        }
+        Ok(rows)
+    });
+    match result {
+        Ok(r) => {
+            info!("Affected Rows: {}", r);
+            return Ok(r as i32);
+        }
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+}
+/// Select Sensor Entries received by gateway before timestamp, entries saved without receive time
+/// fall back to sensor time, only delivered (sent via MQTT and IOTA) if only_delivered
+pub fn select_expired_sensor_entries(
+    conn: &SqliteConnection,
+    timestamp: i64,
+    only_delivered: bool,
+) -> Result<Vec<models::SensorData>, i32> {
+    use self::sensor_data::dsl::*;
+    let mut query = sensor_data
+        .filter(
+            received_time
+                .gt(0)
+                .and(received_time.lt(timestamp))
+                .or(received_time.eq(0).and(sensor_time.lt(timestamp))),
+        )
+        .into_boxed();
+    if only_delivered {
+        query = query.filter(mqtt.eq(true)).filter(iota.eq(true));
+    }
+    let result = match query.order(id.asc()).load::<models::SensorData>(conn) {
+        Ok(r) => r,
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+    Ok(result)
+}
+/// Select Sensor Entries of a Sensor exceeding the newest max_rows entries,
+/// only delivered (sent via MQTT and IOTA) if only_delivered
+pub fn select_excess_sensor_entries(
+    conn: &SqliteConnection,
+    sensor_identifier: i32,
+    max_rows: i64,
+    only_delivered: bool,
+) -> Result<Vec<models::SensorData>, i32> {
+    use self::sensor_data::dsl::*;
+    let mut query = sensor_data
+        .filter(sensor_id.eq(sensor_identifier))
+        .into_boxed();
+    if only_delivered {
+        query = query.filter(mqtt.eq(true)).filter(iota.eq(true));
+    }
+    let result = match query
+        .order((sensor_time.desc(), id.desc()))
+        // SQLite needs a limit for offset, -1 means no limit
+        .limit(-1)
+        .offset(max_rows)
+        .load::<models::SensorData>(conn)
+    {
+        Ok(r) => r,
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+    Ok(result)
+}
+/// Select IDs of Sensors with Sensor Entries
+pub fn select_sensor_ids_with_entries(conn: &SqliteConnection) -> Result<Vec<i32>, i32> {
+    use self::sensor_data::dsl::*;
+    let result = match sensor_data.select(sensor_id).distinct().load::<i32>(conn) {
+        Ok(r) => r,
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+    Ok(result)
+}
+/// Delete Sensor Entries, all or none are deleted
+pub fn delete_sensor_entries(conn: &SqliteConnection, identifiers: &Vec<i32>) -> Result<i32, i32> {
+    use self::sensor_data::dsl::*;
+    let result = conn.transaction::<usize, diesel::result::Error, _>(|| {
+        let rows = diesel::delete(sensor_data.filter(id.eq_any(identifiers))).execute(conn)?;
+        if rows != identifiers.len() {
+            return Err(diesel::result::Error::RollbackTransaction);
+        }
+        Ok(rows)
+    });
+    match result {
+        Ok(r) => {
+            info!("Deleted Rows: {}", r);
+            return Ok(r as i32);
+        }
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+}
 /// Select Sensor Type
 pub fn select_sensor_type_by_desc(
     conn: &SqliteConnection,
@@ -620,6 +1099,53 @@ pub fn create_sensor<'a>(conn: &SqliteConnection, entry: SensorEntry) -> Result<
     Ok(entry)
 }
 
+/// Update Sensor Entry to configured channel, type and name, sensor is activated
+pub fn update_sensor(conn: &SqliteConnection, entry: SensorEntry) -> Result<i32, i32> {
+    use self::sensors::dsl::*;
+    match diesel::update(sensors)
+        .filter(sensor_id.eq(&entry.sensor_id))
+        .set((
+            channel_id.eq(entry.channel_id),
+            sensor_types_id.eq(entry.sensor_types_id),
+            sensor_name.eq(&entry.sensor_name),
+            active.eq(true),
+        ))
+        .execute(conn)
+    {
+        Ok(r) => {
+            info!("Affected Rows: {}", r);
+            return Ok(r as i32);
+        }
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+}
+
+/// Mark Sensors not in list as inactive
+pub fn update_sensors_to_inactive(
+    conn: &SqliteConnection,
+    sensor_identifiers: &Vec<String>,
+) -> Result<i32, i32> {
+    use self::sensors::dsl::*;
+    match diesel::update(sensors)
+        .filter(sensor_id.ne_all(sensor_identifiers))
+        .filter(active.eq(true))
+        .set(active.eq(false))
+        .execute(conn)
+    {
+        Ok(r) => {
+            info!("Affected Rows: {}", r);
+            return Ok(r as i32);
+        }
+        Err(e) => {
+            error!("{}", e);
+            return Err(-1);
+        }
+    };
+}
+
 /// Table Sensor Data CRUD
 /// Struct Sensor Data Entry
 pub struct SensorDataEntry {
@@ -629,6 +1155,7 @@ pub struct SensorDataEntry {
     pub mqtt: bool,
     pub iota: bool,
     pub verified: bool,
+    pub received_time: i64,
 }
 /// Create Sensor Data
 pub fn create_sensor_data<'a>(
@@ -642,6 +1169,7 @@ pub fn create_sensor_data<'a>(
         mqtt: entry.mqtt,
         iota: entry.iota,
         verified: entry.verified,
+        received_time: entry.received_time,
     };
 
     let entry = match diesel::insert_into(sensor_data::table)
@@ -8,20 +8,25 @@ extern crate diesel_migrations;
 extern crate dotenv;
 
 mod config;
+mod config_watcher;
 mod connected_sensors;
 mod db_module;
 mod models;
 mod prolog;
+mod query_service;
 mod recv_mqtt;
 mod req_verification;
+mod retention;
 mod schema;
 mod send_mqtt;
 mod state_machine;
+mod streams_jobs;
 mod util;
 
 use db_module as db;
 use sensor_grpc_adapter as adapter;
 use tokio::join;
+use tokio::sync::mpsc;
 use tokio::time::{sleep, Duration};
 
 pub mod grpc_streams {
@@ -36,11 +41,16 @@ pub mod mqtt_encoder {
 pub mod grpc_mqtt {
     tonic::include_proto!("mqtt_grpc");
 }
+pub mod grpc_query {
+    tonic::include_proto!("gateway_query");
+}
 
 use config::{load_config_file, DEFAULT_BUFFER_SIZE};
-use connected_sensors::receive_sensor_data;
+use connected_sensors::{receive_sensor_data, SensorCommand};
 use prolog::init;
-use state_machine::state_machine;
+use query_service::run_query_server;
+use retention::retention_worker;
+use state_machine::{state_machine, Event};
 // Embed SQL in Binary
 embed_migrations!();
 
@@ -51,6 +61,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     init_db();
     let cfg = load_config_file();
     let addr = cfg.grpc.socket.clone();
+    let query_addr = cfg.query.socket.clone();
     info!("Initialize Gateway");
     while !(match init().await {
         Ok(r) => r,
@@ -61,10 +72,26 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }) {}
     info!("----------------------------- Start Main Program -----------------------------");
     let (service, rx) = adapter::SensorAdapterService::new(DEFAULT_BUFFER_SIZE);
+    // Channel used to wake sensor data worker on new DB entries
+    let (data_tx, data_rx) = mpsc::channel::<Event>(DEFAULT_BUFFER_SIZE);
+    // Channel used to forward MQTT commands to sensors
+    let (cmd_tx, cmd_rx) = mpsc::channel::<SensorCommand>(DEFAULT_BUFFER_SIZE);
     let grpc_server = adapter::run_sensor_adapter_server(service, &addr);
-    let sensor_worker = receive_sensor_data(rx);
-    let gateway_worker = state_machine();
-    let _result = join!(sensor_worker, gateway_worker, grpc_server);
+    let sensor_worker = receive_sensor_data(rx, data_tx.clone(), cmd_rx);
+    let gateway_worker = state_machine(data_tx, data_rx, cmd_tx);
+    // Local query API for stored sensor data and stream state
+    let query_server = async {
+        if let Err(e) = run_query_server(&query_addr).await {
+            error!("{}", e);
+        }
+    };
+    let _result = join!(
+        sensor_worker,
+        gateway_worker,
+        grpc_server,
+        query_server,
+        retention_worker()
+    );
 
     Ok(())
 }
@@ -1,6 +1,6 @@
 use crate::schema::{
-    channels, config, identification, identities, sensor_data, sensor_types, sensors, streams,
-    things,
+    channels, commands, config, identification, identities, sensor_data, sensor_types, sensors,
+    streams, streams_jobs, subscribers, things,
 };
 use diesel::{Insertable, Queryable};
 // Database Models
@@ -30,7 +30,7 @@ pub struct NewChannel<'a> {
     pub channel_key: &'a str,
 }
 
-#[derive(Queryable, Debug)]
+#[derive(Queryable, Debug, Clone)]
 pub struct Identification {
     pub id: i32,
     pub thing_id: i32,
@@ -55,6 +55,8 @@ pub struct Stream {
     pub key_link: Option<String>,
     pub msg_link: Option<String>,
     pub num_subs: Option<i32>,
+    pub branch: String,
+    pub seed_nonce: String,
 }
 
 #[derive(Insertable)]
@@ -66,6 +68,62 @@ pub struct NewStream<'a> {
     pub key_link: &'a str,
     pub msg_link: &'a str,
     pub num_subs: i32,
+    pub branch: &'a str,
+    pub seed_nonce: &'a str,
+}
+
+#[derive(Queryable, Debug)]
+pub struct StreamsJob {
+    pub id: i32,
+    pub job_id: String,
+    pub channel_id: i32,
+    pub msg_type: i32,
+    pub did: String,
+    pub state: String,
+    pub link: Option<String>,
+    pub status: Option<String>,
+    pub created: i64,
+}
+
+#[derive(Insertable)]
+#[table_name = "streams_jobs"]
+pub struct NewStreamsJob<'a> {
+    pub job_id: &'a str,
+    pub channel_id: i32,
+    pub msg_type: i32,
+    pub did: &'a str,
+    pub state: &'a str,
+    pub created: i64,
+}
+
+#[derive(Queryable, Debug)]
+pub struct Command {
+    pub id: i32,
+    pub command_id: String,
+    pub did: String,
+    pub issued_at: i64,
+}
+
+#[derive(Insertable)]
+#[table_name = "commands"]
+pub struct NewCommand<'a> {
+    pub command_id: &'a str,
+    pub did: &'a str,
+    pub issued_at: i64,
+}
+
+#[derive(Queryable, Debug)]
+pub struct Subscriber {
+    pub id: i32,
+    pub channel_id: i32,
+    pub did: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "subscribers"]
+pub struct NewSubscriber<'a> {
+    pub channel_id: i32,
+    pub did: &'a str,
 }
 
 #[derive(Queryable, Debug)]
@@ -121,6 +179,7 @@ pub struct Sensor {
     pub sensor_types_id: i32,
     pub sensor_id: String,
     pub sensor_name: Option<String>,
+    pub active: bool,
 }
 
 #[derive(Insertable)]
@@ -141,6 +200,7 @@ pub struct SensorData {
     pub mqtt: Option<bool>,
     pub iota: Option<bool>,
     pub verified: Option<bool>,
+    pub received_time: i64,
 }
 
 #[derive(Insertable)]
@@ -152,4 +212,5 @@ pub struct NewSensorData<'a> {
     pub mqtt: bool,
     pub iota: bool,
     pub verified: bool,
+    pub received_time: i64,
 }
@@ -3,27 +3,33 @@ use serde_json::json;
 use std::env;
 
 use crate::config::{
-    load_config_file, Sensor, ENV_CHANNEL_KEY, ENV_DEVICE_ID, ENV_DEVICE_NAME, ENV_DEVICE_TYPE,
-    ENV_THING_KEY, IDENTITY_SOCKET, MQTT_SOCKET, STREAMS_SOCKET, TOPIC_IDENTITY, TOPIC_SETTING,
-    TOPIC_STREAM,
+    get_channels, load_config_file, ChannelConfig, Sensor, SensorConfig, ENV_DEVICE_ID,
+    ENV_DEVICE_NAME, ENV_DEVICE_TYPE, ENV_THING_KEY, IDENTITY_SOCKET, MQTT_SOCKET, STREAMS_SOCKET,
+    TOPIC_IDENTITY, TOPIC_SETTING,
 };
 use crate::db_module as db;
 use crate::grpc_identity::iota_identifier_client::IotaIdentifierClient;
 use crate::grpc_identity::{IotaIdentityCreationRequest, IotaIdentityRequest};
 use crate::grpc_mqtt::mqtt_operator_client::MqttOperatorClient;
 use crate::grpc_streams::iota_streamer_client::IotaStreamerClient;
-use crate::grpc_streams::IotaStreamsRequest;
+use crate::grpc_streams::{IotaStreamsJobRequest, IotaStreamsRequest};
 use crate::models::Identification;
 use crate::models::{Channel, Thing};
 use crate::mqtt_encoder as enc;
+use crate::streams_jobs::submit_streams_job;
 use crate::util::{generate_random_sequence, send_mqtt_message, serialize_msg};
 
 pub async fn init() -> Result<bool, bool> {
     let cfg = load_config_file();
-    let author_id = env::var(ENV_DEVICE_ID).expect("ENV for Author ID not Found");
-    info!("ENV: {} = {}", ENV_DEVICE_ID, &author_id);
-    let channel_key = env::var(ENV_CHANNEL_KEY).expect("ENV for Channel Key not Found");
-    info!("ENV: {} = {}", ENV_CHANNEL_KEY, &channel_key);
+    let channels = get_channels(&cfg);
+    let channel_keys: Vec<String> = channels.iter().map(|r| r.channel_key.clone()).collect();
+    info!("Channels: {:?}", &channel_keys);
+    // Device ID defaults to author of first channel
+    let device_id = match env::var(ENV_DEVICE_ID) {
+        Ok(r) => r,
+        Err(_) => channels[0].author_id.clone(),
+    };
+    info!("Device ID: {}", &device_id);
     let thing_key = env::var(ENV_THING_KEY).expect("ENV for Thing Key not Found");
     info!("ENV: {} = {}", ENV_THING_KEY, &thing_key);
     let device_name = env::var(ENV_DEVICE_NAME).expect("ENV for Device Name not Found");
@@ -48,23 +54,26 @@ pub async fn init() -> Result<bool, bool> {
     };
     // Get Thing ID
     let thing = get_thing(&db_client, &thing_key)?;
-    // Create Channel Entry
-    match db::create_channel(&db_client, thing.id, &channel_key) {
-        Ok(_) => info!("New Channel Entry Created for Key: {}", &channel_key),
-        Err(_) => error!("Channel Entry Not Created for Key: {}", &channel_key),
-    };
+    // Create Channel Entries
+    for channel_key in &channel_keys {
+        match db::create_channel(&db_client, thing.id, channel_key) {
+            Ok(_) => info!("New Channel Entry Created for Key: {}", channel_key),
+            Err(_) => error!("Channel Entry Not Created for Key: {}", channel_key),
+        };
+    }
     // Identity
     // Get own DID
     info!("Generate and Make Gateway DID Known");
-    let identity = generate_gateway_did(
+    generate_gateway_did(
         &db_client,
         &mut identity_client,
         &mut mqtt_client,
         thing.id,
-        &author_id,
+        &device_id,
         &device_name,
         &device_type,
         cfg.sensors.list.clone(),
+        &channel_keys,
     )
     .await?;
     // Create Config Entry
@@ -76,26 +85,45 @@ pub async fn init() -> Result<bool, bool> {
         pk_timestamp: 0,
         pk: "".to_string(),
     });
-    helper_send_mqtt(&mut mqtt_client, payload, TOPIC_SETTING).await?;
-    // Get Channel ID
-    let channel = get_channel(&db_client, &channel_key)?;
+    for channel_key in &channel_keys {
+        helper_send_mqtt(
+            &mut mqtt_client,
+            payload.clone(),
+            TOPIC_SETTING,
+            channel_key,
+        )
+        .await?;
+    }
     // Create Entries: SensorType, Sensor
     info!("Update Sensor Entries");
-    update_sensor_entries(&db_client, channel.id, cfg.sensors.list)?;
+    sync_sensor_entries(&db_client, &cfg)?;
+    for channel_cfg in channels {
+        init_channel(&db_client, &mut stream_client, &channel_cfg).await?;
+    }
+    info!("Gateway Successful Initialized");
+    Ok(true)
+}
+
+/// Create IOTA Streams author of channel
+async fn init_channel(
+    db_client: &diesel::SqliteConnection,
+    stream_client: &mut IotaStreamerClient<tonic::transport::Channel>,
+    channel_cfg: &ChannelConfig,
+) -> Result<(), bool> {
+    info!("Initialize Channel: {}", &channel_cfg.channel_key);
+    // Get Channel ID
+    let channel = get_channel(&db_client, &channel_cfg.channel_key)?;
     // On Start-Up Check if Entries have been made
     // Initialize Streams Connection
     info!("Generate Channel and Send Announcement Link");
     init_streams(
         &db_client,
-        &mut stream_client,
-        &mut mqtt_client,
-        identity,
+        stream_client,
         channel.id,
-        &author_id,
+        &channel_cfg.author_id,
+        channel_cfg.multi_branch,
     )
-    .await?;
-    info!("Gateway Successful Initialized");
-    Ok(true)
+    .await
 }
 
 async fn connect_mqtt() -> Result<MqttOperatorClient<tonic::transport::Channel>, bool> {
@@ -189,6 +217,7 @@ pub async fn generate_gateway_did(
     device_name: &str,
     device_type: &str,
     sensors: Vec<Sensor>,
+    channel_keys: &Vec<String>,
 ) -> Result<Identification, bool> {
     // Check if DID already exists
     match db::select_identification(&db_client, thing_id) {
@@ -258,7 +287,9 @@ pub async fn generate_gateway_did(
                 vc: response.verifiable_credential,
                 proof: false,
             });
-            helper_send_mqtt(mqtt_client, payload, TOPIC_IDENTITY).await?;
+            for channel_key in channel_keys {
+                helper_send_mqtt(mqtt_client, payload.clone(), TOPIC_IDENTITY, channel_key).await?;
+            }
             return Ok(Identification {
                 id: 0,
                 thing_id: thing_id,
@@ -290,8 +321,9 @@ async fn helper_send_mqtt(
     mqtt_client: &mut MqttOperatorClient<tonic::transport::Channel>,
     payload: Vec<u8>,
     topic: &str,
+    channel_key: &str,
 ) -> Result<(), bool> {
-    match send_mqtt_message(mqtt_client, payload, topic).await {
+    match send_mqtt_message(mqtt_client, payload, topic, channel_key).await {
         Ok(_) => info!("MQTT Message Transmitted to Service for Topic {}", topic),
         Err(e) => {
             error!("Error Sending MQTT Message: {}", e);
@@ -314,6 +346,35 @@ fn get_channel(db_client: &diesel::SqliteConnection, channel_key: &str) -> Resul
     };
 }
 
+/// Apply configured sensors to DB, sensors are assigned to their channel,
+/// sensors not assigned to any channel are marked inactive
+pub fn sync_sensor_entries(
+    db_client: &diesel::SqliteConnection,
+    cfg: &SensorConfig,
+) -> Result<(), bool> {
+    let mut active_sensors = Vec::new();
+    for channel_cfg in get_channels(cfg) {
+        let channel = get_channel(&db_client, &channel_cfg.channel_key)?;
+        let sensors: Vec<Sensor> = cfg
+            .sensors
+            .list
+            .iter()
+            .filter(|r| channel_cfg.sensors.contains(&r.sensor_id))
+            .cloned()
+            .collect();
+        active_sensors.extend(sensors.iter().map(|r| r.sensor_id.clone()));
+        update_sensor_entries(&db_client, channel.id, sensors)?;
+    }
+    match db::update_sensors_to_inactive(&db_client, &active_sensors) {
+        Ok(r) => info!("Sensors Marked as Inactive: {}", r),
+        Err(_) => {
+            error!("Unable to Mark Sensors as Inactive");
+            return Err(false);
+        }
+    };
+    Ok(())
+}
+
 pub fn update_sensor_entries(
     db_client: &diesel::SqliteConnection,
     channel_id: i32,
@@ -353,22 +414,44 @@ pub fn update_sensor_entries(
                 "Sensor Entry Created for ID: {}, Name: {}",
                 &sensor.sensor_id, &sensor.sensor_name
             ),
-            Err(_) => error!(
-                "Error Creating Sensor Entry for ID: {}, Name: {}",
-                &sensor.sensor_id, &sensor.sensor_name
-            ),
+            Err(_) => {
+                info!(
+                    "Sensor Entry Exists for ID: {}, Name: {}",
+                    &sensor.sensor_id, &sensor.sensor_name
+                );
+                // Sensor might have changed or been removed before
+                match db::update_sensor(
+                    &db_client,
+                    db::SensorEntry {
+                        channel_id: channel_id,
+                        sensor_types_id: sensor_type.id,
+                        sensor_id: sensor.sensor_id.clone(),
+                        sensor_name: sensor.sensor_name.clone(),
+                    },
+                ) {
+                    Ok(_) => info!(
+                        "Sensor Entry Updated for ID: {}, Channel ID: {}",
+                        &sensor.sensor_id, channel_id
+                    ),
+                    Err(_) => {
+                        error!("Unable to Update Sensor Entry: {}", &sensor.sensor_id);
+                        return Err(false);
+                    }
+                };
+            }
         };
     }
     Ok(())
 }
 
+/// Submit job creating IOTA Streams author of channel if no stream entry exists,
+/// announcement link is saved and sent by job worker once job is done
 pub async fn init_streams(
     db_client: &diesel::SqliteConnection,
     stream_client: &mut IotaStreamerClient<tonic::transport::Channel>,
-    mqtt_client: &mut MqttOperatorClient<tonic::transport::Channel>,
-    identity: Identification,
     channel_id: i32,
     author_id: &str,
+    multi_branch: bool,
 ) -> Result<(), bool> {
     match db::select_stream(&db_client, channel_id) {
         Ok(res) => info!(
@@ -381,50 +464,40 @@ pub async fn init_streams(
                 "Error Selecting Stream Entry for Channel ID: {}",
                 channel_id
             );
+            // Channel is already being created
+            if db::select_pending_streams_job(&db_client, channel_id, 1, "").is_ok() {
+                info!("Channel Creation Pending for Channel ID: {}", channel_id);
+                return Ok(());
+            }
             info!("Create New Channel with Streams Service");
-            let author = match stream_client
-                .create_new_author(IotaStreamsRequest {
-                    id: author_id.to_string(),
+            match submit_streams_job(
+                db_client,
+                stream_client,
+                IotaStreamsJobRequest {
                     msg_type: 1, // CreateNewAuthor
-                    link: "".to_string(),
-                })
-                .await
+                    request: Some(IotaStreamsRequest {
+                        id: author_id.to_string(),
+                        msg_type: 1, // CreateNewAuthor
+                        link: "".to_string(),
+                        branch: "".to_string(),
+                        multi_branch: multi_branch,
+                        seed_nonce: "".to_string(),
+                    }),
+                    subscriber_request: None,
+                    message_request: None,
+                },
+                channel_id,
+                "",
+            )
+            .await
             {
-                Ok(res) => res.into_inner(),
-                Err(_) => {
+                Ok(job_id) => info!("Create Channel Job Submitted: {}", job_id),
+                Err(e) => {
+                    error!("{}", e);
                     println!("Error Creating New Channel for Author ID: {}", author_id);
                     return Err(false);
                 }
             };
-            info!("Announcement Link: {}", &author.link);
-            match db::create_stream(
-                &db_client,
-                db::StreamsEntry {
-                    channel_id: channel_id,
-                    ann_link: author.link.clone(),
-                    sub_link: "".to_string(),
-                    key_link: "".to_string(),
-                    msg_link: "".to_string(),
-                },
-            ) {
-                Ok(_) => info!("Streams Entry Created for Channel ID: {}", channel_id),
-                Err(_) => error!(
-                    "Error Creating Streams Entry for Channel ID: {}",
-                    channel_id
-                ),
-            }
-            let payload = serialize_msg(&enc::Streams {
-                announcement_link: author.link,
-                subscription_link: "".to_string(),
-                keyload_link: "".to_string(),
-                did: identity.did,
-                vc: match identity.vc {
-                    Some(r) => r,
-                    None => "".to_string(),
-                },
-            });
-            info!("Sending MQTT Message with Announcement Link to Streams Topic");
-            helper_send_mqtt(mqtt_client, payload, TOPIC_STREAM).await?;
         }
     };
     Ok(())
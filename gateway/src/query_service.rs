@@ -0,0 +1,248 @@
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+use crate::config::load_channels;
+use crate::db_module as db;
+use crate::grpc_query::gateway_query_server::{GatewayQuery, GatewayQueryServer};
+use crate::grpc_query::{
+    QueryRequest, SensorDataEntry, SensorDataReply, SensorDataRequest, SensorEntry,
+    SensorTypeEntry, SensorTypesReply, SensorsReply, StreamReply, StreamRequest,
+};
+
+/// Default number of sensor entries per page
+const DEFAULT_PAGE_SIZE: i32 = 100;
+/// Maximum number of sensor entries per page
+const MAX_PAGE_SIZE: i32 = 1000;
+
+/// Structure for Implementing GRPC Calls
+pub struct GatewayQueryService {}
+
+/// Start local query GRPC server
+pub async fn run_query_server(addr: &str) -> Result<(), String> {
+    info!("--- run_query_server() ---");
+    let addr = match addr.parse() {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to Parse Query Socket: {}", e)),
+    };
+    match Server::builder()
+        .add_service(GatewayQueryServer::new(GatewayQueryService {}))
+        .serve(addr)
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Query Server Stopped: {}", e)),
+    }
+}
+
+/// Implementation of GRPC Calls
+/// list_sensors, list_sensor_types, get_sensor_data, get_stream
+#[tonic::async_trait]
+impl GatewayQuery for GatewayQueryService {
+    async fn list_sensors(
+        &self,
+        _request: Request<QueryRequest>,
+    ) -> Result<Response<SensorsReply>, Status> {
+        info!("list_sensors()");
+        let (sensors, status, code) = match list_sensors() {
+            Ok(r) => (r, "Sensors Selected".to_string(), 0),
+            Err(e) => (Vec::new(), e, -1),
+        };
+        Ok(Response::new(SensorsReply {
+            sensors: sensors,
+            status: status,
+            code: code,
+        }))
+    }
+
+    async fn list_sensor_types(
+        &self,
+        _request: Request<QueryRequest>,
+    ) -> Result<Response<SensorTypesReply>, Status> {
+        info!("list_sensor_types()");
+        let db_client = db::establish_connection();
+        let (sensor_types, status, code) = match db::select_sensor_types(&db_client) {
+            Ok(r) => (
+                r.into_iter()
+                    .map(|r| SensorTypeEntry {
+                        description: r.description,
+                        unit: match r.unit {
+                            Some(r) => r,
+                            None => "".to_string(),
+                        },
+                    })
+                    .collect(),
+                "Sensor Types Selected".to_string(),
+                0,
+            ),
+            Err(_) => (Vec::new(), "Unable to Select Sensor Types".to_string(), -1),
+        };
+        Ok(Response::new(SensorTypesReply {
+            sensor_types: sensor_types,
+            status: status,
+            code: code,
+        }))
+    }
+
+    async fn get_sensor_data(
+        &self,
+        request: Request<SensorDataRequest>,
+    ) -> Result<Response<SensorDataReply>, Status> {
+        let request = request.into_inner();
+        info!("get_sensor_data(): Sensor ID: {}", &request.sensor_id);
+        let (entries, status, code) = match get_sensor_data(&request) {
+            Ok(r) => (r, "Sensor Data Selected".to_string(), 0),
+            Err(e) => (Vec::new(), e, -1),
+        };
+        // Full page indicates further entries
+        let next_after_id = match entries.last() {
+            Some(r) if entries.len() as i32 == get_page_size(request.page_size) => r.id,
+            _ => 0,
+        };
+        Ok(Response::new(SensorDataReply {
+            entries: entries,
+            next_after_id: next_after_id,
+            status: status,
+            code: code,
+        }))
+    }
+
+    async fn get_stream(
+        &self,
+        request: Request<StreamRequest>,
+    ) -> Result<Response<StreamReply>, Status> {
+        let request = request.into_inner();
+        let channel_key = if request.channel_key.is_empty() {
+            load_channels()[0].channel_key.clone()
+        } else {
+            request.channel_key
+        };
+        info!("get_stream(): Channel Key: {}", &channel_key);
+        let reply = match get_stream(&channel_key) {
+            Ok(r) => r,
+            Err(e) => StreamReply {
+                channel_key: channel_key,
+                status: e,
+                code: -1,
+                ..Default::default()
+            },
+        };
+        Ok(Response::new(reply))
+    }
+}
+
+fn list_sensors() -> Result<Vec<SensorEntry>, String> {
+    let db_client = db::establish_connection();
+    let sensors = match db::select_sensors(&db_client) {
+        Ok(r) => r,
+        Err(_) => return Err("Unable to Select Sensors".to_string()),
+    };
+    let mut entries = Vec::new();
+    for sensor in sensors {
+        let sensor_type = match db::select_sensor_type_by_id(&db_client, sensor.sensor_types_id) {
+            Ok(r) => r,
+            Err(_) => {
+                return Err(format!(
+                    "Unable to Select Sensor Type with ID: {}",
+                    sensor.sensor_types_id
+                ))
+            }
+        };
+        entries.push(SensorEntry {
+            sensor_id: sensor.sensor_id,
+            sensor_name: match sensor.sensor_name {
+                Some(r) => r,
+                None => "".to_string(),
+            },
+            sensor_type: sensor_type.description,
+            unit: match sensor_type.unit {
+                Some(r) => r,
+                None => "".to_string(),
+            },
+            channel_id: sensor.channel_id,
+            active: sensor.active,
+        });
+    }
+    Ok(entries)
+}
+
+fn get_sensor_data(request: &SensorDataRequest) -> Result<Vec<SensorDataEntry>, String> {
+    let db_client = db::establish_connection();
+    let sensor = match db::select_sensor_by_name(&db_client, &request.sensor_id) {
+        Ok(r) => r,
+        Err(_) => {
+            return Err(format!(
+                "Unable to Select Sensor with ID: {}",
+                &request.sensor_id
+            ))
+        }
+    };
+    let entries = match db::select_sensor_entries_page(
+        &db_client,
+        sensor.id,
+        request.from_time,
+        request.to_time,
+        request.after_id,
+        get_page_size(request.page_size) as i64,
+    ) {
+        Ok(r) => r,
+        Err(_) => return Err("Unable to Select Sensor Data".to_string()),
+    };
+    Ok(entries
+        .into_iter()
+        .map(|r| SensorDataEntry {
+            id: r.id,
+            value: r.sensor_value,
+            timestamp: r.sensor_time,
+            mqtt: r.mqtt.unwrap_or(false),
+            iota: r.iota.unwrap_or(false),
+            verified: r.verified.unwrap_or(false),
+        })
+        .collect())
+}
+
+fn get_stream(channel_key: &str) -> Result<StreamReply, String> {
+    let db_client = db::establish_connection();
+    let channel = match db::select_channel(&db_client, channel_key) {
+        Ok(r) => r,
+        Err(_) => return Err(format!("Unable to Select Channel: {}", channel_key)),
+    };
+    let stream = match db::select_stream(&db_client, channel.id) {
+        Ok(r) => r,
+        Err(_) => {
+            return Err(format!(
+                "Unable to Select Stream Entry with Channel ID: {}",
+                channel.id
+            ))
+        }
+    };
+    Ok(StreamReply {
+        channel_key: channel_key.to_string(),
+        announcement_link: stream.ann_link,
+        subscription_link: match stream.sub_link {
+            Some(r) => r,
+            None => "".to_string(),
+        },
+        keyload_link: match stream.key_link {
+            Some(r) => r,
+            None => "".to_string(),
+        },
+        message_link: match stream.msg_link {
+            Some(r) => r,
+            None => "".to_string(),
+        },
+        num_subs: match stream.num_subs {
+            Some(r) => r,
+            None => 0,
+        },
+        status: "Stream Entry Selected".to_string(),
+        code: 0,
+    })
+}
+
+fn get_page_size(page_size: i32) -> i32 {
+    if page_size <= 0 {
+        DEFAULT_PAGE_SIZE
+    } else {
+        page_size.min(MAX_PAGE_SIZE)
+    }
+}
@@ -5,45 +5,66 @@ use std::env;
 use std::io::Cursor;
 
 use crate::config::{
-    ENV_CHANNEL_KEY, ENV_DEVICE_ID, ENV_THING_KEY, ENV_THING_PWD, ENV_TOTAL_NUM_SUBSCRIBER,
-    TOPIC_COMMAND, TOPIC_DID, TOPIC_IDENTITY, TOPIC_SETTING, TOPIC_STREAM,
+    load_config_file, ChannelConfig, CODE_CREDENTIAL_REVOKED, CODE_NOT_VERIFIED, ENV_THING_KEY,
+    ENV_THING_PWD, TOPIC_COMMAND_RESULT, TOPIC_DID, TOPIC_IDENTITY, TOPIC_SETTING, TOPIC_STREAM,
 };
+use crate::connected_sensors::SensorCommand;
 use crate::db_module as db;
 use crate::grpc_identity::iota_identifier_client::IotaIdentifierClient;
 use crate::grpc_identity::IotaIdentityRequest;
 use crate::grpc_mqtt::mqtt_operator_client::MqttOperatorClient;
-use crate::grpc_mqtt::{MqttMsgsReply, MqttRequest};
+use crate::grpc_mqtt::{MqttMessage, MqttRequest};
 use crate::grpc_streams::iota_streamer_client::IotaStreamerClient;
-use crate::grpc_streams::IotaStreamsRequest;
-use crate::models::Identity;
+use crate::grpc_streams::{
+    IotaStreamsJobRequest, IotaStreamsRequest, IotaStreamsSubscriberRequest,
+};
+use crate::models::{Identification, Identity};
 use crate::mqtt_encoder as enc;
+use crate::send_mqtt::send_announcement;
+use crate::state_machine::{wake, Event};
+use crate::streams_jobs::submit_streams_job;
 use crate::util::{
-    connect_identity, connect_mqtt, connect_streams, get_channel, get_identification, get_thing,
-    helper_send_mqtt, parse_env, serialize_msg, update_streams_entry,
+    connect_identity, connect_mqtt, connect_streams, get_branch_stream, get_channel,
+    get_identification, get_thing, helper_send_mqtt, make_branch_list, make_command_challenge,
+    make_command_request_challenge, make_subscription_challenge, serialize_msg,
+    update_branch_entry, update_streams_entry,
 };
 use std::fs;
 use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
 
-pub async fn receive_mqtt_messages() -> Result<String, String> {
-    info!("--- receive_mqtt_messages() ---");
+/// Open MQTT subscription stream of channel, messages are pushed as they arrive
+pub async fn subscribe_mqtt_messages(
+    channel_key: &str,
+    postfix: i32,
+) -> Result<tonic::Streaming<MqttMessage>, String> {
+    info!("--- subscribe_mqtt_messages() ---");
     let mut mqtt_client = connect_mqtt().await?;
-    let mut response = receive_messages(&mut mqtt_client).await?;
-    for (payload, topic) in response.messages.iter_mut().zip(response.topics) {
-        let result = match topic.as_str() {
-            TOPIC_DID => mqtt_identity(payload.to_vec()).await,
-            TOPIC_STREAM => mqtt_streams(payload.to_vec()).await,
-            TOPIC_SETTING => mqtt_settings(payload.to_vec()).await,
-            TOPIC_COMMAND => mqtt_command(payload.to_vec()).await,
-            TOPIC_IDENTITY => mqtt_first_verification(payload.to_vec()).await,
-            e => Err(format!("Topic {} not Found", e)),
-            // Ignore Topics identity & sensors
-        };
-        match result {
-            Ok(_) => (),
-            Err(e) => error!("{}", e),
-        }
+    match mqtt_client
+        .subscribe_mqtt_messages(tonic::Request::new(make_mqtt_request(channel_key, postfix)))
+        .await
+    {
+        Ok(res) => Ok(res.into_inner()),
+        Err(e) => Err(format!("Unable to Subscribe to MQTT Messages: {}", e)),
+    }
+}
+/// Process MQTT message received on channel according to topic
+pub async fn handle_mqtt_message(
+    channel_cfg: &ChannelConfig,
+    topic: &str,
+    payload: Vec<u8>,
+) -> Result<u32, String> {
+    match topic {
+        TOPIC_DID => mqtt_identity(payload, &channel_cfg.channel_key).await,
+        TOPIC_STREAM => mqtt_streams(payload, channel_cfg).await,
+        TOPIC_SETTING => mqtt_settings(payload).await,
+        TOPIC_IDENTITY => mqtt_first_verification(payload).await,
+        // Results are evaluated by the issuer of the command
+        TOPIC_COMMAND_RESULT => Ok(0),
+        e => Err(format!("Topic {} not Found", e)),
+        // Ignore Topics identity & sensors
     }
-    Ok("Exit with Success: receive_mqtt_messages()".to_string())
 }
 
 pub async fn mqtt_first_verification(payload: Vec<u8>) -> Result<u32, String> {
@@ -70,15 +91,18 @@ pub async fn mqtt_first_verification(payload: Vec<u8>) -> Result<u32, String> {
     let is_verified = if response.code == 0 { true } else { false };
     let _ = get_identity(&db_client, &msg.did)?;
     update_identity(&db_client, &msg.did, is_verified)?;
+    if response.code == CODE_CREDENTIAL_REVOKED {
+        error!("Credential of DID {} Revoked", &msg.did);
+    }
+    if is_verified || is_verification_failure(response.code) {
+        update_identity_unverifiable(&db_client, &msg.did, !is_verified)?;
+    }
     Ok(0)
 }
 
-pub async fn mqtt_streams(payload: Vec<u8>) -> Result<u32, String> {
+pub async fn mqtt_streams(payload: Vec<u8>, channel_cfg: &ChannelConfig) -> Result<u32, String> {
     info!("--- mqtt_streams() ---");
-    let author_id = env::var(ENV_DEVICE_ID).expect("ENV for Author ID not Found");
-    //info!("ENV: {} = {}", ENV_DEVICE_ID, &author_id);
-    let channel_key = env::var(ENV_CHANNEL_KEY).expect("ENV for Channel Key not Found");
-    //info!("ENV: {} = {}", ENV_CHANNEL_KEY, &channel_key);
+    let channel_key = &channel_cfg.channel_key;
     let thing_key = env::var(ENV_THING_KEY).expect("ENV for Thing Key not Found");
     //info!("ENV: {} = {}", ENV_THING_KEY, &thing_key);
     // Decode Payload
@@ -107,54 +131,64 @@ pub async fn mqtt_streams(payload: Vec<u8>) -> Result<u32, String> {
         Some(r) => r,
         None => false,
     };
-    let is_subscribed = match msg_identity.subscribed {
+    // Subscriptions are made per channel
+    let channel = get_channel(&db_client, channel_key)?;
+    let is_subscribed = db::select_subscriber(&db_client, channel.id, &msg.did).is_ok();
+    let is_unverifiable = match msg_identity.unverifiable {
         Some(r) => r,
         None => false,
     };
+    if is_unverifiable {
+        return Err(format!("Identity Unverifiable, DID: {}", &msg.did));
+    }
     if !msg.subscription_link.is_empty() && is_verified && !is_subscribed {
+        verify_subscription(&msg).await?;
         add_subscriber(
             &db_client,
             &mut stream_client,
-            &mut mqtt_client,
             &msg.subscription_link,
-            &author_id,
-            &channel_key,
-            &thing_key,
+            channel_cfg,
             &msg.did,
+            &msg.public_key,
+            &msg.branches,
         )
         .await?;
     } else if !msg.subscription_link.is_empty() && is_verified && is_subscribed {
-        // Get number of subscribers
-        let channel = get_channel(&db_client, &channel_key)?;
-        let stream_entry = match db::select_stream(&db_client, channel.id) {
-            Ok(r) => r,
-            Err(e) => return Err(format!("Unable to Select Streams Entry: {}", e)),
-        };
-        let key_link = match stream_entry.key_link {
-            Some(r) => r,
-            None => "".to_string(),
-        };
-        if !key_link.is_empty() {
+        // Send Keyloads of Branches Subscriber has Access to
+        for branch in channel_cfg.get_branches() {
+            if !branch.is_empty() && !msg.branches.is_empty() && !msg.branches.contains(&branch) {
+                continue;
+            }
+            let stream_entry = get_branch_stream(&db_client, channel.id, &branch)?;
+            let key_link = match stream_entry.key_link {
+                Some(r) => r,
+                None => "".to_string(),
+            };
+            if key_link.is_empty() {
+                continue;
+            }
             // Send Keyload over MQTT
             let payload = serialize_msg(&enc::Streams {
                 announcement_link: "".to_string(),
                 subscription_link: "".to_string(),
                 keyload_link: key_link,
-                did: identity.did,
-                vc: match identity.vc {
-                    Some(r) => r,
+                did: identity.did.clone(),
+                vc: match &identity.vc {
+                    Some(r) => r.clone(),
                     None => "".to_string(),
                 },
+                public_key: "".to_string(),
+                branches: make_branch_list(&branch),
             });
             info!("Send Keyload over MQTT");
-            helper_send_mqtt(&mut mqtt_client, payload, TOPIC_STREAM).await?;
+            helper_send_mqtt(&mut mqtt_client, payload, TOPIC_STREAM, channel_key).await?;
         }
     }
     info!("Streams Message Processed");
     Ok(0)
 }
 
-pub async fn mqtt_identity(payload: Vec<u8>) -> Result<u32, String> {
+pub async fn mqtt_identity(payload: Vec<u8>, channel_key: &str) -> Result<u32, String> {
     info!("--- mqtt_identity() ---");
     let thing_key = env::var(ENV_THING_KEY).expect("ENV for Thing Key not Found");
     //info!("ENV: {} = {}", ENV_THING_KEY, &thing_key);
@@ -183,7 +217,7 @@ pub async fn mqtt_identity(payload: Vec<u8>) -> Result<u32, String> {
     // Sign VC with challenge
     if msg.proof && is_thing {
         info!("Proof Gateway Identity");
-        proof_identity(&mut identity_client, &mut mqtt_client, msg).await?;
+        proof_identity(&mut identity_client, &mut mqtt_client, msg, channel_key).await?;
     // Thing should verify received DID
     } else if !msg.proof && !is_thing {
         info!("Verify Participant's Identity");
@@ -223,9 +257,47 @@ async fn verify_identity(
                 update_identity(&db_client, &response.did, true)?;
             }
         };
+        // Identity verified again, subscriptions are accepted again
+        update_identity_unverifiable(&db_client, &response.did, false)?;
+    } else {
+        if response.code == CODE_CREDENTIAL_REVOKED {
+            error!("Credential of DID {} Revoked", &response.did);
+        }
+        let _ = get_identity(&db_client, &response.did)?;
+        update_identity(&db_client, &response.did, false)?;
+        if is_verification_failure(response.code) {
+            update_identity_unverifiable(&db_client, &response.did, true)?;
+        }
     }
     return Ok(0);
 }
+/// Definite failure of verification, other codes (e.g. untrusted issuer) depend on configuration
+fn is_verification_failure(code: i32) -> bool {
+    code == CODE_NOT_VERIFIED || code == CODE_CREDENTIAL_REVOKED
+}
+/// Check subscription link and public key are signed by DID of subscriber
+async fn verify_subscription(msg: &enc::Streams) -> Result<(), String> {
+    let mut identity_client = connect_identity().await?;
+    let challenge = make_subscription_challenge(&msg.did, &msg.subscription_link, &msg.public_key);
+    let response = match identity_client
+        .verify_identity(tonic::Request::new(IotaIdentityRequest {
+            did: msg.did.clone(),
+            challenge: challenge,
+            verifiable_credential: msg.vc.clone(),
+        }))
+        .await
+    {
+        Ok(res) => res.into_inner(),
+        Err(e) => return Err(format!("Unable to Verify Subscription: {}", e)),
+    };
+    if response.code != 0 {
+        return Err(format!(
+            "Subscription Not Signed by DID: {}, Code: {}",
+            &msg.did, response.code
+        ));
+    }
+    Ok(())
+}
 
 pub async fn mqtt_settings(payload: Vec<u8>) -> Result<u32, String> {
     info!("--- mqtt_settings() ---");
@@ -255,41 +327,308 @@ pub async fn mqtt_settings(payload: Vec<u8>) -> Result<u32, String> {
     Ok(0)
 }
 
-pub async fn mqtt_command(payload: Vec<u8>) -> Result<u32, String> {
-    // Not Implemented
-    info!("--- mqtt_command() --- not Implemented");
-    let _msg = enc::Command::decode(&mut Cursor::new(payload));
+/// Execute command received over MQTT on channel and publish signed result
+pub async fn mqtt_command(
+    channel_cfg: &ChannelConfig,
+    payload: Vec<u8>,
+    data_tx: &mpsc::Sender<Event>,
+    cmd_tx: &mpsc::Sender<SensorCommand>,
+) -> Result<u32, String> {
+    info!("--- mqtt_command() ---");
+    let thing_key = env::var(ENV_THING_KEY).expect("ENV for Thing Key not Found");
+    let msg = match enc::Command::decode(&mut Cursor::new(payload)) {
+        Ok(res) => res,
+        Err(e) => return Err(format!("Unable to Decode Payload: {}", e)),
+    };
+    let db_client = db::establish_connection();
+    let thing = get_thing(&db_client, &thing_key)?;
+    let identity = get_identification(&db_client, thing.id)?;
+    // Ignore Commands for other Things
+    if !msg.target.is_empty() && !identity.did.eq(&msg.target) {
+        info!("Command Addressed to DID: {}", &msg.target);
+        return Ok(0);
+    }
+    authenticate_command(&db_client, &msg, load_config_file().commands.max_age_s).await?;
+    let cmd_type = enc::CommandType::from_i32(msg.cmd_type).unwrap_or(enc::CommandType::Unknown);
+    info!("Command: {:?}, ID: {}", cmd_type, &msg.command_id);
+    let (status, code) =
+        match execute_command(&db_client, channel_cfg, &msg, cmd_type, data_tx, cmd_tx).await {
+            Ok(r) => (r, 0),
+            Err(e) => {
+                error!("{}", e);
+                (e, -1)
+            }
+        };
+    send_command_result(
+        identity,
+        &msg.command_id,
+        cmd_type,
+        status,
+        code,
+        &channel_cfg.channel_key,
+    )
+    .await?;
     Ok(0)
 }
+/// Check command is signed by verified identity with challenge of command,
+/// was issued at most max_age_s ago and was not executed before
+async fn authenticate_command(
+    db_client: &diesel::SqliteConnection,
+    msg: &enc::Command,
+    max_age_s: i64,
+) -> Result<(), String> {
+    if msg.did.is_empty() || msg.vc.is_empty() {
+        return Err(format!("Command with ID {} Not Signed", &msg.command_id));
+    }
+    let now = get_timestamp()?;
+    if (now - msg.issued_at).abs() > max_age_s {
+        return Err(format!(
+            "Command with ID {} Expired, Issued at: {}",
+            &msg.command_id, msg.issued_at
+        ));
+    }
+    let sender = match db::select_identity(&db_client, &msg.did) {
+        Ok(res) => res,
+        Err(_) => return Err(format!("Command Sender Unknown, DID: {}", &msg.did)),
+    };
+    if sender.verified != Some(true) || sender.unverifiable == Some(true) {
+        return Err(format!("Command Sender Not Verified, DID: {}", &msg.did));
+    }
+    let challenge = make_command_request_challenge(
+        &msg.command_id,
+        msg.cmd_type,
+        &msg.target,
+        &msg.sensor_id,
+        &msg.cmd,
+        &msg.payload,
+        msg.issued_at,
+    );
+    if !challenge.eq(&msg.challenge) {
+        return Err(format!(
+            "Command with ID {} Doesn't Match Challenge",
+            &msg.command_id
+        ));
+    }
+    let mut identity_client = connect_identity().await?;
+    let response = match identity_client
+        .verify_identity(tonic::Request::new(IotaIdentityRequest {
+            did: msg.did.clone(),
+            challenge: challenge,
+            verifiable_credential: msg.vc.clone(),
+        }))
+        .await
+    {
+        Ok(res) => res.into_inner(),
+        Err(e) => return Err(format!("Unable to Verify Command: {}", e)),
+    };
+    if response.code != 0 {
+        return Err(format!(
+            "Unable to Verify Command with ID {} from DID: {}",
+            &msg.command_id, &msg.did
+        ));
+    }
+    // Commands issued more than max age ago are rejected above, their IDs need not be remembered
+    let _ = db::delete_expired_commands(&db_client, now - max_age_s);
+    match db::create_command(&db_client, &msg.command_id, &msg.did, msg.issued_at) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(format!(
+            "Command with ID {} Already Executed",
+            &msg.command_id
+        )),
+    }
+}
+/// Dispatch command, commands with sensor ID are forwarded to sensor
+async fn execute_command(
+    db_client: &diesel::SqliteConnection,
+    channel_cfg: &ChannelConfig,
+    msg: &enc::Command,
+    cmd_type: enc::CommandType,
+    data_tx: &mpsc::Sender<Event>,
+    cmd_tx: &mpsc::Sender<SensorCommand>,
+) -> Result<String, String> {
+    if !msg.sensor_id.is_empty() {
+        return forward_sensor_command(msg, cmd_type, cmd_tx);
+    }
+    match cmd_type {
+        enc::CommandType::ResendUnsentData => {
+            wake(data_tx, Event::SensorData);
+            Ok("Resend of Unsent Data Scheduled".to_string())
+        }
+        enc::CommandType::RotateKeyload => rotate_keyload(db_client, channel_cfg).await,
+        enc::CommandType::SetSampleInterval => Err("Sensor ID Missing".to_string()),
+        enc::CommandType::ReAnnounce => re_announce(db_client, &channel_cfg.channel_key).await,
+        enc::CommandType::Unknown => Err(format!("Unknown Command: {}", &msg.cmd)),
+    }
+}
+/// Queue command for delivery with next reply to sensor
+fn forward_sensor_command(
+    msg: &enc::Command,
+    cmd_type: enc::CommandType,
+    cmd_tx: &mpsc::Sender<SensorCommand>,
+) -> Result<String, String> {
+    let command = match cmd_type {
+        enc::CommandType::Unknown => msg.cmd.clone(),
+        _ => get_command_name(cmd_type).to_string(),
+    };
+    match cmd_tx.try_send(SensorCommand {
+        sensor_id: msg.sensor_id.clone(),
+        command: command,
+        payload: msg.payload.clone(),
+    }) {
+        Ok(_) => Ok(format!("Command Queued for Sensor: {}", &msg.sensor_id)),
+        Err(e) => Err(format!(
+            "Unable to Queue Command for Sensor {}: {}",
+            &msg.sensor_id, e
+        )),
+    }
+}
+/// Command name sent to sensor, part of sensor protocol
+fn get_command_name(cmd_type: enc::CommandType) -> &'static str {
+    match cmd_type {
+        enc::CommandType::ResendUnsentData => "ResendUnsentData",
+        enc::CommandType::RotateKeyload => "RotateKeyload",
+        enc::CommandType::SetSampleInterval => "SetSampleInterval",
+        enc::CommandType::ReAnnounce => "ReAnnounce",
+        enc::CommandType::Unknown => "Unknown",
+    }
+}
+/// Replace keyload of channel, following messages are linked to new keyload
+async fn rotate_keyload(
+    db_client: &diesel::SqliteConnection,
+    channel_cfg: &ChannelConfig,
+) -> Result<String, String> {
+    let thing_key = env::var(ENV_THING_KEY).expect("ENV for Thing Key not Found");
+    let mut mqtt_client = connect_mqtt().await?;
+    let mut stream_client = connect_streams().await?;
+    let channel = get_channel(&db_client, &channel_cfg.channel_key)?;
+    let thing = get_thing(&db_client, &thing_key)?;
+    let identity = get_identification(&db_client, thing.id)?;
+    let key_links = send_keyload(
+        db_client,
+        &mut stream_client,
+        &mut mqtt_client,
+        channel_cfg,
+        channel.id,
+        identity,
+    )
+    .await?;
+    Ok(format!("Keyload Rotated: {:?}", key_links))
+}
+/// Send announcement link of channel again
+async fn re_announce(
+    db_client: &diesel::SqliteConnection,
+    channel_key: &str,
+) -> Result<String, String> {
+    let mut mqtt_client = connect_mqtt().await?;
+    let channel = get_channel(&db_client, channel_key)?;
+    let stream_entry = match db::select_stream(&db_client, channel.id) {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to Select Streams Entry: {}", e)),
+    };
+    send_announcement(
+        &mut mqtt_client,
+        &db_client,
+        &stream_entry.ann_link,
+        channel_key,
+    )
+    .await
+}
+/// Sign result with challenge and publish it on result topic
+async fn send_command_result(
+    identity: Identification,
+    command_id: &str,
+    cmd_type: enc::CommandType,
+    status: String,
+    code: i32,
+    channel_key: &str,
+) -> Result<(), String> {
+    let mut identity_client = connect_identity().await?;
+    let mut mqtt_client = connect_mqtt().await?;
+    let challenge = make_command_challenge(command_id, cmd_type as i32, code, &status);
+    let response = match identity_client
+        .proof_identity(tonic::Request::new(IotaIdentityRequest {
+            did: identity.did,
+            challenge: challenge,
+            verifiable_credential: match identity.vc {
+                Some(r) => r,
+                None => "".to_string(),
+            },
+        }))
+        .await
+    {
+        Ok(res) => res.into_inner(),
+        Err(e) => return Err(format!("Unable to Sign Command Result: {}", e)),
+    };
+    let payload = serialize_msg(&enc::CommandResult {
+        command_id: command_id.to_string(),
+        cmd_type: cmd_type as i32,
+        status: status,
+        code: code,
+        did: response.did,
+        challenge: response.challenge,
+        vc: response.verifiable_credential,
+    });
+    info!("Send Command Result over MQTT");
+    helper_send_mqtt(&mut mqtt_client, payload, TOPIC_COMMAND_RESULT, channel_key).await
+}
 
+/// Submit job adding subscriber to channel, subscription is saved by
+/// subscriber_added once job is done
 async fn add_subscriber(
     db_client: &diesel::SqliteConnection,
     stream_client: &mut IotaStreamerClient<tonic::transport::Channel>,
-    mqtt_client: &mut MqttOperatorClient<tonic::transport::Channel>,
     sub_link: &str,
-    author_id: &str,
-    channel_key: &str,
-    thing_key: &str,
+    channel_cfg: &ChannelConfig,
     msg_did: &str,
+    public_key: &str,
+    branches: &Vec<String>,
 ) -> Result<u32, String> {
     info!("--- add_subscriber() ---");
-    match stream_client
-        .add_subscriber(tonic::Request::new(IotaStreamsRequest {
-            id: author_id.to_string(),
-            link: sub_link.to_string(),
+    let channel = get_channel(&db_client, &channel_cfg.channel_key)?;
+    // Subscriber is already being added
+    if db::select_pending_streams_job(&db_client, channel.id, 3, msg_did).is_ok() {
+        info!("Subscriber Already Pending, DID: {}", msg_did);
+        return Ok(0);
+    }
+    let job_id = submit_streams_job(
+        db_client,
+        stream_client,
+        IotaStreamsJobRequest {
             msg_type: 3, //  AddSubscriber
-        }))
-        .await
-    {
-        Ok(res) => res.into_inner(),
-        Err(e) => return Err(format!("Error Adding Subscriber: {}", e)),
-    };
-    match db::update_identity_to_subscribed(&db_client, msg_did, true) {
+            request: None,
+            subscriber_request: Some(IotaStreamsSubscriberRequest {
+                id: channel_cfg.author_id.to_string(),
+                link: sub_link.to_string(),
+                msg_type: 3, //  AddSubscriber
+                did: msg_did.to_string(),
+                public_key: public_key.to_string(),
+                branches: branches.clone(),
+            }),
+            message_request: None,
+        },
+        channel.id,
+        msg_did,
+    )
+    .await?;
+    info!("Add Subscriber Job Submitted: {}", job_id);
+    Ok(0)
+}
+/// Save subscription of added subscriber, send keyloads if expected number
+/// of subscribers is reached
+pub async fn subscriber_added(
+    db_client: &diesel::SqliteConnection,
+    stream_client: &mut IotaStreamerClient<tonic::transport::Channel>,
+    mqtt_client: &mut MqttOperatorClient<tonic::transport::Channel>,
+    channel_cfg: &ChannelConfig,
+    thing_key: &str,
+    msg_did: &str,
+) -> Result<u32, String> {
+    // Get number of subscribers
+    let channel = get_channel(&db_client, &channel_cfg.channel_key)?;
+    match db::create_subscriber(&db_client, channel.id, msg_did) {
         Ok(_) => (),
-        Err(e) => return Err(format!("Unable to Update Identity Entry: {}", e)),
+        Err(e) => return Err(format!("Unable to Create Subscriber Entry: {}", e)),
     };
-    // Get number of subscribers
-    let channel = get_channel(&db_client, channel_key)?;
     let num_subscribers = match db::select_stream(&db_client, channel.id) {
         Ok(r) => match r.num_subs {
             Some(r) => r + 1,
@@ -298,51 +637,109 @@ async fn add_subscriber(
         Err(e) => return Err(format!("Unable to Select Streams Entry: {}", e)),
     };
     update_streams_entry(&db_client, "", num_subscribers, "num_subs", channel.id)?;
-    let total_num_subscribers = parse_env::<i32>(ENV_TOTAL_NUM_SUBSCRIBER);
-    if num_subscribers != total_num_subscribers {
+    if num_subscribers != channel_cfg.num_subscriber {
         info!("Number of Subscribers: {}", num_subscribers);
         return Ok(0);
     }
-    // Get Channel ID
-    let channel = get_channel(&db_client, &channel_key)?;
     // Get Thing ID
     let thing = get_thing(&db_client, &thing_key)?;
     // Get own DID
     let identity = get_identification(&db_client, thing.id)?;
-    // Generate Keyload
-    let response = match stream_client
-        .create_keyload(tonic::Request::new(IotaStreamsRequest {
-            id: author_id.to_string(),
-            link: "".to_string(),
-            msg_type: 7, //  CreateKeyload
-        }))
-        .await
-    {
-        Ok(res) => res.into_inner(),
-        Err(e) => return Err(format!("Unable to Create Keyload Link: {}", e)),
-    };
+    send_keyload(
+        db_client,
+        stream_client,
+        mqtt_client,
+        channel_cfg,
+        channel.id,
+        identity,
+    )
+    .await?;
+    Ok(0)
+}
+/// Generate keyload per branch of channel, send links over MQTT and save them
+pub async fn send_keyload(
+    db_client: &diesel::SqliteConnection,
+    stream_client: &mut IotaStreamerClient<tonic::transport::Channel>,
+    mqtt_client: &mut MqttOperatorClient<tonic::transport::Channel>,
+    channel_cfg: &ChannelConfig,
+    channel_id: i32,
+    identity: Identification,
+) -> Result<Vec<String>, String> {
+    let mut key_links = Vec::new();
+    for branch in channel_cfg.get_branches() {
+        // Generate Keyload
+        let response = match stream_client
+            .create_keyload(tonic::Request::new(IotaStreamsRequest {
+                id: channel_cfg.author_id.to_string(),
+                link: "".to_string(),
+                msg_type: 7, //  CreateKeyload
+                branch: branch.clone(),
+                multi_branch: false,
+                seed_nonce: "".to_string(),
+            }))
+            .await
+        {
+            Ok(res) => res.into_inner(),
+            Err(e) => return Err(format!("Unable to Create Keyload Link: {}", e)),
+        };
+        if response.code != 0 {
+            return Err(format!(
+                "Unable to Create Keyload Link for Branch '{}': {}",
+                &branch, response.status
+            ));
+        }
+        publish_keyload(
+            db_client,
+            mqtt_client,
+            &response.link,
+            &channel_cfg.channel_key,
+            channel_id,
+            &branch,
+            identity.clone(),
+        )
+        .await?;
+        key_links.push(response.link);
+    }
+    Ok(key_links)
+}
+/// Send keyload link of branch over MQTT and save it,
+/// following messages of branch are linked to keyload
+pub async fn publish_keyload(
+    db_client: &diesel::SqliteConnection,
+    mqtt_client: &mut MqttOperatorClient<tonic::transport::Channel>,
+    key_link: &str,
+    channel_key: &str,
+    channel_id: i32,
+    branch: &str,
+    identity: Identification,
+) -> Result<(), String> {
     // Send Keyload over MQTT
     let payload = serialize_msg(&enc::Streams {
         announcement_link: "".to_string(),
         subscription_link: "".to_string(),
-        keyload_link: response.link.clone(),
+        keyload_link: key_link.to_string(),
         did: identity.did,
         vc: match identity.vc {
             Some(r) => r,
             None => "".to_string(),
         },
+        public_key: "".to_string(),
+        branches: make_branch_list(branch),
     });
     info!("Send Keyload over MQTT");
-    helper_send_mqtt(mqtt_client, payload, TOPIC_STREAM).await?;
+    helper_send_mqtt(mqtt_client, payload, TOPIC_STREAM, channel_key).await?;
     // Save Keyload Link
-    update_streams_entry(&db_client, &response.link, 0, "keyload", channel.id)?;
-    Ok(0)
+    get_branch_stream(db_client, channel_id, branch)?;
+    update_branch_entry(db_client, key_link, "keyload", channel_id, branch)?;
+    update_branch_entry(db_client, "", "msg_link", channel_id, branch)?;
+    Ok(())
 }
 
 async fn proof_identity(
     identity_client: &mut IotaIdentifierClient<tonic::transport::Channel>,
     mqtt_client: &mut MqttOperatorClient<tonic::transport::Channel>,
     identity: enc::Did,
+    channel_key: &str,
 ) -> Result<(), String> {
     match identity_client
         .proof_identity(tonic::Request::new(IotaIdentityRequest {
@@ -362,7 +759,7 @@ async fn proof_identity(
                 proof: false,
             });
             info!("Send Signed VC over MQTT");
-            helper_send_mqtt(mqtt_client, payload, TOPIC_DID).await?;
+            helper_send_mqtt(mqtt_client, payload, TOPIC_DID, channel_key).await?;
         }
         Err(e) => return Err(format!("Unable to Sign VC: {}", e)),
     };
@@ -386,10 +783,14 @@ fn update_identity(
 fn update_identity_unverifiable(
     db_client: &diesel::SqliteConnection,
     did: &str,
+    is_unverifiable: bool,
 ) -> Result<u32, String> {
-    match db::update_identity_to_unverifiable(db_client, did, true) {
+    match db::update_identity_to_unverifiable(db_client, did, is_unverifiable) {
         Ok(_) => {
-            info!("Identity Marked as Unverifiable with DID: {}", did);
+            info!(
+                "Identity Marked as Unverifiable = {} with DID: {}",
+                is_unverifiable, did
+            );
             return Ok(0);
         }
         Err(_) => {
@@ -401,24 +802,16 @@ fn update_identity_unverifiable(
     };
 }
 
-async fn receive_messages(
-    mqtt_client: &mut MqttOperatorClient<tonic::transport::Channel>,
-) -> Result<MqttMsgsReply, String> {
-    let response = match mqtt_client
-        .receive_mqtt_message(tonic::Request::new(MqttRequest {
-            id: env::var(ENV_THING_KEY).expect("ENV for Thing Key not Found"),
-            postfix: "sub".to_string(),
-            pwd: env::var(ENV_THING_PWD).expect("ENV for Thing PWD not Found"),
-            channel: env::var(ENV_CHANNEL_KEY).expect("ENV for Channel Key not Found"),
-            topic: "".to_string(),
-            message: vec![],
-        }))
-        .await
-    {
-        Ok(res) => res.into_inner(),
-        Err(_) => return Err(format!("Unable to Receive MQTT Messages")),
-    };
-    Ok(response)
+/// Request for all topics of channel, postfix of MQTT client ID is unique per channel
+fn make_mqtt_request(channel_key: &str, postfix: i32) -> MqttRequest {
+    MqttRequest {
+        id: env::var(ENV_THING_KEY).expect("ENV for Thing Key not Found"),
+        postfix: postfix.to_string(),
+        pwd: env::var(ENV_THING_PWD).expect("ENV for Thing PWD not Found"),
+        channel: channel_key.to_string(),
+        topic: "".to_string(),
+        message: vec![],
+    }
 }
 
 fn get_identity(db_client: &diesel::SqliteConnection, did: &str) -> Result<Identity, String> {
@@ -448,3 +841,10 @@ fn make_identity(db_client: &diesel::SqliteConnection, did: &str) -> Result<(),
         }
     };
 }
+
+fn get_timestamp() -> Result<i64, String> {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(r) => Ok(r.as_secs() as i64),
+        Err(e) => Err(format!("Unable to Get Timestamp: {}", e)),
+    }
+}
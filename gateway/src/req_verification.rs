@@ -1,10 +1,18 @@
-use crate::config::TOPIC_DID;
+use std::env;
+
+use crate::config::{ChannelConfig, ENV_THING_KEY, TOPIC_DID};
 use crate::db_module as db;
+use crate::grpc_streams::IotaStreamsSubscriberRequest;
 use crate::models::Identity;
 use crate::mqtt_encoder as enc;
-use crate::util::{connect_mqtt, generate_random_sequence, helper_send_mqtt, serialize_msg};
+use crate::recv_mqtt::{publish_keyload, send_keyload};
+use crate::util::{
+    connect_mqtt, connect_streams, generate_random_sequence, get_channel, get_identification,
+    get_thing, helper_send_mqtt, serialize_msg, update_streams_entry,
+};
 
-pub async fn request_identity_verification() -> Result<String, String> {
+/// Request unverified identities to proof their identity on channel
+pub async fn request_identity_verification(channel_key: &str) -> Result<String, String> {
     info!("--- request_identity_verification() ---");
     // Connect to Database
     let db_client = db::establish_connection();
@@ -18,11 +26,108 @@ pub async fn request_identity_verification() -> Result<String, String> {
             vc: "".to_string(),
             proof: true,
         });
-        helper_send_mqtt(&mut mqtt_client, payload, TOPIC_DID).await?;
+        helper_send_mqtt(&mut mqtt_client, payload, TOPIC_DID, channel_key).await?;
     }
     Ok("Verification Requests Send".to_string())
 }
 
+/// Revoke identities subscribed to channel and marked as unverifiable,
+/// Streams service returns new keyload excluding revoked subscriber
+pub async fn revoke_unverifiable_subscribers(
+    channel_cfg: &ChannelConfig,
+) -> Result<String, String> {
+    info!("--- revoke_unverifiable_subscribers() ---");
+    let author_id = &channel_cfg.author_id;
+    let channel_key = &channel_cfg.channel_key;
+    let thing_key = env::var(ENV_THING_KEY).expect("ENV for Thing Key not Found");
+    // Connect to Database
+    let db_client = db::establish_connection();
+    let channel = get_channel(&db_client, channel_key)?;
+    let identities = match db::select_unverifiable_subscribers(&db_client, channel.id) {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to Select Unverifiable Subscribers: {}", e)),
+    };
+    if identities.is_empty() {
+        return Ok("No Subscribers to Revoke".to_string());
+    }
+    // Connect to MQTT Service
+    let mut mqtt_client = connect_mqtt().await?;
+    // Connect to IOTA Streams Service
+    let mut stream_client = connect_streams().await?;
+    let thing = get_thing(&db_client, &thing_key)?;
+    let mut key_link = None;
+    for identity in identities {
+        let response = match stream_client
+            .revoke_subscriber(tonic::Request::new(IotaStreamsSubscriberRequest {
+                id: author_id.clone(),
+                msg_type: 8, // RevokeSubscriber
+                link: "".to_string(),
+                did: identity.did.clone(),
+                public_key: "".to_string(),
+                branches: Vec::new(),
+            }))
+            .await
+        {
+            Ok(res) => res.into_inner(),
+            Err(e) => return Err(format!("Unable to Revoke Subscriber: {}", e)),
+        };
+        if response.code != 0 {
+            error!(
+                "Unable to Revoke Subscriber with DID {}: {}",
+                &identity.did, response.status
+            );
+            continue;
+        }
+        info!("Revoked Subscriber with DID: {}", &identity.did);
+        match db::delete_subscriber(&db_client, channel.id, &identity.did) {
+            Ok(_) => (),
+            Err(e) => return Err(format!("Unable to Delete Subscriber Entry: {}", e)),
+        };
+        // Update number of subscribers
+        let num_subscribers = match db::select_stream(&db_client, channel.id) {
+            Ok(r) => match r.num_subs {
+                Some(r) if r > 0 => r - 1,
+                _ => 0,
+            },
+            Err(e) => return Err(format!("Unable to Select Streams Entry: {}", e)),
+        };
+        update_streams_entry(&db_client, "", num_subscribers, "num_subs", channel.id)?;
+        key_link = Some(response.link);
+    }
+    // Nothing revoked
+    let key_link = match key_link {
+        Some(r) => r,
+        None => return Ok("No Subscribers Revoked".to_string()),
+    };
+    // Send one set of keyloads excluding revoked subscribers, following messages are linked to it
+    let own_identity = get_identification(&db_client, thing.id)?;
+    if channel_cfg.multi_branch {
+        // New keyload per branch, Streams service sends no keyload on revocation
+        send_keyload(
+            &db_client,
+            &mut stream_client,
+            &mut mqtt_client,
+            channel_cfg,
+            channel.id,
+            own_identity,
+        )
+        .await?;
+    } else {
+        // Keyload of last revocation excludes all revoked subscribers
+        publish_keyload(
+            &db_client,
+            &mut mqtt_client,
+            &key_link,
+            channel_key,
+            channel.id,
+            "",
+            own_identity,
+        )
+        .await?;
+    }
+    Ok("Unverifiable Subscribers Revoked".to_string())
+}
+
 fn get_identities(
     db_client: &diesel::SqliteConnection,
     is_verified: bool,
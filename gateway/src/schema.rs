@@ -6,6 +6,15 @@ table! {
     }
 }
 
+table! {
+    commands (id) {
+        id -> Integer,
+        command_id -> Text,
+        did -> Text,
+        issued_at -> BigInt,
+    }
+}
+
 table! {
     config (thing_id) {
         thing_id -> Integer,
@@ -42,6 +51,7 @@ table! {
         mqtt -> Nullable<Bool>,
         iota -> Nullable<Bool>,
         verified -> Nullable<Bool>,
+        received_time -> BigInt,
     }
 }
 
@@ -60,6 +70,7 @@ table! {
         sensor_types_id -> Integer,
         sensor_id -> Text,
         sensor_name -> Nullable<Text>,
+        active -> Bool,
     }
 }
 
@@ -72,6 +83,30 @@ table! {
         key_link -> Nullable<Text>,
         msg_link -> Nullable<Text>,
         num_subs -> Nullable<Integer>,
+        branch -> Text,
+        seed_nonce -> Text,
+    }
+}
+
+table! {
+    streams_jobs (id) {
+        id -> Integer,
+        job_id -> Text,
+        channel_id -> Integer,
+        msg_type -> Integer,
+        did -> Text,
+        state -> Text,
+        link -> Nullable<Text>,
+        status -> Nullable<Text>,
+        created -> BigInt,
+    }
+}
+
+table! {
+    subscribers (id) {
+        id -> Integer,
+        channel_id -> Integer,
+        did -> Text,
     }
 }
 
@@ -89,9 +124,12 @@ joinable!(sensor_data -> sensors (sensor_id));
 joinable!(sensors -> channels (channel_id));
 joinable!(sensors -> sensor_types (sensor_types_id));
 joinable!(streams -> channels (channel_id));
+joinable!(streams_jobs -> channels (channel_id));
+joinable!(subscribers -> channels (channel_id));
 
 allow_tables_to_appear_in_same_query!(
     channels,
+    commands,
     config,
     identification,
     identities,
@@ -99,5 +137,7 @@ allow_tables_to_appear_in_same_query!(
     sensor_types,
     sensors,
     streams,
+    streams_jobs,
+    subscribers,
     things,
 );
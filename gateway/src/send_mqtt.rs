@@ -1,27 +1,29 @@
 use serde_json::json;
 use std::env;
-use tokio::time::{sleep, Duration};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::config::{
-    ENV_CHANNEL_KEY, ENV_DEVICE_ID, ENV_THING_KEY, TOPIC_SENSOR_VALUE, TOPIC_STREAM,
+    load_config_file, Batch, ChannelConfig, ENV_THING_KEY, TOPIC_SENSOR_VALUE, TOPIC_STREAM,
 };
 use crate::db_module as db;
 use crate::grpc_mqtt::mqtt_operator_client::MqttOperatorClient;
 use crate::grpc_streams::iota_streamer_client::IotaStreamerClient;
 use crate::grpc_streams::{IotaStreamsReply, IotaStreamsSendMessageRequest};
-use crate::models::{Sensor, SensorData, SensorType, Stream};
+use crate::models::{Identification, Sensor, SensorData, SensorType, Stream};
 use crate::mqtt_encoder as enc;
 use crate::util::{
-    connect_mqtt, connect_streams, get_channel, get_identification, get_thing, helper_send_mqtt,
-    serialize_msg, update_streams_entry,
+    connect_mqtt, connect_streams, get_branch_stream, get_channel, get_identification, get_thing,
+    helper_send_mqtt, serialize_msg, update_branch_entry,
 };
 
-pub async fn send_sensor_data() -> Result<String, String> {
+/// Version of public header and payload format of tangle messages,
+/// timestamps are Unix timestamps in s
+const PAYLOAD_SCHEMA_VERSION: u32 = 1;
+
+/// Send stored sensor data of channel sensors over MQTT and IOTA Streams
+pub async fn send_sensor_data(channel_cfg: &ChannelConfig) -> Result<String, String> {
     info!("--- send_sensor_data() ---");
-    let author_id = env::var(ENV_DEVICE_ID).expect("ENV for Author ID not Found");
-    //info!("ENV: {} = {}", ENV_DEVICE_ID, &author_id);
-    let channel_key = env::var(ENV_CHANNEL_KEY).expect("ENV for Channel Key not Found");
-    //info!("ENV: {} = {}", ENV_CHANNEL_KEY, &channel_key);
+    let channel_key = &channel_cfg.channel_key;
     let thing_key = env::var(ENV_THING_KEY).expect("ENV for Thing Key not Found");
     //info!("ENV: {} = {}", ENV_THING_KEY, &thing_key);
     // Connect to Database
@@ -30,10 +32,11 @@ pub async fn send_sensor_data() -> Result<String, String> {
     let mut mqtt_client = connect_mqtt().await?;
     // Connect to IOTA Streams Service
     let mut stream_client = connect_streams().await?;
+    // Get Channel ID
+    let channel = get_channel(&db_client, channel_key)?;
     // Get Sensor Entries for Data not Send via MQTT
-    let val_mqtt = get_sensor_data(&db_client, "mqtt", false)?;
+    let val_mqtt = get_unsent_mqtt_data(&db_client, channel.id)?;
     for val in val_mqtt {
-        sleep(Duration::from_millis(3000)).await;
         let sensor = get_sensor(&db_client, val.sensor_id)?;
         let sensor_type = get_sensor_type(&db_client, sensor.sensor_types_id)?;
         let payload = serialize_msg(&enc::Sensor {
@@ -50,98 +53,187 @@ pub async fn send_sensor_data() -> Result<String, String> {
             },
             timestamp: val.sensor_time,
         });
-        helper_send_mqtt(&mut mqtt_client, payload, TOPIC_SENSOR_VALUE).await?;
+        helper_send_mqtt(&mut mqtt_client, payload, TOPIC_SENSOR_VALUE, channel_key).await?;
         update_sensor_entry(&db_client, val.id, "mqtt", true)?;
     }
-    // Send Data to Tangle
-    // Get Channel ID
-    let channel = get_channel(&db_client, &channel_key)?;
-    // Get Message Link
+    // Send Data to Tangle, each Branch is linked to its own Keyload
     let stream_entry = get_streams(&db_client, channel.id)?;
-    // Check if Keyloads are sent,
-    let key_link = match stream_entry.key_link {
-        Some(r) => {
-            if r.is_empty() {
-                send_announcement(&mut mqtt_client, &db_client, &stream_entry.ann_link).await?;
-                return Ok("No IOTA Streams Connection Established (Keyload Missing)".to_string());
-            }
-            r
-        }
-        None => {
-            send_announcement(&mut mqtt_client, &db_client, &stream_entry.ann_link).await?;
-            return Ok("No IOTA Streams Connection Established (Keyload Missing)".to_string());
-        }
-    };
-    // Check for all subscribers?
-    // Check if IOTA values available?
-    let val_iota = get_sensor_data(&db_client, "iota", false)?;
+    let batch = load_config_file().batch;
     // Get Thing ID
     let thing = get_thing(&db_client, &thing_key)?;
     // Get own DID
     let identity = get_identification(&db_client, thing.id)?;
-
-    let mut msg_link = match stream_entry.msg_link {
-        Some(r) => {
-            if r.is_empty() {
-                info!("IOTA Streams Message Link: {}", &key_link);
-                key_link
-            } else {
+    let mut is_announced = false;
+    for branch in channel_cfg.get_branches() {
+        let branch_entry = get_branch_stream(&db_client, channel.id, &branch)?;
+        // Check if Keyloads are sent,
+        let key_link = match branch_entry.key_link {
+            Some(r) => r,
+            None => "".to_string(),
+        };
+        if key_link.is_empty() {
+            if !is_announced {
+                send_announcement(
+                    &mut mqtt_client,
+                    &db_client,
+                    &stream_entry.ann_link,
+                    channel_key,
+                )
+                .await?;
+                is_announced = true;
+            }
+            info!(
+                "No IOTA Streams Connection Established for Branch '{}' (Keyload Missing)",
+                &branch
+            );
+            continue;
+        }
+        let msg_link = match branch_entry.msg_link {
+            Some(r) if !r.is_empty() => {
                 info!("IOTA Streams Message Link: {}", &r);
                 r
             }
-        }
-        None => {
-            info!("IOTA Streams Message Link (use Key Link): {}", &key_link);
-            key_link
-        }
-    };
-    let vc = match identity.vc {
-        Some(r) => r,
+            _ => {
+                info!("IOTA Streams Message Link (use Key Link): {}", &key_link);
+                key_link
+            }
+        };
+        send_branch_data(
+            &db_client,
+            &mut stream_client,
+            channel_cfg,
+            channel.id,
+            &branch,
+            msg_link,
+            &identity,
+            &batch,
+        )
+        .await?;
+    }
+    Ok("Exit with Success: send_sensor_data()".to_string())
+}
+
+/// Send unsent sensor data of branch sensors to tangle in batches
+async fn send_branch_data(
+    db_client: &diesel::SqliteConnection,
+    stream_client: &mut IotaStreamerClient<tonic::transport::Channel>,
+    channel_cfg: &ChannelConfig,
+    channel_id: i32,
+    branch: &str,
+    mut msg_link: String,
+    identity: &Identification,
+    batch: &Batch,
+) -> Result<(), String> {
+    let sensor_ids = channel_cfg.get_branch_sensors(branch);
+    let vc = match &identity.vc {
+        Some(r) => r.clone(),
         None => "".to_string(),
     };
-    for val in val_iota {
-        sleep(Duration::from_millis(3000)).await;
-        let sensor = get_sensor(&db_client, val.sensor_id)?;
-        let sensor_type = get_sensor_type(&db_client, sensor.sensor_types_id)?;
-        let name = match sensor.sensor_name {
-            Some(r) => r,
-            None => "".to_string(),
-        };
-        let unit = match sensor_type.unit {
-            Some(r) => r,
-            None => "".to_string(),
-        };
+    loop {
+        // Collect Batch of IOTA Values
+        let val_iota = get_unsent_iota_data(db_client, channel_id, &sensor_ids, batch.max_rows)?;
+        if !is_batch_ready(&val_iota, batch) {
+            info!("Waiting for Batch to Fill: {} Entries", val_iota.len());
+            break;
+        }
+        let mut readings = Vec::new();
+        let mut entry_ids = Vec::new();
+        let mut sensor_types: Vec<String> = Vec::new();
+        let mut skipped_ids = Vec::new();
+        let mut timestamp = 0;
+        for val in val_iota {
+            // Entries of unknown sensors are skipped, following entries are still sent
+            let sensor = match get_sensor(db_client, val.sensor_id) {
+                Ok(r) => r,
+                Err(e) => {
+                    error!("Skipped Sensor Entry with ID {}: {}", val.id, e);
+                    skipped_ids.push(val.id);
+                    continue;
+                }
+            };
+            let sensor_type = match get_sensor_type(db_client, sensor.sensor_types_id) {
+                Ok(r) => r,
+                Err(e) => {
+                    error!("Skipped Sensor Entry with ID {}: {}", val.id, e);
+                    skipped_ids.push(val.id);
+                    continue;
+                }
+            };
+            let name = match sensor.sensor_name {
+                Some(r) => r,
+                None => "".to_string(),
+            };
+            let unit = match sensor_type.unit {
+                Some(r) => r,
+                None => "".to_string(),
+            };
+            readings.push(json!({
+                "sensor_id": sensor.sensor_id,
+                "sensor_name": name,
+                "sensor_type": sensor_type.description,
+                "value": val.sensor_value,
+                "unit": unit,
+                "timestamp": val.sensor_time,
+            }));
+            entry_ids.push(val.id);
+            if !sensor_types.contains(&sensor_type.description) {
+                sensor_types.push(sensor_type.description.clone());
+            }
+            timestamp = timestamp.max(val.sensor_time);
+        }
+        // Skipped entries are not selected again
+        if !skipped_ids.is_empty() {
+            update_sensor_entries_to_iota(db_client, &skipped_ids)?;
+        }
+        if entry_ids.is_empty() {
+            continue;
+        }
         // Make Payload
         let payload = json!({
             "did": identity.did,
             "verifiable_credential": vc,
-            "sensor_id": sensor.sensor_id,
-            "sensor_name": name,
-            "sensor_type": sensor_type.description,
-            "value": val.sensor_value,
-            "unit": unit,
-            "timestamp": val.sensor_time,
+            "readings": readings,
+        })
+        .to_string();
+        // Make Public Header, readable without Keyload
+        let public_payload = json!({
+            "schema_version": PAYLOAD_SCHEMA_VERSION,
+            "sensor_types": sensor_types,
+            "timestamp": timestamp,
+            "readings": entry_ids.len(),
         })
         .to_string();
-        info!("Send Message to Tangle");
+        info!(
+            "Send Batch of {} Entries to Tangle, Branch: '{}'",
+            entry_ids.len(),
+            branch
+        );
         // Send IOTA Streams Message
-        let response =
-            send_message_to_tangle(&mut stream_client, &msg_link, &payload, &author_id).await?;
+        let response = send_message_to_tangle(
+            stream_client,
+            &msg_link,
+            branch,
+            &public_payload,
+            &payload,
+            &channel_cfg.author_id,
+        )
+        .await?;
         if response.link.is_empty() {
-            error!("Error: Received Message Link Empty");
-        } else {
-            update_streams_entry(&db_client, &response.link, 0, "msg_link", channel.id)?;
-            update_sensor_entry(&db_client, val.id, "iota", true)?;
-            msg_link = response.link;
+            return Err("Error: Received Message Link Empty".to_string());
         }
+        update_branch_entry(db_client, &response.link, "msg_link", channel_id, branch)?;
+        update_sensor_entries_to_iota(db_client, &entry_ids)?;
+        msg_link = response.link;
     }
-    Ok("Exit with Success: send_sensor_data()".to_string())
+    Ok(())
 }
 
-async fn send_announcement(
+/// Send announcement link over MQTT
+pub async fn send_announcement(
     mqtt_client: &mut MqttOperatorClient<tonic::transport::Channel>,
     db_client: &diesel::SqliteConnection,
     announcement_link: &str,
+    channel_key: &str,
 ) -> Result<String, String> {
     let thing_key = env::var(ENV_THING_KEY).expect("ENV for Thing Key not Found");
     let thing = get_thing(db_client, &thing_key)?;
@@ -155,14 +247,18 @@ async fn send_announcement(
             Some(r) => r,
             None => "".to_string(),
         },
+        public_key: "".to_string(),
+        branches: Vec::new(),
     });
-    helper_send_mqtt(mqtt_client, payload, TOPIC_STREAM).await?;
+    helper_send_mqtt(mqtt_client, payload, TOPIC_STREAM, channel_key).await?;
     Ok("Send Announcement Link".to_string())
 }
 
 async fn send_message_to_tangle(
     stream_client: &mut IotaStreamerClient<tonic::transport::Channel>,
     msg_link: &str,
+    branch: &str,
+    public_payload: &str,
     payload: &str,
     author_id: &str,
 ) -> Result<IotaStreamsReply, String> {
@@ -173,6 +269,8 @@ async fn send_message_to_tangle(
             message_link: msg_link.to_string(),
             message_length: payload.len() as u32,
             message: payload.to_string(),
+            branch: branch.to_string(),
+            public_message: public_payload.to_string(),
         })
         .await
     {
@@ -186,23 +284,50 @@ async fn send_message_to_tangle(
     };
 }
 
-fn get_sensor_data(
+/// Select sensor entries of channel not sent via MQTT
+fn get_unsent_mqtt_data(
     db_client: &diesel::SqliteConnection,
-    query: &str,
-    is_true: bool,
+    channel_id: i32,
 ) -> Result<Vec<SensorData>, String> {
-    match db::select_sensor_entry(db_client, query, is_true, 0) {
+    match db::select_unsent_mqtt_entries(db_client, channel_id) {
         Ok(res) => {
-            info!("Sensor Entries Selected with {}: {}", query, is_true);
+            info!("Unsent MQTT Sensor Entries Selected: {}", res.len());
             return Ok(res);
         }
-        Err(_) => {
-            return Err(format!(
-                "Unable to Select Sensor Entries with {}: {}",
-                query, is_true
-            ))
+        Err(_) => return Err(format!("Unable to Select Unsent MQTT Sensor Entries")),
+    };
+}
+
+/// Select oldest sensor entries of channel or branch sensors not sent to tangle
+fn get_unsent_iota_data(
+    db_client: &diesel::SqliteConnection,
+    channel_id: i32,
+    sensor_ids: &Vec<String>,
+    max_rows: i64,
+) -> Result<Vec<SensorData>, String> {
+    match db::select_unsent_iota_entries(db_client, channel_id, sensor_ids, max_rows) {
+        Ok(res) => {
+            info!("Unsent IOTA Sensor Entries Selected: {}", res.len());
+            return Ok(res);
         }
+        Err(_) => return Err(format!("Unable to Select Unsent IOTA Sensor Entries")),
+    };
+}
+
+/// Batch is ready if full or if oldest entry was received before batch window
+fn is_batch_ready(entries: &Vec<SensorData>, batch: &Batch) -> bool {
+    let oldest = match entries.first() {
+        Some(r) => r,
+        None => return false,
     };
+    if entries.len() as i64 >= batch.max_rows {
+        return true;
+    }
+    let now_ms = match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(r) => r.as_millis() as i64,
+        Err(_) => return true,
+    };
+    now_ms - oldest.received_time * 1000 >= batch.window_ms
 }
 
 fn get_sensor(db_client: &diesel::SqliteConnection, sensor_id: i32) -> Result<Sensor, String> {
@@ -246,6 +371,23 @@ fn update_sensor_entry(
     Ok(())
 }
 
+/// Mark all entries of a batch as sent to tangle
+fn update_sensor_entries_to_iota(
+    db_client: &diesel::SqliteConnection,
+    entry_ids: &Vec<i32>,
+) -> Result<(), String> {
+    match db::update_sensor_entries_to_iota(db_client, entry_ids) {
+        Ok(_) => info!("Sensor Entries Updated to iota = true: {:?}", entry_ids),
+        Err(_) => {
+            return Err(format!(
+                "Unable to Update Sensor Entries to iota = true: {:?}",
+                entry_ids
+            ))
+        }
+    };
+    Ok(())
+}
+
 fn get_streams(db_client: &diesel::SqliteConnection, channel_id: i32) -> Result<Stream, String> {
     match db::select_stream(&db_client, channel_id) {
         Ok(res) => {
@@ -1,35 +1,216 @@
-#![allow(dead_code)]
-use crate::recv_mqtt::receive_mqtt_messages;
-use crate::req_verification::request_identity_verification;
-use crate::send_mqtt::send_sensor_data;
-use tokio::join;
-use tokio::time::{sleep, Duration};
-
-pub async fn state_machine() -> Result<(), Box<dyn std::error::Error>> {
-    info!("--- state_machine() ---");
-    loop {
-        
-        let (rx, tx, id) = join!(
-            // Check for new MQTT Messages
-            receive_mqtt_messages(),
-            // Check for new DB Entries (Search for unsent (MQTT and IOTA) sensor entries and process those)
-            send_sensor_data(),
-            // Check for Unverified Identities
-            request_identity_verification()
-        );
-        match rx {
-            Ok(r) => info!("{}", r),
-            Err(e) => error!("{}", e),
-        }
-        match tx {
-            Ok(r) => info!("{}", r),
-            Err(e) => error!("{}", e),
-        }
-        match id {
-            Ok(r) => info!("{}", r),
-            Err(e) => error!("{}", e),
-        }
-        // Wait ...
-        sleep(Duration::from_millis(10000)).await;
-    }
-}
+#![allow(dead_code)]
+use tokio::join;
+use tokio::sync::mpsc;
+use tokio::time::{sleep, timeout, Duration};
+
+use crate::config::{
+    get_channels, load_config_file, ChannelConfig, DEFAULT_BUFFER_SIZE, TOPIC_COMMAND, TOPIC_DID,
+    TOPIC_IDENTITY, TOPIC_STREAM,
+};
+use crate::config_watcher::config_watcher;
+use crate::connected_sensors::SensorCommand;
+use crate::recv_mqtt::{handle_mqtt_message, mqtt_command, subscribe_mqtt_messages};
+use crate::req_verification::{request_identity_verification, revoke_unverifiable_subscribers};
+use crate::send_mqtt::send_sensor_data;
+use crate::streams_jobs::job_worker;
+/// Events used to wake the gateway workers
+#[derive(Debug)]
+pub enum Event {
+    /// New sensor data saved to DB
+    SensorData,
+    /// MQTT message received: (channel key, topic, payload)
+    MqttMessage(String, String, Vec<u8>),
+    /// New participant identity saved to DB
+    NewIdentity,
+}
+/// Start gateway workers. Each worker waits for events on its own channel,
+/// polling is only used as fallback.
+/// data_tx/data_rx: Channel for sensor data events, sender is shared with sensor GRPC worker
+/// cmd_tx: Channel for commands forwarded to sensors
+pub async fn state_machine(
+    data_tx: mpsc::Sender<Event>,
+    data_rx: mpsc::Receiver<Event>,
+    cmd_tx: mpsc::Sender<SensorCommand>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    info!("--- state_machine() ---");
+    let cfg = load_config_file();
+    let channels = get_channels(&cfg);
+    let poll = Duration::from_millis(cfg.intervals.poll_ms);
+    let identity_intv = Duration::from_millis(cfg.intervals.identity_ms);
+    let config_intv = Duration::from_millis(cfg.intervals.config_ms);
+    let job_intv = Duration::from_millis(cfg.intervals.job_ms);
+    let (mqtt_tx, mqtt_rx) = mpsc::channel::<Event>(DEFAULT_BUFFER_SIZE);
+    let (identity_tx, identity_rx) = mpsc::channel::<Event>(DEFAULT_BUFFER_SIZE);
+    // Subscribe to MQTT Messages of each Channel and Forward them to MQTT Worker,
+    // postfix keeps MQTT client IDs of channels unique
+    for (postfix, channel) in (1..).zip(&channels) {
+        tokio::spawn(mqtt_listener(
+            channel.channel_key.clone(),
+            postfix,
+            mqtt_tx.clone(),
+            poll,
+        ));
+    }
+    drop(mqtt_tx);
+    let _ = join!(
+        // Process MQTT Messages
+        mqtt_worker(
+            mqtt_rx,
+            channels.clone(),
+            data_tx.clone(),
+            identity_tx,
+            cmd_tx
+        ),
+        // Finish Jobs Submitted to Streams Service
+        job_worker(channels.clone(), data_tx, job_intv),
+        // Search for unsent (MQTT and IOTA) sensor entries and process those
+        sensor_data_worker(data_rx, channels.clone(), poll),
+        // Check for Unverified Identities every identity interval or on new identities
+        identity_worker(identity_rx, channels, identity_intv),
+        // Apply Sensor Changes of Configuration File
+        config_watcher(config_intv)
+    );
+    Ok(())
+}
+/// Subscribe to MQTT messages of channel and forward each message to the MQTT worker,
+/// resubscribe after polling interval if stream is closed
+async fn mqtt_listener(channel_key: String, postfix: i32, tx: mpsc::Sender<Event>, poll: Duration) {
+    info!("--- mqtt_listener(): Channel Key: {} ---", &channel_key);
+    loop {
+        let mut stream = match subscribe_mqtt_messages(&channel_key, postfix).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                error!("{}", e);
+                sleep(poll).await;
+                continue;
+            }
+        };
+        loop {
+            match stream.message().await {
+                Ok(Some(msg)) => {
+                    let event = Event::MqttMessage(channel_key.clone(), msg.topic, msg.message);
+                    if let Err(e) = tx.send(event).await {
+                        error!("MQTT Worker Unavailable: {}", e);
+                        return;
+                    }
+                }
+                Ok(None) => {
+                    error!("MQTT Subscription Closed");
+                    break;
+                }
+                Err(e) => {
+                    error!("MQTT Subscription Error: {}", e);
+                    break;
+                }
+            };
+        }
+        sleep(poll).await;
+    }
+}
+/// Process received MQTT messages, wake other workers if needed
+async fn mqtt_worker(
+    mut rx: mpsc::Receiver<Event>,
+    channels: Vec<ChannelConfig>,
+    data_tx: mpsc::Sender<Event>,
+    identity_tx: mpsc::Sender<Event>,
+    cmd_tx: mpsc::Sender<SensorCommand>,
+) {
+    info!("--- mqtt_worker() ---");
+    while let Some(event) = rx.recv().await {
+        let (channel_key, topic, payload) = match event {
+            Event::MqttMessage(channel_key, topic, payload) => (channel_key, topic, payload),
+            e => {
+                error!("Unexpected Event: {:?}", e);
+                continue;
+            }
+        };
+        let channel = match channels.iter().find(|r| r.channel_key.eq(&channel_key)) {
+            Some(r) => r,
+            None => {
+                error!("Channel Not Configured: {}", &channel_key);
+                continue;
+            }
+        };
+        let result = match topic.as_str() {
+            // Commands need access to worker and sensor channels
+            TOPIC_COMMAND => mqtt_command(channel, payload, &data_tx, &cmd_tx).await,
+            _ => handle_mqtt_message(channel, &topic, payload).await,
+        };
+        match result {
+            Ok(_) => match topic.as_str() {
+                // Keyload might be available, try sending stored data
+                TOPIC_STREAM => wake(&data_tx, Event::SensorData),
+                // New identities might be stored, request verification
+                TOPIC_DID | TOPIC_IDENTITY => wake(&identity_tx, Event::NewIdentity),
+                _ => (),
+            },
+            Err(e) => error!("{}", e),
+        };
+    }
+    error!("MQTT Channel Closed");
+}
+/// Send stored sensor data of each channel over MQTT and IOTA Streams, woken on new data
+async fn sensor_data_worker(
+    mut rx: mpsc::Receiver<Event>,
+    channels: Vec<ChannelConfig>,
+    poll: Duration,
+) {
+    info!("--- sensor_data_worker() ---");
+    loop {
+        if !wait_for_event(&mut rx, poll).await {
+            error!("Sensor Data Channel Closed");
+            return;
+        }
+        for channel in &channels {
+            match send_sensor_data(channel).await {
+                Ok(r) => info!("{}: {}", &channel.channel_key, r),
+                Err(e) => error!("{}: {}", &channel.channel_key, e),
+            }
+        }
+    }
+}
+/// Request verification of unverified identities, revoke unverifiable subscribers
+/// of each channel, period: Identity interval used as timeout of waiting for events
+async fn identity_worker(
+    mut rx: mpsc::Receiver<Event>,
+    channels: Vec<ChannelConfig>,
+    period: Duration,
+) {
+    info!("--- identity_worker() ---");
+    loop {
+        if !wait_for_event(&mut rx, period).await {
+            error!("Identity Channel Closed");
+            return;
+        }
+        for channel in &channels {
+            match request_identity_verification(&channel.channel_key).await {
+                Ok(r) => info!("{}: {}", &channel.channel_key, r),
+                Err(e) => error!("{}: {}", &channel.channel_key, e),
+            }
+            match revoke_unverifiable_subscribers(channel).await {
+                Ok(r) => info!("{}: {}", &channel.channel_key, r),
+                Err(e) => error!("{}: {}", &channel.channel_key, e),
+            }
+        }
+    }
+}
+/// Wait for next event or until polling interval elapsed,
+/// events queued in the meantime are handled by the same run.
+/// Returns false if channel is closed
+async fn wait_for_event(rx: &mut mpsc::Receiver<Event>, poll: Duration) -> bool {
+    match timeout(poll, rx.recv()).await {
+        Ok(Some(event)) => info!("Worker Woken by Event: {:?}", event),
+        Ok(None) => return false,
+        Err(_) => info!("Worker Woken by Polling Fallback"),
+    };
+    while rx.try_recv().is_ok() {}
+    true
+}
+/// Wake worker, if channel is full worker is already pending
+pub fn wake(tx: &mpsc::Sender<Event>, event: Event) {
+    match tx.try_send(event) {
+        Ok(_) => (),
+        Err(mpsc::error::TrySendError::Full(_)) => (),
+        Err(e) => error!("Unable to Wake Worker: {}", e),
+    };
+}
@@ -0,0 +1,241 @@
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tokio::time::{interval, Duration};
+use tonic::Code;
+
+use crate::config::{ChannelConfig, ENV_THING_KEY};
+use crate::db_module as db;
+use crate::grpc_streams::iota_streamer_client::IotaStreamerClient;
+use crate::grpc_streams::{IotaStreamsJobQuery, IotaStreamsJobRequest};
+use crate::models::StreamsJob;
+use crate::prolog::init_streams;
+use crate::recv_mqtt::subscriber_added;
+use crate::send_mqtt::send_announcement;
+use crate::state_machine::{wake, Event};
+use crate::util::{connect_mqtt, connect_streams, get_channel};
+
+/// Submit request as job to Streams service and save job, returns job id.
+/// Job is finished by job_worker
+pub async fn submit_streams_job(
+    db_client: &diesel::SqliteConnection,
+    stream_client: &mut IotaStreamerClient<tonic::transport::Channel>,
+    request: IotaStreamsJobRequest,
+    channel_id: i32,
+    did: &str,
+) -> Result<String, String> {
+    let msg_type = request.msg_type as i32;
+    let job = match stream_client.submit_job(tonic::Request::new(request)).await {
+        Ok(res) => res.into_inner(),
+        Err(e) => return Err(format!("Unable to Submit Streams Job: {}", e)),
+    };
+    match db::create_streams_job(
+        db_client,
+        &job.job_id,
+        channel_id,
+        msg_type,
+        did,
+        get_timestamp(),
+    ) {
+        Ok(_) => info!(
+            "Streams Job {} Saved for Channel ID: {}",
+            &job.job_id, channel_id
+        ),
+        Err(e) => return Err(format!("Unable to Save Streams Job: {}", e)),
+    };
+    Ok(job.job_id)
+}
+/// Check jobs submitted to Streams service every period and finish done jobs,
+/// wake sensor data worker if jobs are finished (keyload might be available)
+pub async fn job_worker(
+    channels: Vec<ChannelConfig>,
+    data_tx: mpsc::Sender<Event>,
+    period: Duration,
+) {
+    info!("--- job_worker() ---");
+    let mut timer = interval(period);
+    loop {
+        timer.tick().await;
+        for channel in &channels {
+            match check_jobs(channel).await {
+                Ok(0) => (),
+                Ok(r) => {
+                    info!("{}: {} Streams Jobs Finished", &channel.channel_key, r);
+                    wake(&data_tx, Event::SensorData);
+                }
+                Err(e) => error!("{}: {}", &channel.channel_key, e),
+            }
+        }
+    }
+}
+/// Update state of pending jobs of channel, returns number of done jobs
+async fn check_jobs(channel_cfg: &ChannelConfig) -> Result<u32, String> {
+    let db_client = db::establish_connection();
+    let channel = get_channel(&db_client, &channel_cfg.channel_key)?;
+    let jobs = match db::select_pending_streams_jobs(&db_client, channel.id) {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to Select Streams Jobs: {}", e)),
+    };
+    if jobs.is_empty() {
+        return Ok(0);
+    }
+    let mut stream_client = connect_streams().await?;
+    let mut done = 0;
+    for job in jobs {
+        let reply = match stream_client
+            .get_job(tonic::Request::new(IotaStreamsJobQuery {
+                job_id: job.job_id.clone(),
+            }))
+            .await
+        {
+            Ok(res) => res.into_inner(),
+            // Jobs are kept in memory by Streams service, lost on restart
+            Err(e) if e.code() == Code::NotFound => {
+                error!("Streams Job Lost: {}", &job.job_id);
+                update_job(&db_client, &job.job_id, "failed", "", "Job Lost")?;
+                resubmit_job(&db_client, &mut stream_client, channel_cfg, &job).await?;
+                continue;
+            }
+            Err(e) => return Err(format!("Unable to Get Streams Job: {}", e)),
+        };
+        match reply.state.as_str() {
+            "done" => {
+                match finish_job(
+                    &db_client,
+                    &mut stream_client,
+                    channel_cfg,
+                    &job,
+                    &reply.link,
+                    &reply.seed_nonce,
+                )
+                .await
+                {
+                    Ok(_) => {
+                        update_job(&db_client, &job.job_id, "done", &reply.link, &reply.status)?;
+                        info!("Streams Job {} Finished", &job.job_id);
+                        done += 1;
+                    }
+                    // Job stays pending, finished again on next check
+                    Err(e) => {
+                        error!("Unable to Finish Streams Job {}: {}", &job.job_id, e);
+                        update_job(&db_client, &job.job_id, "running", &reply.link, &e)?;
+                    }
+                };
+            }
+            "failed" => {
+                error!("Streams Job {} Failed: {}", &job.job_id, &reply.status);
+                update_job(&db_client, &job.job_id, "failed", "", &reply.status)?;
+                resubmit_job(&db_client, &mut stream_client, channel_cfg, &job).await?;
+            }
+            state => {
+                if state != job.state {
+                    update_job(&db_client, &job.job_id, state, "", &reply.status)?;
+                }
+            }
+        };
+    }
+    Ok(done)
+}
+/// Submit failed or lost CreateNewAuthor job again, channel is created by next job.
+/// Subscribers are added again on their next subscription message
+async fn resubmit_job(
+    db_client: &diesel::SqliteConnection,
+    stream_client: &mut IotaStreamerClient<tonic::transport::Channel>,
+    channel_cfg: &ChannelConfig,
+    job: &StreamsJob,
+) -> Result<(), String> {
+    if job.msg_type != 1 {
+        return Ok(());
+    }
+    info!("Resubmit Create Channel Job: {}", &job.job_id);
+    match init_streams(
+        db_client,
+        stream_client,
+        job.channel_id,
+        &channel_cfg.author_id,
+        channel_cfg.multi_branch,
+    )
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(_) => Err(format!(
+            "Unable to Resubmit Create Channel Job: {}",
+            &job.job_id
+        )),
+    }
+}
+/// Continue with result of done job:
+/// CreateNewAuthor: Save announcement link and seed nonce (needed to recover the author),
+/// send announcement link,
+/// AddSubscriber: Save subscription and send keyload if all subscribers are added
+async fn finish_job(
+    db_client: &diesel::SqliteConnection,
+    stream_client: &mut IotaStreamerClient<tonic::transport::Channel>,
+    channel_cfg: &ChannelConfig,
+    job: &StreamsJob,
+    link: &str,
+    seed_nonce: &str,
+) -> Result<(), String> {
+    let mut mqtt_client = connect_mqtt().await?;
+    match job.msg_type {
+        // CreateNewAuthor
+        1 => {
+            info!("Announcement Link: {}", link);
+            // Entry exists if finishing job is retried
+            if db::select_stream(&db_client, job.channel_id).is_err() {
+                match db::create_stream(
+                    &db_client,
+                    db::StreamsEntry {
+                        channel_id: job.channel_id,
+                        ann_link: link.to_string(),
+                        sub_link: "".to_string(),
+                        key_link: "".to_string(),
+                        msg_link: "".to_string(),
+                        branch: "".to_string(),
+                        seed_nonce: seed_nonce.to_string(),
+                    },
+                ) {
+                    Ok(_) => info!("Streams Entry Created for Channel ID: {}", job.channel_id),
+                    Err(e) => return Err(format!("Unable to Create Streams Entry: {}", e)),
+                };
+            }
+            info!("Sending MQTT Message with Announcement Link to Streams Topic");
+            send_announcement(&mut mqtt_client, db_client, link, &channel_cfg.channel_key).await?;
+        }
+        // AddSubscriber
+        3 => {
+            let thing_key = env::var(ENV_THING_KEY).expect("ENV for Thing Key not Found");
+            subscriber_added(
+                db_client,
+                stream_client,
+                &mut mqtt_client,
+                channel_cfg,
+                &thing_key,
+                &job.did,
+            )
+            .await?;
+        }
+        e => return Err(format!("Unexpected Message Type of Job: {}", e)),
+    };
+    Ok(())
+}
+
+fn update_job(
+    db_client: &diesel::SqliteConnection,
+    job_id: &str,
+    state: &str,
+    link: &str,
+    status: &str,
+) -> Result<(), String> {
+    match db::update_streams_job(db_client, job_id, state, link, status) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Unable to Update Streams Job: {}", e)),
+    }
+}
+
+fn get_timestamp() -> i64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(r) => r.as_secs() as i64,
+        Err(_) => 0,
+    }
+}
@@ -1,18 +1,14 @@
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
 use std::env;
-use std::fmt::Debug;
-use std::str::FromStr;
 
-use crate::config::{
-    ENV_CHANNEL_KEY, ENV_THING_KEY, ENV_THING_PWD, IDENTITY_SOCKET, MQTT_SOCKET, STREAMS_SOCKET,
-};
+use crate::config::{ENV_THING_KEY, ENV_THING_PWD, IDENTITY_SOCKET, MQTT_SOCKET, STREAMS_SOCKET};
 use crate::db_module as db;
 use crate::grpc_identity::iota_identifier_client::IotaIdentifierClient;
 use crate::grpc_mqtt::mqtt_operator_client::MqttOperatorClient;
 use crate::grpc_mqtt::MqttRequest;
 use crate::grpc_streams::iota_streamer_client::IotaStreamerClient;
-use crate::models::{Channel, Identification, Thing};
+use crate::models::{Channel, Identification, Stream, Thing};
 
 pub fn serialize_msg<T: prost::Message>(msg: &T) -> Vec<u8> {
     let mut buf = Vec::new();
@@ -21,15 +17,6 @@ pub fn serialize_msg<T: prost::Message>(msg: &T) -> Vec<u8> {
     buf
 }
 
-pub fn parse_env<T>(env_var_name: &str) -> T
-where
-    T: FromStr,
-    T::Err: Debug,
-{
-    let var = env::var(env_var_name).unwrap();
-    var.parse().unwrap()
-}
-
 pub fn update_streams_entry(
     db_client: &diesel::SqliteConnection,
     link: &str,
@@ -44,17 +31,92 @@ pub fn update_streams_entry(
     Ok(())
 }
 
+/// Update keyload or message link of branch, "" for channel without branch
+pub fn update_branch_entry(
+    db_client: &diesel::SqliteConnection,
+    link: &str,
+    query: &str,
+    channel_id: i32,
+    branch: &str,
+) -> Result<(), String> {
+    match db::update_branch_stream(db_client, channel_id, branch, query, link, 0) {
+        Ok(_) => info!("Update {} of Branch {} to {}", query, branch, link),
+        Err(_) => {
+            return Err(format!(
+                "Unable to Update {} Entry of Branch {} to {}",
+                query, branch, link
+            ))
+        }
+    };
+    Ok(())
+}
+/// Select streams entry of branch, entry is created from channel entry if missing
+pub fn get_branch_stream(
+    db_client: &diesel::SqliteConnection,
+    channel_id: i32,
+    branch: &str,
+) -> Result<Stream, String> {
+    if let Ok(r) = db::select_branch_stream(db_client, channel_id, branch) {
+        return Ok(r);
+    }
+    let stream = match db::select_stream(db_client, channel_id) {
+        Ok(r) => r,
+        Err(_) => {
+            return Err(format!(
+                "Unable to Select Stream Entry with Channel ID: {}",
+                channel_id
+            ))
+        }
+    };
+    match db::create_stream(
+        db_client,
+        db::StreamsEntry {
+            channel_id: channel_id,
+            ann_link: stream.ann_link,
+            sub_link: "".to_string(),
+            key_link: "".to_string(),
+            msg_link: "".to_string(),
+            branch: branch.to_string(),
+            seed_nonce: "".to_string(),
+        },
+    ) {
+        Ok(_) => info!("Stream Entry Created for Branch: {}", branch),
+        Err(_) => {
+            return Err(format!(
+                "Unable to Create Stream Entry for Branch: {}",
+                branch
+            ))
+        }
+    };
+    match db::select_branch_stream(db_client, channel_id, branch) {
+        Ok(r) => Ok(r),
+        Err(_) => Err(format!(
+            "Unable to Select Stream Entry of Branch: {}",
+            branch
+        )),
+    }
+}
+/// Branch list sent with keyloads over MQTT, empty for channel without branch
+pub fn make_branch_list(branch: &str) -> Vec<String> {
+    if branch.is_empty() {
+        Vec::new()
+    } else {
+        vec![branch.to_string()]
+    }
+}
+
 pub async fn send_mqtt_message(
     client: &mut MqttOperatorClient<tonic::transport::Channel>,
     payload: Vec<u8>,
     topic: &str,
+    channel_id: &str,
 ) -> Result<String, String> {
     let _response = match client
         .send_mqtt_message(tonic::Request::new(MqttRequest {
             id: env::var(ENV_THING_KEY).expect("ENV for Thing Key not Found"),
             postfix: "pub".to_string(),
             pwd: env::var(ENV_THING_PWD).expect("ENV for Thing PWD not Found"),
-            channel: env::var(ENV_CHANNEL_KEY).expect("ENV for Channel Key not Found"),
+            channel: channel_id.to_string(),
             topic: topic.to_string(),
             message: payload,
         }))
@@ -65,6 +127,32 @@ pub async fn send_mqtt_message(
     };
 }
 
+/// Challenge used to sign subscriptions, binds public key and link to DID
+pub fn make_subscription_challenge(did: &str, sub_link: &str, public_key: &str) -> String {
+    format!("{}:{}:{}", did, sub_link, public_key)
+}
+
+/// Challenge used to sign commands, binds signature to command and its issue time
+pub fn make_command_request_challenge(
+    command_id: &str,
+    cmd_type: i32,
+    target: &str,
+    sensor_id: &str,
+    cmd: &str,
+    payload: &str,
+    issued_at: i64,
+) -> String {
+    format!(
+        "{}:{}:{}:{}:{}:{}:{}",
+        command_id, cmd_type, target, sensor_id, cmd, payload, issued_at
+    )
+}
+
+/// Challenge used to sign command results, binds signature to result
+pub fn make_command_challenge(command_id: &str, cmd_type: i32, code: i32, status: &str) -> String {
+    format!("{}:{}:{}:{}", command_id, cmd_type, code, status)
+}
+
 pub fn generate_random_sequence() -> String {
     let rand_string: String = thread_rng()
         .sample_iter(&Alphanumeric)
@@ -110,8 +198,9 @@ pub async fn helper_send_mqtt(
     mqtt_client: &mut MqttOperatorClient<tonic::transport::Channel>,
     payload: Vec<u8>,
     topic: &str,
+    channel_id: &str,
 ) -> Result<(), String> {
-    match send_mqtt_message(mqtt_client, payload, topic).await {
+    match send_mqtt_message(mqtt_client, payload, topic, channel_id).await {
         Ok(_) => info!("MQTT Message Transmitted to Service for Topic {}", topic),
         Err(e) => {
             error!("Error Sending MQTT Message: {}", e);
@@ -1,4 +1,4 @@
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tonic_build::compile_protos("proto/iota_identity_grpc.proto")?;
-    Ok(())
-}
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tonic_build::compile_protos("proto/iota_identity_grpc.proto")?;
+    Ok(())
+}
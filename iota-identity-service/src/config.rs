@@ -1,93 +1,248 @@
-#![allow(dead_code)]
-use confy;
-use serde_derive::{Deserialize, Serialize};
-use std::env;
-
-/// ENV for Stronghold Password
-pub const ENV_STRONGHOLD_PWD: &str = "IDENTITY_STRONGHOLD_PWD";
-/// Default Stronghold Password
-pub const DEFAULT_STRONGHOLD_PWD: &str = "123456";
-/// Folder to hold Stronghold Storage
-pub const STRONGHOLD_FOLDER: &str = "storage";
-/// File Name of Stronghold Storage
-pub const STRONGHOLD_FILE: &str = "identity.hodl";
-
-/// ENV for GRPC Socket
-const ENV_GRPC_SOCKET: &str = "IDENTITY_GRPC_SOCKET";
-/// Default GRPC Socket
-const DEFAULT_GRPC_SOCKET: &str = "0.0.0.0:50053";
-
-/// ENV for Sign Method Name
-const ENV_IDENTITY_FRAGMENT: &str = "IDENTITY_FRAGMENT";
-/// Default Sign Method Name
-const DEFAULT_IDENTITY_FRAGMENT: &str = "dev-1";
-/// ENV for Credential Description (Type)
-const ENV_IDENTITY_CRED_TYPE: &str = "IDENTITY_CRED_TYPE";
-/// Default Credential Description (Type)
-const DEFAULT_IDENTITY_CRED_TYPE: &str = "Device Identification";
-
-/// Structure used to parse configuration file
-#[derive(Debug, Serialize, Deserialize)]
-pub struct IdentityConfig {
-    pub identity: Identity,
-    pub grpc: Grpc,
-}
-/// Socket needed for GRPC server, for example \[::1]:50051
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Grpc {
-    pub socket: String,
-}
-/// Device Indentity (DID and VC), Sign Method Name and Credential Description
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Identity {
-    pub did: String,
-    pub verifiable_credential: String,
-    pub fragment: String,
-    pub cred_type: String,
-}
-/// Default implementation for Configuration File, default can be set via ENVs
-impl Default for IdentityConfig {
-    fn default() -> Self {
-        IdentityConfig {
-            identity: Identity {
-                did: "".to_string(),
-                verifiable_credential: "".to_string(),
-                fragment: env::var(ENV_IDENTITY_FRAGMENT)
-                    .unwrap_or_else(|_| DEFAULT_IDENTITY_FRAGMENT.to_string()),
-                cred_type: env::var(ENV_IDENTITY_CRED_TYPE)
-                    .unwrap_or_else(|_| DEFAULT_IDENTITY_CRED_TYPE.to_string()),
-            },
-            grpc: Grpc {
-                socket: env::var(ENV_GRPC_SOCKET)
-                    .unwrap_or_else(|_| DEFAULT_GRPC_SOCKET.to_string()),
-            },
-        }
-    }
-}
-/// Saving changes made in IdentityConfig structure to configuration file "identity-grpc.toml"
-/// located at ./config/
-pub fn save_config_file(cfg: IdentityConfig) -> Result<String, String> {
-    let config_dir = env::current_dir()
-        .unwrap()
-        .join("config")
-        .join("identity-grpc.toml");
-    let res = confy::store_path(config_dir, cfg);
-    match res {
-        Ok(_) => return Ok("Config file saved".to_string()),
-        Err(e) => return Err(format!("Error saving config file: {}", e)),
-    };
-}
-/// Configuration file "identity-grpc.toml" is located at ./config/
-/// Function tries to load configuration or creates default
-pub fn load_config_file() -> IdentityConfig {
-    let config_dir = env::current_dir()
-        .unwrap()
-        .join("config")
-        .join("identity-grpc.toml");
-    let res = confy::load_path(config_dir);
-    let cfg: IdentityConfig = match res {
-        Ok(r) => r,
-        Err(_e) => IdentityConfig::default(),
-    };
-    cfg
-}
+#![allow(dead_code)]
+use confy;
+use serde_derive::{Deserialize, Serialize};
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// ENV for Stronghold Password
+pub const ENV_STRONGHOLD_PWD: &str = "IDENTITY_STRONGHOLD_PWD";
+/// Default Stronghold Password
+pub const DEFAULT_STRONGHOLD_PWD: &str = "123456";
+/// Folder to hold Stronghold Storage
+pub const STRONGHOLD_FOLDER: &str = "storage";
+/// File Name of Stronghold Storage
+pub const STRONGHOLD_FILE: &str = "identity.hodl";
+
+/// ENV for GRPC Socket
+const ENV_GRPC_SOCKET: &str = "IDENTITY_GRPC_SOCKET";
+/// Default GRPC Socket, local only as IssueCredential, RevokeCredential and RotateKey
+/// use the identity of the service
+const DEFAULT_GRPC_SOCKET: &str = "127.0.0.1:50053";
+/// ENV for Token needed by IssueCredential, RevokeCredential and RotateKey
+const ENV_ADMIN_TOKEN: &str = "IDENTITY_ADMIN_TOKEN";
+/// Metadata Key of Admin Token in GRPC Requests
+pub const ADMIN_TOKEN_KEY: &str = "x-admin-token";
+
+/// ENV for Sign Method Name
+const ENV_IDENTITY_FRAGMENT: &str = "IDENTITY_FRAGMENT";
+/// Default Sign Method Name
+const DEFAULT_IDENTITY_FRAGMENT: &str = "dev-1";
+/// ENV for Credential Description (Type)
+const ENV_IDENTITY_CRED_TYPE: &str = "IDENTITY_CRED_TYPE";
+/// Default Credential Description (Type)
+const DEFAULT_IDENTITY_CRED_TYPE: &str = "Device Identification";
+
+/// File Name of persisted DID Document Cache, located in STRONGHOLD_FOLDER
+pub const DOCUMENT_CACHE_FILE: &str = "documents.json";
+/// ENV for Time Resolved DID Documents are used without Resolving again
+const ENV_CACHE_TTL: &str = "IDENTITY_CACHE_TTL_S";
+/// Default Time to Live of Cached DID Documents in s
+const DEFAULT_CACHE_TTL: u64 = 300;
+/// ENV for Maximum Age of Cached DID Documents used if Node is Unreachable
+const ENV_CACHE_MAX_STALE: &str = "IDENTITY_CACHE_MAX_STALE_MIN";
+/// Default Maximum Age of Cached DID Documents used if Node is Unreachable in min,
+/// bounds how long a rotated or revoked key can still be accepted
+const DEFAULT_CACHE_MAX_STALE: u64 = 30;
+/// ENV for Persisting Cached DID Documents to Disk ("true", "false")
+const ENV_CACHE_PERSIST: &str = "IDENTITY_CACHE_PERSIST";
+
+/// ENV for Identity Backend ("iota", "local")
+const ENV_BACKEND: &str = "IDENTITY_BACKEND";
+/// Default Identity Backend
+const DEFAULT_BACKEND: &str = "iota";
+/// ENV for Directory of Local DID Documents, shared by all local backends
+const ENV_REGISTRY_DIR: &str = "IDENTITY_REGISTRY_DIR";
+/// Default Directory of Local DID Documents
+const DEFAULT_REGISTRY_DIR: &str = "./registry";
+/// File Name of Local Private Keys, located in STRONGHOLD_FOLDER
+pub const LOCAL_KEYS_FILE: &str = "local-keys.json";
+
+/// Fragment of Service in DID Document listing Revoked Credential Indices
+pub const REVOCATION_SERVICE: &str = "revocation";
+/// Type of Revocation Service, also Type of Credential Status
+pub const REVOCATION_SERVICE_TYPE: &str = "RevocationList";
+
+/// ENV for Trusted Issuer DIDs, separated by ','
+const ENV_TRUSTED_ISSUERS: &str = "IDENTITY_TRUSTED_ISSUERS";
+
+/// Interval of checking for retired keys with expired grace period in s
+pub const REVOCATION_INTERVAL_S: u64 = 60;
+
+/// Structure used to parse configuration file
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IdentityConfig {
+    pub identity: Identity,
+    pub grpc: Grpc,
+    #[serde(default)]
+    pub cache: Cache,
+    #[serde(default)]
+    pub backend: Backend,
+    #[serde(default)]
+    pub trust: Trust,
+}
+/// Socket needed for GRPC server, for example \[::1]:50051,
+/// admin_token: Token needed by calls using the identity of the service, calls refused if empty
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Grpc {
+    pub socket: String,
+    #[serde(default = "default_admin_token")]
+    pub admin_token: String,
+}
+/// Default admin token, default can be set via ENV
+fn default_admin_token() -> String {
+    env::var(ENV_ADMIN_TOKEN).unwrap_or_default()
+}
+/// Device Indentity (DID and VC), Sign Method Name and Credential Description,
+/// retired: Replaced Sign Methods waiting for revocation,
+/// pending_fragment: Sign Method of started rotation, saved before it is published,
+/// issued_credential: Credential issued by third party, presented instead of VC if set,
+/// credential_index: Revocation index of next credential issued by this identity,
+/// fragment_index: Revocation index of first credential signed by fragment
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Identity {
+    pub did: String,
+    pub verifiable_credential: String,
+    pub fragment: String,
+    pub cred_type: String,
+    #[serde(default)]
+    pub retired: Vec<RetiredKey>,
+    #[serde(default)]
+    pub pending_fragment: String,
+    #[serde(default)]
+    pub issued_credential: String,
+    #[serde(default)]
+    pub credential_index: u32,
+    #[serde(default)]
+    pub fragment_index: u32,
+}
+/// Replaced Sign Method, revoked at revoke_at (Unix time in s)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RetiredKey {
+    pub fragment: String,
+    pub revoke_at: u64,
+}
+/// Cache of resolved DID Documents, documents older than ttl_s are resolved again,
+/// documents up to max_stale_min are used if resolving fails
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Cache {
+    pub ttl_s: u64,
+    pub max_stale_min: u64,
+    pub persist: bool,
+}
+/// Default cache settings, default can be set via ENVs
+impl Default for Cache {
+    fn default() -> Self {
+        Cache {
+            ttl_s: env::var(ENV_CACHE_TTL)
+                .ok()
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(DEFAULT_CACHE_TTL),
+            max_stale_min: env::var(ENV_CACHE_MAX_STALE)
+                .ok()
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(DEFAULT_CACHE_MAX_STALE),
+            persist: env::var(ENV_CACHE_PERSIST)
+                .ok()
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(false),
+        }
+    }
+}
+/// Identity Backend, kind: "iota" (Stronghold and Tangle) or "local" (Ed25519 keys and
+/// DID Documents in registry_dir, no network needed)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Backend {
+    pub kind: String,
+    pub registry_dir: String,
+}
+/// Default backend settings, default can be set via ENVs
+impl Default for Backend {
+    fn default() -> Self {
+        Backend {
+            kind: env::var(ENV_BACKEND).unwrap_or_else(|_| DEFAULT_BACKEND.to_string()),
+            registry_dir: env::var(ENV_REGISTRY_DIR)
+                .unwrap_or_else(|_| DEFAULT_REGISTRY_DIR.to_string()),
+        }
+    }
+}
+/// DIDs of trusted credential issuers, all issuers (also self-issued credentials)
+/// are accepted if empty
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Trust {
+    pub issuers: Vec<String>,
+}
+/// Default trust settings, default can be set via ENVs
+impl Default for Trust {
+    fn default() -> Self {
+        Trust {
+            issuers: env::var(ENV_TRUSTED_ISSUERS)
+                .map(|r| {
+                    r.split(',')
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+/// Default implementation for Configuration File, default can be set via ENVs
+impl Default for IdentityConfig {
+    fn default() -> Self {
+        IdentityConfig {
+            identity: Identity {
+                did: "".to_string(),
+                verifiable_credential: "".to_string(),
+                fragment: env::var(ENV_IDENTITY_FRAGMENT)
+                    .unwrap_or_else(|_| DEFAULT_IDENTITY_FRAGMENT.to_string()),
+                cred_type: env::var(ENV_IDENTITY_CRED_TYPE)
+                    .unwrap_or_else(|_| DEFAULT_IDENTITY_CRED_TYPE.to_string()),
+                retired: Vec::new(),
+                pending_fragment: "".to_string(),
+                issued_credential: "".to_string(),
+                credential_index: 0,
+                fragment_index: 0,
+            },
+            grpc: Grpc {
+                socket: env::var(ENV_GRPC_SOCKET)
+                    .unwrap_or_else(|_| DEFAULT_GRPC_SOCKET.to_string()),
+                admin_token: default_admin_token(),
+            },
+            cache: Cache::default(),
+            backend: Backend::default(),
+            trust: Trust::default(),
+        }
+    }
+}
+/// Saving changes made in IdentityConfig structure to configuration file "identity-grpc.toml"
+/// located at ./config/
+pub fn save_config_file(cfg: IdentityConfig) -> Result<String, String> {
+    save_config_to(cfg, &env::current_dir().unwrap())
+}
+/// Configuration file "identity-grpc.toml" is located at ./config/
+/// Function tries to load configuration or creates default
+pub fn load_config_file() -> IdentityConfig {
+    load_config_from(&env::current_dir().unwrap())
+}
+/// Saving IdentityConfig to configuration file located at base_dir/config/
+pub fn save_config_to(cfg: IdentityConfig, base_dir: &Path) -> Result<String, String> {
+    let res = confy::store_path(get_config_path(base_dir), cfg);
+    match res {
+        Ok(_) => return Ok("Config file saved".to_string()),
+        Err(e) => return Err(format!("Error saving config file: {}", e)),
+    };
+}
+/// Load configuration file located at base_dir/config/ or create default
+pub fn load_config_from(base_dir: &Path) -> IdentityConfig {
+    let res = confy::load_path(get_config_path(base_dir));
+    let cfg: IdentityConfig = match res {
+        Ok(r) => r,
+        Err(_e) => IdentityConfig::default(),
+    };
+    cfg
+}
+
+fn get_config_path(base_dir: &Path) -> PathBuf {
+    base_dir.join("config").join("identity-grpc.toml")
+}
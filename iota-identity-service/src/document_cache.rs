@@ -0,0 +1,263 @@
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use identity::core::{FromJson, ToJson};
+use identity::iota::ResolvedIotaDocument;
+
+use crate::config::{Cache, DOCUMENT_CACHE_FILE, STRONGHOLD_FOLDER};
+/// Resolved DID Document, resolved_at: Unix time in s
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    document: ResolvedIotaDocument,
+    resolved_at: u64,
+}
+/// Cache entry saved to disk, document as JSON
+#[derive(Debug, Serialize, Deserialize)]
+struct PersistedEntry {
+    document: String,
+    resolved_at: u64,
+}
+/// Resolved DID Documents by DID, shared by all GRPC calls.
+/// Saved to ./STRONGHOLD_FOLDER/DOCUMENT_CACHE_FILE on every change if persist is set
+#[derive(Debug, Clone)]
+pub struct DocumentCache {
+    entries: Arc<Mutex<HashMap<String, CacheEntry>>>,
+    cfg: Cache,
+    path: PathBuf,
+}
+
+impl DocumentCache {
+    pub fn new(cfg: Cache) -> Self {
+        DocumentCache::with_path(cfg, get_cache_path())
+    }
+    /// Cache persisted to path instead of default file
+    pub fn with_path(cfg: Cache, path: PathBuf) -> Self {
+        let entries = if cfg.persist {
+            load_entries(&path)
+        } else {
+            HashMap::new()
+        };
+        DocumentCache {
+            entries: Arc::new(Mutex::new(entries)),
+            cfg: cfg,
+            path: path,
+        }
+    }
+    /// Cached document of DID and its age in s
+    pub fn get(&self, did: &str) -> Option<(ResolvedIotaDocument, u64)> {
+        let entries = self.entries.lock().unwrap();
+        entries.get(did).map(|entry| {
+            (
+                entry.document.clone(),
+                get_unix_time().saturating_sub(entry.resolved_at),
+            )
+        })
+    }
+    /// Document of age is used without resolving again
+    pub fn is_fresh(&self, age: u64) -> bool {
+        age < self.cfg.ttl_s
+    }
+    /// Document of age (s) is used if resolving fails
+    pub fn is_usable(&self, age: u64) -> bool {
+        age <= self.cfg.max_stale_min * 60
+    }
+
+    pub fn insert(&self, did: &str, document: ResolvedIotaDocument) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.insert(
+            did.to_string(),
+            CacheEntry {
+                document: document,
+                resolved_at: get_unix_time(),
+            },
+        );
+        self.save(&entries);
+    }
+    /// Remove document of DID, all documents if DID is empty.
+    /// Returns number of removed documents
+    pub fn invalidate(&self, did: &str) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let removed = if did.is_empty() {
+            let removed = entries.len();
+            entries.clear();
+            removed
+        } else {
+            match entries.remove(did) {
+                Some(_) => 1,
+                None => 0,
+            }
+        };
+        self.save(&entries);
+        info!("Removed {} Cached Documents", removed);
+        removed
+    }
+
+    fn save(&self, entries: &HashMap<String, CacheEntry>) {
+        if !self.cfg.persist {
+            return;
+        }
+        if let Err(e) = save_entries(&self.path, entries) {
+            error!("Unable to Save Document Cache: {}", e);
+        }
+    }
+}
+
+fn get_cache_path() -> PathBuf {
+    Path::new(".")
+        .join(STRONGHOLD_FOLDER)
+        .join(DOCUMENT_CACHE_FILE)
+}
+/// Load persisted documents, empty if no file or file unreadable
+fn load_entries(path: &Path) -> HashMap<String, CacheEntry> {
+    if !path.exists() {
+        return HashMap::new();
+    }
+    let persisted: HashMap<String, PersistedEntry> = match std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|r| serde_json::from_str(&r).map_err(|e| e.to_string()))
+    {
+        Ok(r) => r,
+        Err(e) => {
+            error!("Unable to Load Document Cache: {}", e);
+            return HashMap::new();
+        }
+    };
+    let mut entries = HashMap::new();
+    for (did, entry) in persisted {
+        match ResolvedIotaDocument::from_json(&entry.document) {
+            Ok(document) => {
+                entries.insert(
+                    did,
+                    CacheEntry {
+                        document: document,
+                        resolved_at: entry.resolved_at,
+                    },
+                );
+            }
+            Err(e) => error!("Unable to Parse Cached Document of DID {}: {}", did, e),
+        };
+    }
+    info!("Loaded {} Cached Documents", entries.len());
+    entries
+}
+/// Write documents to temporary file and rename it, file is never partially written
+fn save_entries(path: &Path, entries: &HashMap<String, CacheEntry>) -> Result<(), String> {
+    let mut persisted = HashMap::new();
+    for (did, entry) in entries {
+        let document = match entry.document.to_json() {
+            Ok(r) => r,
+            Err(e) => return Err(format!("Unable to Serialize Document: {}", e)),
+        };
+        persisted.insert(
+            did.clone(),
+            PersistedEntry {
+                document: document,
+                resolved_at: entry.resolved_at,
+            },
+        );
+    }
+    let data = match serde_json::to_string(&persisted) {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to Serialize Document Cache: {}", e)),
+    };
+    let tmp_path = path.with_extension("json.tmp");
+    let mut file = match std::fs::File::create(&tmp_path) {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to Create Cache File: {}", e)),
+    };
+    if let Err(e) = file
+        .write_all(data.as_bytes())
+        .and_then(|_| file.sync_all())
+    {
+        return Err(format!("Unable to Write Cache File: {}", e));
+    }
+    match std::fs::rename(&tmp_path, path) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Unable to Replace Cache File: {}", e)),
+    }
+}
+
+pub fn get_unix_time() -> u64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(r) => r.as_secs(),
+        Err(_) => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use identity::crypto::{KeyPair, KeyType};
+    use identity::iota::IotaDocument;
+
+    fn make_cache(persist: bool, path: PathBuf) -> DocumentCache {
+        DocumentCache::with_path(
+            Cache {
+                ttl_s: 300,
+                max_stale_min: 30,
+                persist: persist,
+            },
+            path,
+        )
+    }
+    fn make_document() -> (String, ResolvedIotaDocument) {
+        let keypair = KeyPair::new(KeyType::Ed25519).unwrap();
+        let document = IotaDocument::new(&keypair).unwrap();
+        (
+            document.id().to_string(),
+            ResolvedIotaDocument::from(document),
+        )
+    }
+    fn get_test_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}_{}.json", name, std::process::id()))
+    }
+    #[test]
+    fn test_is_fresh_is_usable() {
+        let cache = make_cache(false, get_test_path("test_is_fresh"));
+        assert!(cache.is_fresh(0));
+        assert!(cache.is_fresh(299));
+        assert!(!cache.is_fresh(300));
+        assert!(cache.is_usable(300));
+        assert!(cache.is_usable(30 * 60));
+        assert!(!cache.is_usable(30 * 60 + 1));
+    }
+    #[test]
+    fn test_invalidate() {
+        let cache = make_cache(false, get_test_path("test_invalidate"));
+        let (did, document) = make_document();
+        let (other_did, other_document) = make_document();
+        cache.insert(&did, document);
+        cache.insert(&other_did, other_document);
+        let (_, age) = cache.get(&did).unwrap();
+        assert!(cache.is_fresh(age));
+        assert_eq!(cache.invalidate(&did), 1);
+        assert!(cache.get(&did).is_none());
+        assert!(cache.get(&other_did).is_some());
+        assert_eq!(cache.invalidate(&did), 0);
+        assert_eq!(cache.invalidate(""), 1);
+        assert!(cache.get(&other_did).is_none());
+    }
+    #[test]
+    fn test_persist() {
+        let path = get_test_path("test_persist");
+        let (did, document) = make_document();
+        let (other_did, other_document) = make_document();
+        let cache = make_cache(true, path.clone());
+        cache.insert(&did, document.clone());
+        cache.insert(&other_did, other_document);
+        cache.invalidate(&other_did);
+        // Loaded on start-up
+        let loaded = make_cache(true, path.clone());
+        let (loaded_document, _) = loaded.get(&did).unwrap();
+        assert_eq!(loaded_document.document.id(), document.document.id());
+        assert!(loaded.get(&other_did).is_none());
+        // Not loaded if persist is not set
+        let not_loaded = make_cache(false, path.clone());
+        assert!(not_loaded.get(&did).is_none());
+        std::fs::remove_file(path).unwrap();
+    }
+}
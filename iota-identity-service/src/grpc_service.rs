@@ -1,97 +1,334 @@
-use grpc_identity::iota_identifier_server::IotaIdentifier;
-use grpc_identity::{IotaIdentityCreationRequest, IotaIdentityReply, IotaIdentityRequest};
-use tonic::{Request, Response, Status};
-
-use crate::iota_identity_module as identity;
-
-/// Protobuffer v3 file
-pub mod grpc_identity {
-    tonic::include_proto!("iota_identity_grpc");
-}
-/// Structure for Implementing GRPC Calls
-#[derive(Debug, Default)]
-pub struct IotaIdentityService {}
-/// Implementation of GRPC Calls
-/// create_identity, verify_identity, proof_identity
-#[tonic::async_trait]
-impl IotaIdentifier for IotaIdentityService {
-    async fn create_identity(
-        &self,
-        request: Request<IotaIdentityCreationRequest>,
-    ) -> Result<Response<IotaIdentityReply>, Status> {
-        let request = request.into_inner();
-        info!("create_new_identity: {:?}", request);
-        let reply = match identity::create_identity(&request.verifiable_credential).await {
-            Ok(r) => r,
-            Err(e) => {
-                return Err(Status::cancelled(format!(
-                    "Unable to Create Identity: {}",
-                    e
-                )))
-            }
-        };
-
-        Ok(Response::new(IotaIdentityReply {
-            did: reply.did,
-            challenge: reply.challenge,
-            verifiable_credential: reply.verifiable_credential,
-            status: reply.status,
-            code: reply.code,
-        }))
-    }
-
-    async fn verify_identity(
-        &self,
-        request: Request<IotaIdentityRequest>,
-    ) -> Result<Response<IotaIdentityReply>, Status> {
-        let request = request.into_inner();
-        info!("verify_identity: {:?}", request);
-        let reply = match identity::verify_identity(identity::IdentityInformationRequest {
-            did: request.did,
-            challenge: request.challenge,
-            verifiable_credential: request.verifiable_credential,
-        })
-        .await
-        {
-            Ok(r) => r,
-            Err(e) => {
-                return Err(Status::cancelled(format!(
-                    "Unable to Verify Identity: {}",
-                    e
-                )))
-            }
-        };
-        Ok(Response::new(IotaIdentityReply {
-            did: reply.did,
-            challenge: reply.challenge,
-            verifiable_credential: reply.verifiable_credential,
-            status: reply.status,
-            code: reply.code,
-        }))
-    }
-
-    async fn proof_identity(
-        &self,
-        request: Request<IotaIdentityRequest>,
-    ) -> Result<Response<IotaIdentityReply>, Status> {
-        let request = request.into_inner();
-        info!("proof_identity: {:?}", request);
-        let reply = match identity::proof_identity(identity::IdentityInformationRequest {
-            did: request.did,
-            challenge: request.challenge,
-            verifiable_credential: request.verifiable_credential,
-        })
-        .await
-        {
-            Ok(r) => r,
-            Err(e) => return Err(Status::cancelled(format!("Unable to Create Proof: {}", e))),
-        };
-        Ok(Response::new(IotaIdentityReply {
-            did: reply.did,
-            challenge: reply.challenge,
-            verifiable_credential: reply.verifiable_credential,
-            status: reply.status,
-            code: reply.code,
-        }))
-    }
-}
+use grpc_identity::iota_identifier_server::IotaIdentifier;
+use grpc_identity::{
+    IotaIdentityCreationRequest, IotaIdentityIssueRequest, IotaIdentityReply, IotaIdentityRequest,
+    IotaIdentityRotationRequest,
+};
+use std::sync::Arc;
+use tonic::{Request, Response, Status};
+
+use crate::config::ADMIN_TOKEN_KEY;
+use crate::identity_backend::IdentityBackend;
+use crate::iota_identity_module as identity;
+
+/// Protobuffer v3 file
+pub mod grpc_identity {
+    tonic::include_proto!("iota_identity_grpc");
+}
+/// Structure for Implementing GRPC Calls
+/// backend: Identity backend selected in config file,
+/// admin_token: Token needed by calls using the identity of the service
+pub struct IotaIdentityService {
+    backend: Arc<dyn IdentityBackend>,
+    admin_token: String,
+}
+
+impl IotaIdentityService {
+    pub fn new(backend: Arc<dyn IdentityBackend>, admin_token: String) -> IotaIdentityService {
+        IotaIdentityService {
+            backend: backend,
+            admin_token: admin_token,
+        }
+    }
+    /// Check admin token in metadata of request, all calls refused if no token is configured
+    fn authorize<T>(&self, request: &Request<T>) -> Result<(), Status> {
+        if self.admin_token.is_empty() {
+            return Err(Status::permission_denied("No Admin Token Configured"));
+        }
+        let token = match request.metadata().get(ADMIN_TOKEN_KEY) {
+            Some(r) => r.as_bytes(),
+            None => return Err(Status::unauthenticated("Admin Token Missing")),
+        };
+        if !is_token_equal(token, self.admin_token.as_bytes()) {
+            warn!("Request with Invalid Admin Token Refused");
+            return Err(Status::unauthenticated("Invalid Admin Token"));
+        }
+        Ok(())
+    }
+}
+/// Compare tokens in constant time, time does not reveal matching prefix
+fn is_token_equal(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b.iter()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+/// Implementation of GRPC Calls
+/// create_identity, verify_identity, proof_identity, invalidate_document, rotate_key,
+/// issue_credential, revoke_credential, check_revocation
+#[tonic::async_trait]
+impl IotaIdentifier for IotaIdentityService {
+    async fn create_identity(
+        &self,
+        request: Request<IotaIdentityCreationRequest>,
+    ) -> Result<Response<IotaIdentityReply>, Status> {
+        let request = request.into_inner();
+        info!("create_new_identity: {:?}", request);
+        let reply = match self
+            .backend
+            .create_identity(&request.verifiable_credential)
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                return Err(Status::cancelled(format!(
+                    "Unable to Create Identity: {}",
+                    e
+                )))
+            }
+        };
+
+        Ok(Response::new(IotaIdentityReply {
+            did: reply.did,
+            challenge: reply.challenge,
+            verifiable_credential: reply.verifiable_credential,
+            status: reply.status,
+            code: reply.code,
+            cache_age: reply.cache_age,
+        }))
+    }
+
+    async fn verify_identity(
+        &self,
+        request: Request<IotaIdentityRequest>,
+    ) -> Result<Response<IotaIdentityReply>, Status> {
+        let request = request.into_inner();
+        info!("verify_identity: {:?}", request);
+        let reply = match self
+            .backend
+            .verify_identity(identity::IdentityInformationRequest {
+                did: request.did,
+                challenge: request.challenge,
+                verifiable_credential: request.verifiable_credential,
+            })
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                return Err(Status::cancelled(format!(
+                    "Unable to Verify Identity: {}",
+                    e
+                )))
+            }
+        };
+        Ok(Response::new(IotaIdentityReply {
+            did: reply.did,
+            challenge: reply.challenge,
+            verifiable_credential: reply.verifiable_credential,
+            status: reply.status,
+            code: reply.code,
+            cache_age: reply.cache_age,
+        }))
+    }
+
+    async fn proof_identity(
+        &self,
+        request: Request<IotaIdentityRequest>,
+    ) -> Result<Response<IotaIdentityReply>, Status> {
+        let request = request.into_inner();
+        info!("proof_identity: {:?}", request);
+        let reply = match self
+            .backend
+            .proof_identity(identity::IdentityInformationRequest {
+                did: request.did,
+                challenge: request.challenge,
+                verifiable_credential: request.verifiable_credential,
+            })
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => return Err(Status::cancelled(format!("Unable to Create Proof: {}", e))),
+        };
+        Ok(Response::new(IotaIdentityReply {
+            did: reply.did,
+            challenge: reply.challenge,
+            verifiable_credential: reply.verifiable_credential,
+            status: reply.status,
+            code: reply.code,
+            cache_age: reply.cache_age,
+        }))
+    }
+
+    async fn invalidate_document(
+        &self,
+        request: Request<IotaIdentityRequest>,
+    ) -> Result<Response<IotaIdentityReply>, Status> {
+        let request = request.into_inner();
+        info!("invalidate_document: {:?}", request.did);
+        let removed = self.backend.invalidate_document(&request.did);
+        Ok(Response::new(IotaIdentityReply {
+            did: request.did,
+            challenge: "".to_string(),
+            verifiable_credential: "".to_string(),
+            status: format!("Removed Cached Documents: {}", removed),
+            code: 0,
+            cache_age: 0,
+        }))
+    }
+
+    async fn rotate_key(
+        &self,
+        request: Request<IotaIdentityRotationRequest>,
+    ) -> Result<Response<IotaIdentityReply>, Status> {
+        self.authorize(&request)?;
+        let request = request.into_inner();
+        info!("rotate_key: {:?}", request);
+        let reply = match self
+            .backend
+            .rotate_key(identity::KeyRotationRequest {
+                did: request.did,
+                revoke: request.revoke,
+                grace_s: request.grace_s,
+            })
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => return Err(Status::cancelled(format!("Unable to Rotate Key: {}", e))),
+        };
+        Ok(Response::new(IotaIdentityReply {
+            did: reply.did,
+            challenge: reply.challenge,
+            verifiable_credential: reply.verifiable_credential,
+            status: reply.status,
+            code: reply.code,
+            cache_age: reply.cache_age,
+        }))
+    }
+
+    async fn issue_credential(
+        &self,
+        request: Request<IotaIdentityIssueRequest>,
+    ) -> Result<Response<IotaIdentityReply>, Status> {
+        self.authorize(&request)?;
+        let request = request.into_inner();
+        info!("issue_credential: {:?}", request);
+        let reply = match self
+            .backend
+            .issue_credential(identity::CredentialIssueRequest {
+                did: request.did,
+                verifiable_credential: request.verifiable_credential,
+            })
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                return Err(Status::cancelled(format!(
+                    "Unable to Issue Credential: {}",
+                    e
+                )))
+            }
+        };
+        Ok(Response::new(IotaIdentityReply {
+            did: reply.did,
+            challenge: reply.challenge,
+            verifiable_credential: reply.verifiable_credential,
+            status: reply.status,
+            code: reply.code,
+            cache_age: reply.cache_age,
+        }))
+    }
+
+    async fn revoke_credential(
+        &self,
+        request: Request<IotaIdentityRequest>,
+    ) -> Result<Response<IotaIdentityReply>, Status> {
+        self.authorize(&request)?;
+        let request = request.into_inner();
+        info!("revoke_credential: {:?}", request);
+        let reply = match self
+            .backend
+            .revoke_credential(identity::IdentityInformationRequest {
+                did: request.did,
+                challenge: request.challenge,
+                verifiable_credential: request.verifiable_credential,
+            })
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                return Err(Status::cancelled(format!(
+                    "Unable to Revoke Credential: {}",
+                    e
+                )))
+            }
+        };
+        Ok(Response::new(IotaIdentityReply {
+            did: reply.did,
+            challenge: reply.challenge,
+            verifiable_credential: reply.verifiable_credential,
+            status: reply.status,
+            code: reply.code,
+            cache_age: reply.cache_age,
+        }))
+    }
+
+    async fn check_revocation(
+        &self,
+        request: Request<IotaIdentityRequest>,
+    ) -> Result<Response<IotaIdentityReply>, Status> {
+        let request = request.into_inner();
+        info!("check_revocation: {:?}", request);
+        let reply = match self
+            .backend
+            .check_revocation(identity::IdentityInformationRequest {
+                did: request.did,
+                challenge: request.challenge,
+                verifiable_credential: request.verifiable_credential,
+            })
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                return Err(Status::cancelled(format!(
+                    "Unable to Check Revocation: {}",
+                    e
+                )))
+            }
+        };
+        Ok(Response::new(IotaIdentityReply {
+            did: reply.did,
+            challenge: reply.challenge,
+            verifiable_credential: reply.verifiable_credential,
+            status: reply.status,
+            code: reply.code,
+            cache_age: reply.cache_age,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::IdentityConfig;
+    use crate::identity_backend::make_backend;
+
+    fn make_request(token: Option<&str>) -> Request<()> {
+        let mut request = Request::new(());
+        if let Some(token) = token {
+            request
+                .metadata_mut()
+                .insert(ADMIN_TOKEN_KEY, token.parse().unwrap());
+        }
+        request
+    }
+    #[test]
+    fn test_authorize() {
+        let backend = make_backend(&IdentityConfig::default());
+        let service = IotaIdentityService::new(backend.clone(), "secret".to_string());
+        assert!(service.authorize(&make_request(Some("secret"))).is_ok());
+        for token in [Some("secreT"), Some("secret1"), Some(""), None] {
+            assert_eq!(
+                service.authorize(&make_request(token)).unwrap_err().code(),
+                tonic::Code::Unauthenticated
+            );
+        }
+        // No token configured, all calls refused
+        let service = IotaIdentityService::new(backend, "".to_string());
+        assert_eq!(
+            service
+                .authorize(&make_request(Some("")))
+                .unwrap_err()
+                .code(),
+            tonic::Code::PermissionDenied
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::{interval, Duration};
+
+use crate::config::{IdentityConfig, REVOCATION_INTERVAL_S};
+use crate::document_cache::DocumentCache;
+use crate::iota_identity_module::{
+    CredentialIssueRequest, IdentityInformationReply, IdentityInformationRequest,
+    KeyRotationRequest,
+};
+use crate::{iota_identity_module, local_identity_module};
+
+/// Operations of identity backend, selected by kind in section backend of config file
+#[tonic::async_trait]
+pub trait IdentityBackend: Send + Sync {
+    async fn create_identity(&self, vc: &str) -> Result<IdentityInformationReply, String>;
+
+    async fn proof_identity(
+        &self,
+        request: IdentityInformationRequest,
+    ) -> Result<IdentityInformationReply, String>;
+
+    async fn verify_identity(
+        &self,
+        request: IdentityInformationRequest,
+    ) -> Result<IdentityInformationReply, String>;
+
+    async fn rotate_key(
+        &self,
+        request: KeyRotationRequest,
+    ) -> Result<IdentityInformationReply, String>;
+    /// Revoke retired keys with expired grace period, returns number of revoked keys
+    async fn revoke_expired_keys(&self) -> Result<usize, String>;
+
+    async fn issue_credential(
+        &self,
+        request: CredentialIssueRequest,
+    ) -> Result<IdentityInformationReply, String>;
+
+    async fn revoke_credential(
+        &self,
+        request: IdentityInformationRequest,
+    ) -> Result<IdentityInformationReply, String>;
+
+    async fn check_revocation(
+        &self,
+        request: IdentityInformationRequest,
+    ) -> Result<IdentityInformationReply, String>;
+    /// Remove cached document of DID, all documents if DID is empty.
+    /// Returns number of removed documents
+    fn invalidate_document(&self, did: &str) -> usize;
+}
+
+/// Stronghold account, DID Documents resolved from Tangle and cached,
+/// update_lock: Serializes calls updating config file or DID Document
+pub struct IotaBackend {
+    cache: DocumentCache,
+    update_lock: Mutex<()>,
+}
+
+#[tonic::async_trait]
+impl IdentityBackend for IotaBackend {
+    async fn create_identity(&self, vc: &str) -> Result<IdentityInformationReply, String> {
+        let _lock = self.update_lock.lock().await;
+        iota_identity_module::create_identity(vc).await
+    }
+
+    async fn proof_identity(
+        &self,
+        request: IdentityInformationRequest,
+    ) -> Result<IdentityInformationReply, String> {
+        iota_identity_module::proof_identity(request).await
+    }
+
+    async fn verify_identity(
+        &self,
+        request: IdentityInformationRequest,
+    ) -> Result<IdentityInformationReply, String> {
+        iota_identity_module::verify_identity(request, &self.cache).await
+    }
+
+    async fn rotate_key(
+        &self,
+        request: KeyRotationRequest,
+    ) -> Result<IdentityInformationReply, String> {
+        let _lock = self.update_lock.lock().await;
+        iota_identity_module::rotate_key(request, &self.cache).await
+    }
+
+    async fn revoke_expired_keys(&self) -> Result<usize, String> {
+        let _lock = self.update_lock.lock().await;
+        iota_identity_module::revoke_expired_keys(&self.cache).await
+    }
+
+    async fn issue_credential(
+        &self,
+        request: CredentialIssueRequest,
+    ) -> Result<IdentityInformationReply, String> {
+        let _lock = self.update_lock.lock().await;
+        iota_identity_module::issue_credential(request).await
+    }
+
+    async fn revoke_credential(
+        &self,
+        request: IdentityInformationRequest,
+    ) -> Result<IdentityInformationReply, String> {
+        let _lock = self.update_lock.lock().await;
+        iota_identity_module::revoke_credential(request, &self.cache).await
+    }
+
+    async fn check_revocation(
+        &self,
+        request: IdentityInformationRequest,
+    ) -> Result<IdentityInformationReply, String> {
+        iota_identity_module::check_revocation(request, &self.cache).await
+    }
+
+    fn invalidate_document(&self, did: &str) -> usize {
+        self.cache.invalidate(did)
+    }
+}
+
+/// Ed25519 keys on disk, DID Documents in registry_dir, no network needed,
+/// base_dir: Directory of config file and keys,
+/// update_lock: Serializes calls updating config file, keys or DID Documents
+pub struct LocalBackend {
+    registry_dir: String,
+    base_dir: PathBuf,
+    update_lock: Mutex<()>,
+}
+
+#[tonic::async_trait]
+impl IdentityBackend for LocalBackend {
+    async fn create_identity(&self, vc: &str) -> Result<IdentityInformationReply, String> {
+        let _lock = self.update_lock.lock().await;
+        local_identity_module::create_identity(vc, &self.registry_dir, &self.base_dir)
+    }
+
+    async fn proof_identity(
+        &self,
+        request: IdentityInformationRequest,
+    ) -> Result<IdentityInformationReply, String> {
+        local_identity_module::proof_identity(request, &self.registry_dir, &self.base_dir)
+    }
+
+    async fn verify_identity(
+        &self,
+        request: IdentityInformationRequest,
+    ) -> Result<IdentityInformationReply, String> {
+        local_identity_module::verify_identity(request, &self.registry_dir, &self.base_dir)
+    }
+
+    async fn rotate_key(
+        &self,
+        request: KeyRotationRequest,
+    ) -> Result<IdentityInformationReply, String> {
+        let _lock = self.update_lock.lock().await;
+        local_identity_module::rotate_key(request, &self.registry_dir, &self.base_dir)
+    }
+
+    async fn revoke_expired_keys(&self) -> Result<usize, String> {
+        let _lock = self.update_lock.lock().await;
+        local_identity_module::revoke_expired_keys(&self.registry_dir, &self.base_dir)
+    }
+
+    async fn issue_credential(
+        &self,
+        request: CredentialIssueRequest,
+    ) -> Result<IdentityInformationReply, String> {
+        let _lock = self.update_lock.lock().await;
+        local_identity_module::issue_credential(request, &self.registry_dir, &self.base_dir)
+    }
+
+    async fn revoke_credential(
+        &self,
+        request: IdentityInformationRequest,
+    ) -> Result<IdentityInformationReply, String> {
+        let _lock = self.update_lock.lock().await;
+        local_identity_module::revoke_credential(request, &self.registry_dir, &self.base_dir)
+    }
+
+    async fn check_revocation(
+        &self,
+        request: IdentityInformationRequest,
+    ) -> Result<IdentityInformationReply, String> {
+        local_identity_module::check_revocation(request, &self.registry_dir, &self.base_dir)
+    }
+    /// Documents are read from registry on every call, nothing cached
+    fn invalidate_document(&self, _did: &str) -> usize {
+        0
+    }
+}
+
+/// Backend of kind set in config file, "iota" if kind is unknown
+pub fn make_backend(cfg: &IdentityConfig) -> Arc<dyn IdentityBackend> {
+    match cfg.backend.kind.as_str() {
+        "local" => {
+            info!(
+                "Local Identity Backend, Registry: {}",
+                &cfg.backend.registry_dir
+            );
+            warn!("Local Identity Backend Saves Private Keys Unencrypted, Use for Testing Only");
+            Arc::new(LocalBackend {
+                registry_dir: cfg.backend.registry_dir.clone(),
+                base_dir: PathBuf::from("."),
+                update_lock: Mutex::new(()),
+            })
+        }
+        kind => {
+            if kind != "iota" {
+                warn!("Unknown Identity Backend '{}', IOTA Backend Used", kind);
+            }
+            info!("IOTA Identity Backend");
+            Arc::new(IotaBackend {
+                cache: DocumentCache::new(cfg.cache.clone()),
+                update_lock: Mutex::new(()),
+            })
+        }
+    }
+}
+/// Revoke retired keys with expired grace period every REVOCATION_INTERVAL_S
+pub async fn revocation_worker(backend: Arc<dyn IdentityBackend>) {
+    let mut timer = interval(Duration::from_secs(REVOCATION_INTERVAL_S));
+    loop {
+        timer.tick().await;
+        match backend.revoke_expired_keys().await {
+            Ok(0) => (),
+            Ok(r) => info!("{} Retired Keys Revoked", r),
+            Err(e) => error!("Unable to Revoke Retired Keys: {}", e),
+        };
+    }
+}
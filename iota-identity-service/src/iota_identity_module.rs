@@ -1,278 +1,1124 @@
-use serde_json::Value;
-use std::env;
-use std::path::{Path, PathBuf};
-
-use identity::account::{Account, AccountStorage, IdentitySetup, Result};
-use identity::core::{FromJson, ToJson, Url};
-use identity::credential::{Credential, Subject};
-use identity::crypto::SignatureOptions;
-use identity::did::verifiable::VerifierOptions;
-use identity::did::DID;
-use identity::iota::{IotaDID, ResolvedIotaDocument, Resolver};
-
-use crate::config::{
-    load_config_file, save_config_file, DEFAULT_STRONGHOLD_PWD, ENV_STRONGHOLD_PWD,
-    STRONGHOLD_FILE, STRONGHOLD_FOLDER,
-};
-/// Structure for exchanging data needed to make proofs,
-/// verify and exchange identity information for requests
-#[derive(Debug, Default)]
-pub struct IdentityInformationRequest {
-    pub did: String,
-    pub challenge: String,
-    pub verifiable_credential: String,
-}
-/// Structure for exchanging data needed to make proofs,
-/// verify and exchange identity information for replies
-#[derive(Debug, Default)]
-pub struct IdentityInformationReply {
-    pub did: String,
-    pub challenge: String,
-    pub verifiable_credential: String,
-    pub status: String,
-    pub code: i32,
-}
-/// Verifies verifiable credential signed with challenge.
-/// Structure IdentityInformation needs did, challenge and VC to verifiy
-/// Returns verification status ("Verified" or "Not Verified") or error
-pub async fn verify_identity(
-    request: IdentityInformationRequest,
-) -> Result<IdentityInformationReply, String> {
-    let did = parse_did(&request.did)?;
-    // Make credential form string
-    let credential = parse_vc(&request.verifiable_credential)?;
-    // Fetch the DID Document from the Tangle
-    let resolver: Resolver = match Resolver::new().await {
-        Ok(r) => r,
-        Err(e) => {
-            error!("Unable to Fetch Document from Tangle: {}", e);
-            return Err(format!("Unable to Fetch Document from Tangle: {}", e));
-        }
-    };
-    let resolved: ResolvedIotaDocument = match resolver.resolve(&did).await {
-        Ok(r) => r,
-        Err(e) => {
-            error!("Unable to Resolve Document: {}", e);
-            return Err(format!("Unable to Resolve Document: {}", e));
-        }
-    };
-    // Ensure the resolved DID Document can verify the credential signature
-    let verified: bool = resolved
-        .document
-        .verify_data(
-            &credential,
-            &VerifierOptions {
-                method_scope: None,
-                method_type: None,
-                challenge: Some(request.challenge.clone()),
-                domain: None,
-                purpose: None,
-                allow_expired: None,
-            },
-        )
-        .is_ok();
-    let (status, code) = if verified {
-        ("Identity Successfully Verified".to_string(), 0)
-    } else {
-        ("Unable to Verify Identity".to_string(), -1)
-    };
-    info!("DID: '{}' is Verified: {}", did, verified);
-    Ok(IdentityInformationReply {
-        did: request.did,
-        challenge: request.challenge,
-        verifiable_credential: request.verifiable_credential,
-        status: status,
-        code: code,
-    })
-}
-/// Generates verifiable credential signed with challenge.
-/// Structure IdentityInformation needs did and challenge
-/// Returns verification DID, Challenge (used to sign VC) and signed VC or error
-pub async fn proof_identity(
-    request: IdentityInformationRequest,
-) -> Result<IdentityInformationReply, String> {
-    let cfg = load_config_file();
-    // Load account from disk
-    let stronghold_path: PathBuf = Path::new(".").join(STRONGHOLD_FOLDER).join(STRONGHOLD_FILE);
-    let password: String =
-        env::var(ENV_STRONGHOLD_PWD).unwrap_or_else(|_| DEFAULT_STRONGHOLD_PWD.to_string());
-    // Parse DID
-    let did = parse_did(&request.did)?;
-    let account: Account = match Account::builder()
-        .storage(AccountStorage::Stronghold(
-            stronghold_path,
-            Some(password),
-            None,
-        ))
-        .load_identity(did)
-        .await
-    {
-        Ok(r) => r,
-        Err(e) => {
-            error!("Unable to Import Account: {}", e);
-            return Err(format!("Unable to Import Account: {}", e));
-        }
-    };
-    // Load VC and convert to Credential
-    let mut credential = parse_vc(&cfg.identity.verifiable_credential)?;
-    // Sign the Credential with Challenge
-    match account
-        .sign(
-            cfg.identity.fragment.as_str(),
-            &mut credential,
-            SignatureOptions {
-                created: None,
-                expires: None,
-                challenge: Some(request.challenge.clone()),
-                domain: None,
-                purpose: None,
-            },
-        )
-        .await
-    {
-        Ok(_) => info!(
-            "Signed Verifiable Credential with Challenge: {}",
-            &request.challenge
-        ),
-        Err(e) => {
-            error!(
-                "Unable to Sign VC with Challenge '{}': {}",
-                &request.challenge, e
-            );
-            return Err(format!(
-                "Unable to Sign VC with Challenge '{}': {}",
-                &request.challenge, e
-            ));
-        }
-    };
-    let vc = credential_to_json(credential)?;
-    Ok(IdentityInformationReply {
-        did: request.did,
-        challenge: request.challenge,
-        verifiable_credential: vc,
-        status: "Ok".to_string(),
-        code: 0,
-    })
-}
-/// Creates new identity.
-/// Structure IdentityCreation needs device ID, Name and Type
-/// Returns DID, unsigned VC (DID and VC also saved to config file) or error
-pub async fn create_identity(vc: &str) -> Result<IdentityInformationReply, String> {
-    let mut cfg = load_config_file();
-    // Stronghold settings
-    let stronghold_path: PathBuf = Path::new(".").join(STRONGHOLD_FOLDER).join(STRONGHOLD_FILE);
-    let password: String =
-        env::var(ENV_STRONGHOLD_PWD).unwrap_or_else(|_| DEFAULT_STRONGHOLD_PWD.to_string());
-    // Create a new Account with stronghold storage.
-    let mut account: Account = match Account::builder()
-        .storage(AccountStorage::Stronghold(
-            stronghold_path,
-            Some(password),
-            None,
-        ))
-        .create_identity(IdentitySetup::default())
-        .await
-    {
-        Ok(r) => r,
-        Err(e) => {
-            error!("Unable to Create Account: {}", e);
-            return Err(format!("Unable to Create Account: {}", e));
-        }
-    };
-    // Add a new Ed25519 Verification Method to the identity
-    match account
-        .update_identity()
-        .create_method()
-        .fragment(cfg.identity.fragment.as_str())
-        .apply()
-        .await
-    {
-        Ok(_r) => info!("New Verification Method Added to Account"),
-        Err(e) => {
-            error!("Unable to Add Verification Method to Account: {}", e);
-            return Err(format!(
-                "Unable to Add Verification Method to Account: {}",
-                e
-            ));
-        }
-    };
-    // Create a subject DID for the recipient of a `UniversityDegree` credential.
-    let vc_json: Value = match serde_json::from_str(vc) {
-        Ok(r) => r,
-        Err(e) => {
-            error!("Unable to Parse VC to JSON: {}", e);
-            return Err(format!("Unable to Parse VC to JSON: {}", e));
-        }
-    };
-    let subject: Subject = match Subject::from_json_value(vc_json) {
-        Ok(r) => r,
-        Err(e) => {
-            error!("Unable to Parse VC to Subject: {}", e);
-            return Err(format!("Unable to Parse VC to Subject: {}", e));
-        }
-    };
-    // Issue an unsigned Credential...
-    let did = match Url::parse(account.did().as_str()) {
-        Ok(r) => r,
-        Err(e) => {
-            error!("Unable to Import DID From Account: {}", e);
-            return Err(format!("Unable to Import DID From Account: {}", e));
-        }
-    };
-    let credential: Credential = match Credential::builder(Default::default())
-        .issuer(did.clone())
-        .type_(cfg.identity.cred_type.as_str())
-        .subject(subject)
-        .build()
-    {
-        Ok(r) => r,
-        Err(e) => {
-            error!("Unable to Create VC: {}", e);
-            return Err(format!("Unable to Create VC: {}", e));
-        }
-    };
-    let cred_json = credential_to_json(credential)?;
-    // Save VC to file
-    info!("Save DID: {} and VC {} to Config File", &did, &cred_json);
-    cfg.identity.did = did.to_string();
-    cfg.identity.verifiable_credential = cred_json.clone();
-    save_config_file(cfg)?;
-
-    Ok(IdentityInformationReply {
-        did: did.to_string(),
-        challenge: "".to_string(),
-        verifiable_credential: cred_json,
-        status: "Ok".to_string(),
-        code: 0,
-    })
-}
-
-fn credential_to_json(credential: Credential) -> Result<String, String> {
-    match credential.to_json() {
-        Ok(r) => return Ok(r),
-        Err(e) => {
-            error!("Unable to Parse Credential to JSON: {}", e);
-            return Err(format!("Unable to Parse Credential to JSON: {}", e));
-        }
-    };
-}
-
-fn parse_did(did: &str) -> Result<IotaDID, String> {
-    match IotaDID::parse(did) {
-        Ok(res) => return Ok(res),
-        Err(e) => {
-            error!("Unable to Parse DID: {}", e);
-            return Err(format!("Unable to Parse DID: {}", e));
-        }
-    };
-}
-
-fn parse_vc(vc: &str) -> Result<Credential, String> {
-    match Credential::from_json(vc) {
-        Ok(r) => return Ok(r),
-        Err(e) => {
-            error!("Unable to Parse VC from Request: {}", e);
-            return Err(format!("Unable to Parse VC from Request: {}", e));
-        }
-    };
-}
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::env;
+use std::path::{Path, PathBuf};
+
+use identity::account::{Account, AccountStorage, IdentitySetup, Result};
+use identity::core::{FromJson, ToJson, Url};
+use identity::credential::{Credential, Presentation, Subject};
+use identity::crypto::{SetSignature, SignatureOptions, TrySignature};
+use identity::did::verifiable::VerifierOptions;
+use identity::did::DID;
+use identity::iota::{IotaDID, IotaDocument, ResolvedIotaDocument, Resolver};
+
+use crate::config::{
+    load_config_file, load_config_from, save_config_to, IdentityConfig, RetiredKey, Trust,
+    DEFAULT_STRONGHOLD_PWD, ENV_STRONGHOLD_PWD, REVOCATION_SERVICE, REVOCATION_SERVICE_TYPE,
+    STRONGHOLD_FILE, STRONGHOLD_FOLDER,
+};
+use crate::document_cache::{get_unix_time, DocumentCache};
+/// Structure for exchanging data needed to make proofs,
+/// verify and exchange identity information for requests
+#[derive(Debug, Default)]
+pub struct IdentityInformationRequest {
+    pub did: String,
+    pub challenge: String,
+    pub verifiable_credential: String,
+}
+/// Structure for exchanging data needed to make proofs,
+/// verify and exchange identity information for replies
+/// cache_age: Age of DID Document used for verification in s, 0 if resolved by call
+#[derive(Debug, Default)]
+pub struct IdentityInformationReply {
+    pub did: String,
+    pub challenge: String,
+    pub verifiable_credential: String,
+    pub status: String,
+    pub code: i32,
+    pub cache_age: u64,
+}
+/// Structure for rotating the key of the identity,
+/// old key is revoked after grace_s if revoke is set
+#[derive(Debug, Default)]
+pub struct KeyRotationRequest {
+    pub did: String,
+    pub revoke: bool,
+    pub grace_s: u64,
+}
+/// Structure for issuing a credential with subject verifiable_credential (JSON) to did
+#[derive(Debug, Default)]
+pub struct CredentialIssueRequest {
+    pub did: String,
+    pub verifiable_credential: String,
+}
+/// Verifies verifiable credential or presentation signed with challenge.
+/// Structure IdentityInformation needs did, challenge and VC to verifiy
+/// DID Document is taken from cache if younger than TTL
+/// Returns verification status ("Verified" or "Not Verified") or error,
+/// code -2 if issuer of credential is not trusted
+pub async fn verify_identity(
+    request: IdentityInformationRequest,
+    cache: &DocumentCache,
+) -> Result<IdentityInformationReply, String> {
+    let cfg = load_config_file();
+    let did = parse_did(&request.did)?;
+    let (resolved, mut cache_age) = resolve_document(&did, cache).await?;
+    // Ensure the resolved DID Document can verify the credential signature
+    let mut verified = verify_proof(
+        &resolved.document,
+        &request.verifiable_credential,
+        &request.challenge,
+    )?;
+    // Cached DID Document might not contain rotated key yet
+    if !verified && cache_age > 0 {
+        if let Ok(resolved) = resolve_from_tangle(&did).await {
+            cache.insert(did.as_str(), resolved.clone());
+            verified = verify_proof(
+                &resolved.document,
+                &request.verifiable_credential,
+                &request.challenge,
+            )?;
+            cache_age = 0;
+        }
+    }
+    info!("DID: '{}' is Verified: {}", did, verified);
+    if !verified {
+        return Ok(make_verification_reply(request, -1, cache_age));
+    }
+    let code = match get_issued_credential(&request.verifiable_credential)? {
+        // Issuer resolved for every verification, cached revocation list might be outdated
+        Some(credential) => {
+            let issuer = get_issuer_did(&credential)?;
+            let issuer_document = resolve_from_tangle(&issuer).await.map(|resolved| {
+                cache.insert(issuer.as_str(), resolved.clone());
+                resolved.document
+            });
+            check_resolved_issuer(did.as_str(), &credential, issuer_document, &cfg.trust)?
+        }
+        // Self-issued credential
+        None => check_issuer(did.as_str(), &cfg.trust),
+    };
+    Ok(make_verification_reply(request, code, cache_age))
+}
+/// Verify signature of credential or presentation (JSON) signed with challenge by DID Document
+pub fn verify_proof(document: &IotaDocument, vc: &str, challenge: &str) -> Result<bool, String> {
+    if is_presentation(vc) {
+        let presentation = parse_presentation(vc)?;
+        Ok(verify_signature(document, &presentation, Some(challenge)))
+    } else {
+        let credential = parse_vc(vc)?;
+        Ok(verify_signature(document, &credential, Some(challenge)))
+    }
+}
+/// Verify signature of data by DID Document, challenge is not checked if None
+pub fn verify_signature<X: Serialize + TrySignature>(
+    document: &IotaDocument,
+    data: &X,
+    challenge: Option<&str>,
+) -> bool {
+    document
+        .verify_data(
+            data,
+            &VerifierOptions {
+                method_scope: None,
+                method_type: None,
+                challenge: challenge.map(|r| r.to_string()),
+                domain: None,
+                purpose: None,
+                allow_expired: None,
+            },
+        )
+        .is_ok()
+}
+/// Check credential issued to DID, issuer_document: DID Document of issuer.
+/// Returns 0 if credential is valid and issuer trusted, -1 if invalid, -2 if not trusted,
+/// -3 if revoked
+pub fn check_issued_credential(
+    did: &str,
+    credential: &Credential,
+    issuer_document: &IotaDocument,
+    trust: &Trust,
+) -> i32 {
+    let is_subject = credential
+        .credential_subject
+        .iter()
+        .any(|r| r.id.as_ref().map(|id| id.as_str()) == Some(did));
+    if !is_subject {
+        error!("Issued Credential Not Issued to DID: {}", did);
+        return -1;
+    }
+    if !verify_signature(issuer_document, credential, None) {
+        error!("Unable to Verify Issued Credential of DID: {}", did);
+        return -1;
+    }
+    match is_revoked(credential, issuer_document) {
+        Ok(true) => {
+            error!("Issued Credential of DID {} is Revoked", did);
+            return -3;
+        }
+        Ok(false) => (),
+        Err(e) => {
+            error!("Unable to Check Revocation of Credential: {}", e);
+            return -1;
+        }
+    };
+    check_issuer(issuer_document.id().as_str(), trust)
+}
+/// Check credential issued to DID with resolved DID Document of issuer,
+/// error if issuer could not be resolved, unreachable node is no failed verification
+pub fn check_resolved_issuer(
+    did: &str,
+    credential: &Credential,
+    issuer_document: Result<IotaDocument, String>,
+    trust: &Trust,
+) -> Result<i32, String> {
+    match issuer_document {
+        Ok(r) => Ok(check_issued_credential(did, credential, &r, trust)),
+        Err(e) => {
+            error!(
+                "Unable to Resolve Issuer {} for Revocation Check: {}",
+                credential.issuer.url(),
+                e
+            );
+            Err(format!(
+                "Unable to Resolve Issuer for Revocation Check: {}",
+                e
+            ))
+        }
+    }
+}
+/// Returns 0 if issuer is trusted or no trusted issuers are set, -2 if not
+pub fn check_issuer(issuer: &str, trust: &Trust) -> i32 {
+    if trust.issuers.is_empty() || trust.issuers.iter().any(|r| r == issuer) {
+        0
+    } else {
+        error!("Issuer Not Trusted: {}", issuer);
+        -2
+    }
+}
+/// Reply of verification, code: 0 if verified, -1 if not, -2 if issuer not trusted,
+/// -3 if credential revoked
+pub fn make_verification_reply(
+    request: IdentityInformationRequest,
+    code: i32,
+    cache_age: u64,
+) -> IdentityInformationReply {
+    let status = match code {
+        0 => "Identity Successfully Verified".to_string(),
+        -2 => "Issuer of Credential Not Trusted".to_string(),
+        -3 => "Credential Revoked".to_string(),
+        _ => "Unable to Verify Identity".to_string(),
+    };
+    IdentityInformationReply {
+        did: request.did,
+        challenge: request.challenge,
+        verifiable_credential: request.verifiable_credential,
+        status: status,
+        code: code,
+        cache_age: cache_age,
+    }
+}
+/// Resolve DID Document, taken from cache if younger than TTL.
+/// If resolving fails, cached documents up to max stale age are used
+/// Returns DID Document and its age in s
+async fn resolve_document(
+    did: &IotaDID,
+    cache: &DocumentCache,
+) -> Result<(ResolvedIotaDocument, u64), String> {
+    let cached = match cache.get(did.as_str()) {
+        Some((document, age)) if cache.is_fresh(age) => {
+            info!("Cached Document Used for DID: {}, Age: {} s", did, age);
+            return Ok((document, age));
+        }
+        cached => cached,
+    };
+    match resolve_from_tangle(did).await {
+        Ok(document) => {
+            cache.insert(did.as_str(), document.clone());
+            Ok((document, 0))
+        }
+        Err(e) => match cached {
+            Some((document, age)) if cache.is_usable(age) => {
+                warn!("{}, Cached Document Used, Age: {} s", e, age);
+                Ok((document, age))
+            }
+            _ => Err(e),
+        },
+    }
+}
+/// Fetch the DID Document from the Tangle
+async fn resolve_from_tangle(did: &IotaDID) -> Result<ResolvedIotaDocument, String> {
+    let resolver: Resolver = match Resolver::new().await {
+        Ok(r) => r,
+        Err(e) => {
+            error!("Unable to Fetch Document from Tangle: {}", e);
+            return Err(format!("Unable to Fetch Document from Tangle: {}", e));
+        }
+    };
+    match resolver.resolve(did).await {
+        Ok(r) => return Ok(r),
+        Err(e) => {
+            error!("Unable to Resolve Document: {}", e);
+            return Err(format!("Unable to Resolve Document: {}", e));
+        }
+    };
+}
+/// Generates verifiable credential signed with challenge,
+/// presentation of issued credential if set in config file.
+/// Structure IdentityInformation needs did and challenge
+/// Returns verification DID, Challenge (used to sign VC) and signed VC or error
+pub async fn proof_identity(
+    request: IdentityInformationRequest,
+) -> Result<IdentityInformationReply, String> {
+    let cfg = load_config_file();
+    // Load account from disk
+    let account = load_account(&request.did).await?;
+    let vc = if cfg.identity.issued_credential.is_empty() {
+        // Load VC and convert to Credential
+        let mut credential = parse_vc(&cfg.identity.verifiable_credential)?;
+        // Sign the Credential with Challenge
+        sign_with_account(
+            &account,
+            &cfg.identity.fragment,
+            &mut credential,
+            Some(&request.challenge),
+        )
+        .await?;
+        credential_to_json(credential)?
+    } else {
+        let mut presentation = make_presentation(&request.did, &cfg.identity.issued_credential)?;
+        sign_with_account(
+            &account,
+            &cfg.identity.fragment,
+            &mut presentation,
+            Some(&request.challenge),
+        )
+        .await?;
+        presentation_to_json(presentation)?
+    };
+    info!(
+        "Signed Verifiable Credential with Challenge: {}",
+        &request.challenge
+    );
+    Ok(IdentityInformationReply {
+        did: request.did,
+        challenge: request.challenge,
+        verifiable_credential: vc,
+        status: "Ok".to_string(),
+        code: 0,
+        cache_age: 0,
+    })
+}
+/// Issues credential to DID of request, signed by identity of service.
+/// Returns DID and signed credential or error
+pub async fn issue_credential(
+    request: CredentialIssueRequest,
+) -> Result<IdentityInformationReply, String> {
+    let cfg = load_config_file();
+    let issuer = get_identity_did(&cfg, "")?;
+    let credential = build_credential(
+        &issuer,
+        &request.did,
+        &request.verifiable_credential,
+        &cfg.identity.cred_type,
+    )?;
+    let mut credential =
+        set_credential_status(credential, &issuer, next_credential_index(Path::new("."))?)?;
+    let account = load_account(&issuer).await?;
+    sign_with_account(&account, &cfg.identity.fragment, &mut credential, None).await?;
+    info!("Credential Issued to DID: {}", &request.did);
+    Ok(make_issue_reply(
+        &request.did,
+        credential_to_json(credential)?,
+    ))
+}
+/// Revokes credential (JSON) issued by identity of service,
+/// revocation index is added to revocation list of DID Document
+/// Returns status or error, code -3 if credential is revoked
+pub async fn revoke_credential(
+    request: IdentityInformationRequest,
+    cache: &DocumentCache,
+) -> Result<IdentityInformationReply, String> {
+    let cfg = load_config_file();
+    let issuer = get_identity_did(&cfg, "")?;
+    let index = get_own_revocation_index(&request.verifiable_credential, &issuer)?;
+    let mut account = load_account(&issuer).await?;
+    let revoked = get_revocation_list(account.document())?;
+    let mut list = revoked.clone().unwrap_or_default();
+    if list.contains(&index) {
+        return Ok(make_revocation_reply(request, true));
+    }
+    list.push(index);
+    list.sort_unstable();
+    let endpoint = match Url::parse(&make_revocation_endpoint(&list)) {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to Create Revocation List: {}", e)),
+    };
+    // Service is replaced, endpoint can not be updated
+    if revoked.is_some() {
+        if let Err(e) = account
+            .update_identity()
+            .delete_service()
+            .fragment(REVOCATION_SERVICE)
+            .apply()
+            .await
+        {
+            error!("Unable to Remove Revocation List: {}", e);
+            return Err(format!("Unable to Remove Revocation List: {}", e));
+        }
+    }
+    match account
+        .update_identity()
+        .create_service()
+        .fragment(REVOCATION_SERVICE)
+        .type_(REVOCATION_SERVICE_TYPE)
+        .endpoint(endpoint)
+        .apply()
+        .await
+    {
+        Ok(_) => info!("Credential with Revocation Index {} Revoked", index),
+        Err(e) => {
+            error!("Unable to Publish Revocation List: {}", e);
+            return Err(format!("Unable to Publish Revocation List: {}", e));
+        }
+    };
+    cache.invalidate(&issuer);
+    Ok(make_revocation_reply(request, true))
+}
+/// Checks if credential or presentation (JSON) is revoked by issuer,
+/// DID Document of issuer is always resolved
+/// Returns status or error, code -3 if credential is revoked
+pub async fn check_revocation(
+    request: IdentityInformationRequest,
+    cache: &DocumentCache,
+) -> Result<IdentityInformationReply, String> {
+    let credential = get_credential(&request.verifiable_credential)?;
+    let issuer = get_issuer_did(&credential)?;
+    let resolved = resolve_from_tangle(&issuer).await?;
+    cache.insert(issuer.as_str(), resolved.clone());
+    let revoked = is_revoked(&credential, &resolved.document)?;
+    Ok(make_revocation_reply(request, revoked))
+}
+/// Sign data with method fragment of account, challenge is not set if None
+async fn sign_with_account<U: Serialize + SetSignature>(
+    account: &Account,
+    fragment: &str,
+    data: &mut U,
+    challenge: Option<&str>,
+) -> Result<(), String> {
+    match account
+        .sign(
+            fragment,
+            data,
+            SignatureOptions {
+                created: None,
+                expires: None,
+                challenge: challenge.map(|r| r.to_string()),
+                domain: None,
+                purpose: None,
+            },
+        )
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            error!("Unable to Sign VC: {}", e);
+            Err(format!("Unable to Sign VC: {}", e))
+        }
+    }
+}
+/// Adds new verification method to identity and publishes DID Document,
+/// proofs are signed with new method. Old method is revoked after grace_s if revoke is set,
+/// revoke is refused while credentials signed by old method are not revoked
+/// Returns DID and fragment of new method or error
+pub async fn rotate_key(
+    request: KeyRotationRequest,
+    cache: &DocumentCache,
+) -> Result<IdentityInformationReply, String> {
+    let cfg = load_config_file();
+    let did = get_identity_did(&cfg, &request.did)?;
+    let mut account = load_account(&did).await?;
+    if request.revoke {
+        check_outstanding_credentials(&cfg, account.document())?;
+    }
+    let fragment = get_rotation_fragment(&cfg, Path::new("."))?;
+    // Method might already be published if rotation was interrupted
+    if account
+        .document()
+        .resolve_method(fragment.as_str())
+        .is_some()
+    {
+        info!("Verification Method '{}' Already Published", &fragment);
+    } else {
+        match account
+            .update_identity()
+            .create_method()
+            .fragment(fragment.as_str())
+            .apply()
+            .await
+        {
+            Ok(_r) => info!("New Verification Method '{}' Added to Account", &fragment),
+            Err(e) => {
+                error!("Unable to Add Verification Method to Account: {}", e);
+                return Err(format!(
+                    "Unable to Add Verification Method to Account: {}",
+                    e
+                ));
+            }
+        };
+    }
+    // Cached DID Document does not contain new method
+    cache.invalidate(&did);
+    save_rotation(
+        cfg,
+        &fragment,
+        request.revoke,
+        request.grace_s,
+        Path::new("."),
+    )?;
+    Ok(make_rotation_reply(&did, &fragment))
+}
+/// Removes retired verification methods with expired grace period from identity
+/// Returns number of revoked methods or error
+pub async fn revoke_expired_keys(cache: &DocumentCache) -> Result<usize, String> {
+    let cfg = load_config_file();
+    let expired = get_expired_keys(&cfg);
+    if expired.is_empty() {
+        return Ok(0);
+    }
+    let mut account = load_account(&cfg.identity.did).await?;
+    for fragment in &expired {
+        match account
+            .update_identity()
+            .delete_method()
+            .fragment(fragment.as_str())
+            .apply()
+            .await
+        {
+            Ok(_r) => info!("Verification Method '{}' Revoked", fragment),
+            Err(e) => {
+                error!("Unable to Revoke Verification Method '{}': {}", fragment, e);
+                return Err(format!(
+                    "Unable to Revoke Verification Method '{}': {}",
+                    fragment, e
+                ));
+            }
+        };
+        remove_retired_key(fragment, Path::new("."))?;
+    }
+    cache.invalidate(&cfg.identity.did);
+    Ok(expired.len())
+}
+/// Creates new identity.
+/// Structure IdentityCreation needs device ID, Name and Type
+/// Returns DID, unsigned VC (DID and VC also saved to config file) or error
+pub async fn create_identity(vc: &str) -> Result<IdentityInformationReply, String> {
+    let cfg = load_config_file();
+    // Stronghold settings
+    let stronghold_path: PathBuf = Path::new(".").join(STRONGHOLD_FOLDER).join(STRONGHOLD_FILE);
+    let password: String =
+        env::var(ENV_STRONGHOLD_PWD).unwrap_or_else(|_| DEFAULT_STRONGHOLD_PWD.to_string());
+    // Create a new Account with stronghold storage.
+    let mut account: Account = match Account::builder()
+        .storage(AccountStorage::Stronghold(
+            stronghold_path,
+            Some(password),
+            None,
+        ))
+        .create_identity(IdentitySetup::default())
+        .await
+    {
+        Ok(r) => r,
+        Err(e) => {
+            error!("Unable to Create Account: {}", e);
+            return Err(format!("Unable to Create Account: {}", e));
+        }
+    };
+    // Add a new Ed25519 Verification Method to the identity
+    match account
+        .update_identity()
+        .create_method()
+        .fragment(cfg.identity.fragment.as_str())
+        .apply()
+        .await
+    {
+        Ok(_r) => info!("New Verification Method Added to Account"),
+        Err(e) => {
+            error!("Unable to Add Verification Method to Account: {}", e);
+            return Err(format!(
+                "Unable to Add Verification Method to Account: {}",
+                e
+            ));
+        }
+    };
+    let did = account.did().to_string();
+    let cred_json = make_credential(&did, vc, &cfg.identity.cred_type)?;
+    save_identity(cfg, &did, &cred_json, Path::new("."))?;
+
+    Ok(IdentityInformationReply {
+        did: did,
+        challenge: "".to_string(),
+        verifiable_credential: cred_json,
+        status: "Ok".to_string(),
+        code: 0,
+        cache_age: 0,
+    })
+}
+/// Issue unsigned credential of DID with subject vc (JSON) and type cred_type
+/// Returns credential as JSON
+pub fn make_credential(did: &str, vc: &str, cred_type: &str) -> Result<String, String> {
+    credential_to_json(build_credential(did, "", vc, cred_type)?)
+}
+/// Issue unsigned credential of issuer with subject vc (JSON) and type cred_type,
+/// subject_did is set as id of subject if not empty
+pub fn build_credential(
+    did: &str,
+    subject_did: &str,
+    vc: &str,
+    cred_type: &str,
+) -> Result<Credential, String> {
+    // Create a subject DID for the recipient of a `UniversityDegree` credential.
+    let mut vc_json: Value = match serde_json::from_str(vc) {
+        Ok(r) => r,
+        Err(e) => {
+            error!("Unable to Parse VC to JSON: {}", e);
+            return Err(format!("Unable to Parse VC to JSON: {}", e));
+        }
+    };
+    if !subject_did.is_empty() {
+        parse_did(subject_did)?;
+        match vc_json.as_object_mut() {
+            Some(r) => r.insert("id".to_string(), Value::String(subject_did.to_string())),
+            None => return Err("VC is not a JSON Object".to_string()),
+        };
+    }
+    let subject: Subject = match Subject::from_json_value(vc_json) {
+        Ok(r) => r,
+        Err(e) => {
+            error!("Unable to Parse VC to Subject: {}", e);
+            return Err(format!("Unable to Parse VC to Subject: {}", e));
+        }
+    };
+    // Issue an unsigned Credential...
+    let did = match Url::parse(did) {
+        Ok(r) => r,
+        Err(e) => {
+            error!("Unable to Import DID From Account: {}", e);
+            return Err(format!("Unable to Import DID From Account: {}", e));
+        }
+    };
+    let credential: Credential = match Credential::builder(Default::default())
+        .issuer(did)
+        .type_(cred_type)
+        .subject(subject)
+        .build()
+    {
+        Ok(r) => r,
+        Err(e) => {
+            error!("Unable to Create VC: {}", e);
+            return Err(format!("Unable to Create VC: {}", e));
+        }
+    };
+    Ok(credential)
+}
+/// Save DID and VC of created identity to config file in base_dir
+pub fn save_identity(
+    mut cfg: IdentityConfig,
+    did: &str,
+    vc: &str,
+    base_dir: &Path,
+) -> Result<(), String> {
+    info!("Save DID: {} and VC {} to Config File", did, vc);
+    cfg.identity.did = did.to_string();
+    cfg.identity.verifiable_credential = vc.to_string();
+    save_config_to(cfg, base_dir)?;
+    Ok(())
+}
+/// Load Stronghold account of DID
+async fn load_account(did: &str) -> Result<Account, String> {
+    let stronghold_path: PathBuf = Path::new(".").join(STRONGHOLD_FOLDER).join(STRONGHOLD_FILE);
+    let password: String =
+        env::var(ENV_STRONGHOLD_PWD).unwrap_or_else(|_| DEFAULT_STRONGHOLD_PWD.to_string());
+    // Parse DID
+    let did = parse_did(did)?;
+    match Account::builder()
+        .storage(AccountStorage::Stronghold(
+            stronghold_path,
+            Some(password),
+            None,
+        ))
+        .load_identity(did)
+        .await
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            error!("Unable to Import Account: {}", e);
+            Err(format!("Unable to Import Account: {}", e))
+        }
+    }
+}
+/// DID of identity in config file, requested DID needs to match if not empty
+pub fn get_identity_did(cfg: &IdentityConfig, did: &str) -> Result<String, String> {
+    if !did.is_empty() && did != cfg.identity.did {
+        return Err(format!("DID Does Not Match Identity of Service: {}", did));
+    }
+    if cfg.identity.did.is_empty() {
+        return Err("No Identity Created".to_string());
+    }
+    Ok(cfg.identity.did.clone())
+}
+/// Fragment of next verification method, trailing number incremented ("dev-1" -> "dev-2")
+pub fn next_fragment(fragment: &str) -> String {
+    match fragment.rsplit_once('-') {
+        Some((base, n)) => match n.parse::<u32>() {
+            Ok(n) => format!("{}-{}", base, n + 1),
+            Err(_) => format!("{}-1", fragment),
+        },
+        None => format!("{}-1", fragment),
+    }
+}
+/// Fragment of new method, pending rotation is resumed.
+/// New rotation is saved as pending to config file in base_dir before method is published
+pub fn get_rotation_fragment(cfg: &IdentityConfig, base_dir: &Path) -> Result<String, String> {
+    if !cfg.identity.pending_fragment.is_empty() {
+        info!("Resume Rotation to '{}'", &cfg.identity.pending_fragment);
+        return Ok(cfg.identity.pending_fragment.clone());
+    }
+    let fragment = next_fragment(&cfg.identity.fragment);
+    let mut pending = load_config_from(base_dir);
+    pending.identity.pending_fragment = fragment.clone();
+    save_config_to(pending, base_dir)?;
+    Ok(fragment)
+}
+/// Save fragment of new method to config file in base_dir and finish pending rotation,
+/// old method is added to retired methods if revoke is set
+pub fn save_rotation(
+    mut cfg: IdentityConfig,
+    fragment: &str,
+    revoke: bool,
+    grace_s: u64,
+    base_dir: &Path,
+) -> Result<(), String> {
+    if revoke {
+        info!(
+            "Verification Method '{}' is Revoked in {} s",
+            &cfg.identity.fragment, grace_s
+        );
+        cfg.identity.retired.push(RetiredKey {
+            fragment: cfg.identity.fragment.clone(),
+            revoke_at: get_unix_time() + grace_s,
+        });
+    }
+    cfg.identity.fragment = fragment.to_string();
+    cfg.identity.fragment_index = cfg.identity.credential_index;
+    cfg.identity.pending_fragment = "".to_string();
+    save_config_to(cfg, base_dir)?;
+    Ok(())
+}
+/// Method of fragment can not be revoked while credentials signed by it are not revoked,
+/// verification of these credentials would fail. document: DID Document of identity
+pub fn check_outstanding_credentials(
+    cfg: &IdentityConfig,
+    document: &IotaDocument,
+) -> Result<(), String> {
+    let revoked = get_revocation_list(document)?.unwrap_or_default();
+    let outstanding = (cfg.identity.fragment_index..cfg.identity.credential_index)
+        .filter(|r| !revoked.contains(r))
+        .count();
+    if outstanding > 0 {
+        return Err(format!(
+            "Unable to Revoke Verification Method '{}', {} Issued Credentials Not Revoked",
+            &cfg.identity.fragment, outstanding
+        ));
+    }
+    Ok(())
+}
+/// Fragments of retired methods with expired grace period
+pub fn get_expired_keys(cfg: &IdentityConfig) -> Vec<String> {
+    let now = get_unix_time();
+    cfg.identity
+        .retired
+        .iter()
+        .filter(|r| r.revoke_at <= now)
+        .map(|r| r.fragment.clone())
+        .collect()
+}
+/// Remove revoked method from retired methods in config file in base_dir
+pub fn remove_retired_key(fragment: &str, base_dir: &Path) -> Result<(), String> {
+    let mut cfg = load_config_from(base_dir);
+    cfg.identity.retired.retain(|r| r.fragment != fragment);
+    save_config_to(cfg, base_dir)?;
+    Ok(())
+}
+
+pub fn make_issue_reply(did: &str, vc: String) -> IdentityInformationReply {
+    IdentityInformationReply {
+        did: did.to_string(),
+        challenge: "".to_string(),
+        verifiable_credential: vc,
+        status: "Ok".to_string(),
+        code: 0,
+        cache_age: 0,
+    }
+}
+/// Unsigned presentation of credential issued to DID (JSON), holder: DID
+pub fn make_presentation(did: &str, issued_credential: &str) -> Result<Presentation, String> {
+    let credential = parse_vc(issued_credential)?;
+    let holder = match Url::parse(did) {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to Parse DID: {}", e)),
+    };
+    match Presentation::builder(Default::default())
+        .holder(holder)
+        .credential(credential)
+        .build()
+    {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            error!("Unable to Create Presentation: {}", e);
+            Err(format!("Unable to Create Presentation: {}", e))
+        }
+    }
+}
+/// Credential issued by third party if vc is a presentation
+pub fn get_issued_credential(vc: &str) -> Result<Option<Credential>, String> {
+    if !is_presentation(vc) {
+        return Ok(None);
+    }
+    let presentation = parse_presentation(vc)?;
+    match presentation.verifiable_credential.iter().next() {
+        Some(r) => Ok(Some(r.clone())),
+        None => Err("Presentation Contains No Credential".to_string()),
+    }
+}
+
+pub fn get_issuer_did(credential: &Credential) -> Result<IotaDID, String> {
+    parse_did(credential.issuer.url().as_str())
+}
+/// Presentations contain field "verifiableCredential"
+fn is_presentation(vc: &str) -> bool {
+    match serde_json::from_str::<Value>(vc) {
+        Ok(r) => r.get("verifiableCredential").is_some(),
+        Err(_) => false,
+    }
+}
+
+/// Credential of credential or presentation (JSON)
+pub fn get_credential(vc: &str) -> Result<Credential, String> {
+    match get_issued_credential(vc)? {
+        Some(r) => Ok(r),
+        None => parse_vc(vc),
+    }
+}
+/// Take revocation index of next issued credential from config file in base_dir,
+/// callers are serialized by update lock of backend
+pub fn next_credential_index(base_dir: &Path) -> Result<u32, String> {
+    let mut cfg = load_config_from(base_dir);
+    let index = cfg.identity.credential_index;
+    cfg.identity.credential_index += 1;
+    save_config_to(cfg, base_dir)?;
+    Ok(index)
+}
+/// Add credential status with revocation index, revocation list is service of issuer
+pub fn set_credential_status(
+    credential: Credential,
+    issuer: &str,
+    index: u32,
+) -> Result<Credential, String> {
+    let mut json = match credential.to_json_value() {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to Parse Credential to JSON: {}", e)),
+    };
+    json["credentialStatus"] = json!({
+        "id": format!("{}#{}", issuer, REVOCATION_SERVICE),
+        "type": REVOCATION_SERVICE_TYPE,
+        "revocationListIndex": index.to_string(),
+    });
+    match Credential::from_json_value(json) {
+        Ok(r) => Ok(r),
+        Err(e) => Err(format!("Unable to Set Credential Status: {}", e)),
+    }
+}
+/// Revocation index of credential, None if credential can not be revoked
+pub fn get_revocation_index(credential: &Credential) -> Result<Option<u32>, String> {
+    let json = match credential.to_json_value() {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to Parse Credential to JSON: {}", e)),
+    };
+    let status = &json["credentialStatus"];
+    if status["type"].as_str() != Some(REVOCATION_SERVICE_TYPE) {
+        return Ok(None);
+    }
+    match status["revocationListIndex"].as_str().map(|r| r.parse()) {
+        Some(Ok(r)) => Ok(Some(r)),
+        _ => Err("Invalid Revocation Index of Credential".to_string()),
+    }
+}
+/// Revocation index of credential (JSON) issued by issuer
+pub fn get_own_revocation_index(vc: &str, issuer: &str) -> Result<u32, String> {
+    let credential = get_credential(vc)?;
+    if get_issuer_did(&credential)?.as_str() != issuer {
+        return Err("Credential Not Issued by Identity of Service".to_string());
+    }
+    match get_revocation_index(&credential)? {
+        Some(r) => Ok(r),
+        None => Err("Credential Can Not be Revoked".to_string()),
+    }
+}
+/// Revoked indices of revocation service in DID Document, None if no service
+pub fn get_revocation_list(document: &IotaDocument) -> Result<Option<Vec<u32>>, String> {
+    let json = match document.to_json_value() {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to Parse DID Document to JSON: {}", e)),
+    };
+    // Document might be wrapped with metadata
+    let json = json.get("doc").unwrap_or(&json);
+    let fragment = format!("#{}", REVOCATION_SERVICE);
+    let service = match json["service"].as_array().and_then(|r| {
+        r.iter()
+            .find(|s| s["id"].as_str().map_or(false, |id| id.ends_with(&fragment)))
+    }) {
+        Some(r) => r,
+        None => return Ok(None),
+    };
+    let endpoint = match service["serviceEndpoint"].as_str() {
+        Some(r) => r,
+        None => return Err("Invalid Revocation List".to_string()),
+    };
+    parse_revocation_endpoint(endpoint).map(Some)
+}
+/// Revocation list as data URL, "data:,1,5,7"
+pub fn make_revocation_endpoint(list: &[u32]) -> String {
+    let list: Vec<String> = list.iter().map(|r| r.to_string()).collect();
+    format!("data:,{}", list.join(","))
+}
+
+fn parse_revocation_endpoint(endpoint: &str) -> Result<Vec<u32>, String> {
+    let list = match endpoint.strip_prefix("data:,") {
+        Some(r) => r,
+        None => return Err(format!("Invalid Revocation List: {}", endpoint)),
+    };
+    let mut indices = Vec::new();
+    for index in list.split(',').filter(|r| !r.is_empty()) {
+        match index.parse() {
+            Ok(r) => indices.push(r),
+            Err(e) => return Err(format!("Invalid Revocation Index '{}': {}", index, e)),
+        };
+    }
+    Ok(indices)
+}
+/// True if revocation index of credential is listed in DID Document of issuer
+pub fn is_revoked(credential: &Credential, issuer_document: &IotaDocument) -> Result<bool, String> {
+    let index = match get_revocation_index(credential)? {
+        Some(r) => r,
+        None => return Ok(false),
+    };
+    match get_revocation_list(issuer_document)? {
+        Some(r) => Ok(r.contains(&index)),
+        None => Ok(false),
+    }
+}
+/// Reply of revocation, code: 0 if credential is not revoked, -3 if revoked
+pub fn make_revocation_reply(
+    request: IdentityInformationRequest,
+    revoked: bool,
+) -> IdentityInformationReply {
+    let (status, code) = if revoked {
+        ("Credential Revoked".to_string(), -3)
+    } else {
+        ("Credential Not Revoked".to_string(), 0)
+    };
+    IdentityInformationReply {
+        did: request.did,
+        challenge: request.challenge,
+        verifiable_credential: request.verifiable_credential,
+        status: status,
+        code: code,
+        cache_age: 0,
+    }
+}
+
+pub fn make_rotation_reply(did: &str, fragment: &str) -> IdentityInformationReply {
+    IdentityInformationReply {
+        did: did.to_string(),
+        challenge: "".to_string(),
+        verifiable_credential: "".to_string(),
+        status: format!("Key Rotated, Fragment: {}", fragment),
+        code: 0,
+        cache_age: 0,
+    }
+}
+
+pub fn credential_to_json(credential: Credential) -> Result<String, String> {
+    match credential.to_json() {
+        Ok(r) => return Ok(r),
+        Err(e) => {
+            error!("Unable to Parse Credential to JSON: {}", e);
+            return Err(format!("Unable to Parse Credential to JSON: {}", e));
+        }
+    };
+}
+
+pub fn parse_did(did: &str) -> Result<IotaDID, String> {
+    match IotaDID::parse(did) {
+        Ok(res) => return Ok(res),
+        Err(e) => {
+            error!("Unable to Parse DID: {}", e);
+            return Err(format!("Unable to Parse DID: {}", e));
+        }
+    };
+}
+
+pub fn presentation_to_json(presentation: Presentation) -> Result<String, String> {
+    match presentation.to_json() {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            error!("Unable to Parse Presentation to JSON: {}", e);
+            Err(format!("Unable to Parse Presentation to JSON: {}", e))
+        }
+    }
+}
+
+pub fn parse_presentation(vc: &str) -> Result<Presentation, String> {
+    match Presentation::from_json(vc) {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            error!("Unable to Parse Presentation from Request: {}", e);
+            Err(format!("Unable to Parse Presentation from Request: {}", e))
+        }
+    }
+}
+
+pub fn parse_vc(vc: &str) -> Result<Credential, String> {
+    match Credential::from_json(vc) {
+        Ok(r) => return Ok(r),
+        Err(e) => {
+            error!("Unable to Parse VC from Request: {}", e);
+            return Err(format!("Unable to Parse VC from Request: {}", e));
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use identity::crypto::{KeyPair, KeyType};
+
+    #[test]
+    fn test_next_fragment() {
+        assert_eq!(next_fragment("dev-1"), "dev-2");
+        assert_eq!(next_fragment("dev-9"), "dev-10");
+        assert_eq!(next_fragment("my-dev-3"), "my-dev-4");
+        assert_eq!(next_fragment("dev"), "dev-1");
+        assert_eq!(next_fragment("dev-a"), "dev-a-1");
+        assert_eq!(next_fragment(""), "-1");
+    }
+    #[test]
+    fn test_get_expired_keys() {
+        let now = get_unix_time();
+        let mut cfg = IdentityConfig::default();
+        assert!(get_expired_keys(&cfg).is_empty());
+        cfg.identity.retired = vec![
+            RetiredKey {
+                fragment: "dev-1".to_string(),
+                revoke_at: now - 10,
+            },
+            RetiredKey {
+                fragment: "dev-2".to_string(),
+                revoke_at: now,
+            },
+            RetiredKey {
+                fragment: "dev-3".to_string(),
+                revoke_at: now + 3600,
+            },
+        ];
+        assert_eq!(get_expired_keys(&cfg), vec!["dev-1", "dev-2"]);
+    }
+    #[test]
+    fn test_revocation_endpoint() {
+        assert_eq!(make_revocation_endpoint(&[]), "data:,");
+        assert_eq!(make_revocation_endpoint(&[1, 5, 7]), "data:,1,5,7");
+        assert_eq!(
+            parse_revocation_endpoint("data:,").unwrap(),
+            Vec::<u32>::new()
+        );
+        assert_eq!(
+            parse_revocation_endpoint(&make_revocation_endpoint(&[1, 5, 7])).unwrap(),
+            vec![1, 5, 7]
+        );
+        assert!(parse_revocation_endpoint("1,5,7").is_err());
+        assert!(parse_revocation_endpoint("data:,1,x").is_err());
+    }
+    fn make_document() -> IotaDocument {
+        let keypair = KeyPair::new(KeyType::Ed25519).unwrap();
+        IotaDocument::new(&keypair).unwrap()
+    }
+    /// Document with revocation service listing revoked indices
+    fn set_revocation_list(document: &IotaDocument, list: &[u32]) -> IotaDocument {
+        let mut json = document.to_json_value().unwrap();
+        // Document might be wrapped with metadata
+        let doc = if json.get("doc").is_some() {
+            &mut json["doc"]
+        } else {
+            &mut json
+        };
+        doc["service"] = json!([{
+            "id": format!("{}#{}", document.id(), REVOCATION_SERVICE),
+            "type": REVOCATION_SERVICE_TYPE,
+            "serviceEndpoint": make_revocation_endpoint(list),
+        }]);
+        IotaDocument::from_json_value(json).unwrap()
+    }
+    #[test]
+    fn test_revocation_index() {
+        let issuer = make_document();
+        let holder = make_document();
+        let issuer_did = issuer.id().to_string();
+        let credential = build_credential(
+            &issuer_did,
+            holder.id().as_str(),
+            r#"{"name":"sensor"}"#,
+            "Device Identification",
+        )
+        .unwrap();
+        assert_eq!(get_revocation_index(&credential).unwrap(), None);
+        assert!(!is_revoked(&credential, &set_revocation_list(&issuer, &[5])).unwrap());
+
+        let credential = set_credential_status(credential, &issuer_did, 5).unwrap();
+        assert_eq!(get_revocation_index(&credential).unwrap(), Some(5));
+        let vc = credential_to_json(credential.clone()).unwrap();
+        assert_eq!(get_own_revocation_index(&vc, &issuer_did).unwrap(), 5);
+        assert!(get_own_revocation_index(&vc, holder.id().as_str()).is_err());
+
+        // No revocation service, index not listed, index listed
+        assert!(!is_revoked(&credential, &issuer).unwrap());
+        assert!(!is_revoked(&credential, &set_revocation_list(&issuer, &[1, 7])).unwrap());
+        assert!(is_revoked(&credential, &set_revocation_list(&issuer, &[1, 5, 7])).unwrap());
+    }
+    #[test]
+    fn test_resolve_issuer_failure() {
+        let issuer = make_document();
+        let holder = make_document();
+        let credential = build_credential(
+            issuer.id().as_str(),
+            holder.id().as_str(),
+            r#"{"name":"sensor"}"#,
+            "Device Identification",
+        )
+        .unwrap();
+        let trust = Trust { issuers: vec![] };
+        // Failed lookup is an error, never a verification code
+        assert!(check_resolved_issuer(
+            holder.id().as_str(),
+            &credential,
+            Err("Unable to Resolve Document".to_string()),
+            &trust
+        )
+        .is_err());
+        // Unsigned credential is not verified by resolved issuer
+        assert_eq!(
+            check_resolved_issuer(holder.id().as_str(), &credential, Ok(issuer), &trust),
+            Ok(-1)
+        );
+    }
+}
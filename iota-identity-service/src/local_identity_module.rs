@@ -0,0 +1,595 @@
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use identity::core::{decode_b58, encode_b58, FromJson, ToJson};
+use identity::crypto::{KeyPair, KeyType, SetSignature, SignatureOptions, TryMethod};
+use identity::did::MethodScope;
+use identity::iota::{IotaDocument, IotaVerificationMethod};
+
+use crate::config::{
+    load_config_from, LOCAL_KEYS_FILE, REVOCATION_SERVICE, REVOCATION_SERVICE_TYPE,
+    STRONGHOLD_FOLDER,
+};
+use crate::iota_identity_module::{
+    build_credential, check_issued_credential, check_issuer, check_outstanding_credentials,
+    credential_to_json, get_credential, get_expired_keys, get_identity_did, get_issued_credential,
+    get_issuer_did, get_own_revocation_index, get_revocation_list, get_rotation_fragment,
+    is_revoked, make_credential, make_issue_reply, make_presentation, make_revocation_endpoint,
+    make_revocation_reply, make_rotation_reply, make_verification_reply, next_credential_index,
+    parse_did, parse_vc, presentation_to_json, remove_retired_key, save_identity, save_rotation,
+    set_credential_status, verify_proof, CredentialIssueRequest, IdentityInformationReply,
+    IdentityInformationRequest, KeyRotationRequest,
+};
+
+/// Creates new identity with Ed25519 key, DID Document saved to registry_dir,
+/// config file and key saved to base_dir. Returns DID, unsigned VC (DID and VC also saved to config file) or error
+pub fn create_identity(
+    vc: &str,
+    registry_dir: &str,
+    base_dir: &Path,
+) -> Result<IdentityInformationReply, String> {
+    let cfg = load_config_from(base_dir);
+    let keypair = new_keypair()?;
+    let document = match IotaDocument::new_with_options(
+        &keypair,
+        None,
+        Some(cfg.identity.fragment.as_str()),
+    ) {
+        Ok(r) => r,
+        Err(e) => {
+            error!("Unable to Create DID Document: {}", e);
+            return Err(format!("Unable to Create DID Document: {}", e));
+        }
+    };
+    let did = document.id().to_string();
+    save_document(registry_dir, &did, &document)?;
+    save_private_key(base_dir, &did, &cfg.identity.fragment, &keypair)?;
+    let cred_json = make_credential(&did, vc, &cfg.identity.cred_type)?;
+    save_identity(cfg, &did, &cred_json, base_dir)?;
+
+    Ok(IdentityInformationReply {
+        did: did,
+        challenge: "".to_string(),
+        verifiable_credential: cred_json,
+        status: "Ok".to_string(),
+        code: 0,
+        cache_age: 0,
+    })
+}
+/// Generates verifiable credential signed with challenge by local key of DID,
+/// presentation of issued credential if set in config file.
+/// Returns verification DID, Challenge (used to sign VC) and signed VC or error
+pub fn proof_identity(
+    request: IdentityInformationRequest,
+    registry_dir: &str,
+    base_dir: &Path,
+) -> Result<IdentityInformationReply, String> {
+    let cfg = load_config_from(base_dir);
+    let document = load_document(registry_dir, &request.did)?;
+    let keypair = load_keypair(base_dir, &request.did, &cfg.identity.fragment)?;
+    let vc = if cfg.identity.issued_credential.is_empty() {
+        let mut credential = parse_vc(&cfg.identity.verifiable_credential)?;
+        sign_with_document(
+            &document,
+            &keypair,
+            &cfg.identity.fragment,
+            &mut credential,
+            Some(&request.challenge),
+        )?;
+        credential_to_json(credential)?
+    } else {
+        let mut presentation = make_presentation(&request.did, &cfg.identity.issued_credential)?;
+        sign_with_document(
+            &document,
+            &keypair,
+            &cfg.identity.fragment,
+            &mut presentation,
+            Some(&request.challenge),
+        )?;
+        presentation_to_json(presentation)?
+    };
+    info!(
+        "Signed Verifiable Credential with Challenge: {}",
+        &request.challenge
+    );
+    Ok(IdentityInformationReply {
+        did: request.did,
+        challenge: request.challenge,
+        verifiable_credential: vc,
+        status: "Ok".to_string(),
+        code: 0,
+        cache_age: 0,
+    })
+}
+/// Verifies verifiable credential or presentation signed with challenge,
+/// DID Documents are loaded from registry_dir. Code -2 if issuer of credential is not trusted
+pub fn verify_identity(
+    request: IdentityInformationRequest,
+    registry_dir: &str,
+    base_dir: &Path,
+) -> Result<IdentityInformationReply, String> {
+    let cfg = load_config_from(base_dir);
+    let document = load_document(registry_dir, &request.did)?;
+    let verified = verify_proof(
+        &document,
+        &request.verifiable_credential,
+        &request.challenge,
+    )?;
+    info!("DID: '{}' is Verified: {}", &request.did, verified);
+    if !verified {
+        return Ok(make_verification_reply(request, -1, 0));
+    }
+    let code = match get_issued_credential(&request.verifiable_credential)? {
+        Some(credential) => {
+            let issuer = get_issuer_did(&credential)?;
+            let issuer_document = load_document(registry_dir, issuer.as_str())?;
+            check_issued_credential(&request.did, &credential, &issuer_document, &cfg.trust)
+        }
+        // Self-issued credential
+        None => check_issuer(&request.did, &cfg.trust),
+    };
+    Ok(make_verification_reply(request, code, 0))
+}
+/// Issues credential to DID of request, signed by local key of identity.
+/// Returns DID and signed credential or error
+pub fn issue_credential(
+    request: CredentialIssueRequest,
+    registry_dir: &str,
+    base_dir: &Path,
+) -> Result<IdentityInformationReply, String> {
+    let cfg = load_config_from(base_dir);
+    let issuer = get_identity_did(&cfg, "")?;
+    let credential = build_credential(
+        &issuer,
+        &request.did,
+        &request.verifiable_credential,
+        &cfg.identity.cred_type,
+    )?;
+    let mut credential =
+        set_credential_status(credential, &issuer, next_credential_index(base_dir)?)?;
+    let document = load_document(registry_dir, &issuer)?;
+    let keypair = load_keypair(base_dir, &issuer, &cfg.identity.fragment)?;
+    sign_with_document(
+        &document,
+        &keypair,
+        &cfg.identity.fragment,
+        &mut credential,
+        None,
+    )?;
+    info!("Credential Issued to DID: {}", &request.did);
+    Ok(make_issue_reply(
+        &request.did,
+        credential_to_json(credential)?,
+    ))
+}
+/// Revokes credential (JSON) issued by local identity,
+/// revocation index is added to revocation list of DID Document in registry_dir
+/// Returns status or error, code -3 if credential is revoked
+pub fn revoke_credential(
+    request: IdentityInformationRequest,
+    registry_dir: &str,
+    base_dir: &Path,
+) -> Result<IdentityInformationReply, String> {
+    let cfg = load_config_from(base_dir);
+    let issuer = get_identity_did(&cfg, "")?;
+    let index = get_own_revocation_index(&request.verifiable_credential, &issuer)?;
+    let document = load_document(registry_dir, &issuer)?;
+    let mut list = get_revocation_list(&document)?.unwrap_or_default();
+    if list.contains(&index) {
+        return Ok(make_revocation_reply(request, true));
+    }
+    list.push(index);
+    list.sort_unstable();
+    let document = set_revocation_list(&document, &list)?;
+    save_document(registry_dir, &issuer, &document)?;
+    info!("Credential with Revocation Index {} Revoked", index);
+    Ok(make_revocation_reply(request, true))
+}
+/// Checks if credential or presentation (JSON) is revoked by issuer,
+/// DID Document of issuer is loaded from registry_dir
+/// Returns status or error, code -3 if credential is revoked
+pub fn check_revocation(
+    request: IdentityInformationRequest,
+    registry_dir: &str,
+    base_dir: &Path,
+) -> Result<IdentityInformationReply, String> {
+    let credential = get_credential(&request.verifiable_credential)?;
+    let issuer = get_issuer_did(&credential)?;
+    let document = load_document(registry_dir, issuer.as_str())?;
+    let revoked = is_revoked(&credential, &document)?;
+    Ok(make_revocation_reply(request, revoked))
+}
+/// Replace revocation service of DID Document with service listing revoked indices
+fn set_revocation_list(document: &IotaDocument, list: &[u32]) -> Result<IotaDocument, String> {
+    let mut json = match document.to_json_value() {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to Parse DID Document to JSON: {}", e)),
+    };
+    let fragment = format!("#{}", REVOCATION_SERVICE);
+    let service = json!({
+        "id": format!("{}{}", document.id(), &fragment),
+        "type": REVOCATION_SERVICE_TYPE,
+        "serviceEndpoint": make_revocation_endpoint(list),
+    });
+    // Document might be wrapped with metadata
+    let doc = if json.get("doc").is_some() {
+        &mut json["doc"]
+    } else {
+        &mut json
+    };
+    if !doc["service"].is_array() {
+        doc["service"] = json!([]);
+    }
+    if let Some(services) = doc["service"].as_array_mut() {
+        services.retain(|s| !s["id"].as_str().map_or(false, |id| id.ends_with(&fragment)));
+        services.push(service);
+    }
+    match IotaDocument::from_json_value(json) {
+        Ok(r) => Ok(r),
+        Err(e) => Err(format!("Unable to Update DID Document: {}", e)),
+    }
+}
+/// Sign data with local key of method fragment, challenge is not set if None
+fn sign_with_document<X: Serialize + SetSignature + TryMethod>(
+    document: &IotaDocument,
+    keypair: &KeyPair,
+    fragment: &str,
+    data: &mut X,
+    challenge: Option<&str>,
+) -> Result<(), String> {
+    match document.sign_data(
+        data,
+        keypair.private(),
+        fragment,
+        SignatureOptions {
+            created: None,
+            expires: None,
+            challenge: challenge.map(|r| r.to_string()),
+            domain: None,
+            purpose: None,
+        },
+    ) {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            error!("Unable to Sign VC: {}", e);
+            Err(format!("Unable to Sign VC: {}", e))
+        }
+    }
+}
+/// Adds new verification method with Ed25519 key to DID Document in registry_dir,
+/// proofs are signed with new method. Old method is revoked after grace_s if revoke is set,
+/// revoke is refused while credentials signed by old method are not revoked
+/// Returns DID and fragment of new method or error
+pub fn rotate_key(
+    request: KeyRotationRequest,
+    registry_dir: &str,
+    base_dir: &Path,
+) -> Result<IdentityInformationReply, String> {
+    let cfg = load_config_from(base_dir);
+    let did = get_identity_did(&cfg, &request.did)?;
+    let mut document = load_document(registry_dir, &did)?;
+    if request.revoke {
+        check_outstanding_credentials(&cfg, &document)?;
+    }
+    let fragment = get_rotation_fragment(&cfg, base_dir)?;
+    // Method might already be added if rotation was interrupted
+    if document.resolve_method(fragment.as_str()).is_some() {
+        info!("Verification Method '{}' Already Added", &fragment);
+    } else {
+        let keypair = new_keypair()?;
+        let method = match IotaVerificationMethod::new(
+            document.id().clone(),
+            KeyType::Ed25519,
+            keypair.public(),
+            &fragment,
+        ) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("Unable to Create Verification Method: {}", e)),
+        };
+        if let Err(e) = document.insert_method(method, MethodScope::VerificationMethod) {
+            return Err(format!("Unable to Add Verification Method: {}", e));
+        }
+        save_private_key(base_dir, &did, &fragment, &keypair)?;
+        save_document(registry_dir, &did, &document)?;
+        info!("New Verification Method '{}' Added to Document", &fragment);
+    }
+    save_rotation(cfg, &fragment, request.revoke, request.grace_s, base_dir)?;
+    Ok(make_rotation_reply(&did, &fragment))
+}
+/// Removes retired verification methods with expired grace period from DID Document
+/// Returns number of revoked methods or error
+pub fn revoke_expired_keys(registry_dir: &str, base_dir: &Path) -> Result<usize, String> {
+    let cfg = load_config_from(base_dir);
+    let expired = get_expired_keys(&cfg);
+    if expired.is_empty() {
+        return Ok(0);
+    }
+    let mut document = load_document(registry_dir, &cfg.identity.did)?;
+    for fragment in &expired {
+        let method_url = match document.id().to_url().join(format!("#{}", fragment)) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("Unable to Parse Method '{}': {}", fragment, e)),
+        };
+        // Method might already be removed if saving config failed before
+        if let Err(e) = document.remove_method(&method_url) {
+            warn!("Unable to Remove Verification Method '{}': {}", fragment, e);
+        }
+        save_document(registry_dir, &cfg.identity.did, &document)?;
+        remove_private_key(base_dir, &cfg.identity.did, fragment)?;
+        remove_retired_key(fragment, base_dir)?;
+        info!("Verification Method '{}' Revoked", fragment);
+    }
+    Ok(expired.len())
+}
+/// Path of DID Document in registry_dir, ':' of DID replaced by '_'
+fn get_document_path(registry_dir: &str, did: &str) -> PathBuf {
+    Path::new(registry_dir).join(format!("{}.json", did.replace(':', "_")))
+}
+
+fn save_document(registry_dir: &str, did: &str, document: &IotaDocument) -> Result<(), String> {
+    if let Err(e) = std::fs::create_dir_all(registry_dir) {
+        return Err(format!("Unable to Create Registry Directory: {}", e));
+    }
+    let json = match document.to_json() {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to Serialize DID Document: {}", e)),
+    };
+    write_file(&get_document_path(registry_dir, did), &json)?;
+    info!("DID Document Saved to Registry: {}", did);
+    Ok(())
+}
+/// Load DID Document of DID from registry_dir, DID needs to match document
+fn load_document(registry_dir: &str, did: &str) -> Result<IotaDocument, String> {
+    let did = parse_did(did)?;
+    let json = match std::fs::read_to_string(get_document_path(registry_dir, did.as_str())) {
+        Ok(r) => r,
+        Err(e) => {
+            error!("DID Document Not Found in Registry: {}", e);
+            return Err(format!("DID Document Not Found in Registry: {}", e));
+        }
+    };
+    let document = match IotaDocument::from_json(&json) {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to Parse DID Document: {}", e)),
+    };
+    if document.id() != &did {
+        return Err(format!("DID Document Does Not Match DID: {}", did));
+    }
+    Ok(document)
+}
+
+/// Local keys located at base_dir/STRONGHOLD_FOLDER
+fn get_keys_path(base_dir: &Path) -> PathBuf {
+    base_dir.join(STRONGHOLD_FOLDER).join(LOCAL_KEYS_FILE)
+}
+fn new_keypair() -> Result<KeyPair, String> {
+    match KeyPair::new(KeyType::Ed25519) {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            error!("Unable to Create Key Pair: {}", e);
+            Err(format!("Unable to Create Key Pair: {}", e))
+        }
+    }
+}
+/// Key of method in local keys, "DID#fragment"
+fn get_key_id(did: &str, fragment: &str) -> String {
+    format!("{}#{}", did, fragment)
+}
+/// Private keys (base58) by method, empty if no file
+fn load_private_keys(base_dir: &Path) -> Result<HashMap<String, String>, String> {
+    let path = get_keys_path(base_dir);
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let json = match std::fs::read_to_string(&path) {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to Read Local Keys: {}", e)),
+    };
+    match serde_json::from_str(&json) {
+        Ok(r) => Ok(r),
+        Err(e) => Err(format!("Unable to Parse Local Keys: {}", e)),
+    }
+}
+
+fn save_private_key(
+    base_dir: &Path,
+    did: &str,
+    fragment: &str,
+    keypair: &KeyPair,
+) -> Result<(), String> {
+    let mut keys = load_private_keys(base_dir)?;
+    keys.insert(
+        get_key_id(did, fragment),
+        encode_b58(keypair.private().as_ref()),
+    );
+    save_private_keys(base_dir, &keys)
+}
+
+fn remove_private_key(base_dir: &Path, did: &str, fragment: &str) -> Result<(), String> {
+    let mut keys = load_private_keys(base_dir)?;
+    keys.remove(&get_key_id(did, fragment));
+    save_private_keys(base_dir, &keys)
+}
+
+fn save_private_keys(base_dir: &Path, keys: &HashMap<String, String>) -> Result<(), String> {
+    let json = match serde_json::to_string(keys) {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to Serialize Local Keys: {}", e)),
+    };
+    write_file(&get_keys_path(base_dir), &json)
+}
+
+fn load_keypair(base_dir: &Path, did: &str, fragment: &str) -> Result<KeyPair, String> {
+    let keys = load_private_keys(base_dir)?;
+    let private_key = match keys.get(&get_key_id(did, fragment)) {
+        Some(r) => r,
+        None => {
+            return Err(format!(
+                "No Local Key Found for DID: {}, Fragment: {}",
+                did, fragment
+            ))
+        }
+    };
+    let private_key = match decode_b58(private_key) {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to Decode Local Key: {}", e)),
+    };
+    match KeyPair::try_from_private_key_bytes(KeyType::Ed25519, &private_key) {
+        Ok(r) => Ok(r),
+        Err(e) => Err(format!("Unable to Import Local Key: {}", e)),
+    }
+}
+/// Write data to temporary file and rename it, file is never partially written
+fn write_file(path: &Path, data: &str) -> Result<(), String> {
+    let tmp_path = path.with_extension("json.tmp");
+    let mut file = match std::fs::File::create(&tmp_path) {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to Create File: {}", e)),
+    };
+    if let Err(e) = file
+        .write_all(data.as_bytes())
+        .and_then(|_| file.sync_all())
+    {
+        return Err(format!("Unable to Write File: {}", e));
+    }
+    match std::fs::rename(&tmp_path, path) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Unable to Replace File: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::save_config_to;
+    use crate::iota_identity_module::next_fragment;
+
+    fn make_request(did: &str, challenge: &str, vc: &str) -> IdentityInformationRequest {
+        IdentityInformationRequest {
+            did: did.to_string(),
+            challenge: challenge.to_string(),
+            verifiable_credential: vc.to_string(),
+        }
+    }
+    /// Removes test directory when dropped, also if test fails
+    struct TestDir(PathBuf);
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_local_flow() {
+        let dir =
+            TestDir(std::env::temp_dir().join(format!("local_identity_{}", std::process::id())));
+        let base_dir = dir.0.as_path();
+        std::fs::create_dir_all(base_dir.join(STRONGHOLD_FOLDER)).unwrap();
+        let registry = base_dir.join("registry");
+        let registry_dir = registry.to_str().unwrap();
+
+        // Create, proof and verify
+        let did = create_identity(r#"{"name":"sensor"}"#, registry_dir, base_dir)
+            .unwrap()
+            .did;
+        let verify = |vc: &str, challenge: &str| {
+            verify_identity(make_request(&did, challenge, vc), registry_dir, base_dir)
+                .unwrap()
+                .code
+        };
+        let old_vc = proof_identity(make_request(&did, "c1", ""), registry_dir, base_dir)
+            .unwrap()
+            .verifiable_credential;
+        assert_eq!(verify(&old_vc, "c1"), 0);
+        assert_eq!(verify(&old_vc, "c2"), -1);
+
+        // Rotate, old key valid until revoked
+        let fragment = load_config_from(base_dir).identity.fragment;
+        let rotated = rotate_key(
+            KeyRotationRequest {
+                did: "".to_string(),
+                revoke: true,
+                grace_s: 0,
+            },
+            registry_dir,
+            base_dir,
+        )
+        .unwrap();
+        assert_eq!(rotated.did, did);
+        assert_eq!(
+            load_config_from(base_dir).identity.fragment,
+            next_fragment(&fragment)
+        );
+        let new_vc = proof_identity(make_request(&did, "c3", ""), registry_dir, base_dir)
+            .unwrap()
+            .verifiable_credential;
+        assert_eq!(verify(&new_vc, "c3"), 0);
+        assert_eq!(verify(&old_vc, "c1"), 0);
+        assert_eq!(revoke_expired_keys(registry_dir, base_dir).unwrap(), 1);
+        assert_eq!(revoke_expired_keys(registry_dir, base_dir).unwrap(), 0);
+        assert_eq!(verify(&old_vc, "c1"), -1);
+        assert_eq!(verify(&new_vc, "c3"), 0);
+        assert!(load_keypair(base_dir, &did, &fragment).is_err());
+
+        // Issue credential, presented by identity
+        let issued = issue_credential(
+            CredentialIssueRequest {
+                did: did.clone(),
+                verifiable_credential: r#"{"name":"holder"}"#.to_string(),
+            },
+            registry_dir,
+            base_dir,
+        )
+        .unwrap()
+        .verifiable_credential;
+        let check = |vc: &str| {
+            check_revocation(make_request(&did, "", vc), registry_dir, base_dir)
+                .unwrap()
+                .code
+        };
+        assert_eq!(check(&issued), 0);
+        let mut cfg = load_config_from(base_dir);
+        cfg.identity.issued_credential = issued.clone();
+        save_config_to(cfg, base_dir).unwrap();
+        let vp = proof_identity(make_request(&did, "c4", ""), registry_dir, base_dir)
+            .unwrap()
+            .verifiable_credential;
+        assert_eq!(verify(&vp, "c4"), 0);
+
+        // Signing key can not be revoked while issued credential is valid
+        let rotate = |revoke: bool| {
+            rotate_key(
+                KeyRotationRequest {
+                    did: "".to_string(),
+                    revoke,
+                    grace_s: 0,
+                },
+                registry_dir,
+                base_dir,
+            )
+        };
+        let fragment = load_config_from(base_dir).identity.fragment;
+        assert!(rotate(true).is_err());
+        assert_eq!(load_config_from(base_dir).identity.fragment, fragment);
+        rotate(false).unwrap();
+        assert_eq!(revoke_expired_keys(registry_dir, base_dir).unwrap(), 0);
+        assert_eq!(verify(&vp, "c4"), 0);
+        assert_eq!(check(&issued), 0);
+
+        // Revoke credential, key of current method can be revoked
+        assert_eq!(
+            revoke_credential(make_request(&did, "", &issued), registry_dir, base_dir)
+                .unwrap()
+                .code,
+            -3
+        );
+        assert_eq!(check(&issued), -3);
+        assert_eq!(verify(&vp, "c4"), -3);
+        rotate(true).unwrap();
+        assert_eq!(revoke_expired_keys(registry_dir, base_dir).unwrap(), 1);
+        assert_eq!(verify(&new_vc, "c3"), 0);
+    }
+}
@@ -1,107 +1,138 @@
-use grpc_streams::iota_streamer_client::IotaStreamerClient;
-use grpc_streams::{IotaStreamsRequest, IotaStreamsSendMessageRequest};
-
-pub mod grpc_streams {
-    tonic::include_proto!("iota_streams_grpc");
-}
-
-mod config;
-use crate::config::load_config_file;
-
-mod msg_util;
-use msg_util::MsgType;
-/// Client implementation for Iota Streams GRPC Service
-/// - Example creates Author and Subscriber instance
-/// - Adds subscriber via subscription link
-/// - Sends keyload to subscriber
-/// - Author sends signed test message over private Single Branch
-/// - Subscriber receives message
-/// Communicatin between Client and Server via GRPC
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cfg = load_config_file();
-    let mut client = IotaStreamerClient::connect(format!("http://{}", cfg.socket)).await?;
-    let author = "1".to_string();
-    let subscriber = "2".to_string();
-    // Create Author
-    let msg = IotaStreamsRequest {
-        id: author.clone(),
-        msg_type: MsgType::CreateNewAuthor as u32,
-        link: "".to_string(),
-    };
-    let response = client.create_new_author(tonic::Request::new(msg)).await?;
-    let response = response.into_inner();
-    let ann_link = response.link;
-    println!("Received Announcement Link: {}", ann_link);
-    // Create Subscriber
-    let msg = IotaStreamsRequest {
-        id: subscriber.clone(),
-        msg_type: MsgType::CreateNewSubscriber as u32,
-        link: ann_link,
-    };
-    let response = client
-        .create_new_subscriber(tonic::Request::new(msg))
-        .await?;
-    let response = response.into_inner();
-    let sub_link = response.link;
-    println!("Received Subscription Link: {}", sub_link);
-    // Author subscribe Subscriber to channel
-    let msg = IotaStreamsRequest {
-        id: author.clone(),
-        msg_type: MsgType::AddSubscriber as u32,
-        link: sub_link,
-    };
-    let response = client.add_subscriber(tonic::Request::new(msg)).await?;
-    let response = response.into_inner();
-    println!("Add Subscriber Status: {}", response.status);
-    // Author Generate Keyload
-    let msg = IotaStreamsRequest {
-        id: author.clone(),
-        msg_type: MsgType::CreateKeyload as u32,
-        link: "".to_string(),
-    };
-    let response = client.create_keyload(tonic::Request::new(msg)).await?;
-    let response = response.into_inner();
-    let key_link = response.link;
-    println!("Received Keyload Link: {}", key_link);
-    // Subscriber receive Keyload Link
-    let msg = IotaStreamsRequest {
-        id: subscriber.clone(),
-        msg_type: MsgType::ReceiveKeyload as u32,
-        link: key_link.clone(),
-    };
-    let response = client.receive_keyload(tonic::Request::new(msg)).await?;
-    let response = response.into_inner();
-    let status = response.status;
-    println!("Subscriber received keyload link: {}", status);
-    // Author send message
-    let msg = "Important message from author".to_string();
-    let msg = IotaStreamsSendMessageRequest {
-        id: author.clone(),
-        msg_type: MsgType::SendMessage as u32,
-        message_link: key_link,
-        message_length: msg.len() as u32,
-        message: msg,
-    };
-    let response = client.send_message(tonic::Request::new(msg)).await?;
-    let response = response.into_inner();
-    let status = response.status;
-    let msg_link = response.link;
-    println!("Send Message: Link: {}, Status: {}", msg_link, status);
-
-    // Subscriber receiving messages
-    let msg = IotaStreamsRequest {
-        id: subscriber.clone(),
-        msg_type: MsgType::ReceiveMessages as u32,
-        link: "".to_string(),
-    };
-    let response = client.receive_messages(tonic::Request::new(msg)).await?;
-    let response = response.into_inner();
-    let status = response.status;
-    let messages = response.messages;
-    println!("Operation Status: {}", status);
-    for msg in messages {
-        println!("Received Message: {}", msg);
-    }
-    Ok(())
-}
+use grpc_streams::iota_streamer_client::IotaStreamerClient;
+use grpc_streams::{
+    IotaStreamsRequest, IotaStreamsSendMessageRequest, IotaStreamsSubscriberRequest,
+};
+
+pub mod grpc_streams {
+    tonic::include_proto!("iota_streams_grpc");
+}
+
+mod config;
+use crate::config::load_config_file;
+
+mod msg_util;
+use msg_util::MsgType;
+/// Client implementation for Iota Streams GRPC Service
+/// - Example creates Author and Subscriber instance
+/// - Adds subscriber via subscription link
+/// - Sends keyload to subscriber
+/// - Author sends signed test message over private Single Branch
+/// - Subscriber receives message
+/// Communicatin between Client and Server via GRPC
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cfg = load_config_file();
+    let mut client = IotaStreamerClient::connect(format!("http://{}", cfg.socket)).await?;
+    let author = "1".to_string();
+    let subscriber = "2".to_string();
+    // Create Author
+    let msg = IotaStreamsRequest {
+        id: author.clone(),
+        msg_type: MsgType::CreateNewAuthor as u32,
+        link: "".to_string(),
+        branch: "".to_string(),
+        multi_branch: false,
+        seed_nonce: "".to_string(),
+    };
+    let response = client.create_new_author(tonic::Request::new(msg)).await?;
+    let response = response.into_inner();
+    let ann_link = response.link;
+    println!("Received Announcement Link: {}", ann_link);
+    println!("Received Seed Nonce: {}", response.seed_nonce);
+    // Create Subscriber
+    let msg = IotaStreamsRequest {
+        id: subscriber.clone(),
+        msg_type: MsgType::CreateNewSubscriber as u32,
+        link: ann_link,
+        branch: "".to_string(),
+        multi_branch: false,
+        seed_nonce: "".to_string(),
+    };
+    let response = client
+        .create_new_subscriber(tonic::Request::new(msg))
+        .await?;
+    let response = response.into_inner();
+    let sub_link = response.link;
+    let public_key = response.public_key;
+    println!("Received Subscription Link: {}", sub_link);
+    // Author subscribe Subscriber to channel
+    let msg = IotaStreamsSubscriberRequest {
+        id: author.clone(),
+        msg_type: MsgType::AddSubscriber as u32,
+        link: sub_link,
+        did: "".to_string(),
+        public_key: public_key,
+        branches: Vec::new(),
+    };
+    let response = client.add_subscriber(tonic::Request::new(msg)).await?;
+    let response = response.into_inner();
+    println!("Add Subscriber Status: {}", response.status);
+    // Author Generate Keyload
+    let msg = IotaStreamsRequest {
+        id: author.clone(),
+        msg_type: MsgType::CreateKeyload as u32,
+        link: "".to_string(),
+        branch: "".to_string(),
+        multi_branch: false,
+        seed_nonce: "".to_string(),
+    };
+    let response = client.create_keyload(tonic::Request::new(msg)).await?;
+    let response = response.into_inner();
+    let key_link = response.link;
+    println!("Received Keyload Link: {}", key_link);
+    // Subscriber receive Keyload Link
+    let msg = IotaStreamsRequest {
+        id: subscriber.clone(),
+        msg_type: MsgType::ReceiveKeyload as u32,
+        link: key_link.clone(),
+        branch: "".to_string(),
+        multi_branch: false,
+        seed_nonce: "".to_string(),
+    };
+    let response = client.receive_keyload(tonic::Request::new(msg)).await?;
+    let response = response.into_inner();
+    let status = response.status;
+    println!("Subscriber received keyload link: {}", status);
+    // Author send message
+    let msg = "Important message from author".to_string();
+    let msg = IotaStreamsSendMessageRequest {
+        id: author.clone(),
+        msg_type: MsgType::SendMessage as u32,
+        message_link: key_link,
+        message_length: msg.len() as u32,
+        message: msg,
+        branch: "".to_string(),
+        public_message: "Public header from author".to_string(),
+    };
+    let response = client.send_message(tonic::Request::new(msg)).await?;
+    let response = response.into_inner();
+    let status = response.status;
+    let msg_link = response.link;
+    println!("Send Message: Link: {}, Status: {}", msg_link, status);
+
+    // Subscriber receiving messages
+    let msg = IotaStreamsRequest {
+        id: subscriber.clone(),
+        msg_type: MsgType::ReceiveMessages as u32,
+        link: "".to_string(),
+        branch: "".to_string(),
+        multi_branch: false,
+        seed_nonce: "".to_string(),
+    };
+    let response = client.receive_messages(tonic::Request::new(msg)).await?;
+    let response = response.into_inner();
+    let status = response.status;
+    let messages = response.messages;
+    println!("Operation Status: {}", status);
+    for msg in messages {
+        println!(
+            "Received Message: {}, Public: {}, Link: {}, Publisher: {}, Seq: {}",
+            String::from_utf8_lossy(&msg.masked_payload),
+            String::from_utf8_lossy(&msg.public_payload),
+            msg.link,
+            msg.publisher,
+            msg.seq_num
+        );
+    }
+    Ok(())
+}
@@ -1,48 +1,141 @@
-#![allow(dead_code)]
-use confy;
-use serde_derive::{Deserialize, Serialize};
-use std::env;
-/// ENV for GRPC Socket
-const ENV_GRPC_SOCKET: &str = "STREAMS_GRPC_SOCKET";
-/// Default GRPC Socket
-const DEFAULT_GRPC_SOCKET: &str = "0.0.0.0:50052";
-/// ENV name for Node URL for Tangle communication: STREAMS_NODE_URL
-pub const ENV_NODE_URL: &str = "STREAMS_NODE_URL";
-/// ENV name for local Proof of work setting: STREAMS_LOCAL_POW (default: false)
-pub const ENV_LOCAL_POW: &str = "STREAMS_LOCAL_POW";
-/// ENV name to set password for exporting author and subscriber state
-pub const ENV_STATE_PWD: &str = "STREAMS_STATE_PWD";
-/// Default valuefor node URL and password
-pub const DEFAULT_NODE_URL: &str = "https://chrysalis-nodes.iota.org";
-/// Default value for password to export states
-pub const DEFAULT_STATE_PWD: &str = "123456";
-/// Default value for folder name for saving exported states
-pub const EXPORT_STATE_PATH: &str = "storage";
-/// Structure used to parse configuration file
-/// Socket needed for GRPC server, for example \[::1]:50051
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Grpc {
-    pub socket: String,
-}
-/// Default implementation uses socket at \[::1]:50051, default can be set via ENVs
-impl Default for Grpc {
-    fn default() -> Self {
-        Grpc {
-            socket: env::var(ENV_GRPC_SOCKET).unwrap_or_else(|_| DEFAULT_GRPC_SOCKET.to_string()),
-        }
-    }
-}
-/// Configuration file "streams-grpc.toml" is located at ./config/
-/// Function tries to load configuration or creates default
-pub fn load_config_file() -> Grpc {
-    let config_dir = env::current_dir()
-        .unwrap()
-        .join("config")
-        .join("streams-grpc.toml");
-    let res = confy::load_path(config_dir);
-    let cfg: Grpc = match res {
-        Ok(r) => r,
-        Err(_e) => Grpc::default(),
-    };
-    cfg
-}
+#![allow(dead_code)]
+use confy;
+use serde_derive::{Deserialize, Serialize};
+use std::env;
+/// ENV for GRPC Socket
+const ENV_GRPC_SOCKET: &str = "STREAMS_GRPC_SOCKET";
+/// Default GRPC Socket, local only as ExportChannelState returns channel keys
+const DEFAULT_GRPC_SOCKET: &str = "127.0.0.1:50052";
+/// ENV name for Node URL for Tangle communication: STREAMS_NODE_URL
+pub const ENV_NODE_URL: &str = "STREAMS_NODE_URL";
+/// ENV name for local Proof of work setting: STREAMS_LOCAL_POW (default: false)
+pub const ENV_LOCAL_POW: &str = "STREAMS_LOCAL_POW";
+/// ENV name to set password for exporting author and subscriber state
+pub const ENV_STATE_PWD: &str = "STREAMS_STATE_PWD";
+/// ENV name of key author seeds are derived from, authors can be recovered with key, id and
+/// announcement link: STREAMS_RECOVERY_KEY (not set: random seeds, no recovery)
+pub const ENV_RECOVERY_KEY: &str = "STREAMS_RECOVERY_KEY";
+/// ENV name for number of previous state generations kept as backup (<id>.1 newest):
+/// STREAMS_STATE_GENERATIONS
+pub const ENV_STATE_GENERATIONS: &str = "STREAMS_STATE_GENERATIONS";
+/// Default number of previous state generations kept
+pub const DEFAULT_STATE_GENERATIONS: usize = 3;
+/// ENV name for minimum age in s of the newest state generation before a write keeps the
+/// current state as new generation: STREAMS_STATE_GENERATION_INTERVAL_S
+pub const ENV_STATE_GENERATION_INTERVAL: &str = "STREAMS_STATE_GENERATION_INTERVAL_S";
+/// Default minimum interval between state generations in s
+pub const DEFAULT_STATE_GENERATION_INTERVAL: u64 = 60;
+/// Default valuefor node URL and password
+pub const DEFAULT_NODE_URL: &str = "https://chrysalis-nodes.iota.org";
+/// Default value for password to export states
+pub const DEFAULT_STATE_PWD: &str = "123456";
+/// Default value for folder name for saving exported states
+pub const EXPORT_STATE_PATH: &str = "storage";
+/// ENV for Interval Writing Changed States to Disk, 0 writes on every change
+const ENV_CACHE_FLUSH: &str = "STREAMS_CACHE_FLUSH_MS";
+/// Default Interval Writing Changed States to Disk in ms, states are written before replying.
+/// Every write syncs the state file, previous states are kept as generations at most every
+/// STREAMS_STATE_GENERATION_INTERVAL_S
+const DEFAULT_CACHE_FLUSH: u64 = 0;
+/// ENV for Time after which unused Instances are removed from Cache, 0 disables eviction
+const ENV_CACHE_IDLE: &str = "STREAMS_CACHE_IDLE_S";
+/// Default Time after which unused Instances are removed from Cache in s
+const DEFAULT_CACHE_IDLE: u64 = 600;
+/// ENV for Time after which finished Jobs are removed
+const ENV_JOB_RETENTION: &str = "STREAMS_JOB_RETENTION_S";
+/// Default Time after which finished Jobs are removed in s
+const DEFAULT_JOB_RETENTION: u64 = 3600;
+/// ENV for Number of Worker Threads handling Requests
+const ENV_WORKER_THREADS: &str = "STREAMS_WORKER_THREADS";
+/// Default Number of Worker Threads
+const DEFAULT_WORKER_THREADS: usize = 4;
+/// Structure used to parse configuration file
+/// Socket needed for GRPC server, for example \[::1]:50051
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Grpc {
+    pub socket: String,
+    #[serde(default)]
+    pub cache: Cache,
+    #[serde(default)]
+    pub jobs: Jobs,
+    #[serde(default)]
+    pub workers: Workers,
+}
+/// Default implementation uses socket at \[::1]:50051, default can be set via ENVs
+impl Default for Grpc {
+    fn default() -> Self {
+        Grpc {
+            socket: env::var(ENV_GRPC_SOCKET).unwrap_or_else(|_| DEFAULT_GRPC_SOCKET.to_string()),
+            cache: Cache::default(),
+            jobs: Jobs::default(),
+            workers: Workers::default(),
+        }
+    }
+}
+/// Settings for Author and Subscriber Instance Cache
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Cache {
+    pub flush_ms: u64,
+    pub idle_s: u64,
+}
+/// Default cache settings, default can be set via ENVs
+impl Default for Cache {
+    fn default() -> Self {
+        Cache {
+            flush_ms: env::var(ENV_CACHE_FLUSH)
+                .ok()
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(DEFAULT_CACHE_FLUSH),
+            idle_s: env::var(ENV_CACHE_IDLE)
+                .ok()
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(DEFAULT_CACHE_IDLE),
+        }
+    }
+}
+/// Settings for Jobs of slow calls
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Jobs {
+    pub retention_s: u64,
+}
+/// Default job settings, default can be set via ENV
+impl Default for Jobs {
+    fn default() -> Self {
+        Jobs {
+            retention_s: env::var(ENV_JOB_RETENTION)
+                .ok()
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(DEFAULT_JOB_RETENTION),
+        }
+    }
+}
+/// Settings for Worker Threads, ids are spread over threads
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Workers {
+    pub threads: usize,
+}
+/// Default worker settings, default can be set via ENV
+impl Default for Workers {
+    fn default() -> Self {
+        Workers {
+            threads: env::var(ENV_WORKER_THREADS)
+                .ok()
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(DEFAULT_WORKER_THREADS),
+        }
+    }
+}
+/// Configuration file "streams-grpc.toml" is located at ./config/
+/// Function tries to load configuration or creates default
+pub fn load_config_file() -> Grpc {
+    let config_dir = env::current_dir()
+        .unwrap()
+        .join("config")
+        .join("streams-grpc.toml");
+    let res = confy::load_path(config_dir);
+    let cfg: Grpc = match res {
+        Ok(r) => r,
+        Err(_e) => Grpc::default(),
+    };
+    cfg
+}
@@ -1,284 +1,794 @@
-use tokio::sync::{mpsc, oneshot};
-use tonic::{Request, Response, Status};
-
-use grpc_streams::iota_streamer_server::IotaStreamer;
-use grpc_streams::{
-    IotaStreamsRecvMessagesReply, IotaStreamsReply, IotaStreamsRequest,
-    IotaStreamsSendMessageRequest,
-};
-
-use crate::msg_util::{convert_from_msgtype, convert_to_msgtype, MsgType};
-/// Protobuffer v3 file
-pub mod grpc_streams {
-    tonic::include_proto!("iota_streams_grpc");
-}
-/// Queue Element used for communication between GRPC Server and Streams State Machine
-#[derive(Debug)]
-pub enum QueueElem {
-    Request(ChannelRequest),
-    Reply(ChannelReply),
-}
-/// Request Message, from Server to State Machine
-/// tx: Per call communication channel
-#[derive(Debug)]
-pub struct ChannelRequest {
-    pub id: String,
-    pub msg_type: MsgType,
-    pub link: String,
-    pub tx: oneshot::Sender<QueueElem>,
-    pub messages: Option<Vec<String>>,
-}
-/// Reply Message, from State Machine to Server
-#[derive(Debug)]
-pub struct ChannelReply {
-    pub id: String,
-    pub msg_type: MsgType,
-    pub status: String,
-    pub code: i32,
-    pub link: String,
-    pub messages: Option<Vec<String>>,
-}
-/// Structure for Implementing GRPC Calls,
-/// tx: Stable MPSC Communication channel
-#[derive(Debug)]
-pub struct IotaStreamsService {
-    tx: mpsc::Sender<QueueElem>,
-}
-
-impl IotaStreamsService {
-    pub fn new(tx: mpsc::Sender<QueueElem>) -> IotaStreamsService {
-        IotaStreamsService { tx: tx }
-    }
-}
-/// Implementation of GRPC Calls
-/// create_new_author, create_new_subscriber, add_subscriber,
-/// receive_keyload, send_message, receive_messages, revoke_access
-#[tonic::async_trait]
-impl IotaStreamer for IotaStreamsService {
-    async fn create_new_author(
-        &self,
-        request: Request<IotaStreamsRequest>,
-    ) -> Result<Response<IotaStreamsReply>, Status> {
-        info!("create_new_author: {:?}", request);
-        match thread_communication(request, self.tx.clone()).await {
-            Ok(response) => {
-                return Ok(Response::new(IotaStreamsReply {
-                    id: response.id,
-                    msg_type: convert_from_msgtype(response.msg_type),
-                    link: response.link,
-                    status: response.status,
-                    code: response.code,
-                }))
-            }
-            Err(e) => return Err(Status::cancelled(format!("Author Not Generated: {}", e))),
-        };
-    }
-
-    async fn create_new_subscriber(
-        &self,
-        request: Request<IotaStreamsRequest>,
-    ) -> Result<Response<IotaStreamsReply>, Status> {
-        info!("create_new_subscriber: {:?}", request);
-        match thread_communication(request, self.tx.clone()).await {
-            Ok(response) => {
-                return Ok(Response::new(IotaStreamsReply {
-                    id: response.id,
-                    msg_type: convert_from_msgtype(response.msg_type),
-                    link: response.link,
-                    status: response.status,
-                    code: response.code,
-                }))
-            }
-            Err(e) => {
-                return Err(Status::cancelled(format!(
-                    "Subscriber Not Generated: {}",
-                    e
-                )))
-            }
-        };
-    }
-
-    async fn add_subscriber(
-        &self,
-        request: Request<IotaStreamsRequest>,
-    ) -> Result<Response<IotaStreamsReply>, Status> {
-        info!("add_subscriber: {:?}", request);
-        match thread_communication(request, self.tx.clone()).await {
-            Ok(response) => {
-                return Ok(Response::new(IotaStreamsReply {
-                    id: response.id,
-                    msg_type: convert_from_msgtype(response.msg_type),
-                    link: response.link,
-                    status: response.status,
-                    code: response.code,
-                }))
-            }
-            Err(e) => return Err(Status::cancelled(format!("Subscriber Not Added: {}", e))),
-        };
-    }
-
-    async fn receive_keyload(
-        &self,
-        request: Request<IotaStreamsRequest>,
-    ) -> Result<Response<IotaStreamsReply>, Status> {
-        info!("receive_keyload: {:?}", request);
-        match thread_communication(request, self.tx.clone()).await {
-            Ok(response) => {
-                return Ok(Response::new(IotaStreamsReply {
-                    id: response.id,
-                    msg_type: convert_from_msgtype(response.msg_type),
-                    link: response.link,
-                    status: response.status,
-                    code: response.code,
-                }))
-            }
-            Err(e) => return Err(Status::cancelled(format!("Keyload Not Received: {}", e))),
-        };
-    }
-
-    async fn send_message(
-        &self,
-        request: Request<IotaStreamsSendMessageRequest>,
-    ) -> Result<Response<IotaStreamsReply>, Status> {
-        info!("send_message: {:?}", request);
-        let (tx_one, rx_one) = oneshot::channel();
-        let request = request.into_inner();
-        let tx = self.tx.clone();
-        match tx
-            .send(QueueElem::Request(ChannelRequest {
-                id: request.id,
-                msg_type: convert_to_msgtype(request.msg_type),
-                link: request.message_link,
-                tx: tx_one,
-                messages: Some(vec![request.message]),
-            }))
-            .await
-        {
-            Ok(_) => (),
-            Err(e) => return Err(Status::cancelled(format!("Keyload Not Received: {}", e))),
-        };
-        let response = match rx_one.await {
-            Ok(resp) => match resp {
-                QueueElem::Reply(resp) => resp,
-                _ => {
-                    return Err(Status::cancelled(
-                        "Keyload Not Received: Wrong Data Structure Returned",
-                    ))
-                }
-            },
-            Err(e) => return Err(Status::cancelled(format!("Keyload Not Received: {}", e))),
-        };
-
-        return Ok(Response::new(IotaStreamsReply {
-            id: response.id,
-            msg_type: convert_from_msgtype(response.msg_type),
-            link: response.link,
-            status: response.status,
-            code: response.code,
-        }));
-    }
-
-    async fn receive_messages(
-        &self,
-        request: Request<IotaStreamsRequest>,
-    ) -> Result<Response<IotaStreamsRecvMessagesReply>, Status> {
-        info!("receive_messages: {:?}", request);
-        let (tx_one, rx_one) = oneshot::channel();
-        let request = request.into_inner();
-        let tx = self.tx.clone();
-        match tx
-            .send(QueueElem::Request(ChannelRequest {
-                id: request.id,
-                msg_type: convert_to_msgtype(request.msg_type),
-                link: request.link,
-                tx: tx_one,
-                messages: None,
-            }))
-            .await
-        {
-            Ok(_) => (),
-            Err(e) => {
-                return Err(Status::cancelled(format!(
-                    "Error Receiving Messsages: {}",
-                    e
-                )))
-            }
-        };
-        let response = match rx_one.await {
-            Ok(resp) => match resp {
-                QueueElem::Reply(resp) => resp,
-                _ => {
-                    return Err(Status::cancelled(
-                        "Error Receiving Messsages: Wrong Data Structure Returned",
-                    ))
-                }
-            },
-            Err(e) => {
-                return Err(Status::cancelled(format!(
-                    "Error Receiving Messsages: {}",
-                    e
-                )))
-            }
-        };
-        let messages = match response.messages {
-            Some(msgs) => msgs,
-            None => vec![],
-        };
-        return Ok(Response::new(IotaStreamsRecvMessagesReply {
-            id: response.id,
-            msg_type: convert_from_msgtype(response.msg_type),
-            link: response.link,
-            code: response.code,
-            status: response.status,
-            received_messages: messages.len() as u32,
-            messages: messages,
-        }));
-    }
-
-    async fn create_keyload(
-        &self,
-        request: Request<IotaStreamsRequest>,
-    ) -> Result<Response<IotaStreamsReply>, Status> {
-        info!("revoke_access: {:?}", request);
-        match thread_communication(request, self.tx.clone()).await {
-            Ok(response) => {
-                return Ok(Response::new(IotaStreamsReply {
-                    id: response.id,
-                    msg_type: convert_from_msgtype(response.msg_type),
-                    link: response.link,
-                    status: response.status,
-                    code: response.code,
-                }))
-            }
-            Err(e) => return Err(Status::cancelled(format!("Access Not Removed: {}", e))),
-        };
-    }
-}
-/// Basic routine to poplate and distribute Request and Response
-async fn thread_communication(
-    request: Request<IotaStreamsRequest>,
-    tx: mpsc::Sender<QueueElem>,
-) -> Result<ChannelReply, String> {
-    let (tx_one, rx_one) = oneshot::channel();
-    let request = request.into_inner();
-    match tx
-        .send(QueueElem::Request(ChannelRequest {
-            id: request.id,
-            msg_type: convert_to_msgtype(request.msg_type),
-            link: request.link,
-            tx: tx_one,
-            messages: None,
-        }))
-        .await
-    {
-        Ok(_) => (),
-        Err(e) => return Err(e.to_string()),
-    };
-    match rx_one.await {
-        Ok(resp) => match resp {
-            QueueElem::Reply(resp) => return Ok(resp),
-            _ => return Err("Wrong Data Structure Returned".to_string()),
-        },
-        Err(e) => return Err(e.to_string()),
-    };
-}
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use grpc_streams::iota_streamer_server::IotaStreamer;
+use grpc_streams::{
+    IotaStreamsBundleReply, IotaStreamsBundleRequest, IotaStreamsJobQuery, IotaStreamsJobReply,
+    IotaStreamsJobRequest, IotaStreamsQueueReply, IotaStreamsRecvMessagesReply, IotaStreamsReply,
+    IotaStreamsRequest, IotaStreamsSendMessageRequest, IotaStreamsSubscriberRequest,
+    IotaStreamsSyncReply, IotaStreamsSyncRequest, QueueDepth, StreamsMessage,
+};
+
+use crate::iota_streams_module::streams_subscriber::{ReceivedMessage, SequenceGap};
+use crate::job_registry::{Job, JobRegistry};
+use crate::msg_util::{convert_from_msgtype, convert_to_msgtype, MsgType};
+/// Protobuffer v3 file
+pub mod grpc_streams {
+    tonic::include_proto!("iota_streams_grpc");
+}
+/// Queue Element used for communication between GRPC Server and Streams State Machine
+#[derive(Debug)]
+pub enum QueueElem {
+    Request(ChannelRequest),
+    Reply(ChannelReply),
+}
+/// Buffer of job updates per WatchJob call
+const JOB_BUFFER_SIZE: usize = 8;
+/// Request Message, from Server to State Machine
+/// tx: Per call communication channel
+/// job_id: Job of request, empty if not submitted as job
+/// seed_nonce: Seed nonce of author to recover (RecoverAuthor)
+#[derive(Debug)]
+pub struct ChannelRequest {
+    pub id: String,
+    pub msg_type: MsgType,
+    pub link: String,
+    pub tx: oneshot::Sender<QueueElem>,
+    pub messages: Option<Vec<String>>,
+    pub public_messages: Option<Vec<String>>,
+    pub subscriber: Option<SubscriberInfo>,
+    pub bundle: Option<BundleInfo>,
+    pub branch: String,
+    pub multi_branch: bool,
+    pub walk_back: u32,
+    pub job_id: String,
+    pub seed_nonce: String,
+}
+/// Subscriber Information, used for adding and revoking Subscribers
+/// branches: Branches subscriber gets access to, empty for all
+#[derive(Debug)]
+pub struct SubscriberInfo {
+    pub did: String,
+    pub public_key: String,
+    pub branches: Vec<String>,
+}
+/// Bundle Information, used for exporting and importing channel states
+/// bundle: Bundle to import, empty for export
+#[derive(Debug)]
+pub struct BundleInfo {
+    pub password: String,
+    pub bundle: String,
+}
+/// Reply Message, from State Machine to Server
+/// messages: Received messages (ReceiveMessages)
+/// queue_depths: Pending requests per id (QueueDepth)
+/// bundle: Exported channel state (ExportChannelState)
+/// gaps: Sequence gaps per publisher (SyncSubscriber)
+/// seed_nonce: Seed nonce of created author (CreateNewAuthor)
+#[derive(Debug)]
+pub struct ChannelReply {
+    pub id: String,
+    pub msg_type: MsgType,
+    pub status: String,
+    pub code: i32,
+    pub link: String,
+    pub messages: Option<Vec<ReceivedMessage>>,
+    pub queue_depths: Option<Vec<(String, u32)>>,
+    pub gaps: Option<Vec<SequenceGap>>,
+    pub bundle: String,
+    pub public_key: String,
+    pub seed_nonce: String,
+}
+/// Structure for Implementing GRPC Calls,
+/// tx: Stable MPSC Communication channel
+/// jobs: Jobs of submitted requests, shared with state machine
+pub struct IotaStreamsService {
+    tx: mpsc::Sender<QueueElem>,
+    jobs: JobRegistry,
+}
+
+impl IotaStreamsService {
+    pub fn new(tx: mpsc::Sender<QueueElem>, jobs: JobRegistry) -> IotaStreamsService {
+        IotaStreamsService { tx: tx, jobs: jobs }
+    }
+}
+/// Implementation of GRPC Calls
+/// create_new_author, create_new_subscriber, add_subscriber,
+/// receive_keyload, send_message, receive_messages, create_keyload, revoke_subscriber,
+/// get_queue_depth, export_channel_state, import_channel_state, sync_subscriber,
+/// recover_author, submit_job, get_job, watch_job
+#[tonic::async_trait]
+impl IotaStreamer for IotaStreamsService {
+    type WatchJobStream = ReceiverStream<Result<IotaStreamsJobReply, Status>>;
+
+    async fn create_new_author(
+        &self,
+        request: Request<IotaStreamsRequest>,
+    ) -> Result<Response<IotaStreamsReply>, Status> {
+        info!("create_new_author: {:?}", request);
+        match thread_communication(request, self.tx.clone()).await {
+            Ok(response) => {
+                return Ok(Response::new(IotaStreamsReply {
+                    id: response.id,
+                    msg_type: convert_from_msgtype(response.msg_type),
+                    link: response.link,
+                    status: response.status,
+                    code: response.code,
+                    public_key: response.public_key,
+                    seed_nonce: response.seed_nonce,
+                }))
+            }
+            Err(e) => return Err(Status::cancelled(format!("Author Not Generated: {}", e))),
+        };
+    }
+
+    async fn create_new_subscriber(
+        &self,
+        request: Request<IotaStreamsRequest>,
+    ) -> Result<Response<IotaStreamsReply>, Status> {
+        info!("create_new_subscriber: {:?}", request);
+        match thread_communication(request, self.tx.clone()).await {
+            Ok(response) => {
+                return Ok(Response::new(IotaStreamsReply {
+                    id: response.id,
+                    msg_type: convert_from_msgtype(response.msg_type),
+                    link: response.link,
+                    status: response.status,
+                    code: response.code,
+                    public_key: response.public_key,
+                    seed_nonce: response.seed_nonce,
+                }))
+            }
+            Err(e) => {
+                return Err(Status::cancelled(format!(
+                    "Subscriber Not Generated: {}",
+                    e
+                )))
+            }
+        };
+    }
+
+    async fn add_subscriber(
+        &self,
+        request: Request<IotaStreamsSubscriberRequest>,
+    ) -> Result<Response<IotaStreamsReply>, Status> {
+        info!("add_subscriber: {:?}", request);
+        match subscriber_communication(request, self.tx.clone()).await {
+            Ok(response) => {
+                return Ok(Response::new(IotaStreamsReply {
+                    id: response.id,
+                    msg_type: convert_from_msgtype(response.msg_type),
+                    link: response.link,
+                    status: response.status,
+                    code: response.code,
+                    public_key: response.public_key,
+                    seed_nonce: response.seed_nonce,
+                }))
+            }
+            Err(e) => return Err(Status::cancelled(format!("Subscriber Not Added: {}", e))),
+        };
+    }
+
+    async fn receive_keyload(
+        &self,
+        request: Request<IotaStreamsRequest>,
+    ) -> Result<Response<IotaStreamsReply>, Status> {
+        info!("receive_keyload: {:?}", request);
+        match thread_communication(request, self.tx.clone()).await {
+            Ok(response) => {
+                return Ok(Response::new(IotaStreamsReply {
+                    id: response.id,
+                    msg_type: convert_from_msgtype(response.msg_type),
+                    link: response.link,
+                    status: response.status,
+                    code: response.code,
+                    public_key: response.public_key,
+                    seed_nonce: response.seed_nonce,
+                }))
+            }
+            Err(e) => return Err(Status::cancelled(format!("Keyload Not Received: {}", e))),
+        };
+    }
+
+    async fn send_message(
+        &self,
+        request: Request<IotaStreamsSendMessageRequest>,
+    ) -> Result<Response<IotaStreamsReply>, Status> {
+        info!("send_message: {:?}", request);
+        let (tx_one, rx_one) = oneshot::channel();
+        let tx = self.tx.clone();
+        match tx
+            .send(QueueElem::Request(make_message_request(
+                request.into_inner(),
+                tx_one,
+            )))
+            .await
+        {
+            Ok(_) => (),
+            Err(e) => return Err(Status::cancelled(format!("Keyload Not Received: {}", e))),
+        };
+        let response = match rx_one.await {
+            Ok(resp) => match resp {
+                QueueElem::Reply(resp) => resp,
+                _ => {
+                    return Err(Status::cancelled(
+                        "Keyload Not Received: Wrong Data Structure Returned",
+                    ))
+                }
+            },
+            Err(e) => return Err(Status::cancelled(format!("Keyload Not Received: {}", e))),
+        };
+
+        return Ok(Response::new(IotaStreamsReply {
+            id: response.id,
+            msg_type: convert_from_msgtype(response.msg_type),
+            link: response.link,
+            status: response.status,
+            code: response.code,
+            public_key: response.public_key,
+            seed_nonce: response.seed_nonce,
+        }));
+    }
+
+    async fn receive_messages(
+        &self,
+        request: Request<IotaStreamsRequest>,
+    ) -> Result<Response<IotaStreamsRecvMessagesReply>, Status> {
+        info!("receive_messages: {:?}", request);
+        let (tx_one, rx_one) = oneshot::channel();
+        let request = request.into_inner();
+        let tx = self.tx.clone();
+        match tx
+            .send(QueueElem::Request(ChannelRequest {
+                id: request.id,
+                msg_type: convert_to_msgtype(request.msg_type),
+                link: request.link,
+                tx: tx_one,
+                messages: None,
+                public_messages: None,
+                subscriber: None,
+                bundle: None,
+                branch: request.branch,
+                multi_branch: false,
+                walk_back: 0,
+                job_id: "".to_string(),
+                seed_nonce: "".to_string(),
+            }))
+            .await
+        {
+            Ok(_) => (),
+            Err(e) => {
+                return Err(Status::cancelled(format!(
+                    "Error Receiving Messsages: {}",
+                    e
+                )))
+            }
+        };
+        let response = match rx_one.await {
+            Ok(resp) => match resp {
+                QueueElem::Reply(resp) => resp,
+                _ => {
+                    return Err(Status::cancelled(
+                        "Error Receiving Messsages: Wrong Data Structure Returned",
+                    ))
+                }
+            },
+            Err(e) => {
+                return Err(Status::cancelled(format!(
+                    "Error Receiving Messsages: {}",
+                    e
+                )))
+            }
+        };
+        let messages = make_streams_messages(response.messages);
+        return Ok(Response::new(IotaStreamsRecvMessagesReply {
+            id: response.id,
+            msg_type: convert_from_msgtype(response.msg_type),
+            link: response.link,
+            code: response.code,
+            status: response.status,
+            received_messages: messages.len() as u32,
+            messages: messages,
+        }));
+    }
+
+    async fn create_keyload(
+        &self,
+        request: Request<IotaStreamsRequest>,
+    ) -> Result<Response<IotaStreamsReply>, Status> {
+        info!("revoke_access: {:?}", request);
+        match thread_communication(request, self.tx.clone()).await {
+            Ok(response) => {
+                return Ok(Response::new(IotaStreamsReply {
+                    id: response.id,
+                    msg_type: convert_from_msgtype(response.msg_type),
+                    link: response.link,
+                    status: response.status,
+                    code: response.code,
+                    public_key: response.public_key,
+                    seed_nonce: response.seed_nonce,
+                }))
+            }
+            Err(e) => return Err(Status::cancelled(format!("Access Not Removed: {}", e))),
+        };
+    }
+
+    async fn revoke_subscriber(
+        &self,
+        request: Request<IotaStreamsSubscriberRequest>,
+    ) -> Result<Response<IotaStreamsReply>, Status> {
+        info!("revoke_subscriber: {:?}", request);
+        match subscriber_communication(request, self.tx.clone()).await {
+            Ok(response) => {
+                return Ok(Response::new(IotaStreamsReply {
+                    id: response.id,
+                    msg_type: convert_from_msgtype(response.msg_type),
+                    link: response.link,
+                    status: response.status,
+                    code: response.code,
+                    public_key: response.public_key,
+                    seed_nonce: response.seed_nonce,
+                }))
+            }
+            Err(e) => return Err(Status::cancelled(format!("Subscriber Not Revoked: {}", e))),
+        };
+    }
+
+    async fn get_queue_depth(
+        &self,
+        request: Request<IotaStreamsRequest>,
+    ) -> Result<Response<IotaStreamsQueueReply>, Status> {
+        info!("get_queue_depth: {:?}", request);
+        match thread_communication(request, self.tx.clone()).await {
+            Ok(response) => {
+                let queues = match response.queue_depths {
+                    Some(r) => r
+                        .into_iter()
+                        .map(|(id, depth)| QueueDepth {
+                            id: id,
+                            depth: depth,
+                        })
+                        .collect(),
+                    None => vec![],
+                };
+                return Ok(Response::new(IotaStreamsQueueReply {
+                    id: response.id,
+                    msg_type: convert_from_msgtype(response.msg_type),
+                    status: response.status,
+                    code: response.code,
+                    queues: queues,
+                }));
+            }
+            Err(e) => return Err(Status::cancelled(format!("Queue Depth Not Read: {}", e))),
+        };
+    }
+
+    async fn export_channel_state(
+        &self,
+        request: Request<IotaStreamsBundleRequest>,
+    ) -> Result<Response<IotaStreamsBundleReply>, Status> {
+        info!("export_channel_state: {:?}", request.get_ref().id);
+        match bundle_communication(request, self.tx.clone()).await {
+            Ok(response) => {
+                return Ok(Response::new(IotaStreamsBundleReply {
+                    id: response.id,
+                    msg_type: convert_from_msgtype(response.msg_type),
+                    status: response.status,
+                    code: response.code,
+                    link: response.link,
+                    bundle: response.bundle,
+                }))
+            }
+            Err(e) => return Err(Status::cancelled(format!("State Not Exported: {}", e))),
+        };
+    }
+
+    async fn import_channel_state(
+        &self,
+        request: Request<IotaStreamsBundleRequest>,
+    ) -> Result<Response<IotaStreamsBundleReply>, Status> {
+        info!("import_channel_state: {:?}", request.get_ref().id);
+        match bundle_communication(request, self.tx.clone()).await {
+            Ok(response) => {
+                return Ok(Response::new(IotaStreamsBundleReply {
+                    id: response.id,
+                    msg_type: convert_from_msgtype(response.msg_type),
+                    status: response.status,
+                    code: response.code,
+                    link: response.link,
+                    bundle: response.bundle,
+                }))
+            }
+            Err(e) => return Err(Status::cancelled(format!("State Not Imported: {}", e))),
+        };
+    }
+
+    async fn sync_subscriber(
+        &self,
+        request: Request<IotaStreamsSyncRequest>,
+    ) -> Result<Response<IotaStreamsSyncReply>, Status> {
+        info!("sync_subscriber: {:?}", request);
+        match sync_communication(request, self.tx.clone()).await {
+            Ok(response) => {
+                let gaps = match response.gaps {
+                    Some(r) => r
+                        .into_iter()
+                        .map(|gap| grpc_streams::SequenceGap {
+                            publisher: gap.publisher,
+                            from_seq: gap.from_seq,
+                            to_seq: gap.to_seq,
+                            recovered: gap.recovered,
+                        })
+                        .collect(),
+                    None => vec![],
+                };
+                return Ok(Response::new(IotaStreamsSyncReply {
+                    id: response.id,
+                    msg_type: convert_from_msgtype(response.msg_type),
+                    status: response.status,
+                    code: response.code,
+                    messages: make_streams_messages(response.messages),
+                    gaps: gaps,
+                }));
+            }
+            Err(e) => return Err(Status::cancelled(format!("Subscriber Not Synced: {}", e))),
+        };
+    }
+
+    async fn recover_author(
+        &self,
+        request: Request<IotaStreamsRequest>,
+    ) -> Result<Response<IotaStreamsReply>, Status> {
+        info!("recover_author: {:?}", request);
+        match thread_communication(request, self.tx.clone()).await {
+            Ok(response) => {
+                return Ok(Response::new(IotaStreamsReply {
+                    id: response.id,
+                    msg_type: convert_from_msgtype(response.msg_type),
+                    link: response.link,
+                    status: response.status,
+                    code: response.code,
+                    public_key: response.public_key,
+                    seed_nonce: response.seed_nonce,
+                }))
+            }
+            Err(e) => return Err(Status::cancelled(format!("Author Not Recovered: {}", e))),
+        };
+    }
+
+    async fn submit_job(
+        &self,
+        request: Request<IotaStreamsJobRequest>,
+    ) -> Result<Response<IotaStreamsJobReply>, Status> {
+        info!("submit_job: {:?}", request);
+        let (tx_one, rx_one) = oneshot::channel();
+        let request = request.into_inner();
+        let msg_type = request.msg_type;
+        let mut channel_request = match make_job_request(request, tx_one) {
+            Ok(r) => r,
+            Err(e) => {
+                return Err(Status::invalid_argument(format!(
+                    "Job Not Submitted: {}",
+                    e
+                )))
+            }
+        };
+        let job = self.jobs.submit(&channel_request.id, msg_type);
+        channel_request.job_id = job.job_id.clone();
+        if let Err(e) = self.tx.send(QueueElem::Request(channel_request)).await {
+            self.jobs.fail(&job.job_id, &e.to_string());
+            return Err(Status::cancelled(format!("Job Not Submitted: {}", e)));
+        }
+        // Reply of state machine finishes job
+        let jobs = self.jobs.clone();
+        let job_id = job.job_id.clone();
+        tokio::spawn(async move {
+            match rx_one.await {
+                Ok(QueueElem::Reply(resp)) => jobs.finish(&job_id, resp),
+                Ok(_) => jobs.fail(&job_id, "Wrong Data Structure Returned"),
+                Err(e) => jobs.fail(&job_id, &e.to_string()),
+            };
+        });
+        Ok(Response::new(make_job_reply(job)))
+    }
+
+    async fn get_job(
+        &self,
+        request: Request<IotaStreamsJobQuery>,
+    ) -> Result<Response<IotaStreamsJobReply>, Status> {
+        info!("get_job: {:?}", request);
+        let job_id = request.into_inner().job_id;
+        match self.jobs.get(&job_id) {
+            Some(job) => return Ok(Response::new(make_job_reply(job))),
+            None => return Err(Status::not_found(format!("Job Not Found: {}", job_id))),
+        };
+    }
+
+    async fn watch_job(
+        &self,
+        request: Request<IotaStreamsJobQuery>,
+    ) -> Result<Response<Self::WatchJobStream>, Status> {
+        info!("watch_job: {:?}", request);
+        let job_id = request.into_inner().job_id;
+        let mut job_rx = match self.jobs.watch(&job_id) {
+            Some(r) => r,
+            None => return Err(Status::not_found(format!("Job Not Found: {}", job_id))),
+        };
+        // Forward Updates to Caller until Job is Finished
+        let (tx, rx) = mpsc::channel(JOB_BUFFER_SIZE);
+        tokio::spawn(async move {
+            loop {
+                let job = job_rx.borrow().clone();
+                let is_finished = job.state.is_finished();
+                if tx.send(Ok(make_job_reply(job))).await.is_err() {
+                    info!("Job Watcher Disconnected");
+                    break;
+                }
+                if is_finished || job_rx.changed().await.is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+/// Convert job to GRPC reply
+fn make_job_reply(job: Job) -> IotaStreamsJobReply {
+    IotaStreamsJobReply {
+        job_id: job.job_id,
+        id: job.id,
+        msg_type: job.msg_type,
+        state: job.state.as_str().to_string(),
+        status: job.status,
+        code: job.code,
+        link: job.link,
+        public_key: job.public_key,
+        seed_nonce: job.seed_nonce,
+        created: job.created,
+        updated: job.updated,
+    }
+}
+/// Request of job, only slow calls can be submitted as jobs
+fn make_job_request(
+    request: IotaStreamsJobRequest,
+    tx: oneshot::Sender<QueueElem>,
+) -> Result<ChannelRequest, String> {
+    match convert_to_msgtype(request.msg_type) {
+        MsgType::CreateNewAuthor | MsgType::CreateKeyload => match request.request {
+            Some(r) => Ok(make_request(
+                IotaStreamsRequest {
+                    msg_type: request.msg_type,
+                    ..r
+                },
+                tx,
+            )),
+            None => Err("Request Missing".to_string()),
+        },
+        MsgType::AddSubscriber => match request.subscriber_request {
+            Some(r) => Ok(make_subscriber_request(
+                IotaStreamsSubscriberRequest {
+                    msg_type: request.msg_type,
+                    ..r
+                },
+                tx,
+            )),
+            None => Err("Subscriber Request Missing".to_string()),
+        },
+        MsgType::SendMessage => match request.message_request {
+            Some(r) => Ok(make_message_request(
+                IotaStreamsSendMessageRequest {
+                    msg_type: request.msg_type,
+                    ..r
+                },
+                tx,
+            )),
+            None => Err("Message Request Missing".to_string()),
+        },
+        e => Err(format!("Message Type Not Supported: {:?}", e)),
+    }
+}
+/// Convert received messages to GRPC messages, empty if not Set
+fn make_streams_messages(messages: Option<Vec<ReceivedMessage>>) -> Vec<StreamsMessage> {
+    match messages {
+        Some(msgs) => msgs
+            .into_iter()
+            .map(|msg| StreamsMessage {
+                link: msg.link,
+                prev_link: msg.prev_link,
+                publisher: msg.publisher,
+                public_payload: msg.public_payload,
+                masked_payload: msg.masked_payload,
+                seq_num: msg.seq_num,
+            })
+            .collect(),
+        None => vec![],
+    }
+}
+/// Channel request of basic request
+fn make_request(request: IotaStreamsRequest, tx: oneshot::Sender<QueueElem>) -> ChannelRequest {
+    ChannelRequest {
+        id: request.id,
+        msg_type: convert_to_msgtype(request.msg_type),
+        link: request.link,
+        tx: tx,
+        messages: None,
+        public_messages: None,
+        bundle: None,
+        subscriber: None,
+        branch: request.branch,
+        multi_branch: request.multi_branch,
+        walk_back: 0,
+        job_id: "".to_string(),
+        seed_nonce: request.seed_nonce,
+    }
+}
+/// Channel request of subscriber request
+fn make_subscriber_request(
+    request: IotaStreamsSubscriberRequest,
+    tx: oneshot::Sender<QueueElem>,
+) -> ChannelRequest {
+    ChannelRequest {
+        id: request.id,
+        msg_type: convert_to_msgtype(request.msg_type),
+        link: request.link,
+        tx: tx,
+        messages: None,
+        public_messages: None,
+        bundle: None,
+        subscriber: Some(SubscriberInfo {
+            did: request.did,
+            public_key: request.public_key,
+            branches: request.branches,
+        }),
+        branch: "".to_string(),
+        multi_branch: false,
+        walk_back: 0,
+        job_id: "".to_string(),
+        seed_nonce: "".to_string(),
+    }
+}
+/// Channel request of send message request
+fn make_message_request(
+    request: IotaStreamsSendMessageRequest,
+    tx: oneshot::Sender<QueueElem>,
+) -> ChannelRequest {
+    ChannelRequest {
+        id: request.id,
+        msg_type: convert_to_msgtype(request.msg_type),
+        link: request.message_link,
+        tx: tx,
+        messages: Some(vec![request.message]),
+        public_messages: Some(vec![request.public_message]),
+        subscriber: None,
+        bundle: None,
+        branch: request.branch,
+        multi_branch: false,
+        walk_back: 0,
+        job_id: "".to_string(),
+        seed_nonce: "".to_string(),
+    }
+}
+/// Basic routine to poplate and distribute Request and Response
+async fn thread_communication(
+    request: Request<IotaStreamsRequest>,
+    tx: mpsc::Sender<QueueElem>,
+) -> Result<ChannelReply, String> {
+    let (tx_one, rx_one) = oneshot::channel();
+    match tx
+        .send(QueueElem::Request(make_request(
+            request.into_inner(),
+            tx_one,
+        )))
+        .await
+    {
+        Ok(_) => (),
+        Err(e) => return Err(e.to_string()),
+    };
+    match rx_one.await {
+        Ok(resp) => match resp {
+            QueueElem::Reply(resp) => return Ok(resp),
+            _ => return Err("Wrong Data Structure Returned".to_string()),
+        },
+        Err(e) => return Err(e.to_string()),
+    };
+}
+/// Routine to poplate and distribute Subscriber Request and Response
+async fn subscriber_communication(
+    request: Request<IotaStreamsSubscriberRequest>,
+    tx: mpsc::Sender<QueueElem>,
+) -> Result<ChannelReply, String> {
+    let (tx_one, rx_one) = oneshot::channel();
+    match tx
+        .send(QueueElem::Request(make_subscriber_request(
+            request.into_inner(),
+            tx_one,
+        )))
+        .await
+    {
+        Ok(_) => (),
+        Err(e) => return Err(e.to_string()),
+    };
+    match rx_one.await {
+        Ok(resp) => match resp {
+            QueueElem::Reply(resp) => return Ok(resp),
+            _ => return Err("Wrong Data Structure Returned".to_string()),
+        },
+        Err(e) => return Err(e.to_string()),
+    };
+}
+/// Routine to poplate and distribute Bundle Request and Response
+async fn bundle_communication(
+    request: Request<IotaStreamsBundleRequest>,
+    tx: mpsc::Sender<QueueElem>,
+) -> Result<ChannelReply, String> {
+    let (tx_one, rx_one) = oneshot::channel();
+    let request = request.into_inner();
+    match tx
+        .send(QueueElem::Request(ChannelRequest {
+            id: request.id,
+            msg_type: convert_to_msgtype(request.msg_type),
+            link: "".to_string(),
+            tx: tx_one,
+            messages: None,
+            public_messages: None,
+            subscriber: None,
+            bundle: Some(BundleInfo {
+                password: request.password,
+                bundle: request.bundle,
+            }),
+            branch: "".to_string(),
+            multi_branch: false,
+            walk_back: 0,
+            job_id: "".to_string(),
+            seed_nonce: "".to_string(),
+        }))
+        .await
+    {
+        Ok(_) => (),
+        Err(e) => return Err(e.to_string()),
+    };
+    match rx_one.await {
+        Ok(resp) => match resp {
+            QueueElem::Reply(resp) => return Ok(resp),
+            _ => return Err("Wrong Data Structure Returned".to_string()),
+        },
+        Err(e) => return Err(e.to_string()),
+    };
+}
+/// Routine to poplate and distribute Sync Request and Response
+async fn sync_communication(
+    request: Request<IotaStreamsSyncRequest>,
+    tx: mpsc::Sender<QueueElem>,
+) -> Result<ChannelReply, String> {
+    let (tx_one, rx_one) = oneshot::channel();
+    let request = request.into_inner();
+    match tx
+        .send(QueueElem::Request(ChannelRequest {
+            id: request.id,
+            msg_type: convert_to_msgtype(request.msg_type),
+            link: "".to_string(),
+            tx: tx_one,
+            messages: None,
+            public_messages: None,
+            subscriber: None,
+            bundle: None,
+            branch: "".to_string(),
+            multi_branch: false,
+            walk_back: request.walk_back,
+            job_id: "".to_string(),
+            seed_nonce: "".to_string(),
+        }))
+        .await
+    {
+        Ok(_) => (),
+        Err(e) => return Err(e.to_string()),
+    };
+    match rx_one.await {
+        Ok(resp) => match resp {
+            QueueElem::Reply(resp) => return Ok(resp),
+            _ => return Err("Wrong Data Structure Returned".to_string()),
+        },
+        Err(e) => return Err(e.to_string()),
+    };
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Instant;
+use tokio::sync::Mutex;
+use tokio::time::{interval, Duration};
+
+use crate::config::{load_config_file, Cache};
+use crate::iota_streams_module::{streams_author, streams_subscriber};
+
+/// Cached instance, dirty if state changed since last export
+struct CacheEntry<T> {
+    instance: Rc<Mutex<T>>,
+    dirty: bool,
+    last_used: Instant,
+}
+
+/// Live Author or Subscriber instances by id. Instances are locked while in use,
+/// changed states are written to disk by cache_worker or on every change (write through)
+pub struct InstanceCache<T> {
+    entries: HashMap<String, CacheEntry<T>>,
+    write_through: bool,
+}
+
+impl<T> InstanceCache<T> {
+    pub fn new() -> Self {
+        InstanceCache::with_write_through(load_config_file().cache.flush_ms == 0)
+    }
+
+    pub fn with_write_through(write_through: bool) -> Self {
+        InstanceCache {
+            entries: HashMap::new(),
+            write_through: write_through,
+        }
+    }
+
+    pub fn get(&mut self, id: &str) -> Option<Rc<Mutex<T>>> {
+        match self.entries.get_mut(id) {
+            Some(entry) => {
+                entry.last_used = Instant::now();
+                Some(entry.instance.clone())
+            }
+            None => None,
+        }
+    }
+
+    /// Insert instance, replaces cached instance with same id
+    pub fn insert(&mut self, id: &str, instance: T) -> Rc<Mutex<T>> {
+        let instance = Rc::new(Mutex::new(instance));
+        self.entries.insert(
+            id.to_string(),
+            CacheEntry {
+                instance: instance.clone(),
+                dirty: false,
+                last_used: Instant::now(),
+            },
+        );
+        instance
+    }
+
+    pub fn set_dirty(&mut self, id: &str, dirty: bool) {
+        if let Some(entry) = self.entries.get_mut(id) {
+            entry.dirty = dirty;
+        }
+    }
+
+    pub fn is_write_through(&self) -> bool {
+        self.write_through
+    }
+
+    /// Return changed instances and reset dirty flags
+    pub fn take_dirty(&mut self) -> Vec<(String, Rc<Mutex<T>>)> {
+        let mut dirty = Vec::new();
+        for (id, entry) in self.entries.iter_mut() {
+            if entry.dirty {
+                entry.dirty = false;
+                dirty.push((id.clone(), entry.instance.clone()));
+            }
+        }
+        dirty
+    }
+
+    /// Remove instances unused for longer than idle, changed or locked instances are kept
+    pub fn evict_idle(&mut self, idle: Duration) -> usize {
+        let len = self.entries.len();
+        self.entries.retain(|_, entry| {
+            entry.dirty || Rc::strong_count(&entry.instance) > 1 || entry.last_used.elapsed() < idle
+        });
+        len - self.entries.len()
+    }
+}
+
+/// Periodically write changed states to disk and evict idle instances
+pub async fn cache_worker() {
+    info!("--- cache_worker() ---");
+    let cfg = load_config_file().cache;
+    let period = match get_period(&cfg) {
+        Some(r) => r,
+        None => {
+            info!("Cache Flush and Eviction Disabled");
+            return;
+        }
+    };
+    let idle = match cfg.idle_s {
+        0 => None,
+        r => Some(Duration::from_secs(r)),
+    };
+    let mut timer = interval(period);
+    loop {
+        timer.tick().await;
+        flush_instances(idle).await;
+    }
+}
+
+/// Write changed states of all cached instances to disk
+pub async fn flush_instances(idle: Option<Duration>) {
+    streams_author::flush_states(idle).await;
+    streams_subscriber::flush_states(idle).await;
+}
+
+/// Flush interval, eviction interval if states are written on every change
+fn get_period(cfg: &Cache) -> Option<Duration> {
+    if cfg.flush_ms > 0 {
+        Some(Duration::from_millis(cfg.flush_ms))
+    } else if cfg.idle_s > 0 {
+        Some(Duration::from_secs(cfg.idle_s))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_dirty() {
+        let mut cache: InstanceCache<u32> = InstanceCache::with_write_through(false);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        assert!(cache.take_dirty().is_empty());
+        cache.set_dirty("a", true);
+        cache.set_dirty("unknown", true);
+        let dirty = cache.take_dirty();
+        assert_eq!(dirty.len(), 1);
+        assert_eq!(dirty[0].0, "a");
+        // Dirty flags are reset
+        assert!(cache.take_dirty().is_empty());
+    }
+
+    #[test]
+    fn test_evict_idle() {
+        let mut cache: InstanceCache<u32> = InstanceCache::with_write_through(false);
+        cache.insert("clean", 1);
+        cache.insert("dirty", 2);
+        let locked = cache.insert("locked", 3);
+        cache.set_dirty("dirty", true);
+        assert_eq!(cache.evict_idle(Duration::from_secs(600)), 0);
+        assert_eq!(cache.evict_idle(Duration::from_secs(0)), 1);
+        assert!(cache.get("clean").is_none());
+        assert!(cache.get("dirty").is_some());
+        assert!(cache.get("locked").is_some());
+        // Instances can be evicted once flushed and released
+        drop(locked);
+        cache.take_dirty();
+        assert_eq!(cache.evict_idle(Duration::from_secs(0)), 2);
+    }
+}
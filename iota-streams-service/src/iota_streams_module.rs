@@ -1,452 +1,1778 @@
-/// streams_author contains helper functions simplifying the use of
-/// the IOTA Streams library for the Author instance. Communication via Public and Private Single Branch.
-///
-/// API Calls with a high-level of abstraction are:
-/// - create_new_author(id: &str) -> String:
-///     Generates a new author instance, send an announcement message to the tangle
-///     and exports the instance to ./EXPORT_STATE_PATH/<id>
-///     Returns the announcement link as a string
-///
-/// - add_subscriber(id: &str, subscription_link: &str) -> String
-///     Author adding Subscriber through subscription link, Author
-///     instance imported from disk and afterwards exported with altert state
-///     Returns keyload link as string
-///  
-/// - send_message(id: &str, msg_link: &str, message: &str) -> String
-///     Author sending signed message to tangle. Author
-///     instance imported from disk and afterwards exported with altert state
-///     Returns next message link as string
-///
-pub mod streams_author {
-    use crate::iota_streams_module::util::{
-        generate_seed, get_state_password, get_state_path, make_client, parse_address,
-    };
-    use iota_streams::{
-        app::transport::tangle::{client::Client, TangleAddress},
-        app_channels::api::tangle::{Address, Author, Bytes, ChannelType},
-        app_channels::Tangle,
-        core_edsig::signature::ed25519::PublicKey,
-    };
-
-    pub async fn create_new_author(id: &str) -> Result<String, String> {
-        let client = make_client().await?;
-        info!("Create New Channel Through Announcement");
-        let mut author = make_author(client);
-        let ann_link = make_announcement(&mut author).await?;
-        export_state(&mut author, id).await?;
-        Ok(ann_link.to_string())
-    }
-
-    pub async fn add_subscriber(id: &str, subscription_link: &str) -> Result<String, String> {
-        let mut author = import_state(id).await?;
-        let sub_link = parse_address(subscription_link)?;
-        info!(
-            "Add Subscriber with Subscription Link: {}",
-            subscription_link
-        );
-        receive_subscription(&mut author, &sub_link).await?;
-        export_state(&mut author, id).await?;
-        Ok("Subscriber Succesfully Added".to_string())
-    }
-
-    pub async fn announce_keyload(id: &str) -> Result<String, String> {
-        let mut author = import_state(id).await?;
-        let announcement_link = match author.announcement_link().clone() {
-            Some(address) => address,
-            None => return Err("No Announcement Link Found".to_string()),
-        };
-        let (keyload_link, _) = make_keyload(&mut author, &announcement_link).await?;
-        export_state(&mut author, id).await?;
-        Ok(keyload_link.to_string())
-    }
-
-    pub async fn remove_access(id: &str, pk: &Vec<u8>) -> Result<String, String> {
-        let mut author = import_state(id).await?;
-        let public_key = match PublicKey::from_bytes(pk) {
-            Ok(res) => res,
-            Err(e) => return Err(format!("Unable to Convert String to Public Key: {}", e)),
-        };
-        // info!("Public Key to Remove: {:?}", public_key);
-        match author.remove_subscriber(public_key) {
-            Ok(_) => {
-                info!("Successfully Removed Subscriber");
-                export_state(&mut author, id).await?;
-                return Ok("Successfully Removed Subscriber".to_string());
-            }
-            Err(e) => {
-                error!("Unable to Remove Subscriber: {}", e);
-                return Err(format!("Unable to Remove Subscriber: {}", e));
-            }
-        };
-    }
-
-    pub async fn send_message(id: &str, msg_link: &str, message: &str) -> Result<String, String> {
-        let mut author = import_state(id).await?;
-        let msg_link = parse_address(msg_link)?;
-        info!("Send message: {}", message);
-        let (msg_link, _seq_link) = match author
-            .send_signed_packet(
-                &msg_link,
-                &Bytes::default(),
-                &Bytes(message.as_bytes().to_vec()),
-            )
-            .await
-        {
-            Ok(send) => {
-                info!(
-                    "Sent msg: {}, tangle index: {:#}",
-                    msg_link,
-                    msg_link.to_msg_index()
-                );
-                send
-            }
-            Err(e) => return Err(format!("Error: Sending Message: {}", e)),
-        };
-        export_state(&mut author, id).await?;
-        Ok(msg_link.to_string())
-    }
-
-    pub fn make_author(client: Client) -> Author<Tangle> {
-        let seed = generate_seed();
-        let author = Author::new(&seed, ChannelType::SingleBranch, client);
-        author
-    }
-
-    pub async fn make_announcement(author: &mut Author<Tangle>) -> Result<Address, String> {
-        match author.send_announce().await {
-            Ok(link) => {
-                info!("Announcement Link: {}", &link.to_string());
-                return Ok(link);
-            }
-            Err(e) => {
-                error!("{}", e);
-                return Err(format!("Unable to Make Announcement: {}", e));
-            }
-        };
-    }
-
-    pub async fn make_keyload(
-        author: &mut Author<Tangle>,
-        announcement_link: &Address,
-    ) -> Result<(TangleAddress, Option<TangleAddress>), String> {
-        match author.send_keyload_for_everyone(announcement_link).await {
-            Ok(link) => {
-                info!("Keyload Link: {}", &link.0.to_string());
-                return Ok(link);
-            }
-            Err(e) => {
-                error!("{}", e);
-                return Err(format!("Unable to Make Keyload: {}", e));
-            }
-        };
-    }
-
-    pub async fn receive_subscription(
-        author: &mut Author<Tangle>,
-        subscription_link: &Address,
-    ) -> Result<(), String> {
-        match author.receive_subscribe(subscription_link).await {
-            Ok(_) => {
-                info!("Author Received Subscription");
-                return Ok(());
-            }
-            Err(e) => {
-                error!("{}", e);
-                return Err(format!("Unable to Receive Subscription: {}", e));
-            }
-        };
-    }
-
-    pub async fn export_state(caller: &mut Author<Tangle>, id: &str) -> Result<(), String> {
-        let password = get_state_password();
-        let path = get_state_path(id);
-        match caller.export(&password).await {
-            Ok(state) => {
-                match std::fs::write(path, state) {
-                    Ok(_) => {
-                        info!("State Successfully Exported");
-                        return Ok(());
-                    }
-                    Err(e) => return Err(format!("Unable To Write State: {}", e)),
-                };
-            }
-            Err(e) => return Err(format!("Unable To Export State: {}", e)),
-        };
-    }
-
-    pub async fn import_state(id: &str) -> Result<Author<Tangle>, String> {
-        let password = get_state_password();
-        let client = make_client().await?;
-        let path = get_state_path(id);
-        let binary = match std::fs::read(path) {
-            Ok(r) => r,
-            Err(e) => return Err(format!("Unable To Read State: {}", e)),
-        };
-        match Author::import(&binary, &password, client).await {
-            Ok(r) => {
-                info!("State Successfully Imported");
-                return Ok(r);
-            }
-            Err(e) => return Err(format!("Unable To Import State: {}", e)),
-        };
-    }
-}
-/// streams_subscriber contains helper functions simplifying the use of
-/// the IOTA Streams library for the Subscriber instance.
-///
-/// API Calls with a high-level of abstraction are:
-/// - create_new_subscriber(id: &str, announcement_link: &str) -> String:
-///     Generates a new subscriber instance and subscripes to channel via announcement link
-///     and exports the instance to ./EXPORT_STATE_PATH/<id>
-///     Returns the subscription link as a string
-///
-/// - receive_messages(id: &str) -> Vec<String>
-///     Subscriber receiving messages send from author. Subscriber
-///     instance imported from disk and afterwards exported with altert state
-///     Returns received messages as vector of strings
-///  
-/// - receive_keyload(id: &str, keyload_link: &str) -> String
-///     Subscriber receiving keyload link. Subscriber
-///     instance imported from disk and afterwards exported with altert state
-///     Returns information stating success or failure
-///
-pub mod streams_subscriber {
-    use crate::iota_streams_module::util::{
-        generate_seed, get_state_password, get_state_path, make_client, parse_address,
-    };
-    use iota_streams::{
-        app::transport::tangle::client::Client,
-        app_channels::api::tangle::{Address, Bytes, Subscriber},
-        app_channels::Tangle,
-    };
-    pub async fn create_new_subscriber(
-        id: &str,
-        announcement_link: &str,
-    ) -> Result<String, String> {
-        let client = make_client().await?;
-        let mut subscriber = make_subscriber(client);
-        let ann_link = parse_address(announcement_link)?;
-        receive_announcement(&mut subscriber, &ann_link).await?;
-        let subscription_link = make_subscription(&mut subscriber, &ann_link).await?;
-        export_state(&mut subscriber, id).await?;
-        Ok(subscription_link.to_string())
-    }
-
-    pub async fn receive_messages(id: &str) -> Result<Vec<String>, String> {
-        let mut subscriber = import_state(id).await?;
-        info!("Fetch Next Messages");
-        let wrapped_msgs = match subscriber.fetch_next_msgs().await {
-            Ok(r) => r,
-            Err(e) => return Err(format!("Unable To Fetch Messages: {}", e)),
-        };
-        let mut msgs = Vec::new();
-        for msg in wrapped_msgs {
-            msgs.push(
-                msg.body
-                    .masked_payload()
-                    .and_then(Bytes::as_str)
-                    .unwrap_or("None")
-                    .to_string(),
-            )
-        }
-        export_state(&mut subscriber, id).await?;
-        Ok(msgs)
-    }
-
-    pub async fn receive_keyload(id: &str, keyload_link: &str) -> Result<String, String> {
-        let mut subscriber = import_state(id).await?;
-        let key_link = parse_address(keyload_link)?;
-        let is_received = match subscriber.receive_keyload(&key_link).await {
-            Ok(r) => r,
-            Err(e) => return Err(format!("Subscriber Unable To Receive Keyload: {}", e)),
-        };
-        if is_received {
-            info!("Subscriber Received Keyload");
-            export_state(&mut subscriber, id).await?;
-            return Ok("Subscriber Received Keyload".to_string());
-        } else {
-            return Err("Subscriber Unable To Receive Keyload".to_string());
-        }
-    }
-
-    pub fn make_subscriber(client: Client) -> Subscriber<Tangle> {
-        let seed = generate_seed();
-        let subscriber = Subscriber::new(&seed, client);
-        subscriber
-    }
-
-    pub async fn receive_announcement(
-        subscriber: &mut Subscriber<Tangle>,
-        announcement_link: &Address,
-    ) -> Result<(), String> {
-        match subscriber.receive_announcement(announcement_link).await {
-            Ok(_r) => {
-                info!("Subscriber Received Announcement Link");
-                return Ok(());
-            }
-            Err(e) => {
-                error!("{}", e);
-                return Err(format!("Unable to Receive Announcement: {}", e));
-            }
-        };
-    }
-
-    pub async fn make_subscription(
-        subscriber: &mut Subscriber<Tangle>,
-        announcement_link: &Address,
-    ) -> Result<Address, String> {
-        match subscriber.send_subscribe(announcement_link).await {
-            Ok(link) => {
-                info!("Subscription Link: {}", &link.to_string());
-                return Ok(link);
-            }
-            Err(e) => {
-                error!("{}", e);
-                return Err(format!("Unable to Make Subscription: {}", e));
-            }
-        };
-    }
-
-    pub async fn export_state(caller: &mut Subscriber<Tangle>, id: &str) -> Result<(), String> {
-        let password = get_state_password();
-        let path = get_state_path(id);
-        let state = match caller.export(&password).await {
-            Ok(r) => r,
-            Err(e) => return Err(format!("Unable to Write State: {}", e)),
-        };
-        match std::fs::write(path, state) {
-            Ok(_) => {
-                info!("State Successfully Exported");
-                return Ok(());
-            }
-            Err(e) => return Err(format!("Unable to Export State: {}", e)),
-        }
-    }
-
-    pub async fn import_state(id: &str) -> Result<Subscriber<Tangle>, String> {
-        let password = get_state_password();
-        let client = make_client().await?;
-        let path = get_state_path(id);
-        let binary = match std::fs::read(path) {
-            Ok(r) => r,
-            Err(e) => return Err(format!("Unable to Read State: {}", e)),
-        };
-        match Subscriber::import(&binary, &password, client).await {
-            Ok(r) => {
-                info!("State Successfully Imported");
-                return Ok(r);
-            }
-            Err(e) => return Err(format!("Unable to Import State: {}", e)),
-        };
-    }
-}
-/// util contains helper functions simplifying the use of
-/// the IOTA Streams library for the Subscriber and Author instance.
-///
-pub mod util {
-    use crate::config::{
-        DEFAULT_NODE_URL, DEFAULT_STATE_PWD, ENV_LOCAL_POW, ENV_NODE_URL, ENV_STATE_PWD,
-        EXPORT_STATE_PATH,
-    };
-    use iota_client::ClientBuilder;
-    use iota_streams::app::transport::tangle::client::{Client, SendOptions};
-    use iota_streams::app::transport::tangle::TangleAddress;
-    use iota_streams::app_channels::api::tangle::Address;
-    use rand::Rng;
-    use std::env;
-    use std::path::Path;
-
-    pub const ALPH9: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ9";
-
-    pub fn generate_seed() -> String {
-        let seed: String = (0..81)
-            .map(|_| {
-                ALPH9
-                    .chars()
-                    .nth(rand::thread_rng().gen_range(0..27))
-                    .unwrap()
-            })
-            .collect::<String>();
-        seed
-    }
-
-    pub fn get_state_path(filename: &str) -> std::path::PathBuf {
-        let path = Path::new(".").join(EXPORT_STATE_PATH).join(filename);
-        path
-    }
-
-    pub fn get_state_password() -> String {
-        let password = env::var(ENV_STATE_PWD).unwrap_or_else(|_| DEFAULT_STATE_PWD.to_string());
-        password
-    }
-
-    pub fn parse_address(link: &str) -> Result<Address, String> {
-        match link.parse::<TangleAddress>() {
-            Ok(address) => return Ok(address),
-            Err(e) => return Err(format!("Unable To Parse Address From String: {}", e)),
-        }
-    }
-
-    pub async fn make_client() -> Result<Client, String> {
-        let node_url = env::var(ENV_NODE_URL).unwrap_or_else(|_| DEFAULT_NODE_URL.to_string());
-        let local_pow = match env::var(ENV_LOCAL_POW)
-            .unwrap_or_else(|_| "false".to_string())
-            .as_str()
-        {
-            "true" => true,
-            "false" => false,
-            _ => false,
-        };
-        let client_opt = make_client_opt(&node_url, local_pow);
-        let iota_cli = match ClientBuilder::new()
-            .with_node(&node_url)
-            .unwrap()
-            .with_local_pow(local_pow)
-            .finish()
-            .await
-        {
-            Ok(r) => {
-                info!("Streams Client Created");
-                r
-            }
-            Err(e) => return Err(format!("Unable to Create Streams Client: {}", e)),
-        };
-        let client = Client::new(client_opt, iota_cli);
-        Ok(client)
-    }
-
-    pub fn make_client_opt(url: &str, local_pow: bool) -> SendOptions {
-        SendOptions {
-            url: url.to_string(),
-            local_pow: local_pow,
-        }
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    // cargo test -- --nocapture //for std out
-    use super::*;
-    #[test]
-    fn test_generate_seed() {
-        let samples = 100;
-        let mut seeds: Vec<String> = vec![String::new(); samples];
-        let mut len = 0;
-        for _ in 1..=samples {
-            let seed = util::generate_seed();
-            println!("{}: {}", len, &seed);
-            len += 1;
-            seeds.push(seed);
-        }
-        seeds.sort();
-        seeds.dedup();
-        let mut len = 0;
-        for seed in &seeds {
-            if !seed.is_empty() {
-                println!("{}: {}", len, seed);
-                len += 1;
-            }
-        }
-        assert_eq!(len, samples);
-    }
-}
+/// streams_author contains helper functions simplifying the use of
+/// the IOTA Streams library for the Author instance. Communication via Public and Private Single
+/// or Multi Branch.
+///
+/// API Calls with a high-level of abstraction are:
+/// - create_new_author(id: &str, multi_branch: bool) -> (String, String):
+///     Generates a new author instance, send an announcement message to the tangle
+///     and exports the instance to ./EXPORT_STATE_PATH/<id>. Seed is derived from
+///     STREAMS_RECOVERY_KEY, id and a random nonce if set
+///     Returns the announcement link and the seed nonce (empty without recovery key)
+///
+/// - recover_author(id: &str, announcement_link: &str, multi_branch: bool, seed_nonce: &str)
+///   -> String:
+///     Rebuilds author instance from seed (derived from STREAMS_RECOVERY_KEY, id and seed nonce)
+///     and announcement link, syncs it to the latest message and exports the instance
+///     Returns the announcement link as a string
+///
+/// - add_subscriber(id: &str, subscription_link: &str, did: &str, public_key: &str,
+///   branches: &Vec<String>) -> String
+///     Author adding Subscriber through subscription link, Author
+///     instance taken from cache (imported from disk if not cached).
+///     DID and public key are saved to ./EXPORT_STATE_PATH/<id>.subscribers,
+///     branches the subscriber gets access to are saved to ./EXPORT_STATE_PATH/<id>.branches
+///     Returns keyload link as string
+///
+/// - announce_keyload(id: &str, branch: &str) -> String
+///     Author sending keyload for all subscribers or, for a branch, for subscribers
+///     with access to the branch. New branches start at the keyload
+///     Returns keyload link as string
+///
+/// - revoke_subscriber(id: &str, did: &str, public_key: &str) -> String
+///     Author removing Subscriber by public key (hex) or registered DID
+///     and sending new keyload excluding the Subscriber
+///     Returns keyload link as string
+///  
+/// - send_message(id: &str, msg_link: &str, branch: &str, public: &str, message: &str) -> String
+///     Author sending signed message to tangle with public (readable by anyone) and
+///     masked (readable by subscribers) payload, linked to last keyload of branch
+///     if msg_link is empty. Author instance taken from cache (imported from disk if not cached)
+///     Returns next message link as string
+///
+/// Changed states are written to disk by instance_cache::cache_worker,
+/// or on every change if cache flush is disabled
+///
+pub mod streams_author {
+    use crate::instance_cache::InstanceCache;
+    use crate::iota_streams_module::util::{
+        derive_seed, generate_nonce, get_author_seed, get_recovery_key, get_state_files,
+        get_state_password, get_state_path, load_branches, load_links, load_subscribers,
+        make_client, parse_address, parse_public_key, restore_generation, save_branches,
+        save_subscribers, update_links, write_state, BranchRegistry, StreamsTransport,
+    };
+    use iota_streams::{
+        app::identifier::Identifier,
+        app::transport::tangle::TangleAddress,
+        app_channels::api::tangle::{Address, Author, Bytes, ChannelType},
+        core_edsig::signature::ed25519::PublicKey,
+    };
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use tokio::sync::Mutex;
+    use tokio::time::Duration;
+
+    thread_local! {
+        static AUTHORS: RefCell<InstanceCache<Author<StreamsTransport>>> =
+            RefCell::new(InstanceCache::new());
+    }
+
+    pub async fn create_new_author(
+        id: &str,
+        multi_branch: bool,
+    ) -> Result<(String, String), String> {
+        create_author(id, multi_branch, get_recovery_key()).await
+    }
+
+    /// Create author with seed derived from recovery_key if set,
+    /// returns announcement link and seed nonce (empty without recovery key)
+    pub async fn create_author(
+        id: &str,
+        multi_branch: bool,
+        recovery_key: Option<String>,
+    ) -> Result<(String, String), String> {
+        let client = make_client().await?;
+        info!("Create New Channel Through Announcement");
+        // New nonce per author, re-created ids get a different seed
+        let nonce = match recovery_key {
+            Some(_) => generate_nonce(),
+            None => "".to_string(),
+        };
+        update_links(id, "seed_nonce", &nonce)?;
+        let mut author = make_author(
+            client,
+            &get_author_seed(id, &nonce, recovery_key),
+            multi_branch,
+        );
+        let ann_link = make_announcement(&mut author).await?;
+        export_state(&mut author, id).await?;
+        AUTHORS.with(|r| r.borrow_mut().insert(id, author));
+        update_links(id, "kind", "author")?;
+        update_links(id, "ann_link", &ann_link.to_string())?;
+        Ok((ann_link.to_string(), nonce))
+    }
+
+    pub async fn recover_author(
+        id: &str,
+        announcement_link: &str,
+        multi_branch: bool,
+        seed_nonce: &str,
+    ) -> Result<String, String> {
+        match get_recovery_key() {
+            Some(r) => recover_with_key(id, announcement_link, multi_branch, seed_nonce, &r).await,
+            None => Err("No Recovery Key Set".to_string()),
+        }
+    }
+
+    /// Recover author with seed derived from recovery_key, id and seed_nonce,
+    /// nonce saved with the links is used if seed_nonce is empty
+    pub async fn recover_with_key(
+        id: &str,
+        announcement_link: &str,
+        multi_branch: bool,
+        seed_nonce: &str,
+        recovery_key: &str,
+    ) -> Result<String, String> {
+        if get_state_path(id).exists() {
+            return Err(format!("State Already Exists for ID: {}", id));
+        }
+        let ann_link = parse_address(announcement_link)?;
+        let nonce = match seed_nonce {
+            "" => load_links(id)?.seed_nonce,
+            r => r.to_string(),
+        };
+        if nonce.is_empty() {
+            return Err(format!("Seed Nonce Missing for ID: {}", id));
+        }
+        let client = make_client().await?;
+        info!(
+            "Recover Author from Seed and Announcement Link: {}",
+            announcement_link
+        );
+        let mut author = match Author::recover(
+            &derive_seed(recovery_key, id, &nonce),
+            &ann_link,
+            get_channel_type(multi_branch),
+            client,
+        )
+        .await
+        {
+            Ok(r) => r,
+            Err(e) => return Err(format!("Unable to Recover Author: {}", e)),
+        };
+        // Re-sync to latest message of channel
+        if let Err(e) = author.sync_state().await {
+            return Err(format!("Unable to Sync Author State: {}", e));
+        }
+        export_state(&mut author, id).await?;
+        AUTHORS.with(|r| r.borrow_mut().insert(id, author));
+        update_links(id, "kind", "author")?;
+        update_links(id, "ann_link", announcement_link)?;
+        Ok(announcement_link.to_string())
+    }
+
+    pub async fn add_subscriber(
+        id: &str,
+        subscription_link: &str,
+        did: &str,
+        public_key: &str,
+        branches: &Vec<String>,
+    ) -> Result<String, String> {
+        // Keyloads of branches are addressed by Public Key
+        if !branches.is_empty() && public_key.is_empty() {
+            return Err("Public Key Needed for Branch Access".to_string());
+        }
+        let author = get_author(id).await?;
+        let mut author = author.lock().await;
+        let sub_link = parse_address(subscription_link)?;
+        info!(
+            "Add Subscriber with Subscription Link: {}",
+            subscription_link
+        );
+        receive_subscription(&mut author, &sub_link).await?;
+        save_state(&mut author, id).await?;
+        // Register Public Key, needed to Revoke Subscriber by DID
+        if !did.is_empty() && !public_key.is_empty() {
+            let mut subscribers = load_subscribers(id)?;
+            subscribers.insert(did.to_string(), public_key.to_string());
+            save_subscribers(id, &subscribers)?;
+        }
+        if !branches.is_empty() {
+            let mut registry = load_branches(id)?;
+            registry
+                .access
+                .insert(public_key.to_string(), branches.clone());
+            save_branches(id, &registry)?;
+        }
+        Ok("Subscriber Succesfully Added".to_string())
+    }
+
+    pub async fn revoke_subscriber(
+        id: &str,
+        did: &str,
+        public_key: &str,
+    ) -> Result<String, String> {
+        let mut subscribers = load_subscribers(id)?;
+        let public_key = if public_key.is_empty() {
+            match subscribers.get(did) {
+                Some(r) => r.clone(),
+                None => return Err(format!("No Public Key Registered for DID: {}", did)),
+            }
+        } else {
+            public_key.to_string()
+        };
+        info!("Revoke Subscriber with Public Key: {}", &public_key);
+        let pk = parse_public_key(&public_key)?;
+        remove_access(id, &pk).await?;
+        subscribers.retain(|_, r| !r.eq(&public_key));
+        save_subscribers(id, &subscribers)?;
+        let mut registry = load_branches(id)?;
+        if registry.access.remove(&public_key).is_some() {
+            save_branches(id, &registry)?;
+        }
+        // Branches get their new keyloads with CreateKeyload, no keyload for all branches
+        if !registry.keyloads.is_empty() {
+            return Ok("".to_string());
+        }
+        // Keyload for Remaining Subscribers
+        announce_keyload(id, "").await
+    }
+
+    pub async fn announce_keyload(id: &str, branch: &str) -> Result<String, String> {
+        let author = get_author(id).await?;
+        let mut author = author.lock().await;
+        let announcement_link = match author.announcement_link().clone() {
+            Some(address) => address,
+            None => return Err("No Announcement Link Found".to_string()),
+        };
+        let mut registry = load_branches(id)?;
+        // Without branch access rules all subscribers get access
+        let (keyload_link, _) = if branch.is_empty() || registry.access.is_empty() {
+            make_keyload(&mut author, &announcement_link).await?
+        } else {
+            let public_keys = get_branch_subscribers(&author, id, &registry, branch)?;
+            info!(
+                "Keyload for Branch {} with {} Subscribers",
+                branch,
+                public_keys.len()
+            );
+            make_branch_keyload(&mut author, &announcement_link, &public_keys).await?
+        };
+        save_state(&mut author, id).await?;
+        if !branch.is_empty() {
+            registry
+                .keyloads
+                .insert(branch.to_string(), keyload_link.to_string());
+            save_branches(id, &registry)?;
+        }
+        update_links(id, "key_link", &keyload_link.to_string())?;
+        Ok(keyload_link.to_string())
+    }
+
+    pub async fn remove_access(id: &str, pk: &Vec<u8>) -> Result<String, String> {
+        let author = get_author(id).await?;
+        let mut author = author.lock().await;
+        let public_key = match PublicKey::from_bytes(pk) {
+            Ok(res) => res,
+            Err(e) => return Err(format!("Unable to Convert String to Public Key: {}", e)),
+        };
+        // info!("Public Key to Remove: {:?}", public_key);
+        match author.remove_subscriber(public_key) {
+            Ok(_) => {
+                info!("Successfully Removed Subscriber");
+                save_state(&mut author, id).await?;
+                return Ok("Successfully Removed Subscriber".to_string());
+            }
+            Err(e) => {
+                error!("Unable to Remove Subscriber: {}", e);
+                return Err(format!("Unable to Remove Subscriber: {}", e));
+            }
+        };
+    }
+
+    pub async fn send_message(
+        id: &str,
+        msg_link: &str,
+        branch: &str,
+        public: &str,
+        message: &str,
+    ) -> Result<String, String> {
+        // Messages of a branch are linked to its keyload
+        let msg_link = if msg_link.is_empty() && !branch.is_empty() {
+            match load_branches(id)?.keyloads.get(branch) {
+                Some(r) => parse_address(r)?,
+                None => return Err(format!("No Keyload Found for Branch: {}", branch)),
+            }
+        } else {
+            parse_address(msg_link)?
+        };
+        let author = get_author(id).await?;
+        let mut author = author.lock().await;
+        info!("Send message: {}", message);
+        let (msg_link, _seq_link) = match author
+            .send_signed_packet(
+                &msg_link,
+                &Bytes(public.as_bytes().to_vec()),
+                &Bytes(message.as_bytes().to_vec()),
+            )
+            .await
+        {
+            Ok(send) => {
+                info!(
+                    "Sent msg: {}, tangle index: {:#}",
+                    msg_link,
+                    msg_link.to_msg_index()
+                );
+                send
+            }
+            Err(e) => return Err(format!("Error: Sending Message: {}", e)),
+        };
+        save_state(&mut author, id).await?;
+        update_links(id, "msg_link", &msg_link.to_string())?;
+        Ok(msg_link.to_string())
+    }
+
+    pub fn make_author(
+        client: StreamsTransport,
+        seed: &str,
+        multi_branch: bool,
+    ) -> Author<StreamsTransport> {
+        let author = Author::new(seed, get_channel_type(multi_branch), client);
+        author
+    }
+
+    fn get_channel_type(multi_branch: bool) -> ChannelType {
+        if multi_branch {
+            ChannelType::MultiBranch
+        } else {
+            ChannelType::SingleBranch
+        }
+    }
+
+    pub async fn make_announcement(
+        author: &mut Author<StreamsTransport>,
+    ) -> Result<Address, String> {
+        match author.send_announce().await {
+            Ok(link) => {
+                info!("Announcement Link: {}", &link.to_string());
+                return Ok(link);
+            }
+            Err(e) => {
+                error!("{}", e);
+                return Err(format!("Unable to Make Announcement: {}", e));
+            }
+        };
+    }
+
+    pub async fn make_keyload(
+        author: &mut Author<StreamsTransport>,
+        announcement_link: &Address,
+    ) -> Result<(TangleAddress, Option<TangleAddress>), String> {
+        match author.send_keyload_for_everyone(announcement_link).await {
+            Ok(link) => {
+                info!("Keyload Link: {}", &link.0.to_string());
+                return Ok(link);
+            }
+            Err(e) => {
+                error!("{}", e);
+                return Err(format!("Unable to Make Keyload: {}", e));
+            }
+        };
+    }
+
+    /// Keyload for subscribers given by Public Key (hex), starts a new branch
+    pub async fn make_branch_keyload(
+        author: &mut Author<StreamsTransport>,
+        announcement_link: &Address,
+        public_keys: &Vec<String>,
+    ) -> Result<(TangleAddress, Option<TangleAddress>), String> {
+        let mut identifiers: Vec<Identifier> = Vec::new();
+        for public_key in public_keys {
+            match PublicKey::from_bytes(&parse_public_key(public_key)?) {
+                Ok(r) => identifiers.push(Identifier::from(r)),
+                Err(e) => return Err(format!("Unable to Convert String to Public Key: {}", e)),
+            };
+        }
+        match author.send_keyload(announcement_link, &identifiers).await {
+            Ok(link) => {
+                info!("Keyload Link: {}", &link.0.to_string());
+                return Ok(link);
+            }
+            Err(e) => {
+                error!("{}", e);
+                return Err(format!("Unable to Make Keyload: {}", e));
+            }
+        };
+    }
+
+    /// Public Keys of subscribers with access to branch, subscribers without
+    /// access rules (also subscribers added without DID or public key) have access
+    /// to all branches
+    fn get_branch_subscribers(
+        author: &Author<StreamsTransport>,
+        id: &str,
+        registry: &BranchRegistry,
+        branch: &str,
+    ) -> Result<Vec<String>, String> {
+        let mut public_keys: Vec<String> = load_subscribers(id)?.into_values().collect();
+        public_keys.extend(registry.access.keys().cloned());
+        public_keys.extend(get_subscriber_keys(author)?);
+        public_keys.sort();
+        public_keys.dedup();
+        public_keys.retain(|r| match registry.access.get(r) {
+            Some(branches) => branches.iter().any(|b| b.eq(branch)),
+            None => true,
+        });
+        Ok(public_keys)
+    }
+
+    /// Public Keys (hex) of all subscribers known to author
+    fn get_subscriber_keys(author: &Author<StreamsTransport>) -> Result<Vec<String>, String> {
+        let own_key = hex::encode(author.get_public_key().as_bytes());
+        match author.fetch_state() {
+            Ok(state) => Ok(state
+                .into_iter()
+                .map(|(id, _)| hex::encode(id.to_bytes()))
+                // Pre-shared keys have no public key
+                .filter(|r| r.len() == 64 && !r.eq(&own_key))
+                .collect()),
+            Err(e) => Err(format!("Unable To Fetch Author State: {}", e)),
+        }
+    }
+
+    pub async fn receive_subscription(
+        author: &mut Author<StreamsTransport>,
+        subscription_link: &Address,
+    ) -> Result<(), String> {
+        match author.receive_subscribe(subscription_link).await {
+            Ok(_) => {
+                info!("Author Received Subscription");
+                return Ok(());
+            }
+            Err(e) => {
+                error!("{}", e);
+                return Err(format!("Unable to Receive Subscription: {}", e));
+            }
+        };
+    }
+
+    /// Cached Author instance, imported from disk if not cached
+    pub async fn get_author(id: &str) -> Result<Rc<Mutex<Author<StreamsTransport>>>, String> {
+        if let Some(r) = AUTHORS.with(|r| r.borrow_mut().get(id)) {
+            return Ok(r);
+        }
+        let author = import_state(id).await?;
+        Ok(AUTHORS.with(|r| r.borrow_mut().insert(id, author)))
+    }
+
+    /// Mark state as changed, exported immediately if cache flush is disabled
+    pub async fn save_state(caller: &mut Author<StreamsTransport>, id: &str) -> Result<(), String> {
+        if AUTHORS.with(|r| r.borrow().is_write_through()) {
+            return export_state(caller, id).await;
+        }
+        AUTHORS.with(|r| r.borrow_mut().set_dirty(id, true));
+        Ok(())
+    }
+
+    /// Export changed states and evict instances unused for longer than idle
+    pub async fn flush_states(idle: Option<Duration>) {
+        let dirty = AUTHORS.with(|r| r.borrow_mut().take_dirty());
+        for (id, author) in dirty {
+            let mut author = author.lock().await;
+            if let Err(e) = export_state(&mut author, &id).await {
+                error!("{}", e);
+                AUTHORS.with(|r| r.borrow_mut().set_dirty(&id, true));
+            }
+        }
+        if let Some(idle) = idle {
+            let evicted = AUTHORS.with(|r| r.borrow_mut().evict_idle(idle));
+            if evicted > 0 {
+                info!("Evicted {} Idle Author Instances", evicted);
+            }
+        }
+    }
+
+    pub async fn export_state(
+        caller: &mut Author<StreamsTransport>,
+        id: &str,
+    ) -> Result<(), String> {
+        let password = get_state_password();
+        let path = get_state_path(id);
+        match caller.export(&password).await {
+            Ok(state) => {
+                match write_state(&path, &state) {
+                    Ok(_) => {
+                        info!("State Successfully Exported");
+                        return Ok(());
+                    }
+                    Err(e) => return Err(format!("Unable To Write State: {}", e)),
+                };
+            }
+            Err(e) => return Err(format!("Unable To Export State: {}", e)),
+        };
+    }
+
+    /// Export state encrypted with password, e.g. for state bundles
+    pub async fn export_with_password(id: &str, password: &str) -> Result<Vec<u8>, String> {
+        let author = get_author(id).await?;
+        let author = author.lock().await;
+        match author.export(password).await {
+            Ok(r) => return Ok(r),
+            Err(e) => return Err(format!("Unable To Export State: {}", e)),
+        };
+    }
+
+    /// Import state encrypted with password, saved with state password and cached
+    pub async fn import_with_password(
+        id: &str,
+        state: &[u8],
+        password: &str,
+    ) -> Result<(), String> {
+        let client = make_client().await?;
+        let mut author = match Author::import(state, password, client).await {
+            Ok(r) => r,
+            Err(e) => return Err(format!("Unable To Import State: {}", e)),
+        };
+        export_state(&mut author, id).await?;
+        AUTHORS.with(|r| r.borrow_mut().insert(id, author));
+        Ok(())
+    }
+
+    /// Import state of id, falls back to newest readable generation if current state
+    /// can not be read or imported, generation is synced to latest message of channel
+    pub async fn import_state(id: &str) -> Result<Author<StreamsTransport>, String> {
+        let password = get_state_password();
+        let mut last_error = String::new();
+        for (i, path) in get_state_files(id).iter().enumerate() {
+            let binary = match std::fs::read(path) {
+                Ok(r) => r,
+                Err(e) => {
+                    last_error = format!("Unable To Read State: {}", e);
+                    continue;
+                }
+            };
+            let client = make_client().await?;
+            match Author::import(&binary, &password, client).await {
+                Ok(mut r) => {
+                    if i > 0 {
+                        // Older generation misses last messages, sync before publishing again
+                        if let Err(e) = r.sync_state().await {
+                            return Err(format!(
+                                "Unable To Sync Author State of {}: {}",
+                                path.display(),
+                                e
+                            ));
+                        }
+                        let state = match r.export(&password).await {
+                            Ok(r) => r,
+                            Err(e) => return Err(format!("Unable To Export State: {}", e)),
+                        };
+                        restore_generation(id, &state, path);
+                    }
+                    info!("State Successfully Imported");
+                    return Ok(r);
+                }
+                Err(e) => {
+                    warn!("Unable To Import State {}: {}", path.display(), e);
+                    last_error = format!("Unable To Import State: {}", e);
+                }
+            };
+        }
+        Err(last_error)
+    }
+}
+/// streams_subscriber contains helper functions simplifying the use of
+/// the IOTA Streams library for the Subscriber instance.
+///
+/// API Calls with a high-level of abstraction are:
+/// - create_new_subscriber(id: &str, announcement_link: &str) -> (String, String):
+///     Generates a new subscriber instance and subscripes to channel via announcement link
+///     and exports the instance to ./EXPORT_STATE_PATH/<id>
+///     Returns the subscription link and public key (hex) as strings
+///
+/// - receive_messages(id: &str) -> Vec<ReceivedMessage>
+///     Subscriber receiving messages send from author. Subscriber
+///     instance taken from cache (imported from disk if not cached)
+///     Returns received packets with link, previous link, publisher,
+///     public and masked payload and sequence number
+///  
+/// - sync_subscriber(id: &str, walk_back: usize) -> (Vec<ReceivedMessage>, Vec<SequenceGap>)
+///     Subscriber fetching next messages, compared to the messages already delivered.
+///     Messages missed by the subscriber state (e.g. state restored or imported ahead of
+///     delivered messages) are reported as sequence gaps per publisher, messages delivered
+///     before (e.g. state restored from older backup) are skipped. With walk_back > 0
+///     up to walk_back messages per gap are fetched backwards from the last message
+///     Returns received and recovered messages and sequence gaps
+///
+/// - receive_keyload(id: &str, keyload_link: &str) -> String
+///     Subscriber receiving keyload link. Subscriber
+///     instance taken from cache (imported from disk if not cached)
+///     Returns information stating success or failure
+///
+/// Changed states are written to disk by instance_cache::cache_worker,
+/// or on every change if cache flush is disabled
+///
+pub mod streams_subscriber {
+    use crate::instance_cache::InstanceCache;
+    use crate::iota_streams_module::util::{
+        generate_seed, get_state_files, get_state_password, get_state_path, load_delivered,
+        make_client, parse_address, restore_generation, save_delivered, update_links, write_state,
+        StreamsTransport,
+    };
+    use iota_streams::app_channels::api::tangle::{
+        Address, MessageContent, Subscriber, UnwrappedMessage,
+    };
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+    use tokio::sync::Mutex;
+    use tokio::time::Duration;
+
+    /// Signed or tagged packet received by Subscriber,
+    /// publisher: Public key (hex), empty for tagged packets
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct ReceivedMessage {
+        pub link: String,
+        pub prev_link: String,
+        pub publisher: String,
+        pub public_payload: Vec<u8>,
+        pub masked_payload: Vec<u8>,
+        pub seq_num: u64,
+    }
+
+    /// Sequence numbers of publisher missed by Subscriber, from from_seq to to_seq (exclusive),
+    /// recovered: Number of missed messages fetched by walking back
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct SequenceGap {
+        pub publisher: String,
+        pub from_seq: u64,
+        pub to_seq: u64,
+        pub recovered: u64,
+    }
+
+    thread_local! {
+        static SUBSCRIBERS: RefCell<InstanceCache<Subscriber<StreamsTransport>>> =
+            RefCell::new(InstanceCache::new());
+    }
+
+    pub async fn create_new_subscriber(
+        id: &str,
+        announcement_link: &str,
+    ) -> Result<(String, String), String> {
+        let client = make_client().await?;
+        let mut subscriber = make_subscriber(client);
+        let ann_link = parse_address(announcement_link)?;
+        receive_announcement(&mut subscriber, &ann_link).await?;
+        let subscription_link = make_subscription(&mut subscriber, &ann_link).await?;
+        export_state(&mut subscriber, id).await?;
+        let public_key = hex::encode(subscriber.get_public_key().as_bytes());
+        SUBSCRIBERS.with(|r| r.borrow_mut().insert(id, subscriber));
+        update_links(id, "kind", "subscriber")?;
+        update_links(id, "ann_link", announcement_link)?;
+        Ok((subscription_link.to_string(), public_key))
+    }
+
+    pub async fn receive_messages(id: &str) -> Result<Vec<ReceivedMessage>, String> {
+        let subscriber = get_subscriber(id).await?;
+        let mut subscriber = subscriber.lock().await;
+        // Next sequence numbers of publishers, counted up per received message
+        let mut seq_nums = get_seq_nums(&subscriber)?;
+        let author = get_author_key(&subscriber);
+        info!("Fetch Next Messages");
+        let wrapped_msgs = match subscriber.fetch_next_msgs().await {
+            Ok(r) => r,
+            Err(e) => return Err(format!("Unable To Fetch Messages: {}", e)),
+        };
+        let msgs = unwrap_messages(wrapped_msgs, &mut seq_nums, &author);
+        save_state(&mut subscriber, id).await?;
+        save_delivered(id, &get_delivered(&subscriber, load_delivered(id)?)?)?;
+        if let Some(msg) = msgs.last() {
+            update_links(id, "msg_link", &msg.link)?;
+        }
+        Ok(msgs)
+    }
+
+    pub async fn sync_subscriber(
+        id: &str,
+        walk_back: usize,
+    ) -> Result<(Vec<ReceivedMessage>, Vec<SequenceGap>), String> {
+        let subscriber = get_subscriber(id).await?;
+        let mut subscriber = subscriber.lock().await;
+        // Next sequence numbers of publishers in state, before fetching
+        let state_seq_nums = get_seq_nums(&subscriber)?;
+        let mut seq_nums = state_seq_nums.clone();
+        let delivered = load_delivered(id)?;
+        let author = get_author_key(&subscriber);
+        info!("Fetch Next Messages");
+        let wrapped_msgs = match subscriber.fetch_next_msgs().await {
+            Ok(r) => r,
+            Err(e) => return Err(format!("Unable To Fetch Messages: {}", e)),
+        };
+        // Skip messages delivered before
+        let mut msgs: Vec<ReceivedMessage> = unwrap_messages(wrapped_msgs, &mut seq_nums, &author)
+            .into_iter()
+            .filter(|msg| match delivered.get(&msg.publisher) {
+                Some(r) => msg.seq_num >= *r,
+                None => true,
+            })
+            .collect();
+        let cursors = get_cursors(&subscriber)?;
+        let cursor_seq_nums: HashMap<String, u64> = cursors
+            .iter()
+            .map(|(publisher, (seq_num, _))| (publisher.clone(), *seq_num))
+            .collect();
+        let mut gaps = find_gaps(&delivered, &state_seq_nums, &seq_nums, &cursor_seq_nums);
+        for gap in gaps.iter_mut() {
+            if walk_back > 0 {
+                let (next_seq, last_link) = &cursors[&gap.publisher];
+                let recovered = walk_back_messages(
+                    &mut subscriber,
+                    last_link,
+                    *next_seq,
+                    gap,
+                    walk_back,
+                    &author,
+                )
+                .await?;
+                gap.recovered = recovered.len() as u64;
+                msgs.extend(recovered);
+            }
+            warn!(
+                "Sequence Gap of Publisher {}: {} to {}, Recovered: {}",
+                gap.publisher, gap.from_seq, gap.to_seq, gap.recovered
+            );
+        }
+        save_state(&mut subscriber, id).await?;
+        save_delivered(id, &get_delivered(&subscriber, delivered)?)?;
+        if let Some(msg) = msgs.last() {
+            update_links(id, "msg_link", &msg.link)?;
+        }
+        Ok((msgs, gaps))
+    }
+
+    pub async fn receive_keyload(id: &str, keyload_link: &str) -> Result<String, String> {
+        let subscriber = get_subscriber(id).await?;
+        let mut subscriber = subscriber.lock().await;
+        let key_link = parse_address(keyload_link)?;
+        let is_received = match subscriber.receive_keyload(&key_link).await {
+            Ok(r) => r,
+            Err(e) => return Err(format!("Subscriber Unable To Receive Keyload: {}", e)),
+        };
+        if is_received {
+            info!("Subscriber Received Keyload");
+            save_state(&mut subscriber, id).await?;
+            update_links(id, "key_link", keyload_link)?;
+            return Ok("Subscriber Received Keyload".to_string());
+        } else {
+            return Err("Subscriber Unable To Receive Keyload".to_string());
+        }
+    }
+
+    /// Next sequence number per publisher (public key as hex) known to Subscriber
+    fn get_seq_nums(
+        subscriber: &Subscriber<StreamsTransport>,
+    ) -> Result<HashMap<String, u64>, String> {
+        Ok(get_cursors(subscriber)?
+            .into_iter()
+            .map(|(publisher, (seq_num, _))| (publisher, seq_num))
+            .collect())
+    }
+
+    /// Sequence gaps per publisher, sorted by publisher and sequence number.
+    /// delivered: Next sequence number delivered before, state_seq_nums: Next sequence number
+    /// of state before fetching, counted: Next sequence number after received messages,
+    /// cursor_seq_nums: Next sequence number of state after fetching
+    pub fn find_gaps(
+        delivered: &HashMap<String, u64>,
+        state_seq_nums: &HashMap<String, u64>,
+        counted: &HashMap<String, u64>,
+        cursor_seq_nums: &HashMap<String, u64>,
+    ) -> Vec<SequenceGap> {
+        let mut publishers: Vec<&String> = cursor_seq_nums.keys().collect();
+        publishers.sort();
+        let mut gaps = Vec::new();
+        for publisher in publishers {
+            // Publishers unknown before fetching have no counted messages
+            let state_seq = match state_seq_nums.get(publisher) {
+                Some(r) => *r,
+                None => continue,
+            };
+            // State ahead of delivered messages (restored or imported state)
+            if let Some(delivered_seq) = delivered.get(publisher) {
+                if *delivered_seq < state_seq {
+                    gaps.push(SequenceGap {
+                        publisher: publisher.clone(),
+                        from_seq: *delivered_seq,
+                        to_seq: state_seq,
+                        recovered: 0,
+                    });
+                }
+            }
+            // Cursor moved past messages not received
+            let counted_seq = match counted.get(publisher) {
+                Some(r) => *r,
+                None => state_seq,
+            };
+            let cursor_seq = cursor_seq_nums[publisher];
+            if counted_seq < cursor_seq {
+                gaps.push(SequenceGap {
+                    publisher: publisher.clone(),
+                    from_seq: counted_seq.max(state_seq),
+                    to_seq: cursor_seq,
+                    recovered: 0,
+                });
+            }
+        }
+        gaps
+    }
+
+    /// Next sequence number per publisher delivered, cursors of Subscriber
+    /// merged into the previously delivered sequence numbers
+    fn get_delivered(
+        subscriber: &Subscriber<StreamsTransport>,
+        mut delivered: HashMap<String, u64>,
+    ) -> Result<HashMap<String, u64>, String> {
+        for (publisher, seq_num) in get_seq_nums(subscriber)? {
+            let entry = delivered.entry(publisher).or_insert(0);
+            *entry = seq_num.max(*entry);
+        }
+        Ok(delivered)
+    }
+
+    /// Next sequence number and last link per publisher (public key as hex)
+    fn get_cursors(
+        subscriber: &Subscriber<StreamsTransport>,
+    ) -> Result<HashMap<String, (u64, Address)>, String> {
+        match subscriber.fetch_state() {
+            Ok(state) => Ok(state
+                .into_iter()
+                .map(|(id, cursor)| {
+                    (
+                        hex::encode(id.to_bytes()),
+                        (cursor.seq_no as u64, cursor.link),
+                    )
+                })
+                .collect()),
+            Err(e) => Err(format!("Unable To Fetch Subscriber State: {}", e)),
+        }
+    }
+
+    /// Public key (hex) of channel author, empty if unknown
+    fn get_author_key(subscriber: &Subscriber<StreamsTransport>) -> String {
+        match subscriber.author_public_key() {
+            Some(r) => hex::encode(r.as_bytes()),
+            None => "".to_string(),
+        }
+    }
+
+    /// Convert fetched messages, messages without payload are skipped.
+    /// seq_nums: Next sequence number per publisher, counted up per message
+    fn unwrap_messages(
+        wrapped_msgs: Vec<UnwrappedMessage>,
+        seq_nums: &mut HashMap<String, u64>,
+        author: &str,
+    ) -> Vec<ReceivedMessage> {
+        let mut msgs = Vec::new();
+        for msg in wrapped_msgs {
+            let publisher = match &msg.body {
+                MessageContent::SignedPacket { id, .. } => hex::encode(id.to_bytes()),
+                MessageContent::Keyload { .. } => author.to_string(),
+                _ => "".to_string(),
+            };
+            let seq_num = match seq_nums.get_mut(&publisher) {
+                Some(r) => {
+                    *r += 1;
+                    *r - 1
+                }
+                None => 0,
+            };
+            if let Some(msg) = make_received_message(msg, publisher, seq_num) {
+                msgs.push(msg);
+            }
+        }
+        msgs
+    }
+
+    fn make_received_message(
+        msg: UnwrappedMessage,
+        publisher: String,
+        seq_num: u64,
+    ) -> Option<ReceivedMessage> {
+        let (public_payload, masked_payload) =
+            match (msg.body.public_payload(), msg.body.masked_payload()) {
+                (Some(public), Some(masked)) => (public.0.clone(), masked.0.clone()),
+                _ => {
+                    info!("Skip Message Without Payload: {}", msg.link);
+                    return None;
+                }
+            };
+        Some(ReceivedMessage {
+            link: msg.link.to_string(),
+            prev_link: msg.prev_link.to_string(),
+            publisher: publisher,
+            public_payload: public_payload,
+            masked_payload: masked_payload,
+            seq_num: seq_num,
+        })
+    }
+
+    /// Fetch last message of publisher (next_seq - 1) and up to walk_back - 1 previous messages,
+    /// messages within sequence gap are returned
+    async fn walk_back_messages(
+        subscriber: &mut Subscriber<StreamsTransport>,
+        last_link: &Address,
+        next_seq: u64,
+        gap: &SequenceGap,
+        walk_back: usize,
+        author: &str,
+    ) -> Result<Vec<ReceivedMessage>, String> {
+        info!(
+            "Walk Back {} Messages of Publisher {} from {}",
+            walk_back, gap.publisher, last_link
+        );
+        let last_msg = match subscriber.receive_msg(last_link).await {
+            Ok(r) => r,
+            Err(e) => return Err(format!("Unable To Receive Message: {}", e)),
+        };
+        // Previous messages, oldest first
+        let mut wrapped_msgs = match subscriber.fetch_prev_msgs(last_link, walk_back - 1).await {
+            Ok(r) => r,
+            Err(e) => return Err(format!("Unable To Fetch Previous Messages: {}", e)),
+        };
+        wrapped_msgs.push(last_msg);
+        let first_seq = next_seq.saturating_sub(wrapped_msgs.len() as u64);
+        let mut seq_nums = HashMap::new();
+        seq_nums.insert(gap.publisher.clone(), first_seq);
+        Ok(unwrap_messages(wrapped_msgs, &mut seq_nums, author)
+            .into_iter()
+            .filter(|msg| msg.seq_num >= gap.from_seq && msg.seq_num < gap.to_seq)
+            .collect())
+    }
+
+    pub fn make_subscriber(client: StreamsTransport) -> Subscriber<StreamsTransport> {
+        let seed = generate_seed();
+        let subscriber = Subscriber::new(&seed, client);
+        subscriber
+    }
+
+    pub async fn receive_announcement(
+        subscriber: &mut Subscriber<StreamsTransport>,
+        announcement_link: &Address,
+    ) -> Result<(), String> {
+        match subscriber.receive_announcement(announcement_link).await {
+            Ok(_r) => {
+                info!("Subscriber Received Announcement Link");
+                return Ok(());
+            }
+            Err(e) => {
+                error!("{}", e);
+                return Err(format!("Unable to Receive Announcement: {}", e));
+            }
+        };
+    }
+
+    pub async fn make_subscription(
+        subscriber: &mut Subscriber<StreamsTransport>,
+        announcement_link: &Address,
+    ) -> Result<Address, String> {
+        match subscriber.send_subscribe(announcement_link).await {
+            Ok(link) => {
+                info!("Subscription Link: {}", &link.to_string());
+                return Ok(link);
+            }
+            Err(e) => {
+                error!("{}", e);
+                return Err(format!("Unable to Make Subscription: {}", e));
+            }
+        };
+    }
+
+    /// Cached Subscriber instance, imported from disk if not cached
+    pub async fn get_subscriber(
+        id: &str,
+    ) -> Result<Rc<Mutex<Subscriber<StreamsTransport>>>, String> {
+        if let Some(r) = SUBSCRIBERS.with(|r| r.borrow_mut().get(id)) {
+            return Ok(r);
+        }
+        let subscriber = import_state(id).await?;
+        Ok(SUBSCRIBERS.with(|r| r.borrow_mut().insert(id, subscriber)))
+    }
+
+    /// Mark state as changed, exported immediately if cache flush is disabled
+    pub async fn save_state(
+        caller: &mut Subscriber<StreamsTransport>,
+        id: &str,
+    ) -> Result<(), String> {
+        if SUBSCRIBERS.with(|r| r.borrow().is_write_through()) {
+            return export_state(caller, id).await;
+        }
+        SUBSCRIBERS.with(|r| r.borrow_mut().set_dirty(id, true));
+        Ok(())
+    }
+
+    /// Export changed states and evict instances unused for longer than idle
+    pub async fn flush_states(idle: Option<Duration>) {
+        let dirty = SUBSCRIBERS.with(|r| r.borrow_mut().take_dirty());
+        for (id, subscriber) in dirty {
+            let mut subscriber = subscriber.lock().await;
+            if let Err(e) = export_state(&mut subscriber, &id).await {
+                error!("{}", e);
+                SUBSCRIBERS.with(|r| r.borrow_mut().set_dirty(&id, true));
+            }
+        }
+        if let Some(idle) = idle {
+            let evicted = SUBSCRIBERS.with(|r| r.borrow_mut().evict_idle(idle));
+            if evicted > 0 {
+                info!("Evicted {} Idle Subscriber Instances", evicted);
+            }
+        }
+    }
+
+    pub async fn export_state(
+        caller: &mut Subscriber<StreamsTransport>,
+        id: &str,
+    ) -> Result<(), String> {
+        let password = get_state_password();
+        let path = get_state_path(id);
+        let state = match caller.export(&password).await {
+            Ok(r) => r,
+            Err(e) => return Err(format!("Unable to Write State: {}", e)),
+        };
+        match write_state(&path, &state) {
+            Ok(_) => {
+                info!("State Successfully Exported");
+                return Ok(());
+            }
+            Err(e) => return Err(format!("Unable to Export State: {}", e)),
+        }
+    }
+
+    /// Export state encrypted with password, e.g. for state bundles
+    pub async fn export_with_password(id: &str, password: &str) -> Result<Vec<u8>, String> {
+        let subscriber = get_subscriber(id).await?;
+        let subscriber = subscriber.lock().await;
+        match subscriber.export(password).await {
+            Ok(r) => return Ok(r),
+            Err(e) => return Err(format!("Unable to Export State: {}", e)),
+        };
+    }
+
+    /// Import state encrypted with password, saved with state password and cached
+    pub async fn import_with_password(
+        id: &str,
+        state: &[u8],
+        password: &str,
+    ) -> Result<(), String> {
+        let client = make_client().await?;
+        let mut subscriber = match Subscriber::import(state, password, client).await {
+            Ok(r) => r,
+            Err(e) => return Err(format!("Unable to Import State: {}", e)),
+        };
+        export_state(&mut subscriber, id).await?;
+        SUBSCRIBERS.with(|r| r.borrow_mut().insert(id, subscriber));
+        Ok(())
+    }
+
+    /// Import state of id, falls back to newest readable generation if current state
+    /// can not be read or imported
+    pub async fn import_state(id: &str) -> Result<Subscriber<StreamsTransport>, String> {
+        let password = get_state_password();
+        let mut last_error = String::new();
+        for (i, path) in get_state_files(id).iter().enumerate() {
+            let binary = match std::fs::read(path) {
+                Ok(r) => r,
+                Err(e) => {
+                    last_error = format!("Unable to Read State: {}", e);
+                    continue;
+                }
+            };
+            let client = make_client().await?;
+            match Subscriber::import(&binary, &password, client).await {
+                Ok(r) => {
+                    if i > 0 {
+                        restore_generation(id, &binary, path);
+                    }
+                    info!("State Successfully Imported");
+                    return Ok(r);
+                }
+                Err(e) => {
+                    warn!("Unable to Import State {}: {}", path.display(), e);
+                    last_error = format!("Unable to Import State: {}", e);
+                }
+            };
+        }
+        Err(last_error)
+    }
+}
+/// util contains helper functions simplifying the use of
+/// the IOTA Streams library for the Subscriber and Author instance.
+///
+pub mod util {
+    use crate::config::{
+        DEFAULT_NODE_URL, DEFAULT_STATE_GENERATIONS, DEFAULT_STATE_GENERATION_INTERVAL,
+        DEFAULT_STATE_PWD, ENV_LOCAL_POW, ENV_NODE_URL, ENV_RECOVERY_KEY, ENV_STATE_GENERATIONS,
+        ENV_STATE_GENERATION_INTERVAL, ENV_STATE_PWD, EXPORT_STATE_PATH,
+    };
+    #[cfg(not(feature = "offline"))]
+    use iota_client::ClientBuilder;
+    use iota_streams::app::transport::tangle::client::{Client, SendOptions};
+    use iota_streams::app::transport::tangle::TangleAddress;
+    use iota_streams::app_channels::api::tangle::Address;
+    #[cfg(feature = "offline")]
+    use iota_streams::app_channels::api::tangle::BucketTransport;
+    use rand::Rng;
+    use serde_derive::{Deserialize, Serialize};
+    use sha2::{Digest, Sha256};
+    #[cfg(feature = "offline")]
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::env;
+    use std::io::Write;
+    use std::path::Path;
+    #[cfg(feature = "offline")]
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    pub const ALPH9: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ9";
+
+    /// Transport used by Author and Subscriber, Tangle Client connected to Node
+    #[cfg(not(feature = "offline"))]
+    pub type StreamsTransport = Client;
+    /// Transport used by Author and Subscriber, local In-Memory Bucket (Feature "offline")
+    #[cfg(feature = "offline")]
+    pub type StreamsTransport = Rc<RefCell<BucketTransport>>;
+
+    #[cfg(feature = "offline")]
+    thread_local! {
+        /// Bucket shared by all Instances, Messages are lost on Restart
+        static BUCKET: StreamsTransport = Rc::new(RefCell::new(BucketTransport::new()));
+    }
+
+    pub fn generate_seed() -> String {
+        let seed: String = (0..81)
+            .map(|_| {
+                ALPH9
+                    .chars()
+                    .nth(rand::thread_rng().gen_range(0..27))
+                    .unwrap()
+            })
+            .collect::<String>();
+        seed
+    }
+
+    /// Random nonce (hex) mixed into derived seeds
+    pub fn generate_nonce() -> String {
+        hex::encode(rand::thread_rng().gen::<[u8; 16]>())
+    }
+
+    /// Seed derived from recovery key, id and nonce, same key, id and nonce give the same seed.
+    /// Empty nonce gives the seed of authors created without nonce
+    pub fn derive_seed(recovery_key: &str, id: &str, nonce: &str) -> String {
+        let mut seed = String::new();
+        let mut counter: u8 = 0;
+        while seed.len() < 81 {
+            let mut hasher = Sha256::new()
+                .chain_update(recovery_key.as_bytes())
+                .chain_update([0u8])
+                .chain_update(id.as_bytes());
+            if !nonce.is_empty() {
+                hasher = hasher.chain_update([0u8]).chain_update(nonce.as_bytes());
+            }
+            let digest = hasher.chain_update([counter]).finalize();
+            // Bytes above 242 are skipped, every character equally likely
+            for byte in digest.iter().filter(|r| **r < 243) {
+                if seed.len() < 81 {
+                    seed.push(ALPH9.as_bytes()[(*byte % 27) as usize] as char);
+                }
+            }
+            counter += 1;
+        }
+        seed
+    }
+
+    /// Key author seeds are derived from, None if not set
+    pub fn get_recovery_key() -> Option<String> {
+        match env::var(ENV_RECOVERY_KEY) {
+            Ok(r) if !r.is_empty() => Some(r),
+            _ => None,
+        }
+    }
+
+    /// Seed of new author, derived from recovery key and nonce if set, random otherwise
+    pub fn get_author_seed(id: &str, nonce: &str, recovery_key: Option<String>) -> String {
+        match recovery_key {
+            Some(key) => derive_seed(&key, id, nonce),
+            None => {
+                warn!("No Recovery Key Set, Author {} Can Not Be Recovered", id);
+                generate_seed()
+            }
+        }
+    }
+
+    pub fn get_state_path(filename: &str) -> std::path::PathBuf {
+        let path = Path::new(".").join(EXPORT_STATE_PATH).join(filename);
+        path
+    }
+
+    /// Registry of branches, access: Key: Public Key (hex), Value: Branches of subscriber,
+    /// keyloads: Key: Branch, Value: Last keyload link of branch
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    pub struct BranchRegistry {
+        pub access: HashMap<String, Vec<String>>,
+        pub keyloads: HashMap<String, String>,
+    }
+
+    pub fn get_registry_path(id: &str) -> std::path::PathBuf {
+        let path = Path::new(".")
+            .join(EXPORT_STATE_PATH)
+            .join(format!("{}.subscribers", id));
+        path
+    }
+
+    /// Load registry of subscribers, Key: DID, Value: Public Key (hex)
+    pub fn load_subscribers(id: &str) -> Result<HashMap<String, String>, String> {
+        let path = get_registry_path(id);
+        if !path.exists() {
+            return Ok(HashMap::new());
+        }
+        let data = match std::fs::read_to_string(path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("Unable to Read Subscriber Registry: {}", e)),
+        };
+        match serde_json::from_str(&data) {
+            Ok(r) => return Ok(r),
+            Err(e) => return Err(format!("Unable to Parse Subscriber Registry: {}", e)),
+        }
+    }
+
+    pub fn save_subscribers(id: &str, subscribers: &HashMap<String, String>) -> Result<(), String> {
+        let data = match serde_json::to_string(subscribers) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("Unable to Serialize Subscriber Registry: {}", e)),
+        };
+        match replace_state(&get_registry_path(id), data.as_bytes()) {
+            Ok(_) => return Ok(()),
+            Err(e) => return Err(format!("Unable to Write Subscriber Registry: {}", e)),
+        }
+    }
+
+    /// Write state to temporary file and rename it, state on disk is never partially written.
+    /// Previous state is kept as generation <path>.1, older generations are shifted
+    pub fn write_state(path: &Path, state: &[u8]) -> Result<(), String> {
+        rotate_generations(path, get_generation_interval())?;
+        replace_state(path, state)
+    }
+
+    /// Write state to temporary file, sync it and rename it into place
+    pub fn replace_state(path: &Path, state: &[u8]) -> Result<(), String> {
+        let tmp_path = Path::new(&format!("{}.tmp", path.display())).to_path_buf();
+        let mut file = match std::fs::File::create(&tmp_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("Unable to Create State File: {}", e)),
+        };
+        if let Err(e) = file.write_all(state).and_then(|_| file.sync_all()) {
+            return Err(format!("Unable to Write State File: {}", e));
+        }
+        if let Err(e) = std::fs::rename(&tmp_path, path) {
+            return Err(format!("Unable to Replace State File: {}", e));
+        }
+        // Sync directory so rename survives power loss
+        if let Some(dir) = path.parent() {
+            if let Ok(dir) = std::fs::File::open(dir) {
+                let _ = dir.sync_all();
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of previous state generations kept
+    pub fn get_state_generations() -> usize {
+        env::var(ENV_STATE_GENERATIONS)
+            .ok()
+            .and_then(|r| r.parse().ok())
+            .unwrap_or(DEFAULT_STATE_GENERATIONS)
+    }
+
+    /// Minimum age of newest generation before a new generation is kept
+    pub fn get_generation_interval() -> Duration {
+        Duration::from_secs(
+            env::var(ENV_STATE_GENERATION_INTERVAL)
+                .ok()
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(DEFAULT_STATE_GENERATION_INTERVAL),
+        )
+    }
+
+    pub fn get_generation_path(path: &Path, generation: usize) -> std::path::PathBuf {
+        Path::new(&format!("{}.{}", path.display(), generation)).to_path_buf()
+    }
+
+    /// Shift generations <path>.1 .. <path>.N-1 by one, oldest is dropped,
+    /// and copy current state to <path>.1. Current state stays in place.
+    /// Skipped while <path>.1 is younger than interval, so frequent writes cost no copy
+    pub fn rotate_generations(path: &Path, interval: Duration) -> Result<(), String> {
+        let generations = get_state_generations();
+        if generations == 0 || !path.exists() {
+            return Ok(());
+        }
+        let newest = get_generation_path(path, 1);
+        if let Ok(modified) = std::fs::metadata(&newest).and_then(|r| r.modified()) {
+            if matches!(modified.elapsed(), Ok(age) if age < interval) {
+                return Ok(());
+            }
+        }
+        for generation in (1..generations).rev() {
+            let from = get_generation_path(path, generation);
+            if from.exists() {
+                if let Err(e) = std::fs::rename(&from, get_generation_path(path, generation + 1)) {
+                    return Err(format!("Unable to Rotate State Generation: {}", e));
+                }
+            }
+        }
+        let state = match std::fs::read(path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("Unable to Read State File: {}", e)),
+        };
+        replace_state(&get_generation_path(path, 1), &state)
+    }
+
+    /// State files of id, current state first followed by existing generations, newest first
+    pub fn get_state_files(id: &str) -> Vec<std::path::PathBuf> {
+        let path = get_state_path(id);
+        let mut files = vec![path.clone()];
+        for generation in 1..=get_state_generations() {
+            let generation_path = get_generation_path(&path, generation);
+            if generation_path.exists() {
+                files.push(generation_path);
+            }
+        }
+        files
+    }
+
+    /// Replace current state of id with state of readable generation
+    pub fn restore_generation(id: &str, state: &[u8], generation: &Path) {
+        warn!(
+            "State of ID {} Unreadable, Restored from: {}",
+            id,
+            generation.display()
+        );
+        if let Err(e) = replace_state(&get_state_path(id), state) {
+            error!("Unable to Restore State of ID {}: {}", id, e);
+        }
+    }
+
+    /// Last links of instance, kind: author or subscriber,
+    /// seed_nonce: Nonce of derived author seed (hex)
+    #[derive(Debug, Default, Clone, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct ChannelLinks {
+        pub kind: String,
+        pub ann_link: String,
+        pub key_link: String,
+        pub msg_link: String,
+        pub seed_nonce: String,
+    }
+
+    pub fn get_links_path(id: &str) -> std::path::PathBuf {
+        let path = Path::new(".")
+            .join(EXPORT_STATE_PATH)
+            .join(format!("{}.links", id));
+        path
+    }
+
+    /// Load last links of instance, empty if no links saved
+    pub fn load_links(id: &str) -> Result<ChannelLinks, String> {
+        let path = get_links_path(id);
+        if !path.exists() {
+            return Ok(ChannelLinks::default());
+        }
+        let data = match std::fs::read_to_string(path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("Unable to Read Channel Links: {}", e)),
+        };
+        match serde_json::from_str(&data) {
+            Ok(r) => return Ok(r),
+            Err(e) => return Err(format!("Unable to Parse Channel Links: {}", e)),
+        }
+    }
+
+    pub fn save_links(id: &str, links: &ChannelLinks) -> Result<(), String> {
+        let data = match serde_json::to_string(links) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("Unable to Serialize Channel Links: {}", e)),
+        };
+        match replace_state(&get_links_path(id), data.as_bytes()) {
+            Ok(_) => return Ok(()),
+            Err(e) => return Err(format!("Unable to Write Channel Links: {}", e)),
+        }
+    }
+
+    /// Update entry of links, query: kind, ann_link, key_link, msg_link or seed_nonce
+    pub fn update_links(id: &str, query: &str, value: &str) -> Result<(), String> {
+        let mut links = load_links(id)?;
+        match query {
+            "kind" => links.kind = value.to_string(),
+            "ann_link" => links.ann_link = value.to_string(),
+            "key_link" => links.key_link = value.to_string(),
+            "msg_link" => links.msg_link = value.to_string(),
+            "seed_nonce" => links.seed_nonce = value.to_string(),
+            e => return Err(format!("Unknown Link: {}", e)),
+        };
+        save_links(id, &links)
+    }
+
+    pub fn get_delivered_path(id: &str) -> std::path::PathBuf {
+        let path = Path::new(".")
+            .join(EXPORT_STATE_PATH)
+            .join(format!("{}.seq", id));
+        path
+    }
+
+    /// Load next sequence number per publisher delivered by Subscriber, empty if none saved
+    pub fn load_delivered(id: &str) -> Result<HashMap<String, u64>, String> {
+        let path = get_delivered_path(id);
+        if !path.exists() {
+            return Ok(HashMap::new());
+        }
+        let data = match std::fs::read_to_string(path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("Unable to Read Delivered Messages: {}", e)),
+        };
+        match serde_json::from_str(&data) {
+            Ok(r) => return Ok(r),
+            Err(e) => return Err(format!("Unable to Parse Delivered Messages: {}", e)),
+        }
+    }
+
+    pub fn save_delivered(id: &str, delivered: &HashMap<String, u64>) -> Result<(), String> {
+        let data = match serde_json::to_string(delivered) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("Unable to Serialize Delivered Messages: {}", e)),
+        };
+        replace_state(&get_delivered_path(id), data.as_bytes())
+    }
+
+    pub fn get_branch_registry_path(id: &str) -> std::path::PathBuf {
+        let path = Path::new(".")
+            .join(EXPORT_STATE_PATH)
+            .join(format!("{}.branches", id));
+        path
+    }
+
+    /// Load registry of branches, empty if no branches are used
+    pub fn load_branches(id: &str) -> Result<BranchRegistry, String> {
+        let path = get_branch_registry_path(id);
+        if !path.exists() {
+            return Ok(BranchRegistry::default());
+        }
+        let data = match std::fs::read_to_string(path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("Unable to Read Branch Registry: {}", e)),
+        };
+        match serde_json::from_str(&data) {
+            Ok(r) => return Ok(r),
+            Err(e) => return Err(format!("Unable to Parse Branch Registry: {}", e)),
+        }
+    }
+
+    pub fn save_branches(id: &str, registry: &BranchRegistry) -> Result<(), String> {
+        let data = match serde_json::to_string(registry) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("Unable to Serialize Branch Registry: {}", e)),
+        };
+        match replace_state(&get_branch_registry_path(id), data.as_bytes()) {
+            Ok(_) => return Ok(()),
+            Err(e) => return Err(format!("Unable to Write Branch Registry: {}", e)),
+        }
+    }
+
+    pub fn parse_public_key(public_key: &str) -> Result<Vec<u8>, String> {
+        match hex::decode(public_key) {
+            Ok(r) => return Ok(r),
+            Err(e) => return Err(format!("Unable To Parse Public Key From String: {}", e)),
+        }
+    }
+
+    pub fn get_state_password() -> String {
+        let password = env::var(ENV_STATE_PWD).unwrap_or_else(|_| DEFAULT_STATE_PWD.to_string());
+        password
+    }
+
+    pub fn parse_address(link: &str) -> Result<Address, String> {
+        match link.parse::<TangleAddress>() {
+            Ok(address) => return Ok(address),
+            Err(e) => return Err(format!("Unable To Parse Address From String: {}", e)),
+        }
+    }
+
+    /// Offline Transport, all Instances share the same Bucket
+    #[cfg(feature = "offline")]
+    pub async fn make_client() -> Result<StreamsTransport, String> {
+        info!("Offline Bucket Transport Used");
+        Ok(BUCKET.with(|r| r.clone()))
+    }
+
+    #[cfg(not(feature = "offline"))]
+    pub async fn make_client() -> Result<StreamsTransport, String> {
+        let node_url = env::var(ENV_NODE_URL).unwrap_or_else(|_| DEFAULT_NODE_URL.to_string());
+        let local_pow = match env::var(ENV_LOCAL_POW)
+            .unwrap_or_else(|_| "false".to_string())
+            .as_str()
+        {
+            "true" => true,
+            "false" => false,
+            _ => false,
+        };
+        let client_opt = make_client_opt(&node_url, local_pow);
+        let iota_cli = match ClientBuilder::new()
+            .with_node(&node_url)
+            .unwrap()
+            .with_local_pow(local_pow)
+            .finish()
+            .await
+        {
+            Ok(r) => {
+                info!("Streams Client Created");
+                r
+            }
+            Err(e) => return Err(format!("Unable to Create Streams Client: {}", e)),
+        };
+        let client = Client::new(client_opt, iota_cli);
+        Ok(client)
+    }
+
+    pub fn make_client_opt(url: &str, local_pow: bool) -> SendOptions {
+        SendOptions {
+            url: url.to_string(),
+            local_pow: local_pow,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // cargo test -- --nocapture //for std out
+    use super::*;
+    use std::collections::HashMap;
+    #[test]
+    fn test_generate_seed() {
+        let samples = 100;
+        let mut seeds: Vec<String> = vec![String::new(); samples];
+        let mut len = 0;
+        for _ in 1..=samples {
+            let seed = util::generate_seed();
+            println!("{}: {}", len, &seed);
+            len += 1;
+            seeds.push(seed);
+        }
+        seeds.sort();
+        seeds.dedup();
+        let mut len = 0;
+        for seed in &seeds {
+            if !seed.is_empty() {
+                println!("{}: {}", len, seed);
+                len += 1;
+            }
+        }
+        assert_eq!(len, samples);
+    }
+    #[test]
+    fn test_derive_seed() {
+        let nonce = util::generate_nonce();
+        let seed = util::derive_seed("recovery key", "author", &nonce);
+        assert_eq!(seed.len(), 81);
+        assert!(seed.chars().all(|r| util::ALPH9.contains(r)));
+        assert_eq!(seed, util::derive_seed("recovery key", "author", &nonce));
+        assert_ne!(seed, util::derive_seed("recovery key", "author2", &nonce));
+        assert_ne!(seed, util::derive_seed("other key", "author", &nonce));
+        // Re-created author with same id gets a different seed
+        assert_ne!(nonce, util::generate_nonce());
+        assert_ne!(
+            seed,
+            util::derive_seed("recovery key", "author", &util::generate_nonce())
+        );
+        assert_ne!(seed, util::derive_seed("recovery key", "author", ""));
+    }
+    #[test]
+    fn test_state_generations() {
+        let id = "test_state_generations";
+        std::fs::create_dir_all(util::get_state_path("")).unwrap();
+        let path = util::get_state_path(id);
+        let write = |state: u8, interval: u64| {
+            util::rotate_generations(&path, std::time::Duration::from_secs(interval)).unwrap();
+            util::replace_state(&path, &[state]).unwrap();
+        };
+        for i in 0..5u8 {
+            write(i, 0);
+        }
+        // Newest generation younger than interval, no new generation
+        write(5, 3600);
+        let files = util::get_state_files(id);
+        let states: Vec<Vec<u8>> = files.iter().map(|r| std::fs::read(r).unwrap()).collect();
+        for file in &files {
+            std::fs::remove_file(file).unwrap();
+        }
+        assert_eq!(files.len(), 1 + util::get_state_generations().min(4));
+        assert_eq!(states[0], vec![5]);
+        assert_eq!(states[1], vec![3]);
+        assert_eq!(states.last().unwrap(), &vec![4 - (files.len() as u8 - 1)]);
+    }
+    #[test]
+    fn test_find_gaps() {
+        let map = |entries: &[(&str, u64)]| -> HashMap<String, u64> {
+            entries.iter().map(|(k, v)| (k.to_string(), *v)).collect()
+        };
+        // State restored from older backup, all messages received again
+        let gaps = streams_subscriber::find_gaps(
+            &map(&[("a", 10)]),
+            &map(&[("a", 4)]),
+            &map(&[("a", 12)]),
+            &map(&[("a", 12)]),
+        );
+        assert!(gaps.is_empty());
+        // State ahead of delivered messages and cursor past unread messages
+        let gaps = streams_subscriber::find_gaps(
+            &map(&[("a", 3), ("b", 2)]),
+            &map(&[("a", 7), ("b", 2)]),
+            &map(&[("a", 9), ("b", 3)]),
+            &map(&[("a", 11), ("b", 3), ("c", 2)]),
+        );
+        let found: Vec<(String, u64, u64)> = gaps
+            .into_iter()
+            .map(|r| (r.publisher, r.from_seq, r.to_seq))
+            .collect();
+        assert_eq!(
+            found,
+            vec![("a".to_string(), 3, 7), ("a".to_string(), 9, 11)]
+        );
+    }
+    #[test]
+    fn test_parse_public_key() {
+        let pk = util::parse_public_key(&"ab".repeat(32)).unwrap();
+        assert_eq!(pk, vec![0xab; 32]);
+        assert!(util::parse_public_key("xyz").is_err());
+    }
+    // cargo test --features offline
+    #[cfg(feature = "offline")]
+    #[tokio::test]
+    async fn test_offline_flow() {
+        let author_id = "test_offline_author";
+        let subscriber_id = "test_offline_subscriber";
+        std::fs::create_dir_all(util::get_state_path("")).unwrap();
+        let (ann_link, _) = streams_author::create_new_author(author_id, false)
+            .await
+            .unwrap();
+        let (sub_link, public_key) =
+            streams_subscriber::create_new_subscriber(subscriber_id, &ann_link)
+                .await
+                .unwrap();
+        assert_eq!(public_key.len(), 64);
+        streams_author::add_subscriber(author_id, &sub_link, "", "", &Vec::new())
+            .await
+            .unwrap();
+        let key_link = streams_author::announce_keyload(author_id, "")
+            .await
+            .unwrap();
+        streams_subscriber::receive_keyload(subscriber_id, &key_link)
+            .await
+            .unwrap();
+        let msg_link = streams_author::send_message(
+            author_id,
+            &key_link,
+            "",
+            "offline header",
+            "offline message",
+        )
+        .await
+        .unwrap();
+        let msgs = streams_subscriber::receive_messages(subscriber_id)
+            .await
+            .unwrap();
+        for file in util::get_state_files(author_id)
+            .iter()
+            .chain(util::get_state_files(subscriber_id).iter())
+        {
+            std::fs::remove_file(file).unwrap();
+        }
+        assert_eq!(msgs.len(), 1);
+        assert_eq!(msgs[0].link, msg_link);
+        assert_eq!(msgs[0].prev_link, key_link);
+        assert_eq!(msgs[0].publisher.len(), 64);
+        assert_eq!(msgs[0].public_payload, b"offline header".to_vec());
+        assert_eq!(msgs[0].masked_payload, b"offline message".to_vec());
+    }
+    #[cfg(feature = "offline")]
+    #[tokio::test]
+    async fn test_offline_recover_author() {
+        let id = "test_recover_author";
+        let key = "test recovery key";
+        std::fs::create_dir_all(util::get_state_path("")).unwrap();
+        let (ann_link, nonce) = streams_author::create_author(id, false, Some(key.to_string()))
+            .await
+            .unwrap();
+        // Storage of author lost
+        let mut files = util::get_state_files(id);
+        files.push(util::get_links_path(id));
+        for file in &files {
+            let _ = std::fs::remove_file(file);
+        }
+        let missing = streams_author::recover_with_key(id, &ann_link, false, "", key).await;
+        let wrong =
+            streams_author::recover_with_key(id, &ann_link, false, &util::generate_nonce(), key)
+                .await;
+        let recovered = streams_author::recover_with_key(id, &ann_link, false, &nonce, key).await;
+        let links = util::load_links(id).unwrap();
+        for file in &files {
+            let _ = std::fs::remove_file(file);
+        }
+        assert_eq!(nonce.len(), 32);
+        assert!(missing.is_err());
+        assert!(wrong.is_err());
+        assert_eq!(recovered.unwrap(), ann_link);
+        assert_eq!(links.ann_link, ann_link);
+    }
+    #[cfg(feature = "offline")]
+    #[tokio::test]
+    async fn test_offline_sync_gap() {
+        let author_id = "test_sync_author";
+        let subscriber_id = "test_sync_subscriber";
+        std::fs::create_dir_all(util::get_state_path("")).unwrap();
+        let (ann_link, _) = streams_author::create_new_author(author_id, false)
+            .await
+            .unwrap();
+        let (sub_link, _) = streams_subscriber::create_new_subscriber(subscriber_id, &ann_link)
+            .await
+            .unwrap();
+        streams_author::add_subscriber(author_id, &sub_link, "", "", &Vec::new())
+            .await
+            .unwrap();
+        let key_link = streams_author::announce_keyload(author_id, "")
+            .await
+            .unwrap();
+        streams_subscriber::receive_keyload(subscriber_id, &key_link)
+            .await
+            .unwrap();
+        let first_link = streams_author::send_message(author_id, &key_link, "", "h1", "m1")
+            .await
+            .unwrap();
+        let (msgs, gaps) = streams_subscriber::sync_subscriber(subscriber_id, 0)
+            .await
+            .unwrap();
+        assert_eq!(msgs.len(), 1);
+        assert!(gaps.is_empty());
+        // Delivered messages behind state, e.g. reply lost or state imported
+        let mut delivered = util::load_delivered(subscriber_id).unwrap();
+        let publisher = msgs[0].publisher.clone();
+        let next_seq = delivered[&publisher];
+        delivered.insert(publisher.clone(), next_seq - 1);
+        util::save_delivered(subscriber_id, &delivered).unwrap();
+        let second_link = streams_author::send_message(author_id, &first_link, "", "h2", "m2")
+            .await
+            .unwrap();
+        let (msgs, gaps) = streams_subscriber::sync_subscriber(subscriber_id, 0)
+            .await
+            .unwrap();
+        let mut files = util::get_state_files(author_id);
+        files.extend(util::get_state_files(subscriber_id));
+        files.push(util::get_links_path(author_id));
+        files.push(util::get_links_path(subscriber_id));
+        files.push(util::get_delivered_path(subscriber_id));
+        for file in files {
+            let _ = std::fs::remove_file(file);
+        }
+        assert_eq!(msgs.len(), 1);
+        assert_eq!(msgs[0].link, second_link);
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].publisher, publisher);
+        assert_eq!((gaps[0].from_seq, gaps[0].to_seq), (next_seq - 1, next_seq));
+    }
+}
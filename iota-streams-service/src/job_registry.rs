@@ -0,0 +1,181 @@
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
+
+use crate::grpc_service::ChannelReply;
+
+/// State of job, finished jobs are done or failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobState {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+impl JobState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobState::Queued => "queued",
+            JobState::Running => "running",
+            JobState::Done => "done",
+            JobState::Failed => "failed",
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        *self == JobState::Done || *self == JobState::Failed
+    }
+}
+
+/// Job of submitted request, link: Result of done job, status: Error of failed job,
+/// created/updated: Unix time in s
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub job_id: String,
+    pub id: String,
+    pub msg_type: u32,
+    pub state: JobState,
+    pub status: String,
+    pub code: i32,
+    pub link: String,
+    pub public_key: String,
+    pub seed_nonce: String,
+    pub created: u64,
+    pub updated: u64,
+}
+
+/// Registered job, receiver is kept so that updates never fail
+struct JobEntry {
+    tx: watch::Sender<Job>,
+    rx: watch::Receiver<Job>,
+    finished: Option<Instant>,
+}
+
+/// Jobs by job id, shared by GRPC server and state machine.
+/// Finished jobs are removed after retention_s
+#[derive(Clone)]
+pub struct JobRegistry {
+    jobs: Arc<Mutex<HashMap<String, JobEntry>>>,
+    retention_s: u64,
+}
+
+impl JobRegistry {
+    pub fn new(retention_s: u64) -> Self {
+        JobRegistry {
+            jobs: Arc::new(Mutex::new(HashMap::new())),
+            retention_s: retention_s,
+        }
+    }
+
+    /// Register queued job for request of id
+    pub fn submit(&self, id: &str, msg_type: u32) -> Job {
+        let now = get_unix_time();
+        let job = Job {
+            job_id: generate_job_id(),
+            id: id.to_string(),
+            msg_type: msg_type,
+            state: JobState::Queued,
+            status: "Queued".to_string(),
+            code: 0,
+            link: "".to_string(),
+            public_key: "".to_string(),
+            seed_nonce: "".to_string(),
+            created: now,
+            updated: now,
+        };
+        let (tx, rx) = watch::channel(job.clone());
+        let mut jobs = self.jobs.lock().unwrap();
+        let retention_s = self.retention_s;
+        jobs.retain(|_, entry| match entry.finished {
+            Some(r) => r.elapsed().as_secs() < retention_s,
+            None => true,
+        });
+        jobs.insert(
+            job.job_id.clone(),
+            JobEntry {
+                tx: tx,
+                rx: rx,
+                finished: None,
+            },
+        );
+        info!("Job {} Submitted for ID: {}", &job.job_id, id);
+        job
+    }
+
+    /// Mark job as running, called by worker of id
+    pub fn set_running(&self, job_id: &str) {
+        self.update(job_id, |job| {
+            job.state = JobState::Running;
+            job.status = "Running".to_string();
+        });
+    }
+
+    /// Finish job with reply of state machine, failed if code is not 0
+    pub fn finish(&self, job_id: &str, reply: ChannelReply) {
+        self.update(job_id, |job| {
+            job.state = if reply.code == 0 {
+                JobState::Done
+            } else {
+                JobState::Failed
+            };
+            job.status = reply.status;
+            job.code = reply.code;
+            job.link = reply.link;
+            job.public_key = reply.public_key;
+            job.seed_nonce = reply.seed_nonce;
+        });
+    }
+
+    /// Finish job as failed, e.g. if request could not be handed to state machine
+    pub fn fail(&self, job_id: &str, status: &str) {
+        self.update(job_id, |job| {
+            job.state = JobState::Failed;
+            job.status = status.to_string();
+            job.code = -1;
+        });
+    }
+
+    pub fn get(&self, job_id: &str) -> Option<Job> {
+        let jobs = self.jobs.lock().unwrap();
+        jobs.get(job_id).map(|entry| entry.rx.borrow().clone())
+    }
+
+    /// Receiver notified on every update of job
+    pub fn watch(&self, job_id: &str) -> Option<watch::Receiver<Job>> {
+        let jobs = self.jobs.lock().unwrap();
+        jobs.get(job_id).map(|entry| entry.rx.clone())
+    }
+
+    fn update<F: FnOnce(&mut Job)>(&self, job_id: &str, f: F) {
+        let mut jobs = self.jobs.lock().unwrap();
+        let entry = match jobs.get_mut(job_id) {
+            Some(r) => r,
+            None => {
+                error!("Job Not Found: {}", job_id);
+                return;
+            }
+        };
+        let mut job = entry.rx.borrow().clone();
+        f(&mut job);
+        job.updated = get_unix_time();
+        if job.state.is_finished() {
+            info!("Job {} Finished: {}", job_id, job.state.as_str());
+            entry.finished = Some(Instant::now());
+        }
+        let _ = entry.tx.send(job);
+    }
+}
+
+fn generate_job_id() -> String {
+    hex::encode(rand::thread_rng().gen::<[u8; 16]>())
+}
+
+fn get_unix_time() -> u64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(r) => r.as_secs(),
+        Err(_) => 0,
+    }
+}
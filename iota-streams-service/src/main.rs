@@ -1,39 +1,72 @@
-#[macro_use]
-extern crate log;
-
-use tokio::join;
-use tokio::sync::mpsc;
-use tonic::transport::Server;
-
-mod grpc_service;
-use grpc_service::grpc_streams::iota_streamer_server::IotaStreamerServer;
-use grpc_service::{IotaStreamsService, QueueElem};
-
-mod streams_service;
-use streams_service::streams_state_machine;
-
-mod iota_streams_module;
-pub use crate::iota_streams_module::streams_author;
-
-mod config;
-pub use crate::config::load_config_file;
-
-mod msg_util;
-/// Tokio runtime and start-up code for server implementation
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    env_logger::init();
-    let cfg = load_config_file();
-    let addr = cfg.socket.parse()?;
-    info!("Start IOTA Streams Service");
-    // Communication channel used btw streams_state_machine and GRPC server
-    let (tx, rx) = mpsc::channel::<QueueElem>(32);
-    let service = IotaStreamsService::new(tx.clone());
-    // Start threads
-    let streams_worker = streams_state_machine(rx);
-    let grpc_server = Server::builder()
-        .add_service(IotaStreamerServer::new(service))
-        .serve(addr);
-    let _result = join!(streams_worker, grpc_server);
-    Ok(())
-}
+#[macro_use]
+extern crate log;
+
+use tokio::join;
+use tokio::signal;
+use tokio::sync::mpsc;
+use tonic::transport::Server;
+
+mod grpc_service;
+use grpc_service::grpc_streams::iota_streamer_server::IotaStreamerServer;
+use grpc_service::{IotaStreamsService, QueueElem};
+
+mod streams_service;
+use streams_service::{
+    get_thread_senders, start_worker_threads, stop_worker_threads, streams_state_machine,
+};
+
+mod iota_streams_module;
+pub use crate::iota_streams_module::streams_author;
+
+mod config;
+pub use crate::config::load_config_file;
+
+mod msg_util;
+
+mod instance_cache;
+mod state_bundle;
+
+mod job_registry;
+use job_registry::JobRegistry;
+/// Tokio runtime and start-up code for server implementation
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
+    let cfg = load_config_file();
+    let addr = cfg.socket.parse()?;
+    info!("Start IOTA Streams Service");
+    // Communication channel used btw streams_state_machine and GRPC server
+    let (tx, rx) = mpsc::channel::<QueueElem>(32);
+    // Jobs of slow calls, updated by GRPC server and state machine
+    let jobs = JobRegistry::new(cfg.jobs.retention_s);
+    let service = IotaStreamsService::new(tx.clone(), jobs.clone());
+    // Per id workers of state machine run on worker threads (instances are not Send)
+    let workers = start_worker_threads(cfg.workers.threads, jobs)?;
+    // Start threads
+    let streams_worker = streams_state_machine(rx, get_thread_senders(&workers));
+    let grpc_server = Server::builder()
+        .add_service(IotaStreamerServer::new(service))
+        .serve(addr);
+    tokio::select! {
+        _ = async { join!(streams_worker, grpc_server) } => (),
+        _ = shutdown_signal() => info!("Shutdown IOTA Streams Service"),
+    };
+    // Changed states are written to disk by the worker threads before exiting
+    stop_worker_threads(workers).await;
+    Ok(())
+}
+/// Completes on Ctrl-C or SIGTERM (docker stop)
+async fn shutdown_signal() {
+    let mut sigterm = match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+        Ok(r) => r,
+        Err(e) => {
+            error!("Unable to Listen for SIGTERM: {}", e);
+            let _ = signal::ctrl_c().await;
+            return;
+        }
+    };
+    tokio::select! {
+        _ = signal::ctrl_c() => (),
+        _ = sigterm.recv() => (),
+    };
+}
@@ -1,45 +1,63 @@
-/// Enum MsgType maps u32 to message types defined by rpc calls
-#[derive(Debug)]
-#[repr(u32)]
-#[allow(dead_code)]
-pub enum MsgType {
-    Error = 0,
-    CreateNewAuthor,
-    CreateNewSubscriber,
-    AddSubscriber,
-    ReceiveKeyload,
-    SendMessage,
-    ReceiveMessages,
-    CreateKeyload,
-    Unknown,
-}
-/// Convert u32 to MsgType
-#[allow(dead_code)]
-pub fn convert_to_msgtype(num: u32) -> MsgType {
-    match num {
-        0 => MsgType::Error,
-        1 => MsgType::CreateNewAuthor,
-        2 => MsgType::CreateNewSubscriber,
-        3 => MsgType::AddSubscriber,
-        4 => MsgType::ReceiveKeyload,
-        5 => MsgType::SendMessage,
-        6 => MsgType::ReceiveMessages,
-        7 => MsgType::CreateKeyload,
-        _ => MsgType::Unknown,
-    }
-}
-/// Convert MsgType to u32
-#[allow(dead_code)]
-pub fn convert_from_msgtype(msg_type: MsgType) -> u32 {
-    match msg_type {
-        MsgType::Error => 0,
-        MsgType::CreateNewAuthor => 1,
-        MsgType::CreateNewSubscriber => 2,
-        MsgType::AddSubscriber => 3,
-        MsgType::ReceiveKeyload => 4,
-        MsgType::SendMessage => 5,
-        MsgType::ReceiveMessages => 6,
-        MsgType::CreateKeyload => 7,
-        MsgType::Unknown => 8,
-    }
-}
+/// Enum MsgType maps u32 to message types defined by rpc calls
+#[derive(Debug)]
+#[repr(u32)]
+#[allow(dead_code)]
+pub enum MsgType {
+    Error = 0,
+    CreateNewAuthor,
+    CreateNewSubscriber,
+    AddSubscriber,
+    ReceiveKeyload,
+    SendMessage,
+    ReceiveMessages,
+    CreateKeyload,
+    RevokeSubscriber,
+    QueueDepth,
+    ExportChannelState,
+    ImportChannelState,
+    SyncSubscriber,
+    RecoverAuthor,
+    Unknown,
+}
+/// Convert u32 to MsgType
+#[allow(dead_code)]
+pub fn convert_to_msgtype(num: u32) -> MsgType {
+    match num {
+        0 => MsgType::Error,
+        1 => MsgType::CreateNewAuthor,
+        2 => MsgType::CreateNewSubscriber,
+        3 => MsgType::AddSubscriber,
+        4 => MsgType::ReceiveKeyload,
+        5 => MsgType::SendMessage,
+        6 => MsgType::ReceiveMessages,
+        7 => MsgType::CreateKeyload,
+        8 => MsgType::RevokeSubscriber,
+        9 => MsgType::QueueDepth,
+        10 => MsgType::ExportChannelState,
+        11 => MsgType::ImportChannelState,
+        12 => MsgType::SyncSubscriber,
+        13 => MsgType::RecoverAuthor,
+        _ => MsgType::Unknown,
+    }
+}
+/// Convert MsgType to u32
+#[allow(dead_code)]
+pub fn convert_from_msgtype(msg_type: MsgType) -> u32 {
+    match msg_type {
+        MsgType::Error => 0,
+        MsgType::CreateNewAuthor => 1,
+        MsgType::CreateNewSubscriber => 2,
+        MsgType::AddSubscriber => 3,
+        MsgType::ReceiveKeyload => 4,
+        MsgType::SendMessage => 5,
+        MsgType::ReceiveMessages => 6,
+        MsgType::CreateKeyload => 7,
+        MsgType::RevokeSubscriber => 8,
+        MsgType::QueueDepth => 9,
+        MsgType::ExportChannelState => 10,
+        MsgType::ImportChannelState => 11,
+        MsgType::SyncSubscriber => 12,
+        MsgType::RecoverAuthor => 13,
+        MsgType::Unknown => 14,
+    }
+}
@@ -0,0 +1,262 @@
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hmac::Hmac;
+use rand::Rng;
+use serde_derive::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+
+use crate::iota_streams_module::util::{
+    get_state_path, load_branches, load_links, load_subscribers, save_branches, save_links,
+    save_subscribers, BranchRegistry, ChannelLinks,
+};
+use crate::iota_streams_module::{streams_author, streams_subscriber};
+
+/// Version of bundle format, versions 1 (unkeyed checksum) and 2 (cleartext content)
+/// are not accepted
+pub const BUNDLE_VERSION: u32 = 3;
+/// PBKDF2-HMAC-SHA-256 rounds deriving the bundle key from the bundle password
+pub const BUNDLE_KDF_ROUNDS: u32 = 200_000;
+
+/// Channel state bundle, content encrypted with ChaCha20-Poly1305 (base64), key derived from
+/// bundle password with PBKDF2 and per-bundle salt (hex), header is authenticated with content
+#[derive(Debug, Serialize, Deserialize)]
+struct StateBundle {
+    version: u32,
+    kdf_rounds: u32,
+    salt: String,
+    nonce: String,
+    content: String,
+}
+
+/// Content of bundle, state: Instance state encrypted with bundle password (base64)
+#[derive(Debug, Serialize, Deserialize)]
+struct BundleContent {
+    id: String,
+    state: String,
+    links: ChannelLinks,
+    subscribers: HashMap<String, String>,
+    branches: BranchRegistry,
+}
+
+/// Export state of id as bundle (JSON), state re-encrypted with bundle password
+pub async fn export_bundle(id: &str, password: &str) -> Result<String, String> {
+    if password.is_empty() {
+        return Err("Bundle Password Missing".to_string());
+    }
+    let mut links = load_links(id)?;
+    let state = match links.kind.as_str() {
+        "author" => streams_author::export_with_password(id, password).await?,
+        "subscriber" => streams_subscriber::export_with_password(id, password).await?,
+        // States created without links, kind unknown
+        _ => match streams_author::export_with_password(id, password).await {
+            Ok(r) => {
+                links.kind = "author".to_string();
+                r
+            }
+            Err(_) => {
+                links.kind = "subscriber".to_string();
+                streams_subscriber::export_with_password(id, password).await?
+            }
+        },
+    };
+    let content = BundleContent {
+        id: id.to_string(),
+        state: base64::encode(state),
+        links: links,
+        subscribers: load_subscribers(id)?,
+        branches: load_branches(id)?,
+    };
+    let content = match serde_json::to_string(&content) {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to Serialize Bundle Content: {}", e)),
+    };
+    let bundle = seal_bundle(content, password)?;
+    info!("State Bundle Exported for ID: {}", id);
+    Ok(bundle)
+}
+
+/// Verify and import bundle as id, state saved with state password.
+/// Existing states are not replaced. Returns announcement link
+pub async fn import_bundle(id: &str, bundle: &str, password: &str) -> Result<String, String> {
+    let content = open_bundle(bundle, password)?;
+    let content: BundleContent = match serde_json::from_str(&content) {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to Parse Bundle Content: {}", e)),
+    };
+    let state = match base64::decode(&content.state) {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to Decode Bundle State: {}", e)),
+    };
+    if get_state_path(id).exists() {
+        return Err(format!("State Already Exists for ID: {}", id));
+    }
+    match content.links.kind.as_str() {
+        "author" => streams_author::import_with_password(id, &state, password).await?,
+        "subscriber" => streams_subscriber::import_with_password(id, &state, password).await?,
+        e => return Err(format!("Unknown Instance Kind in Bundle: {}", e)),
+    };
+    save_links(id, &content.links)?;
+    if !content.subscribers.is_empty() {
+        save_subscribers(id, &content.subscribers)?;
+    }
+    if !content.branches.access.is_empty() || !content.branches.keyloads.is_empty() {
+        save_branches(id, &content.branches)?;
+    }
+    info!("State Bundle of ID {} Imported as ID: {}", content.id, id);
+    Ok(content.links.ann_link)
+}
+
+/// Bundle (JSON) of content encrypted with key derived from password
+fn seal_bundle(content: String, password: &str) -> Result<String, String> {
+    let salt = rand::thread_rng().gen::<[u8; 16]>();
+    let nonce = rand::thread_rng().gen::<[u8; 12]>();
+    let mut bundle = StateBundle {
+        version: BUNDLE_VERSION,
+        kdf_rounds: BUNDLE_KDF_ROUNDS,
+        salt: hex::encode(salt),
+        nonce: hex::encode(nonce),
+        content: "".to_string(),
+    };
+    let cipher = make_cipher(password, &salt, bundle.kdf_rounds)?;
+    let header = make_header(&bundle);
+    let payload = Payload {
+        msg: content.as_bytes(),
+        aad: header.as_bytes(),
+    };
+    bundle.content = match cipher.encrypt(Nonce::from_slice(&nonce), payload) {
+        Ok(r) => base64::encode(r),
+        Err(e) => return Err(format!("Unable to Encrypt Bundle Content: {}", e)),
+    };
+    match serde_json::to_string(&bundle) {
+        Ok(r) => Ok(r),
+        Err(e) => Err(format!("Unable to Serialize Bundle: {}", e)),
+    }
+}
+
+/// Content of bundle (JSON) if version is supported and content decrypts with password
+fn open_bundle(bundle: &str, password: &str) -> Result<String, String> {
+    let bundle: StateBundle = match serde_json::from_str(bundle) {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to Parse Bundle: {}", e)),
+    };
+    if bundle.version != BUNDLE_VERSION {
+        return Err(format!("Unsupported Bundle Version: {}", bundle.version));
+    }
+    let (salt, nonce, content) = match (
+        hex::decode(&bundle.salt),
+        hex::decode(&bundle.nonce),
+        base64::decode(&bundle.content),
+    ) {
+        (Ok(salt), Ok(nonce), Ok(content)) if nonce.len() == 12 => (salt, nonce, content),
+        _ => return Err("Unable to Decode Bundle".to_string()),
+    };
+    let cipher = make_cipher(password, &salt, bundle.kdf_rounds)?;
+    let header = make_header(&bundle);
+    let payload = Payload {
+        msg: &content,
+        aad: header.as_bytes(),
+    };
+    let content = match cipher.decrypt(Nonce::from_slice(&nonce), payload) {
+        Ok(r) => r,
+        Err(_) => {
+            return Err("Unable to Decrypt Bundle (Content Changed or Wrong Password)".to_string())
+        }
+    };
+    match String::from_utf8(content) {
+        Ok(r) => Ok(r),
+        Err(e) => Err(format!("Unable to Decode Bundle Content: {}", e)),
+    }
+}
+
+/// Header fields authenticated together with content
+fn make_header(bundle: &StateBundle) -> String {
+    format!(
+        "{}:{}:{}:{}",
+        bundle.version, bundle.kdf_rounds, bundle.salt, bundle.nonce
+    )
+}
+
+/// Cipher keyed with PBKDF2-HMAC-SHA-256 of password and salt
+fn make_cipher(password: &str, salt: &[u8], rounds: u32) -> Result<ChaCha20Poly1305, String> {
+    if password.is_empty() {
+        return Err("Bundle Password Missing".to_string());
+    }
+    if rounds == 0 || salt.is_empty() {
+        return Err("Bundle Key Derivation Parameters Missing".to_string());
+    }
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(password.as_bytes(), salt, rounds, &mut key);
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bundle with first character of content or salt changed
+    fn tamper(bundle: &str, field: &str) -> String {
+        let mut bundle: StateBundle = serde_json::from_str(bundle).unwrap();
+        let value = match field {
+            "salt" => &mut bundle.salt,
+            _ => &mut bundle.content,
+        };
+        let first = if value.starts_with('a') { "b" } else { "a" };
+        value.replace_range(0..1, first);
+        serde_json::to_string(&bundle).unwrap()
+    }
+
+    #[test]
+    fn test_seal_open_bundle() {
+        let bundle = seal_bundle("{\"id\":\"secret\"}".to_string(), "pwd").unwrap();
+        assert!(!bundle.contains("secret"));
+        assert_eq!(open_bundle(&bundle, "pwd").unwrap(), "{\"id\":\"secret\"}");
+        assert!(open_bundle(&bundle, "other").is_err());
+        assert!(open_bundle(&bundle, "").is_err());
+        assert!(seal_bundle("".to_string(), "").is_err());
+        // Salt is random per bundle
+        let other = seal_bundle("{\"id\":\"secret\"}".to_string(), "pwd").unwrap();
+        let salt = |r: &str| serde_json::from_str::<StateBundle>(r).unwrap().salt;
+        assert_ne!(salt(&bundle), salt(&other));
+    }
+
+    #[test]
+    fn test_reject_tampered_bundle() {
+        let bundle = seal_bundle("{\"id\":\"a\"}".to_string(), "pwd").unwrap();
+        assert!(open_bundle(&tamper(&bundle, "content"), "pwd").is_err());
+        assert!(open_bundle(&tamper(&bundle, "salt"), "pwd").is_err());
+        // Older formats
+        let old = bundle.replace("\"version\":3", "\"version\":2");
+        assert_ne!(old, bundle);
+        assert!(open_bundle(&old, "pwd").is_err());
+    }
+
+    // cargo test --features offline
+    #[cfg(feature = "offline")]
+    #[tokio::test]
+    async fn test_bundle_round_trip() {
+        use crate::iota_streams_module::util::{get_links_path, get_state_files};
+        let id = "test_bundle_author";
+        let imported_id = "test_bundle_imported";
+        std::fs::create_dir_all(get_state_path("")).unwrap();
+        let (ann_link, _) = streams_author::create_new_author(id, false).await.unwrap();
+        let bundle = export_bundle(id, "bundle-pwd").await.unwrap();
+        let tampered = tamper(&bundle, "content");
+        let tampered_result = import_bundle(imported_id, &tampered, "bundle-pwd").await;
+        let wrong_pwd_result = import_bundle(imported_id, &bundle, "other").await;
+        let result = import_bundle(imported_id, &bundle, "bundle-pwd").await;
+        let existing_result = import_bundle(id, &bundle, "bundle-pwd").await;
+        for file in get_state_files(id)
+            .iter()
+            .chain(get_state_files(imported_id).iter())
+            .chain([get_links_path(id), get_links_path(imported_id)].iter())
+        {
+            let _ = std::fs::remove_file(file);
+        }
+        assert!(!bundle.contains(&ann_link));
+        assert!(tampered_result.is_err());
+        assert!(wrong_pwd_result.is_err());
+        assert_eq!(result.unwrap(), ann_link);
+        assert!(existing_result.is_err());
+    }
+}
@@ -1,167 +1,499 @@
-use tokio::sync::mpsc;
-use tokio::time::{sleep, Duration};
-
-use crate::grpc_service::{ChannelReply, QueueElem};
-use crate::iota_streams_module::{streams_author, streams_subscriber};
-use crate::msg_util::MsgType;
-/// Implementation of streams state machine. Request are send over stable MPSC Channel,
-/// reply to GRPC Server over Oneshot Channel per call.
-pub async fn streams_state_machine(mut rx: mpsc::Receiver<QueueElem>) {
-    loop {
-        let request = match rx.recv().await {
-            Some(msg) => match msg {
-                QueueElem::Request(req) => req,
-                _ => panic!("Received Wrong Data Type"),
-            },
-            None => panic!("Received No Data"),
-        };
-        match request.msg_type {
-            MsgType::CreateNewAuthor => {
-                let rep = match streams_author::create_new_author(&request.id).await {
-                    Ok(ann_link) => {
-                        make_channel_reply(&request.id, request.msg_type, &ann_link, "Ok", 0)
-                    }
-                    Err(e) => {
-                        error!("{}", e);
-                        make_channel_reply(&request.id, request.msg_type, "", &e, -1)
-                    }
-                };
-                let _ = request.tx.send(QueueElem::Reply(rep));
-            }
-
-            MsgType::CreateNewSubscriber => {
-                let rep =
-                    match streams_subscriber::create_new_subscriber(&request.id, &request.link)
-                        .await
-                    {
-                        Ok(sub_link) => {
-                            make_channel_reply(&request.id, request.msg_type, &sub_link, "Ok", 0)
-                        }
-                        Err(e) => {
-                            error!("{}", e);
-                            make_channel_reply(&request.id, request.msg_type, "", &e, -1)
-                        }
-                    };
-                let _ = request.tx.send(QueueElem::Reply(rep));
-            }
-
-            MsgType::AddSubscriber => {
-                let (status, code) =
-                    match streams_author::add_subscriber(&request.id, &request.link).await {
-                        Ok(r) => (r, 0),
-                        Err(e) => {
-                            error!("{}", e);
-                            (e, -1)
-                        }
-                    };
-                let _ = request.tx.send(QueueElem::Reply(make_channel_reply(
-                    &request.id,
-                    request.msg_type,
-                    "",
-                    &status,
-                    code,
-                )));
-            }
-
-            MsgType::ReceiveKeyload => {
-                let (status, code) =
-                    match streams_subscriber::receive_keyload(&request.id, &request.link).await {
-                        Ok(r) => (r, 0),
-                        Err(e) => (e, -1),
-                    };
-                let _ = request.tx.send(QueueElem::Reply(make_channel_reply(
-                    &request.id,
-                    request.msg_type,
-                    "",
-                    &status,
-                    code,
-                )));
-            }
-
-            MsgType::SendMessage => {
-                let (payload, is_payload) = match request.messages {
-                    Some(r) => (r[0].clone(), true),
-                    None => ("No Payload".to_string(), false),
-                };
-                let rep = if !is_payload {
-                    make_channel_reply(&request.id, request.msg_type, "", &payload, -1)
-                } else {
-                    match streams_author::send_message(&request.id, &request.link, &payload).await {
-                        Ok(msg_link) => {
-                            make_channel_reply(&request.id, request.msg_type, &msg_link, "Ok", 0)
-                        }
-                        Err(e) => {
-                            error!("{}", e);
-                            make_channel_reply(&request.id, request.msg_type, "", &e, -1)
-                        }
-                    }
-                };
-                let _ = request.tx.send(QueueElem::Reply(rep));
-            }
-
-            MsgType::ReceiveMessages => {
-                let rep = match streams_subscriber::receive_messages(&request.id).await {
-                    Ok(msgs) => {
-                        let status = if msgs.len() > 0 {
-                            "Ok".to_string()
-                        } else {
-                            "No messages".to_string()
-                        };
-                        ChannelReply {
-                            id: request.id,
-                            msg_type: request.msg_type,
-                            link: "".to_string(),
-                            status: status,
-                            code: 0,
-                            messages: Some(msgs),
-                        }
-                    }
-                    Err(e) => {
-                        error!("{}", e);
-                        ChannelReply {
-                            id: request.id,
-                            msg_type: request.msg_type,
-                            link: "".to_string(),
-                            status: e,
-                            code: -1,
-                            messages: None,
-                        }
-                    }
-                };
-                let _ = request.tx.send(QueueElem::Reply(rep));
-            }
-
-            MsgType::CreateKeyload => {
-                let rep = match streams_author::announce_keyload(&request.id).await {
-                    Ok(key_link) => {
-                        make_channel_reply(&request.id, request.msg_type, &key_link, "Ok", 0)
-                    }
-                    Err(e) => {
-                        error!("{}", e);
-                        make_channel_reply(&request.id, request.msg_type, "", &e, -1)
-                    }
-                };
-                let _ = request.tx.send(QueueElem::Reply(rep));
-            }
-            _ => error!("Error: Wrong Message Type"),
-        }
-        sleep(Duration::from_millis(2000)).await;
-    }
-}
-
-fn make_channel_reply(
-    id: &str,
-    msg_type: MsgType,
-    link: &str,
-    status: &str,
-    code: i32,
-) -> ChannelReply {
-    ChannelReply {
-        id: id.to_string(),
-        msg_type: msg_type,
-        link: link.to_string(),
-        status: status.to_string(),
-        code: code,
-        messages: None,
-    }
-}
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tokio::runtime;
+use tokio::sync::mpsc;
+use tokio::task::{self, LocalSet};
+
+use crate::grpc_service::{BundleInfo, ChannelReply, ChannelRequest, QueueElem, SubscriberInfo};
+use crate::instance_cache::{cache_worker, flush_instances};
+use crate::iota_streams_module::{streams_author, streams_subscriber};
+use crate::job_registry::JobRegistry;
+use crate::msg_util::MsgType;
+use crate::state_bundle::{export_bundle, import_bundle};
+
+/// Request with queue depth of its id, depth: Requests of id queued or in progress
+pub type QueuedRequest = (ChannelRequest, Arc<AtomicU32>);
+
+/// Worker thread with own runtime, runs the workers of the ids assigned to it
+pub struct WorkerThread {
+    tx: mpsc::UnboundedSender<QueuedRequest>,
+    handle: thread::JoinHandle<()>,
+}
+
+/// Worker of one id on a worker thread, depth of its latest request
+struct IdWorker {
+    tx: mpsc::UnboundedSender<QueuedRequest>,
+    depth: Arc<AtomicU32>,
+}
+
+/// Implementation of streams state machine. Request are send over stable MPSC Channel,
+/// reply to GRPC Server over Oneshot Channel per call.
+/// Requests are handed to the worker thread of their id, see start_worker_threads
+pub async fn streams_state_machine(
+    mut rx: mpsc::Receiver<QueueElem>,
+    threads: Vec<mpsc::UnboundedSender<QueuedRequest>>,
+) {
+    let mut depths: HashMap<String, Arc<AtomicU32>> = HashMap::new();
+    loop {
+        let request = match rx.recv().await {
+            Some(msg) => match msg {
+                QueueElem::Request(req) => req,
+                _ => panic!("Received Wrong Data Type"),
+            },
+            None => panic!("Received No Data"),
+        };
+        // Depths of idle ids are removed
+        depths.retain(|_, depth| depth.load(Ordering::SeqCst) > 0);
+        if let MsgType::QueueDepth = request.msg_type {
+            let _ = request
+                .tx
+                .send(QueueElem::Reply(make_queue_reply(request.id, &depths)));
+            continue;
+        }
+        let depth = depths
+            .entry(request.id.clone())
+            .or_insert_with(|| Arc::new(AtomicU32::new(0)))
+            .clone();
+        depth.fetch_add(1, Ordering::SeqCst);
+        let index = get_thread_index(&request.id, threads.len());
+        if let Err(e) = threads[index].send((request, depth.clone())) {
+            depth.fetch_sub(1, Ordering::SeqCst);
+            error!("Worker Thread Stopped: {}", e);
+        }
+    }
+}
+
+/// Start worker threads, each with a current thread runtime and LocalSet (instances are not Send).
+/// An id is always handled by the same thread, its cached instance stays there. Blocking calls
+/// (local PoW) only hold up the ids of the same thread, ids of other threads run concurrently
+pub fn start_worker_threads(
+    threads: usize,
+    jobs: JobRegistry,
+) -> Result<Vec<WorkerThread>, String> {
+    // Offline bucket is thread local, all ids have to share one thread
+    let threads = if cfg!(feature = "offline") {
+        1
+    } else {
+        threads
+    };
+    let mut workers = Vec::new();
+    for index in 0..threads.max(1) {
+        workers.push(spawn_worker_thread(index, jobs.clone())?);
+    }
+    info!("Started {} Worker Threads", workers.len());
+    Ok(workers)
+}
+
+/// Senders of worker threads passed to streams_state_machine
+pub fn get_thread_senders(workers: &[WorkerThread]) -> Vec<mpsc::UnboundedSender<QueuedRequest>> {
+    workers.iter().map(|r| r.tx.clone()).collect()
+}
+
+/// Stop worker threads, once streams_state_machine is dropped each thread writes
+/// changed states to disk before exiting
+pub async fn stop_worker_threads(workers: Vec<WorkerThread>) {
+    let handles: Vec<thread::JoinHandle<()>> = workers.into_iter().map(|r| r.handle).collect();
+    let _ = task::spawn_blocking(move || {
+        for handle in handles {
+            if handle.join().is_err() {
+                error!("Worker Thread Panicked");
+            }
+        }
+    })
+    .await;
+}
+
+fn spawn_worker_thread(index: usize, jobs: JobRegistry) -> Result<WorkerThread, String> {
+    let (tx, rx) = mpsc::unbounded_channel::<QueuedRequest>();
+    let handle = match thread::Builder::new()
+        .name(format!("streams-worker-{}", index))
+        .spawn(move || {
+            let rt = match runtime::Builder::new_current_thread().enable_all().build() {
+                Ok(r) => r,
+                Err(e) => {
+                    error!("Unable to Start Worker Runtime: {}", e);
+                    return;
+                }
+            };
+            LocalSet::new().block_on(&rt, run_worker_thread(rx, jobs));
+        }) {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to Start Worker Thread: {}", e)),
+    };
+    Ok(WorkerThread {
+        tx: tx,
+        handle: handle,
+    })
+}
+
+/// Hand requests to one worker task per id, requests of the same id are handled in order.
+/// Changed states of cached instances are written to disk once no more requests arrive
+async fn run_worker_thread(mut rx: mpsc::UnboundedReceiver<QueuedRequest>, jobs: JobRegistry) {
+    task::spawn_local(cache_worker());
+    let mut workers: HashMap<String, IdWorker> = HashMap::new();
+    while let Some((request, depth)) = rx.recv().await {
+        // Idle workers are stopped
+        workers.retain(|_, worker| worker.depth.load(Ordering::SeqCst) > 0);
+        let worker = workers
+            .entry(request.id.clone())
+            .or_insert_with(|| spawn_worker(&request.id, jobs.clone()));
+        worker.depth = depth.clone();
+        if let Err(e) = worker.tx.send((request, depth.clone())) {
+            depth.fetch_sub(1, Ordering::SeqCst);
+            error!("Worker Stopped: {}", e);
+        }
+    }
+    flush_instances(None).await;
+}
+
+/// Spawn worker task for id on LocalSet of current worker thread,
+/// jobs of submitted requests are marked as running when the worker starts them
+fn spawn_worker(id: &str, jobs: JobRegistry) -> IdWorker {
+    info!("Start Worker for ID: {}", id);
+    let (tx, mut rx) = mpsc::unbounded_channel::<QueuedRequest>();
+    task::spawn_local(async move {
+        while let Some((request, depth)) = rx.recv().await {
+            if !request.job_id.is_empty() {
+                jobs.set_running(&request.job_id);
+            }
+            handle_request(request).await;
+            depth.fetch_sub(1, Ordering::SeqCst);
+        }
+    });
+    IdWorker {
+        tx: tx,
+        depth: Arc::new(AtomicU32::new(0)),
+    }
+}
+
+/// Worker thread of id
+fn get_thread_index(id: &str, threads: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    id.hash(&mut hasher);
+    (hasher.finish() % threads as u64) as usize
+}
+
+/// Queue depth of id, of all ids with pending requests if id is empty
+fn make_queue_reply(id: String, depths: &HashMap<String, Arc<AtomicU32>>) -> ChannelReply {
+    let queue_depths = if id.is_empty() {
+        depths
+            .iter()
+            .map(|(id, depth)| (id.clone(), depth.load(Ordering::SeqCst)))
+            .collect()
+    } else {
+        match depths.get(&id) {
+            Some(depth) => vec![(id.clone(), depth.load(Ordering::SeqCst))],
+            None => vec![(id.clone(), 0)],
+        }
+    };
+    ChannelReply {
+        queue_depths: Some(queue_depths),
+        ..make_channel_reply(&id, MsgType::QueueDepth, "", "Ok", 0)
+    }
+}
+
+/// Handle request of one id
+async fn handle_request(request: ChannelRequest) {
+    match request.msg_type {
+        MsgType::CreateNewAuthor => {
+            let rep =
+                match streams_author::create_new_author(&request.id, request.multi_branch).await {
+                    Ok((ann_link, seed_nonce)) => ChannelReply {
+                        seed_nonce: seed_nonce,
+                        ..make_channel_reply(&request.id, request.msg_type, &ann_link, "Ok", 0)
+                    },
+                    Err(e) => {
+                        error!("{}", e);
+                        make_channel_reply(&request.id, request.msg_type, "", &e, -1)
+                    }
+                };
+            let _ = request.tx.send(QueueElem::Reply(rep));
+        }
+
+        MsgType::CreateNewSubscriber => {
+            let rep =
+                match streams_subscriber::create_new_subscriber(&request.id, &request.link).await {
+                    Ok((sub_link, public_key)) => ChannelReply {
+                        public_key: public_key,
+                        ..make_channel_reply(&request.id, request.msg_type, &sub_link, "Ok", 0)
+                    },
+                    Err(e) => {
+                        error!("{}", e);
+                        make_channel_reply(&request.id, request.msg_type, "", &e, -1)
+                    }
+                };
+            let _ = request.tx.send(QueueElem::Reply(rep));
+        }
+
+        MsgType::AddSubscriber => {
+            let subscriber = get_subscriber_info(request.subscriber);
+            let (status, code) = match streams_author::add_subscriber(
+                &request.id,
+                &request.link,
+                &subscriber.did,
+                &subscriber.public_key,
+                &subscriber.branches,
+            )
+            .await
+            {
+                Ok(r) => (r, 0),
+                Err(e) => {
+                    error!("{}", e);
+                    (e, -1)
+                }
+            };
+            let _ = request.tx.send(QueueElem::Reply(make_channel_reply(
+                &request.id,
+                request.msg_type,
+                "",
+                &status,
+                code,
+            )));
+        }
+
+        MsgType::ReceiveKeyload => {
+            let (status, code) =
+                match streams_subscriber::receive_keyload(&request.id, &request.link).await {
+                    Ok(r) => (r, 0),
+                    Err(e) => (e, -1),
+                };
+            let _ = request.tx.send(QueueElem::Reply(make_channel_reply(
+                &request.id,
+                request.msg_type,
+                "",
+                &status,
+                code,
+            )));
+        }
+
+        MsgType::SendMessage => {
+            let (payload, is_payload) = match request.messages {
+                Some(r) => (r[0].clone(), true),
+                None => ("No Payload".to_string(), false),
+            };
+            let public_payload = match request.public_messages {
+                Some(r) => r[0].clone(),
+                None => "".to_string(),
+            };
+            let rep = if !is_payload {
+                make_channel_reply(&request.id, request.msg_type, "", &payload, -1)
+            } else {
+                match streams_author::send_message(
+                    &request.id,
+                    &request.link,
+                    &request.branch,
+                    &public_payload,
+                    &payload,
+                )
+                .await
+                {
+                    Ok(msg_link) => {
+                        make_channel_reply(&request.id, request.msg_type, &msg_link, "Ok", 0)
+                    }
+                    Err(e) => {
+                        error!("{}", e);
+                        make_channel_reply(&request.id, request.msg_type, "", &e, -1)
+                    }
+                }
+            };
+            let _ = request.tx.send(QueueElem::Reply(rep));
+        }
+
+        MsgType::ReceiveMessages => {
+            let rep = match streams_subscriber::receive_messages(&request.id).await {
+                Ok(msgs) => {
+                    let status = if msgs.len() > 0 {
+                        "Ok".to_string()
+                    } else {
+                        "No messages".to_string()
+                    };
+                    ChannelReply {
+                        id: request.id,
+                        msg_type: request.msg_type,
+                        link: "".to_string(),
+                        status: status,
+                        code: 0,
+                        messages: Some(msgs),
+                        queue_depths: None,
+                        gaps: None,
+                        bundle: "".to_string(),
+                        public_key: "".to_string(),
+                        seed_nonce: "".to_string(),
+                    }
+                }
+                Err(e) => {
+                    error!("{}", e);
+                    ChannelReply {
+                        id: request.id,
+                        msg_type: request.msg_type,
+                        link: "".to_string(),
+                        status: e,
+                        code: -1,
+                        messages: None,
+                        queue_depths: None,
+                        gaps: None,
+                        bundle: "".to_string(),
+                        public_key: "".to_string(),
+                        seed_nonce: "".to_string(),
+                    }
+                }
+            };
+            let _ = request.tx.send(QueueElem::Reply(rep));
+        }
+
+        MsgType::CreateKeyload => {
+            let rep = match streams_author::announce_keyload(&request.id, &request.branch).await {
+                Ok(key_link) => {
+                    make_channel_reply(&request.id, request.msg_type, &key_link, "Ok", 0)
+                }
+                Err(e) => {
+                    error!("{}", e);
+                    make_channel_reply(&request.id, request.msg_type, "", &e, -1)
+                }
+            };
+            let _ = request.tx.send(QueueElem::Reply(rep));
+        }
+
+        MsgType::RevokeSubscriber => {
+            let subscriber = get_subscriber_info(request.subscriber);
+            let rep = match streams_author::revoke_subscriber(
+                &request.id,
+                &subscriber.did,
+                &subscriber.public_key,
+            )
+            .await
+            {
+                Ok(key_link) => {
+                    make_channel_reply(&request.id, request.msg_type, &key_link, "Ok", 0)
+                }
+                Err(e) => {
+                    error!("{}", e);
+                    make_channel_reply(&request.id, request.msg_type, "", &e, -1)
+                }
+            };
+            let _ = request.tx.send(QueueElem::Reply(rep));
+        }
+
+        MsgType::SyncSubscriber => {
+            let rep =
+                match streams_subscriber::sync_subscriber(&request.id, request.walk_back as usize)
+                    .await
+                {
+                    Ok((msgs, gaps)) => {
+                        let status = if gaps.len() > 0 {
+                            format!("Sequence Gaps: {}", gaps.len())
+                        } else {
+                            "Ok".to_string()
+                        };
+                        ChannelReply {
+                            messages: Some(msgs),
+                            gaps: Some(gaps),
+                            ..make_channel_reply(&request.id, request.msg_type, "", &status, 0)
+                        }
+                    }
+                    Err(e) => {
+                        error!("{}", e);
+                        make_channel_reply(&request.id, request.msg_type, "", &e, -1)
+                    }
+                };
+            let _ = request.tx.send(QueueElem::Reply(rep));
+        }
+
+        MsgType::RecoverAuthor => {
+            let rep = match streams_author::recover_author(
+                &request.id,
+                &request.link,
+                request.multi_branch,
+                &request.seed_nonce,
+            )
+            .await
+            {
+                Ok(ann_link) => {
+                    make_channel_reply(&request.id, request.msg_type, &ann_link, "Ok", 0)
+                }
+                Err(e) => {
+                    error!("{}", e);
+                    make_channel_reply(&request.id, request.msg_type, "", &e, -1)
+                }
+            };
+            let _ = request.tx.send(QueueElem::Reply(rep));
+        }
+
+        MsgType::ExportChannelState => {
+            let bundle = get_bundle_info(request.bundle);
+            let rep = match export_bundle(&request.id, &bundle.password).await {
+                Ok(bundle) => ChannelReply {
+                    bundle: bundle,
+                    ..make_channel_reply(&request.id, request.msg_type, "", "Ok", 0)
+                },
+                Err(e) => {
+                    error!("{}", e);
+                    make_channel_reply(&request.id, request.msg_type, "", &e, -1)
+                }
+            };
+            let _ = request.tx.send(QueueElem::Reply(rep));
+        }
+
+        MsgType::ImportChannelState => {
+            let bundle = get_bundle_info(request.bundle);
+            let rep = match import_bundle(&request.id, &bundle.bundle, &bundle.password).await {
+                Ok(ann_link) => {
+                    make_channel_reply(&request.id, request.msg_type, &ann_link, "Ok", 0)
+                }
+                Err(e) => {
+                    error!("{}", e);
+                    make_channel_reply(&request.id, request.msg_type, "", &e, -1)
+                }
+            };
+            let _ = request.tx.send(QueueElem::Reply(rep));
+        }
+        _ => error!("Error: Wrong Message Type"),
+    }
+}
+
+fn make_channel_reply(
+    id: &str,
+    msg_type: MsgType,
+    link: &str,
+    status: &str,
+    code: i32,
+) -> ChannelReply {
+    ChannelReply {
+        id: id.to_string(),
+        msg_type: msg_type,
+        link: link.to_string(),
+        status: status.to_string(),
+        code: code,
+        messages: None,
+        queue_depths: None,
+        gaps: None,
+        bundle: "".to_string(),
+        public_key: "".to_string(),
+        seed_nonce: "".to_string(),
+    }
+}
+/// Subscriber Information of Request, empty if not Set
+fn get_subscriber_info(subscriber: Option<SubscriberInfo>) -> SubscriberInfo {
+    match subscriber {
+        Some(r) => r,
+        None => SubscriberInfo {
+            did: "".to_string(),
+            public_key: "".to_string(),
+            branches: Vec::new(),
+        },
+    }
+}
+/// Bundle Information of Request, empty if not Set
+fn get_bundle_info(bundle: Option<BundleInfo>) -> BundleInfo {
+    match bundle {
+        Some(r) => r,
+        None => BundleInfo {
+            password: "".to_string(),
+            bundle: "".to_string(),
+        },
+    }
+}
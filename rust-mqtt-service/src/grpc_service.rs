@@ -1,103 +1,170 @@
-use grpc_mqtt::mqtt_operator_server::MqttOperator;
-use grpc_mqtt::{MqttMsgsReply, MqttReply, MqttRequest};
-use tonic::{Request, Response, Status};
-
-use crate::mqtt_module::{
-    init_controller, receive_grpc_messages, send_grpc_message, MessageHandler, MqttHandler,
-};
-
-/// Protobuffer v3 file
-pub mod grpc_mqtt {
-    tonic::include_proto!("mqtt_grpc");
-}
-/// Structure for Implementing GRPC Calls
-pub struct MqttOperatorService {
-    pub handler: MqttHandler,
-}
-
-/// Populate Struct with MQTT Settings
-impl MqttOperatorService {
-    pub async fn new() -> MqttOperatorService {
-        let handler = init_controller().await;
-        MqttOperatorService {
-            handler: MqttHandler { ..handler },
-        }
-    }
-}
-/// Implementation of GRPC Calls
-/// send_mqtt_message, receive_mqtt_message
-#[tonic::async_trait]
-impl MqttOperator for MqttOperatorService {
-    async fn send_mqtt_message(
-        &self,
-        request: Request<MqttRequest>,
-    ) -> Result<Response<MqttReply>, Status> {
-        let request = request.into_inner();
-        info!("send_mqtt_message()");
-        let (status, code) = match send_grpc_message(
-            &self,
-            &MessageHandler {
-                id: &request.id,
-                postfix: &request.postfix,
-                pwd: &request.pwd,
-                channel: &request.channel,
-                topic: &request.topic,
-                payload: &request.message,
-            },
-        )
-        .await
-        {
-            Ok(r) => (r, 0),
-            Err(e) => (e, -1),
-        };
-        Ok(Response::new(MqttReply {
-            status: status,
-            code: code,
-        }))
-    }
-
-    async fn receive_mqtt_message(
-        &self,
-        request: Request<MqttRequest>,
-    ) -> Result<Response<MqttMsgsReply>, Status> {
-        let request = request.into_inner();
-        info!("receive_mqtt_message()");
-        // Check if Topic is set
-        let is_topic = !request.topic.is_empty();
-
-        let (subtopics, messages) = match receive_grpc_messages(
-            &self,
-            &MessageHandler {
-                id: &request.id,
-                postfix: &request.postfix,
-                pwd: &request.pwd,
-                channel: &request.channel,
-                topic: &request.topic,
-                payload: &request.message,
-            },
-            is_topic,
-        )
-        .await
-        {
-            Ok(r) => r,
-            Err(e) => {
-                error!("{}", e);
-                return Err(Status::cancelled(format!(
-                    "Error Receiving Messsages: {}",
-                    e
-                )));
-            }
-        };
-        let (status, code) = if messages.is_empty() {
-            ("No Messages".to_string(), 1)
-        } else {
-            ("Ok".to_string(), 0)
-        };
-        Ok(Response::new(MqttMsgsReply {
-            topics: subtopics,
-            messages: messages,
-            status: status,
-            code: code,
-        }))
-    }
-}
+use grpc_mqtt::mqtt_operator_server::MqttOperator;
+use grpc_mqtt::{MqttMessage, MqttMsgsReply, MqttReply, MqttRequest};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc, Mutex};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use crate::config::MESSAGE_BUFFER_SIZE;
+use crate::mqtt_module::{
+    init_controller, receive_grpc_messages, send_grpc_message, subscribe_grpc_messages,
+    MessageHandler, MqttHandler, Subscriptions,
+};
+
+/// Protobuffer v3 file
+pub mod grpc_mqtt {
+    tonic::include_proto!("mqtt_grpc");
+}
+/// Structure for Implementing GRPC Calls
+pub struct MqttOperatorService {
+    pub handler: MqttHandler,
+    pub subscriptions: Subscriptions,
+}
+
+/// Populate Struct with MQTT Settings
+impl MqttOperatorService {
+    pub async fn new() -> MqttOperatorService {
+        let handler = init_controller().await;
+        MqttOperatorService {
+            handler: MqttHandler { ..handler },
+            subscriptions: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+/// Implementation of GRPC Calls
+/// send_mqtt_message, receive_mqtt_message, subscribe_mqtt_messages
+#[tonic::async_trait]
+impl MqttOperator for MqttOperatorService {
+    type SubscribeMqttMessagesStream = ReceiverStream<Result<MqttMessage, Status>>;
+
+    async fn send_mqtt_message(
+        &self,
+        request: Request<MqttRequest>,
+    ) -> Result<Response<MqttReply>, Status> {
+        let request = request.into_inner();
+        info!("send_mqtt_message()");
+        let (status, code) = match send_grpc_message(
+            &self,
+            &MessageHandler {
+                id: &request.id,
+                postfix: &request.postfix,
+                pwd: &request.pwd,
+                channel: &request.channel,
+                topic: &request.topic,
+                payload: &request.message,
+            },
+        )
+        .await
+        {
+            Ok(r) => (r, 0),
+            Err(e) => (e, -1),
+        };
+        Ok(Response::new(MqttReply {
+            status: status,
+            code: code,
+        }))
+    }
+
+    async fn receive_mqtt_message(
+        &self,
+        request: Request<MqttRequest>,
+    ) -> Result<Response<MqttMsgsReply>, Status> {
+        let request = request.into_inner();
+        info!("receive_mqtt_message()");
+        // Check if Topic is set
+        let is_topic = !request.topic.is_empty();
+
+        let (subtopics, messages) = match receive_grpc_messages(
+            &self,
+            &MessageHandler {
+                id: &request.id,
+                postfix: &request.postfix,
+                pwd: &request.pwd,
+                channel: &request.channel,
+                topic: &request.topic,
+                payload: &request.message,
+            },
+            is_topic,
+        )
+        .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                error!("{}", e);
+                return Err(Status::cancelled(format!(
+                    "Error Receiving Messsages: {}",
+                    e
+                )));
+            }
+        };
+        let (status, code) = if messages.is_empty() {
+            ("No Messages".to_string(), 1)
+        } else {
+            ("Ok".to_string(), 0)
+        };
+        Ok(Response::new(MqttMsgsReply {
+            topics: subtopics,
+            messages: messages,
+            status: status,
+            code: code,
+        }))
+    }
+
+    async fn subscribe_mqtt_messages(
+        &self,
+        request: Request<MqttRequest>,
+    ) -> Result<Response<Self::SubscribeMqttMessagesStream>, Status> {
+        let request = request.into_inner();
+        info!("subscribe_mqtt_messages()");
+        let mut sub_rx = match subscribe_grpc_messages(
+            &self,
+            &MessageHandler {
+                id: &request.id,
+                postfix: &request.postfix,
+                pwd: &request.pwd,
+                channel: &request.channel,
+                topic: &request.topic,
+                payload: &request.message,
+            },
+        )
+        .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                error!("{}", e);
+                return Err(Status::unavailable(format!(
+                    "Error Subscribing to Messages: {}",
+                    e
+                )));
+            }
+        };
+        // Forward Subscription to Caller, optionally filtered by Topic
+        let (tx, rx) = mpsc::channel(MESSAGE_BUFFER_SIZE);
+        tokio::spawn(async move {
+            loop {
+                match sub_rx.recv().await {
+                    Ok((topic, message)) => {
+                        if !request.topic.is_empty() && request.topic != topic {
+                            continue;
+                        }
+                        if tx.send(Ok(MqttMessage { topic, message })).await.is_err() {
+                            info!("Subscriber Disconnected");
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        error!("Subscriber Lagging, {} Messages Skipped", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        let _ = tx
+                            .send(Err(Status::unavailable("Subscription Closed")))
+                            .await;
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
@@ -1,237 +1,383 @@
-use paho_mqtt as mqtt;
-use std::env;
-use tokio::time::{sleep, Duration};
-
-use crate::config::{load_config_file, DEFAULT_SERVER_URL, ENV_SERVER_URL, MESSAGE_BUFFER_SIZE};
-use crate::grpc_service::MqttOperatorService;
-/// Struct to Hold References for GRPC Service
-pub struct MqttHandler {
-    pub mqtt_version: u32,
-    pub qos_pub: i32,
-    pub qos: Vec<i32>,
-    pub topics: Vec<String>,
-    pub host: String,
-    pub tls_opt: paho_mqtt::SslOptions,
-    pub recon_intv: (u64, u64),
-    pub timeout: u64,
-    pub keep_alive: u64,
-}
-/// Struct to Hold Message Information
-pub struct MessageHandler<'a> {
-    pub id: &'a String,
-    pub postfix: &'a String,
-    pub pwd: &'a String,
-    pub channel: &'a String,
-    pub topic: &'a String,
-    pub payload: &'a Vec<u8>,
-}
-/// GRPC Routine for Sending Messages
-pub async fn send_grpc_message<'a>(
-    cfg: &MqttOperatorService,
-    msg: &'a MessageHandler<'a>,
-) -> Result<String, String> {
-    info!("--- send_grpc_message() ---");
-    // Make Client
-    let client_opt = create_client_option_wo_id(&cfg.handler.host); // create_client_option(&cfg.handler.host, msg.id, "pub");
-    let client = create_client(client_opt)?;
-    let conn_opt = create_conn_option(cfg, msg.id, msg.pwd, true);
-    connect_to_broker(&client, conn_opt).await?;
-    let topic = make_topic_address(msg.channel, msg.topic);
-    info!("Send Message to Topic: {}", topic);
-    let status = send_message(&client, &topic, msg.payload.to_vec(), cfg.handler.qos_pub).await?;
-    info!("{}", status);
-    client.disconnect(None);
-    Ok("Message Successfully Transmitted".to_string())
-}
-/// GRPC Routine for Receiving Messages
-pub async fn receive_grpc_messages<'a>(
-    cfg: &MqttOperatorService,
-    msg: &'a MessageHandler<'a>,
-    is_topic: bool,
-) -> Result<(Vec<String>, Vec<Vec<u8>>), String> {
-    info!("--- receive_grpc_message() ---");
-    // Make Client
-    let client_opt = create_client_option(&cfg.handler.host, msg.id, msg.postfix);
-    let mut client = create_client(client_opt)?;
-    let conn_opt = create_conn_option(cfg, msg.id, msg.pwd, false);
-
-    let msg_stream = client.get_stream(MESSAGE_BUFFER_SIZE);
-    connect_to_broker(&client, conn_opt).await?;
-    if is_topic {
-        client.subscribe(
-            make_topic_address(msg.channel, msg.topic),
-            cfg.handler.qos[0],
-        );
-    } else {
-        let mut topics = Vec::new();
-        for topic in cfg.handler.topics.clone() {
-            topics.push(make_topic_address(msg.channel, &topic));
-        }
-        client.subscribe_many(&topics, &cfg.handler.qos);
-    }
-    let (subtopics, messages) = receive_messages(msg_stream).await;
-    client.disconnect(None);
-
-    Ok((subtopics, messages))
-}
-/// Build Client Options
-fn create_client_option(host: &str, id: &str, postfix: &str) -> mqtt::CreateOptions {
-    mqtt::CreateOptionsBuilder::new()
-        .server_uri(host)
-        .client_id(format!("{}_{}", id, postfix))
-        .finalize()
-}
-/// Build Client Options wo id
-#[allow(dead_code)]
-fn create_client_option_wo_id(host: &str) -> mqtt::CreateOptions {
-    mqtt::CreateOptionsBuilder::new()
-        .server_uri(host)
-        .finalize()
-}
-/// Make Client from Option
-pub fn create_client(client_opt: mqtt::CreateOptions) -> Result<paho_mqtt::AsyncClient, String> {
-    match mqtt::AsyncClient::new(client_opt) {
-        Ok(r) => return Ok(r),
-        Err(e) => return Err(format!("Unable to Create MQTT Client: {}", e)),
-    };
-}
-/// Build Connection Option
-fn create_conn_option(
-    cfg: &MqttOperatorService,
-    id: &str,
-    pwd: &str,
-    clean_session: bool,
-) -> paho_mqtt::ConnectOptions {
-    // Connection Options
-    // Set Clean Session
-    mqtt::ConnectOptionsBuilder::new()
-        .mqtt_version(cfg.handler.mqtt_version)
-        .automatic_reconnect(
-            Duration::from_secs(cfg.handler.recon_intv.0),
-            Duration::from_secs(cfg.handler.recon_intv.1),
-        )
-        .keep_alive_interval(Duration::from_secs(cfg.handler.keep_alive))
-        .connect_timeout(Duration::from_secs(cfg.handler.timeout))
-        .ssl_options(cfg.handler.tls_opt.clone())
-        .user_name(id)
-        .password(pwd)
-        .clean_session(clean_session)
-        .finalize()
-}
-/// Build broker URL
-pub fn create_broker_address(url: String, port: String, is_tls: bool) -> String {
-    let ptc = if is_tls {
-        "ssl".to_string()
-    } else {
-        "tcp".to_string()
-    };
-    format!("{}://{}:{}", ptc, url, port)
-}
-// Build Complete Topic
-fn make_topic_address(channel_id: &str, topic: &str) -> String {
-    format!("channels/{}/messages/{}", channel_id, topic)
-}
-/// Establish Connection to Broker
-pub async fn connect_to_broker(
-    client: &paho_mqtt::AsyncClient,
-    conn_opt: mqtt::ConnectOptions,
-) -> Result<(), String> {
-    match client.connect(conn_opt.clone()).await {
-        Ok(_) => return Ok(()),
-        Err(e) => return Err(e.to_string()),
-    };
-}
-/// Try Reconnect
-#[allow(dead_code)]
-pub async fn reconnect_to_broker(client: &paho_mqtt::AsyncClient) {
-    while let Err(_) = client.reconnect().await {
-        sleep(Duration::from_millis(1000)).await;
-    }
-}
-/// Call to Send <essage
-pub async fn send_message(
-    client: &paho_mqtt::AsyncClient,
-    topic: &str,
-    msg: Vec<u8>,
-    qos: i32,
-) -> Result<String, String> {
-    let payload = mqtt::Message::new(topic, msg, qos);
-    let res = client.publish(payload).await;
-    match res {
-        Ok(_) => return Ok("Message Delivered".to_string()),
-        Err(e) => return Err(format!("Unable to Send Message: {}", e)),
-    }
-}
-/// Call to Receive Messages
-pub async fn receive_messages(
-    stream: mqtt::AsyncReceiver<Option<paho_mqtt::Message>>,
-) -> (Vec<String>, Vec<Vec<u8>>) {
-    let mut messages = Vec::<Vec<u8>>::new();
-    let mut subtopics = Vec::<String>::new();
-    sleep(Duration::from_millis(1000)).await;
-    stream.close();
-    let n_msgs = stream.len();
-    info!("Number of Messages: {}", n_msgs);
-    for _ in 0..n_msgs {
-        match stream.recv().await {
-            Ok(msg) => {
-                if let Some(msg) = msg {
-                    let topic: Vec<&str> = msg.topic().split("/").collect();
-                    info!("Topic Received: {:?}", topic);
-                    subtopics.push(if topic.len() > 3 {
-                        topic[3].to_string()
-                    } else {
-                        topic[0].to_string()
-                    });
-                    messages.push(msg.payload().to_vec());
-                    info!("Payload: {:?}", &msg.payload());
-                } else {
-                    error!("Lost connection.");
-                    break;
-                }
-            }
-            Err(e) => {
-                error!("Unable to Read Message: {}", e);
-                break;
-            }
-        }
-    }
-    (subtopics, messages)
-}
-/// Initialize MQTT Subscriber and Publisher, Load configuration from Config Files and Set-Up Communication
-pub async fn init_controller() -> MqttHandler {
-    info!("--- init_controller() ---");
-    let cfg = load_config_file();
-    // Build MQTT Server URL
-    let url = env::var(ENV_SERVER_URL).unwrap_or(DEFAULT_SERVER_URL.to_string());
-    let mqtt_host = create_broker_address(url.clone(), cfg.mqtt.port.to_string(), cfg.mqtt.tls);
-    info!("MQTT Server Address: {}", &mqtt_host);
-    // TLS Options
-    // let ca_cert = env::current_dir()
-    //     .unwrap()
-    //     .join("cert")
-    //     .join(cfg.mqtt.ca_name);
-    let tls_opt = mqtt::SslOptionsBuilder::new()
-        .ca_path("/etc/ssl/certs/")
-        //.trust_store(ca_cert.clone())
-        .unwrap()
-        .finalize();
-    // MQTT Version
-    let mqtt_version = if cfg.mqtt.mqtt_v5 {
-        mqtt::MQTT_VERSION_5
-    } else {
-        mqtt::MQTT_VERSION_3_1_1
-    };
-    // Assign to Handler
-    MqttHandler {
-        mqtt_version: mqtt_version,
-        qos_pub: cfg.mqtt.qos_pub,
-        qos: cfg.mqtt.qos,
-        topics: cfg.mqtt.topics,
-        host: mqtt_host,
-        tls_opt: tls_opt,
-        recon_intv: (cfg.mqtt.recon_intv.0, cfg.mqtt.recon_intv.1),
-        timeout: cfg.mqtt.timeout,
-        keep_alive: cfg.mqtt.keep_alive,
-    }
-}
+use paho_mqtt as mqtt;
+use std::collections::HashMap;
+use std::env;
+use std::sync::Arc;
+use tokio::sync::{broadcast, Mutex};
+use tokio::time::{sleep, timeout, Duration};
+
+use crate::config::{
+    load_config_file, DEFAULT_SERVER_URL, ENV_SERVER_URL, MESSAGE_BUFFER_SIZE,
+    SUBSCRIPTION_BUFFER_SIZE, SUBSCRIPTION_CHECK_INTV,
+};
+use crate::grpc_service::MqttOperatorService;
+/// Message Pushed to Subscription Streams: (Subtopic, Payload)
+pub type StreamMessage = (String, Vec<u8>);
+/// Active Subscriptions, Key: {Thing ID}_{Channel ID}
+pub type Subscriptions = Arc<Mutex<HashMap<String, Subscription>>>;
+/// Running Subscription, pwd: Password used to connect, needed to join
+pub struct Subscription {
+    pwd: String,
+    tx: broadcast::Sender<StreamMessage>,
+}
+/// Struct to Hold References for GRPC Service
+pub struct MqttHandler {
+    pub mqtt_version: u32,
+    pub qos_pub: i32,
+    pub qos: Vec<i32>,
+    pub topics: Vec<String>,
+    pub host: String,
+    pub tls_opt: paho_mqtt::SslOptions,
+    pub recon_intv: (u64, u64),
+    pub timeout: u64,
+    pub keep_alive: u64,
+}
+/// Struct to Hold Message Information
+pub struct MessageHandler<'a> {
+    pub id: &'a String,
+    pub postfix: &'a String,
+    pub pwd: &'a String,
+    pub channel: &'a String,
+    pub topic: &'a String,
+    pub payload: &'a Vec<u8>,
+}
+/// GRPC Routine for Sending Messages
+pub async fn send_grpc_message<'a>(
+    cfg: &MqttOperatorService,
+    msg: &'a MessageHandler<'a>,
+) -> Result<String, String> {
+    info!("--- send_grpc_message() ---");
+    // Make Client
+    let client_opt = create_client_option_wo_id(&cfg.handler.host); // create_client_option(&cfg.handler.host, msg.id, "pub");
+    let client = create_client(client_opt)?;
+    let conn_opt = create_conn_option(cfg, msg.id, msg.pwd, true);
+    connect_to_broker(&client, conn_opt).await?;
+    let topic = make_topic_address(msg.channel, msg.topic);
+    info!("Send Message to Topic: {}", topic);
+    let status = send_message(&client, &topic, msg.payload.to_vec(), cfg.handler.qos_pub).await?;
+    info!("{}", status);
+    client.disconnect(None);
+    Ok("Message Successfully Transmitted".to_string())
+}
+/// GRPC Routine for Receiving Messages
+pub async fn receive_grpc_messages<'a>(
+    cfg: &MqttOperatorService,
+    msg: &'a MessageHandler<'a>,
+    is_topic: bool,
+) -> Result<(Vec<String>, Vec<Vec<u8>>), String> {
+    info!("--- receive_grpc_message() ---");
+    // Make Client
+    let client_opt = create_client_option(&cfg.handler.host, msg.id, msg.postfix);
+    let mut client = create_client(client_opt)?;
+    let conn_opt = create_conn_option(cfg, msg.id, msg.pwd, false);
+
+    let msg_stream = client.get_stream(MESSAGE_BUFFER_SIZE);
+    connect_to_broker(&client, conn_opt).await?;
+    if is_topic {
+        client.subscribe(
+            make_topic_address(msg.channel, msg.topic),
+            cfg.handler.qos[0],
+        );
+    } else {
+        let mut topics = Vec::new();
+        for topic in cfg.handler.topics.clone() {
+            topics.push(make_topic_address(msg.channel, &topic));
+        }
+        client.subscribe_many(&topics, &cfg.handler.qos);
+    }
+    let (subtopics, messages) = receive_messages(msg_stream).await;
+    client.disconnect(None);
+
+    Ok((subtopics, messages))
+}
+/// GRPC Routine for Streaming Messages
+/// Join running subscription for (thing, channel) or start a new one
+pub async fn subscribe_grpc_messages<'a>(
+    cfg: &MqttOperatorService,
+    msg: &'a MessageHandler<'a>,
+) -> Result<broadcast::Receiver<StreamMessage>, String> {
+    info!("--- subscribe_grpc_messages() ---");
+    let key = format!("{}_{}", msg.id, msg.channel);
+    if let Some(rx) = join_subscription(&cfg.subscriptions, &key, msg.pwd).await? {
+        return Ok(rx);
+    }
+    // Lock is not held while connecting, other calls are not blocked by broker
+    let client_opt = create_client_option(&cfg.handler.host, msg.id, msg.postfix);
+    let mut client = create_client(client_opt)?;
+    let conn_opt = create_conn_option(cfg, msg.id, msg.pwd, false);
+    let msg_stream = client.get_stream(MESSAGE_BUFFER_SIZE);
+    connect_to_broker(&client, conn_opt).await?;
+    let mut topics = Vec::new();
+    for topic in cfg.handler.topics.clone() {
+        topics.push(make_topic_address(msg.channel, &topic));
+    }
+    subscribe_topics(&client, &topics, &cfg.handler.qos).await?;
+    let mut subscriptions = cfg.subscriptions.lock().await;
+    // Subscription might have been started by another call while connecting
+    if let Some(subscription) = subscriptions.get(&key) {
+        client.disconnect(None);
+        if subscription.pwd != *msg.pwd {
+            return Err(format!("Credentials Not Valid for Subscription: {}", key));
+        }
+        info!("Join Subscription: {}", key);
+        return Ok(subscription.tx.subscribe());
+    }
+    info!("Start Subscription: {}", key);
+    let (tx, rx) = broadcast::channel(SUBSCRIPTION_BUFFER_SIZE);
+    subscriptions.insert(
+        key.clone(),
+        Subscription {
+            pwd: msg.pwd.to_string(),
+            tx: tx.clone(),
+        },
+    );
+    drop(subscriptions);
+    tokio::spawn(forward_subscription(
+        client,
+        msg_stream,
+        topics,
+        cfg.handler.qos.clone(),
+        tx,
+        cfg.subscriptions.clone(),
+        key,
+    ));
+    Ok(rx)
+}
+/// Join running subscription of key, password has to match password of subscription.
+/// Returns None if no subscription is running
+async fn join_subscription(
+    subscriptions: &Subscriptions,
+    key: &str,
+    pwd: &str,
+) -> Result<Option<broadcast::Receiver<StreamMessage>>, String> {
+    let subscriptions = subscriptions.lock().await;
+    match subscriptions.get(key) {
+        Some(subscription) => {
+            if subscription.pwd != pwd {
+                return Err(format!("Credentials Not Valid for Subscription: {}", key));
+            }
+            info!("Join Subscription: {}", key);
+            Ok(Some(subscription.tx.subscribe()))
+        }
+        None => Ok(None),
+    }
+}
+/// Push received Messages to all Receivers of Subscription,
+/// reconnect on connection loss and stop if no Receiver is left
+async fn forward_subscription(
+    client: paho_mqtt::AsyncClient,
+    stream: mqtt::AsyncReceiver<Option<paho_mqtt::Message>>,
+    topics: Vec<String>,
+    qos: Vec<i32>,
+    tx: broadcast::Sender<StreamMessage>,
+    subscriptions: Subscriptions,
+    key: String,
+) {
+    loop {
+        match timeout(SUBSCRIPTION_CHECK_INTV, stream.recv()).await {
+            Ok(Ok(Some(msg))) => {
+                info!("Topic Received: {}", msg.topic());
+                // Fails only without Receiver, checked below
+                let _ = tx.send((make_subtopic(msg.topic()), msg.payload().to_vec()));
+            }
+            Ok(Ok(None)) => {
+                error!("Lost Connection, Subscription: {}", key);
+                if !client.is_connected() {
+                    reconnect_to_broker(&client).await;
+                }
+                if let Err(e) = subscribe_topics(&client, &topics, &qos).await {
+                    error!("{}", e);
+                }
+                info!("Reconnected, Subscription: {}", key);
+            }
+            Ok(Err(e)) => {
+                error!("Unable to Read Message: {}", e);
+                subscriptions.lock().await.remove(&key);
+                break;
+            }
+            // No Message within Check Interval
+            Err(_) => (),
+        };
+        let mut subscriptions = subscriptions.lock().await;
+        if tx.receiver_count() == 0 {
+            subscriptions.remove(&key);
+            break;
+        }
+    }
+    info!("Stop Subscription: {}", key);
+    client.disconnect(None);
+}
+/// Subscribe to Topics
+async fn subscribe_topics(
+    client: &paho_mqtt::AsyncClient,
+    topics: &Vec<String>,
+    qos: &Vec<i32>,
+) -> Result<(), String> {
+    match client.subscribe_many(topics, qos).await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Unable to Subscribe to Topics: {}", e)),
+    }
+}
+/// Build Client Options
+fn create_client_option(host: &str, id: &str, postfix: &str) -> mqtt::CreateOptions {
+    mqtt::CreateOptionsBuilder::new()
+        .server_uri(host)
+        .client_id(format!("{}_{}", id, postfix))
+        .finalize()
+}
+/// Build Client Options wo id
+#[allow(dead_code)]
+fn create_client_option_wo_id(host: &str) -> mqtt::CreateOptions {
+    mqtt::CreateOptionsBuilder::new()
+        .server_uri(host)
+        .finalize()
+}
+/// Make Client from Option
+pub fn create_client(client_opt: mqtt::CreateOptions) -> Result<paho_mqtt::AsyncClient, String> {
+    match mqtt::AsyncClient::new(client_opt) {
+        Ok(r) => return Ok(r),
+        Err(e) => return Err(format!("Unable to Create MQTT Client: {}", e)),
+    };
+}
+/// Build Connection Option
+fn create_conn_option(
+    cfg: &MqttOperatorService,
+    id: &str,
+    pwd: &str,
+    clean_session: bool,
+) -> paho_mqtt::ConnectOptions {
+    // Connection Options
+    // Set Clean Session
+    mqtt::ConnectOptionsBuilder::new()
+        .mqtt_version(cfg.handler.mqtt_version)
+        .automatic_reconnect(
+            Duration::from_secs(cfg.handler.recon_intv.0),
+            Duration::from_secs(cfg.handler.recon_intv.1),
+        )
+        .keep_alive_interval(Duration::from_secs(cfg.handler.keep_alive))
+        .connect_timeout(Duration::from_secs(cfg.handler.timeout))
+        .ssl_options(cfg.handler.tls_opt.clone())
+        .user_name(id)
+        .password(pwd)
+        .clean_session(clean_session)
+        .finalize()
+}
+/// Build broker URL
+pub fn create_broker_address(url: String, port: String, is_tls: bool) -> String {
+    let ptc = if is_tls {
+        "ssl".to_string()
+    } else {
+        "tcp".to_string()
+    };
+    format!("{}://{}:{}", ptc, url, port)
+}
+// Build Complete Topic
+fn make_topic_address(channel_id: &str, topic: &str) -> String {
+    format!("channels/{}/messages/{}", channel_id, topic)
+}
+/// Extract Subtopic from Complete Topic
+fn make_subtopic(topic: &str) -> String {
+    let topic: Vec<&str> = topic.split("/").collect();
+    if topic.len() > 3 {
+        topic[3].to_string()
+    } else {
+        topic[0].to_string()
+    }
+}
+/// Establish Connection to Broker
+pub async fn connect_to_broker(
+    client: &paho_mqtt::AsyncClient,
+    conn_opt: mqtt::ConnectOptions,
+) -> Result<(), String> {
+    match client.connect(conn_opt.clone()).await {
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(e.to_string()),
+    };
+}
+/// Try Reconnect
+pub async fn reconnect_to_broker(client: &paho_mqtt::AsyncClient) {
+    while let Err(_) = client.reconnect().await {
+        sleep(Duration::from_millis(1000)).await;
+    }
+}
+/// Call to Send <essage
+pub async fn send_message(
+    client: &paho_mqtt::AsyncClient,
+    topic: &str,
+    msg: Vec<u8>,
+    qos: i32,
+) -> Result<String, String> {
+    let payload = mqtt::Message::new(topic, msg, qos);
+    let res = client.publish(payload).await;
+    match res {
+        Ok(_) => return Ok("Message Delivered".to_string()),
+        Err(e) => return Err(format!("Unable to Send Message: {}", e)),
+    }
+}
+/// Call to Receive Messages
+pub async fn receive_messages(
+    stream: mqtt::AsyncReceiver<Option<paho_mqtt::Message>>,
+) -> (Vec<String>, Vec<Vec<u8>>) {
+    let mut messages = Vec::<Vec<u8>>::new();
+    let mut subtopics = Vec::<String>::new();
+    sleep(Duration::from_millis(1000)).await;
+    stream.close();
+    let n_msgs = stream.len();
+    info!("Number of Messages: {}", n_msgs);
+    for _ in 0..n_msgs {
+        match stream.recv().await {
+            Ok(msg) => {
+                if let Some(msg) = msg {
+                    info!("Topic Received: {}", msg.topic());
+                    subtopics.push(make_subtopic(msg.topic()));
+                    messages.push(msg.payload().to_vec());
+                    info!("Payload: {:?}", &msg.payload());
+                } else {
+                    error!("Lost connection.");
+                    break;
+                }
+            }
+            Err(e) => {
+                error!("Unable to Read Message: {}", e);
+                break;
+            }
+        }
+    }
+    (subtopics, messages)
+}
+/// Initialize MQTT Subscriber and Publisher, Load configuration from Config Files and Set-Up Communication
+pub async fn init_controller() -> MqttHandler {
+    info!("--- init_controller() ---");
+    let cfg = load_config_file();
+    // Build MQTT Server URL
+    let url = env::var(ENV_SERVER_URL).unwrap_or(DEFAULT_SERVER_URL.to_string());
+    let mqtt_host = create_broker_address(url.clone(), cfg.mqtt.port.to_string(), cfg.mqtt.tls);
+    info!("MQTT Server Address: {}", &mqtt_host);
+    // TLS Options
+    // let ca_cert = env::current_dir()
+    //     .unwrap()
+    //     .join("cert")
+    //     .join(cfg.mqtt.ca_name);
+    let tls_opt = mqtt::SslOptionsBuilder::new()
+        .ca_path("/etc/ssl/certs/")
+        //.trust_store(ca_cert.clone())
+        .unwrap()
+        .finalize();
+    // MQTT Version
+    let mqtt_version = if cfg.mqtt.mqtt_v5 {
+        mqtt::MQTT_VERSION_5
+    } else {
+        mqtt::MQTT_VERSION_3_1_1
+    };
+    // Assign to Handler
+    MqttHandler {
+        mqtt_version: mqtt_version,
+        qos_pub: cfg.mqtt.qos_pub,
+        qos: cfg.mqtt.qos,
+        topics: cfg.mqtt.topics,
+        host: mqtt_host,
+        tls_opt: tls_opt,
+        recon_intv: (cfg.mqtt.recon_intv.0, cfg.mqtt.recon_intv.1),
+        timeout: cfg.mqtt.timeout,
+        keep_alive: cfg.mqtt.keep_alive,
+    }
+}